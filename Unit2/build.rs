@@ -0,0 +1,151 @@
+// Packs the separate frames in `art/` into the free cells of the checked-in
+// spritesheet, so artists can add a sprite by dropping in a PNG instead of editing
+// the master sheet by hand. Rows are added underneath when the sheet is full. The
+// game embeds the packed sheet, its atlas and its row count from OUT_DIR: the
+// atlas has the hand-drawn sprites named in `sprites.ron`, plus each file from
+// `art/` under the file's name. The source tree is never written to. It also
+// lists every level in `src/content/levels`, so a new one is compiled in without
+// being named anywhere.
+use std::collections::BTreeMap;
+use std::path::Path;
+
+use image::RgbaImage;
+
+const ART_DIR: &str = "art";
+const SHEET_PATH: &str = "src/content/spritesheet.png";
+const SPRITES_PATH: &str = "src/content/sprites.ron";
+const LEVEL_DIR: &str = "src/content/levels";
+const CELL_PIXELS: u32 = 16;
+
+// Where a sprite is on the sheet, in cells from the top left. The hand-drawn
+// bars take up parts of cells.
+#[derive(serde::Serialize, serde::Deserialize)]
+struct Region {
+    x: f32,
+    y: f32,
+    w: f32,
+    h: f32,
+}
+
+fn main() {
+    for path in [ART_DIR, SHEET_PATH, SPRITES_PATH, LEVEL_DIR] {
+        println!("cargo:rerun-if-changed={}", path);
+    }
+    if let Err(e) = pack() {
+        panic!("Couldn't pack {}: {}", ART_DIR, e);
+    }
+    if let Err(e) = list_levels() {
+        panic!("Couldn't list {}: {}", LEVEL_DIR, e);
+    }
+}
+
+// Writes the levels as an array of (name, include_str!(path)), sorted by name.
+fn list_levels() -> Result<(), Box<dyn std::error::Error>> {
+    let out_dir = std::env::var("OUT_DIR")?;
+    let root = std::env::var("CARGO_MANIFEST_DIR")?;
+    let mut levels = vec![];
+    for entry in std::fs::read_dir(LEVEL_DIR)? {
+        let path = entry?.path();
+        if path.extension().is_some_and(|ext| ext == "ron") {
+            let name = path.file_stem().unwrap_or_default().to_string_lossy().into_owned();
+            levels.push((name, Path::new(&root).join(&path)));
+        }
+    }
+    levels.sort();
+    let entries: Vec<String> = levels
+        .iter()
+        .map(|(name, path)| format!("({:?}, include_str!({:?})),", name, path.to_string_lossy()))
+        .collect();
+    std::fs::write(Path::new(&out_dir).join("levels.rs"), format!("[\n{}\n]", entries.join("\n")))?;
+    Ok(())
+}
+
+fn pack() -> Result<(), Box<dyn std::error::Error>> {
+    let out_dir = std::env::var("OUT_DIR")?;
+    let mut sheet = image::open(SHEET_PATH)?.to_rgba8();
+    let mut atlas: BTreeMap<String, Region> = ron::from_str(&std::fs::read_to_string(SPRITES_PATH)?)?;
+
+    // Anything already drawn or named on the sheet is taken.
+    let cells = (sheet.width() / CELL_PIXELS, sheet.height() / CELL_PIXELS);
+    let mut used = vec![vec![false; cells.0 as usize]; cells.1 as usize];
+    for (y, row) in used.iter_mut().enumerate() {
+        for (x, cell) in row.iter_mut().enumerate() {
+            let cell_image = image::imageops::crop_imm(&sheet, x as u32 * CELL_PIXELS, y as u32 * CELL_PIXELS, CELL_PIXELS, CELL_PIXELS);
+            *cell = cell_image.to_image().pixels().any(|pixel| pixel[3] != 0);
+        }
+    }
+    for region in atlas.values() {
+        for row in used.iter_mut().take((region.y + region.h).ceil() as usize).skip(region.y as usize) {
+            row[region.x as usize..(region.x + region.w).ceil() as usize].fill(true);
+        }
+    }
+
+    let mut frames = vec![];
+    if Path::new(ART_DIR).is_dir() {
+        for entry in std::fs::read_dir(ART_DIR)? {
+            let path = entry?.path();
+            if path.extension().is_some_and(|ext| ext == "png") {
+                let name = path.file_stem().unwrap_or_default().to_string_lossy().into_owned();
+                frames.push((name, image::open(&path)?.to_rgba8()));
+            }
+        }
+    }
+    // Tallest first, then by name, so the same files always pack the same way.
+    frames.sort_by(|a, b| b.1.height().cmp(&a.1.height()).then_with(|| a.0.cmp(&b.0)));
+
+    for (name, frame) in frames {
+        if atlas.contains_key(&name) {
+            return Err(format!("{}.png has the same name as a sprite in {}", name, SPRITES_PATH).into());
+        }
+        let size = (frame.width().div_ceil(CELL_PIXELS), frame.height().div_ceil(CELL_PIXELS));
+        if frame.width() % CELL_PIXELS != 0 || frame.height() % CELL_PIXELS != 0 {
+            println!("cargo:warning={}.png isn't a whole number of {}px cells; it's padded out", name, CELL_PIXELS);
+        }
+        if size.0 > cells.0 {
+            return Err(format!("{}.png is wider than the sheet", name).into());
+        }
+        let (x, y) = loop {
+            if let Some(spot) = find_space(&used, size) {
+                break spot;
+            }
+            used.push(vec![false; cells.0 as usize]);
+        };
+        if sheet.height() < used.len() as u32 * CELL_PIXELS {
+            let mut taller = RgbaImage::new(sheet.width(), used.len() as u32 * CELL_PIXELS);
+            image::imageops::replace(&mut taller, &sheet, 0, 0);
+            sheet = taller;
+        }
+        for row in used.iter_mut().skip(y as usize).take(size.1 as usize) {
+            row[x as usize..(x + size.0) as usize].fill(true);
+        }
+        image::imageops::replace(&mut sheet, &frame, (x * CELL_PIXELS) as i64, (y * CELL_PIXELS) as i64);
+        atlas.insert(
+            name,
+            Region {
+                x: x as f32,
+                y: y as f32,
+                w: size.0 as f32,
+                h: size.1 as f32,
+            },
+        );
+    }
+
+    let out_dir = Path::new(&out_dir);
+    std::fs::write(out_dir.join("atlas.ron"), ron::ser::to_string_pretty(&atlas, ron::ser::PrettyConfig::default())?)?;
+    sheet.save(out_dir.join("spritesheet.png"))?;
+    std::fs::write(out_dir.join("sheet_rows.rs"), format!("{:?}", used.len() as f32))?;
+    Ok(())
+}
+
+// The first free spot for `size` cells, scanning rows top to bottom.
+fn find_space(used: &[Vec<bool>], size: (u32, u32)) -> Option<(u32, u32)> {
+    let (w, h) = (size.0 as usize, size.1 as usize);
+    for y in 0..=used.len().checked_sub(h)? {
+        for x in 0..=used[0].len().checked_sub(w)? {
+            if used[y..y + h].iter().all(|row| row[x..x + w].iter().all(|&cell| !cell)) {
+                return Some((x as u32, y as u32));
+            }
+        }
+    }
+    None
+}
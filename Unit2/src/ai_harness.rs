@@ -0,0 +1,268 @@
+// Runs an `AI` on a virtual clock, with no window, GPU or audio, and records every
+// bullet it fires. Pattern tests assert on what was recorded: how many bullets,
+// which way they went and on which frames.
+use std::f32::consts::PI;
+use std::ops::Range;
+
+use rand::rngs::StdRng;
+use rand::SeedableRng;
+
+use crate::enemy_ai::{self, AI};
+use crate::simulation::SimState;
+use crate::spawn_queue::SpawnQueue;
+use crate::{Enemy, Projectile, SpriteHolder};
+
+#[derive(Debug, Clone, Copy)]
+pub struct Shot {
+    // Virtual frame the bullet was fired on, starting at 0.
+    pub frame: u64,
+    pub pos: (f32, f32),
+    // Radians in [0, 2π), measured like everywhere else in the game: 3π/2 is straight down.
+    pub angle: f32,
+    pub speed: f32,
+}
+
+pub struct AiHarness {
+    ai: Box<dyn AI>,
+    enemy: Enemy,
+    // Where the AI is told the player is. It never moves unless a test moves it.
+    player: (f32, f32),
+    spawns: SpawnQueue,
+    projectiles: Vec<Projectile>,
+    sprite_holder: SpriteHolder,
+    rng: StdRng,
+    frame: u64,
+    pub shots: Vec<Shot>,
+}
+
+impl AiHarness {
+    // The enemy starts where the level loaders put it.
+    pub fn new(ai: impl AI + 'static, seed: u64) -> Self {
+        let state = SimState::new(SpriteHolder::new(0), seed);
+        Self {
+            ai: Box::new(ai),
+            enemy: state.enemy.enemy,
+            player: state.player.center(),
+            spawns: SpawnQueue::default(),
+            projectiles: vec![],
+            sprite_holder: SpriteHolder::new(64),
+            rng: StdRng::seed_from_u64(seed),
+            frame: 0,
+            shots: vec![],
+        }
+    }
+
+    // Advances the AI by `frames` ticks. Fired bullets are recorded and removed
+    // straight away, so the stub sprite holder never fills up.
+    pub fn run(&mut self, frames: u64) -> &mut Self {
+        for _ in 0..frames {
+            self.ai.ai_loop(&mut self.spawns, &self.enemy, self.player, &mut self.rng);
+            self.record();
+            self.frame += 1;
+        }
+        self
+    }
+
+    // Lands `amount` damage on the enemy and runs the hooks the simulation would.
+    // Anything fired from them is recorded on the current frame.
+    pub fn hit(&mut self, amount: f32) -> &mut Self {
+        let bar = &mut self.enemy.health_bar;
+        bar.currval = (bar.currval - amount).max(0.0);
+        self.ai.on_damage(amount, &self.enemy);
+        if self.enemy.health_bar.currval <= 0.0 {
+            self.ai.on_death(&mut self.spawns, &self.enemy, &mut self.rng);
+        }
+        self.record();
+        self
+    }
+
+    fn record(&mut self) {
+        self.spawns.flush(&mut self.projectiles, &mut self.sprite_holder);
+        for mut proj in self.projectiles.drain(..) {
+            self.shots.push(Shot {
+                frame: self.frame,
+                pos: proj.body.pos,
+                angle: proj.direction.1.atan2(proj.direction.0).rem_euclid(2.0 * PI),
+                speed: proj.speed,
+            });
+            proj.clean_dead(&mut self.sprite_holder);
+        }
+    }
+
+    pub fn move_player(&mut self, center: (f32, f32)) -> &mut Self {
+        self.player = center;
+        self
+    }
+
+    pub fn ai(&self) -> &dyn AI {
+        self.ai.as_ref()
+    }
+
+    pub fn shots_in(&self, frames: Range<u64>) -> impl Iterator<Item = &Shot> {
+        self.shots.iter().filter(move |shot| frames.contains(&shot.frame))
+    }
+
+    // Frames on which at least one bullet was fired.
+    pub fn firing_frames(&self) -> Vec<u64> {
+        let mut frames: Vec<u64> = self.shots.iter().map(|shot| shot.frame).collect();
+        frames.dedup();
+        frames
+    }
+}
+
+fn assert_angles_within(shots: &[&Shot], range: Range<f32>) {
+    for shot in shots {
+        assert!(
+            range.contains(&shot.angle),
+            "shot on frame {} went at {:.3} rad, outside {:?}",
+            shot.frame,
+            shot.angle,
+            range
+        );
+    }
+}
+
+#[test]
+fn level1_drops_one_fruit_every_41_frames() {
+    let mut harness = AiHarness::new(
+        enemy_ai::Level1AI {
+            max_cooldown: 40,
+            cooldown: 0,
+        },
+        1,
+    );
+    harness.run(41 * 10);
+
+    assert_eq!(harness.shots.len(), 10);
+    assert_eq!(harness.firing_frames(), (0..10).map(|i| i * 41).collect::<Vec<_>>());
+    let shots: Vec<&Shot> = harness.shots.iter().collect();
+    assert_angles_within(&shots, (11.0 * PI / 8.0)..(13.0 * PI / 8.0 + 0.001));
+    assert!(harness.shots.iter().all(|shot| (shot.speed - 6.0).abs() < 0.001));
+    assert!(harness
+        .shots
+        .iter()
+        .all(|shot| (430.0..=470.0).contains(&shot.pos.0) && shot.pos.1 == 650.0));
+}
+
+#[test]
+fn level6_phases_follow_the_clock() {
+    let mut harness = AiHarness::new(
+        enemy_ai::Level6AI {
+            cooldown: 0,
+            telegraphs: vec![],
+        },
+        1,
+    );
+
+    harness.run(600);
+    assert_eq!(harness.ai().phase(), 0);
+    harness.run(1);
+    assert_eq!(harness.ai().phase(), 1);
+    harness.run(599);
+    assert_eq!(harness.ai().phase(), 1);
+    harness.run(1);
+    assert_eq!(harness.ai().phase(), 2);
+    harness.run(599);
+    harness.run(1);
+    assert_eq!(harness.ai().phase(), 3);
+}
+
+#[test]
+fn level6_bullet_counts_per_phase() {
+    let mut harness = AiHarness::new(
+        enemy_ai::Level6AI {
+            cooldown: 0,
+            telegraphs: vec![],
+        },
+        1,
+    );
+    harness.run(2400);
+
+    // Swaying stream: 55 of every 100 frames.
+    assert_eq!(harness.shots_in(0..600).count(), 330);
+    // Triple spread: a volley of three every 30 frames.
+    assert_eq!(harness.shots_in(600..1200).count(), 60);
+    // Rapid sweep: 3 of every 20 frames, plus a firework every 120.
+    assert_eq!(harness.shots_in(1200..1800).count(), 95);
+    // Then it stops.
+    assert_eq!(harness.shots_in(1800..2400).count(), 0);
+}
+
+#[test]
+fn level6_patterns_aim_downwards() {
+    let mut harness = AiHarness::new(
+        enemy_ai::Level6AI {
+            cooldown: 0,
+            telegraphs: vec![],
+        },
+        7,
+    );
+    harness.run(1800);
+
+    let shots: Vec<&Shot> = harness.shots.iter().collect();
+    assert_angles_within(&shots, (PI)..(2.0 * PI));
+}
+
+#[test]
+fn level6_triple_spread_is_evenly_fanned() {
+    let mut harness = AiHarness::new(
+        enemy_ai::Level6AI {
+            cooldown: 0,
+            telegraphs: vec![],
+        },
+        3,
+    );
+    harness.run(1200);
+
+    let volley: Vec<&Shot> = harness.shots_in(600..1200).collect();
+    for three in volley.chunks(3) {
+        assert_eq!(three.len(), 3);
+        assert!(three.iter().all(|shot| shot.frame == three[0].frame));
+        for pair in three.windows(2) {
+            assert!((pair[1].angle - pair[0].angle - PI / 4.0).abs() < 0.001);
+        }
+    }
+}
+
+#[test]
+fn midboss_bursts_aim_at_the_player() {
+    let mut harness = AiHarness::new(
+        enemy_ai::MidBossAI {
+            burst_velocity: (0.0, -7.0),
+            ..Default::default()
+        },
+        1,
+    );
+    // Skip the fan phase, then let one burst start with the player off to the right.
+    harness.run(239);
+    harness.move_player((900.0, 100.0)).run(1);
+    // Moving mid-burst doesn't bend the rest of it.
+    harness.move_player((100.0, 100.0)).run(60);
+
+    let burst: Vec<&Shot> = harness.shots_in(239..299).collect();
+    assert_eq!(burst.len(), 3);
+    let from = (burst[0].pos.0 + 32.0, burst[0].pos.1 + 32.0);
+    let expected = (100.0 - from.1).atan2(900.0 - from.0).rem_euclid(2.0 * PI);
+    assert!(burst.iter().all(|shot| (shot.angle - expected).abs() < 0.001));
+    assert!(burst.iter().all(|shot| (shot.speed - 7.0).abs() < 0.001));
+}
+
+#[test]
+fn midboss_sticks_to_bursts_at_half_health_and_leaves_a_ring() {
+    let mut harness = AiHarness::new(enemy_ai::MidBossAI::default(), 1);
+    let health = harness.enemy.health_bar.maxval;
+    harness.hit(health / 4.0);
+    assert_eq!(harness.ai().phase(), 0);
+    harness.hit(health / 4.0);
+    // Well inside what would have been the fan phase.
+    harness.run(100);
+    assert_eq!(harness.ai().phase(), 1);
+
+    let before = harness.shots.len();
+    harness.hit(health);
+    let ring: Vec<&Shot> = harness.shots[before..].iter().collect();
+    assert_eq!(ring.len(), 12);
+    for pair in ring.windows(2) {
+        assert!((pair[1].angle - pair[0].angle - PI / 6.0).abs() < 0.001);
+    }
+}
@@ -0,0 +1,64 @@
+// Where each named sprite is on the sheet. build.rs writes the atlas alongside the
+// packed sheet, and both are built into the game.
+use std::collections::BTreeMap;
+use std::sync::OnceLock;
+
+use crate::SPRITE_SHEET_RESOLUTION;
+
+pub const SHEET_PNG: &[u8] = include_bytes!(concat!(env!("OUT_DIR"), "/spritesheet.png"));
+// How many rows of cells SHEET_PNG has. The game draws its own rows below them.
+pub const SHEET_ROWS: f32 = include!(concat!(env!("OUT_DIR"), "/sheet_rows.rs"));
+const ATLAS: &str = include_str!(concat!(env!("OUT_DIR"), "/atlas.ron"));
+
+// In cells from the sheet's top left.
+#[derive(Debug, Clone, Copy, PartialEq, serde::Deserialize)]
+pub struct Region {
+    pub x: f32,
+    pub y: f32,
+    pub w: f32,
+    pub h: f32,
+}
+
+impl Region {
+    pub fn contains(&self, [x, y, w, h]: [f32; 4]) -> bool {
+        x >= self.x && y >= self.y && x + w <= self.x + self.w && y + h <= self.y + self.h
+    }
+
+    fn sheet_region(&self) -> [f32; 4] {
+        [
+            self.x / SPRITE_SHEET_RESOLUTION.0,
+            self.y / SPRITE_SHEET_RESOLUTION.1,
+            self.w / SPRITE_SHEET_RESOLUTION.0,
+            self.h / SPRITE_SHEET_RESOLUTION.1,
+        ]
+    }
+}
+
+pub fn entries() -> &'static BTreeMap<String, Region> {
+    static ENTRIES: OnceLock<BTreeMap<String, Region>> = OnceLock::new();
+    ENTRIES.get_or_init(|| ron::from_str(ATLAS).expect("build.rs wrote a broken atlas"))
+}
+
+// A sprite's `sheet_region`. Panics on names that aren't in the atlas.
+pub fn sheet_region(name: &str) -> [f32; 4] {
+    match entries().get(name) {
+        Some(region) => region.sheet_region(),
+        None => panic!("{} isn't in the atlas", name),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn every_entry_is_on_the_artists_part_of_the_sheet() {
+        let sheet = image::load_from_memory(SHEET_PNG).unwrap();
+        let cells = (sheet.width() as f32 / 16.0, sheet.height() as f32 / 16.0);
+        assert!(entries().contains_key("player_right"));
+        for (name, region) in entries() {
+            assert!(region.w > 0.0 && region.h > 0.0, "{}", name);
+            assert!(region.x + region.w <= cells.0 && region.y + region.h <= cells.1, "{} is off the sheet", name);
+        }
+    }
+}
@@ -0,0 +1,285 @@
+use std::time::Duration;
+
+use kira::{
+    clock::{ClockHandle, ClockSpeed},
+    manager::{backend::DefaultBackend, AudioManager},
+    sound::static_sound::{StaticSoundData, StaticSoundHandle, StaticSoundSettings},
+    track::{TrackBuilder, TrackHandle},
+    tween::Tween,
+    StartTime,
+};
+
+// Most copies of one sound allowed to start in the same frame. A screen full of
+// bullets dying at once would otherwise stack dozens of identical plays and clip.
+const MAX_PER_FRAME: usize = 3;
+// The copies that do play are spread out by this much so they don't land on top
+// of each other as one loud click.
+const STAGGER_SECONDS: f64 = 0.015;
+
+// The music is one loop split into stems that all run in sync. Each one is
+// audible once the intensity reaches its threshold, so the track builds up as
+// the fight goes on.
+const MUSIC_LAYERS: [(&str, f32); 3] = [
+    ("src/content/music_base.wav", 0.0),
+    ("src/content/music_drums.wav", 0.35),
+    ("src/content/music_lead.wav", 0.7),
+];
+const LAYER_FADE: Duration = Duration::from_millis(1500);
+// Music volume while a stinger is playing over it.
+const DUCK_VOLUME: f64 = 0.3;
+const DUCK_FADE: Duration = Duration::from_millis(60);
+const UNDUCK_FADE: Duration = Duration::from_millis(400);
+const MUSIC_FADE_OUT: Duration = Duration::from_millis(800);
+
+// Every sound effect the simulation can ask for. The simulation only records
+// these; the frame loop is the one that actually talks to the audio device.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum SoundId {
+    PlayerShoot,
+    PlayerHit,
+    EnemyHit,
+    ProjectileMissed,
+}
+
+impl SoundId {
+    fn path(self) -> &'static str {
+        match self {
+            SoundId::PlayerShoot => "src/content/player_shoot.ogg",
+            SoundId::PlayerHit => "src/content/player_hit.ogg",
+            SoundId::EnemyHit => "src/content/enemy_hit.ogg",
+            SoundId::ProjectileMissed => "src/content/projectile_missed.ogg",
+        }
+    }
+}
+
+// Things that happen in a run that the music reacts to.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MusicEvent {
+    PhaseChanged,
+    SpellCaptured,
+    GameOver,
+}
+
+// A short musical cue played over the background music.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Stinger {
+    PhaseChange,
+    SpellCapture,
+    GameOver,
+}
+
+impl Stinger {
+    fn path(self) -> &'static str {
+        match self {
+            Stinger::PhaseChange => "src/content/stinger_phase.wav",
+            Stinger::SpellCapture => "src/content/stinger_spell.wav",
+            Stinger::GameOver => "src/content/stinger_game_over.wav",
+        }
+    }
+
+    // How many frames the music stays ducked, roughly the length of the cue.
+    fn duck_frames(self) -> u32 {
+        match self {
+            Stinger::PhaseChange => 30,
+            Stinger::SpellCapture => 45,
+            Stinger::GameOver => 0,
+        }
+    }
+}
+
+struct MusicLayer {
+    sound: StaticSoundHandle,
+    threshold: f32,
+    audible: bool,
+}
+
+pub struct SoundDispatcher {
+    manager: AudioManager<DefaultBackend>,
+    // Ticks once per stagger step, for scheduling sounds just ahead of now.
+    // Without it repeated sounds play together and music layers may drift.
+    clock: Option<ClockHandle>,
+    // Music plays through its own track so stingers can turn it down.
+    music_track: Option<TrackHandle>,
+    music: Vec<MusicLayer>,
+    duck_frames_left: u32,
+}
+
+impl SoundDispatcher {
+    pub fn new(mut manager: AudioManager<DefaultBackend>) -> Self {
+        let clock = match manager.add_clock(ClockSpeed::SecondsPerTick(STAGGER_SECONDS)) {
+            Ok(clock) if clock.start().is_ok() => Some(clock),
+            _ => {
+                log::warn!("Couldn't start the audio clock, sounds won't be staggered or synced");
+                None
+            }
+        };
+        let music_track = match manager.add_sub_track(TrackBuilder::new()) {
+            Ok(track) => Some(track),
+            Err(e) => {
+                log::warn!("Couldn't create the music track, playing without music: {}", e);
+                None
+            }
+        };
+        Self {
+            manager,
+            clock,
+            music_track,
+            music: vec![],
+            duck_frames_left: 0,
+        }
+    }
+
+    // Starts the background loop if it isn't already going, with only the
+    // layers for zero intensity audible.
+    pub fn start_music(&mut self) {
+        let Some(track) = &self.music_track else {
+            return;
+        };
+        if !self.music.is_empty() {
+            return;
+        }
+        // Every layer starts on the same tick so they stay lined up.
+        let start = match &self.clock {
+            Some(clock) => StartTime::ClockTime(clock.time() + 2),
+            None => StartTime::Immediate,
+        };
+        for (path, threshold) in MUSIC_LAYERS {
+            let audible = threshold <= 0.0;
+            let settings = StaticSoundSettings::new()
+                .loop_region(..)
+                .output_destination(track)
+                .start_time(start)
+                .volume(if audible { 1.0 } else { 0.0 });
+            let sound = StaticSoundData::from_file(path, settings)
+                .map_err(|e| e.to_string())
+                .and_then(|data| self.manager.play(data).map_err(|e| e.to_string()));
+            match sound {
+                Ok(sound) => self.music.push(MusicLayer { sound, threshold, audible }),
+                Err(e) => log::warn!("Couldn't play {}: {}", path, e),
+            }
+        }
+    }
+
+    pub fn stop_music(&mut self) {
+        for mut layer in self.music.drain(..) {
+            let _ = layer.sound.stop(fade(MUSIC_FADE_OUT));
+        }
+    }
+
+    // Fades music layers in or out. `intensity` runs from 0 (calm) to 1 (everything).
+    pub fn set_intensity(&mut self, intensity: f32) {
+        for layer in self.music.iter_mut() {
+            let audible = intensity >= layer.threshold;
+            if audible != layer.audible {
+                layer.audible = audible;
+                let _ = layer.sound.set_volume(if audible { 1.0 } else { 0.0 }, fade(LAYER_FADE));
+            }
+        }
+    }
+
+    // Where game events are turned into music cues.
+    pub fn handle_event(&mut self, event: MusicEvent) {
+        match event {
+            MusicEvent::PhaseChanged => self.play_stinger(Stinger::PhaseChange),
+            MusicEvent::SpellCaptured => self.play_stinger(Stinger::SpellCapture),
+            MusicEvent::GameOver => {
+                self.stop_music();
+                self.play_stinger(Stinger::GameOver);
+            }
+        }
+    }
+
+    fn play_stinger(&mut self, stinger: Stinger) {
+        match StaticSoundData::from_file(stinger.path(), StaticSoundSettings::default()) {
+            Ok(data) => {
+                let _ = self.manager.play(data);
+            }
+            Err(e) => log::warn!("Couldn't load {}: {}", stinger.path(), e),
+        }
+        if stinger.duck_frames() == 0 {
+            return;
+        }
+        if let Some(track) = &mut self.music_track {
+            let _ = track.set_volume(DUCK_VOLUME, fade(DUCK_FADE));
+        }
+        self.duck_frames_left = self.duck_frames_left.max(stinger.duck_frames());
+    }
+
+    // Call once per rendered frame to bring the music back up after a stinger.
+    pub fn update(&mut self) {
+        if self.duck_frames_left == 0 {
+            return;
+        }
+        self.duck_frames_left -= 1;
+        if self.duck_frames_left == 0 {
+            if let Some(track) = &mut self.music_track {
+                let _ = track.set_volume(1.0, fade(UNDUCK_FADE));
+            }
+        }
+    }
+
+    // Plays every queued sound and empties the queue.
+    pub fn play_sounds(&mut self, sounds: &mut Vec<SoundId>) {
+        for (sound, step) in limit_per_frame(sounds.drain(..)) {
+            let mut settings = StaticSoundSettings::default();
+            if let (Some(clock), true) = (&self.clock, step > 0) {
+                settings = settings.start_time(StartTime::ClockTime(clock.time() + step));
+            }
+            let sound_data = StaticSoundData::from_file(sound.path(), settings).unwrap();
+            let _ = self.manager.play(sound_data);
+        }
+    }
+}
+
+fn fade(duration: Duration) -> Tween {
+    Tween {
+        duration,
+        ..Default::default()
+    }
+}
+
+// Drops copies past MAX_PER_FRAME and numbers the rest, so the nth copy of a
+// sound can be started n stagger steps late.
+fn limit_per_frame(sounds: impl Iterator<Item = SoundId>) -> Vec<(SoundId, u64)> {
+    let mut played: Vec<(SoundId, u64)> = vec![];
+    for sound in sounds {
+        let step = played.iter().filter(|(id, _)| *id == sound).count();
+        if step < MAX_PER_FRAME {
+            played.push((sound, step as u64));
+        }
+    }
+    played
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn music_and_stingers_load() {
+        let stingers = [Stinger::PhaseChange, Stinger::SpellCapture, Stinger::GameOver];
+        let paths = MUSIC_LAYERS.iter().map(|&(path, _)| path).chain(stingers.iter().map(|stinger| stinger.path()));
+        for path in paths {
+            assert!(StaticSoundData::from_file(path, StaticSoundSettings::default()).is_ok(), "{}", path);
+        }
+    }
+
+    #[test]
+    fn repeated_sounds_are_capped_and_staggered() {
+        let mut sounds = vec![SoundId::PlayerShoot];
+        sounds.extend([SoundId::ProjectileMissed; 20]);
+        sounds.push(SoundId::EnemyHit);
+
+        let played = limit_per_frame(sounds.into_iter());
+        assert_eq!(
+            played,
+            vec![
+                (SoundId::PlayerShoot, 0),
+                (SoundId::ProjectileMissed, 0),
+                (SoundId::ProjectileMissed, 1),
+                (SoundId::ProjectileMissed, 2),
+                (SoundId::EnemyHit, 0),
+            ]
+        );
+    }
+}
@@ -0,0 +1,421 @@
+// Boss behaviour as a tree of small nodes, for fights that read better as "do
+// this, then that, unless..." than as a hand-written AI. A tree is plain data, so
+// a level can give its boss one with `ai: Tree(...)`:
+//
+//     Selector(children: [
+//         Sequence(children: [
+//             Condition(HealthBelow(0.5)),
+//             Action(Fire(Ring(bullets: 12, speed: 4.0))),
+//             Action(Wait(frames: 40)),
+//         ]),
+//         Sequence(children: [
+//             Action(MoveTo(pos: (100.0, 690.0), speed: 3.0)),
+//             Action(Fire(Aimed(speed: 6.0))),
+//             Action(MoveTo(pos: (436.0, 690.0), speed: 3.0)),
+//             Action(Fire(Aimed(speed: 6.0))),
+//         ]),
+//     ])
+//
+// The root is ticked once a frame and starts over whenever it finishes, so a tree
+// loops for the whole fight. Sequences and selectors remember which child they
+// were on, so a child that's still running picks up where it left off.
+//
+// `Dash` and `Laser` put up a telegraph and hold still for a second before they
+// go, so the player sees where the boss is headed or where the beam will fall:
+//
+//     Action(Dash(pos: (100.0, 500.0), speed: 14.0)),
+//     Action(Laser(speed: 12.0, frames: 45)),
+//
+// `FireVaried` is a volley with bounds to wander within (see pattern_mutator.rs),
+// settled once per run when the boss spawns:
+//
+//     Action(FireVaried(
+//         volley: Ring(bullets: 12, speed: 4.0),
+//         within: (bullets: Some((8, 18)), turn: Some(0.3), pressure_band: Some(0.15)),
+//     ))
+use std::f32::consts::TAU;
+
+use serde::Deserialize;
+
+use crate::enemy_ai::{aim_at, below_center, AI, TELEGRAPH_FRAMES};
+use crate::pattern_mutator::{vary, Variation};
+use crate::spawn_queue::SpawnQueue;
+use crate::telegraphs::Telegraph;
+use crate::Enemy;
+use rand::rngs::StdRng;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Status {
+    Success,
+    Failure,
+    // Not done yet; tick it again next frame.
+    Running,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub enum Node {
+    // Runs its children in turn. Fails as soon as one does.
+    Sequence {
+        children: Vec<Node>,
+        #[serde(skip)]
+        current: usize,
+    },
+    // Tries its children in turn. Succeeds as soon as one does.
+    Selector {
+        children: Vec<Node>,
+        #[serde(skip)]
+        current: usize,
+    },
+    Condition(Condition),
+    Action(Action),
+}
+
+#[derive(Debug, Clone, Copy, Deserialize)]
+pub enum Condition {
+    // Below this fraction of full health.
+    HealthBelow(f32),
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub enum Action {
+    // Flies to where its bottom-left corner is at `pos`, `speed` units a frame.
+    MoveTo { pos: (f32, f32), speed: f32 },
+    Wait {
+        frames: u32,
+        #[serde(skip)]
+        elapsed: u32,
+    },
+    Fire(Volley),
+    // Marks `pos` and holds still until the mark closes, then flies there like MoveTo.
+    Dash {
+        pos: (f32, f32),
+        speed: f32,
+        #[serde(skip)]
+        warned: u32,
+    },
+    // Marks the column under the boss, then fires a bullet straight down it every
+    // frame for `frames` frames.
+    Laser {
+        speed: f32,
+        frames: u32,
+        #[serde(skip)]
+        elapsed: u32,
+    },
+    // Becomes a plain Fire of a varied volley when the boss spawns.
+    FireVaried { volley: Volley, within: Variation },
+}
+
+// One burst of bullets, all from just below the boss.
+#[derive(Debug, Clone, Copy, Deserialize)]
+pub enum Volley {
+    // Evenly spaced all the way round, the first `turn` radians from straight right.
+    Ring {
+        bullets: usize,
+        speed: f32,
+        #[serde(default)]
+        turn: f32,
+    },
+    // One straight at the player.
+    Aimed { speed: f32 },
+    // Spread across `spread` radians, centred `turn` radians off the player.
+    Fan {
+        bullets: usize,
+        spread: f32,
+        speed: f32,
+        #[serde(default)]
+        turn: f32,
+    },
+}
+
+// What a node can see and do on its tick.
+struct Tick<'a> {
+    spawns: &'a mut SpawnQueue,
+    enemy: &'a Enemy,
+    player: (f32, f32),
+    velocity: (f32, f32),
+    telegraphs: &'a mut Vec<Telegraph>,
+}
+
+// Between the rings marking a laser's column.
+const BEAM_MARK_SPACING: f32 = 128.0;
+
+// Where bullets leave the boss from, centred.
+fn muzzle(enemy: &Enemy) -> (f32, f32) {
+    let offset = below_center(enemy);
+    (enemy.body.pos.0 + offset.0 + 32.0, enemy.body.pos.1 + offset.1 + 32.0)
+}
+
+impl Node {
+    fn tick(&mut self, tick: &mut Tick) -> Status {
+        match self {
+            Node::Sequence { children, current } => Self::run_children(children, current, tick, Status::Success),
+            Node::Selector { children, current } => Self::run_children(children, current, tick, Status::Failure),
+            Node::Condition(condition) => condition.check(tick.enemy),
+            Node::Action(action) => action.tick(tick),
+        }
+    }
+
+    // Settles every FireVaried in the tree.
+    fn vary(&mut self, rng: &mut StdRng) {
+        match self {
+            Node::Sequence { children, .. } | Node::Selector { children, .. } => {
+                for child in children {
+                    child.vary(rng);
+                }
+            }
+            Node::Action(action) => {
+                if let Action::FireVaried { volley, within } = action {
+                    *action = Action::Fire(vary(*volley, within, rng));
+                }
+            }
+            Node::Condition(_) => {}
+        }
+    }
+
+    // Moves through `children` while they return `carry_on`, stopping at anything
+    // else. Returns `carry_on` if they all did.
+    fn run_children(children: &mut [Node], current: &mut usize, tick: &mut Tick, carry_on: Status) -> Status {
+        while let Some(child) = children.get_mut(*current) {
+            match child.tick(tick) {
+                Status::Running => return Status::Running,
+                status if status == carry_on => *current += 1,
+                status => {
+                    *current = 0;
+                    return status;
+                }
+            }
+        }
+        *current = 0;
+        carry_on
+    }
+}
+
+impl Condition {
+    fn check(self, enemy: &Enemy) -> Status {
+        let passed = match self {
+            Condition::HealthBelow(fraction) => enemy.health_bar.currval < enemy.health_bar.maxval * fraction,
+        };
+        if passed {
+            Status::Success
+        } else {
+            Status::Failure
+        }
+    }
+}
+
+impl Action {
+    fn tick(&mut self, tick: &mut Tick) -> Status {
+        match self {
+            Action::MoveTo { pos, speed } => move_to(*pos, *speed, tick),
+            Action::Dash { pos, speed, warned } => {
+                if *warned < TELEGRAPH_FRAMES as u32 {
+                    if *warned == 0 {
+                        let size = tick.enemy.body.size;
+                        tick.telegraphs.push(Telegraph {
+                            pos: (pos.0 + size.0 / 2.0, pos.1 + size.1 / 2.0),
+                            frames: TELEGRAPH_FRAMES as u32,
+                        });
+                    }
+                    *warned += 1;
+                    return Status::Running;
+                }
+                let status = move_to(*pos, *speed, tick);
+                if status == Status::Success {
+                    *warned = 0;
+                }
+                status
+            }
+            Action::Laser { speed, frames, elapsed } => {
+                let (x, top) = muzzle(tick.enemy);
+                if *elapsed == 0 {
+                    let marks = (top / BEAM_MARK_SPACING) as usize;
+                    for i in 0..=marks {
+                        tick.telegraphs.push(Telegraph {
+                            pos: (x, top - i as f32 * BEAM_MARK_SPACING),
+                            frames: TELEGRAPH_FRAMES as u32,
+                        });
+                    }
+                }
+                *elapsed += 1;
+                if *elapsed > TELEGRAPH_FRAMES as u32 {
+                    tick.enemy.spawn_new_projectile(tick.spawns, below_center(tick.enemy), (0.0, -*speed));
+                }
+                if *elapsed < TELEGRAPH_FRAMES as u32 + *frames {
+                    return Status::Running;
+                }
+                *elapsed = 0;
+                Status::Success
+            }
+            Action::Wait { frames, elapsed } => {
+                *elapsed += 1;
+                if *elapsed < *frames {
+                    return Status::Running;
+                }
+                *elapsed = 0;
+                Status::Success
+            }
+            Action::Fire(volley) => {
+                volley.fire(tick);
+                Status::Success
+            }
+            // Only if it never spawned, as in a test; fire it as written.
+            Action::FireVaried { volley, .. } => {
+                volley.fire(tick);
+                Status::Success
+            }
+        }
+    }
+}
+
+// Flies the boss's bottom-left corner to `pos`, `speed` units a frame.
+fn move_to(pos: (f32, f32), speed: f32, tick: &mut Tick) -> Status {
+    let here = tick.enemy.body.pos;
+    let (dx, dy) = (pos.0 - here.0, pos.1 - here.1);
+    let distance = (dx * dx + dy * dy).sqrt();
+    if distance < 0.5 {
+        return Status::Success;
+    }
+    // The last step lands right on it.
+    let step = speed.min(distance);
+    tick.velocity = (dx / distance * step, dy / distance * step);
+    Status::Running
+}
+
+impl Volley {
+    fn fire(self, tick: &mut Tick) {
+        let enemy = tick.enemy;
+        let offset = below_center(enemy);
+        let (x, y) = aim_at(muzzle(enemy), tick.player, 1.0);
+        let at_player = y.atan2(x);
+        let (bullets, first, step, speed) = match self {
+            Volley::Ring { bullets, speed, turn } => (bullets, turn, TAU / bullets.max(1) as f32, speed),
+            Volley::Aimed { speed } => (1, at_player, 0.0, speed),
+            Volley::Fan { bullets, spread, speed, turn } => {
+                let step = if bullets > 1 { spread / (bullets - 1) as f32 } else { 0.0 };
+                (bullets, at_player + turn - step * bullets.saturating_sub(1) as f32 / 2.0, step, speed)
+            }
+        };
+        for i in 0..bullets {
+            let angle = first + step * i as f32;
+            enemy.spawn_new_projectile(tick.spawns, offset, (angle.cos() * speed, angle.sin() * speed));
+        }
+    }
+}
+
+pub struct BehaviorTreeAI {
+    root: Node,
+    // Set by the tree each frame; standing still unless a node moves it.
+    velocity: (f32, f32),
+    telegraphs: Vec<Telegraph>,
+}
+
+impl BehaviorTreeAI {
+    pub fn new(root: Node) -> Self {
+        Self {
+            root,
+            velocity: (0.0, 0.0),
+            telegraphs: vec![],
+        }
+    }
+}
+
+impl AI for BehaviorTreeAI {
+    fn on_spawn(&mut self, _spawns: &mut SpawnQueue, _enemy: &Enemy, rng: &mut StdRng) {
+        self.root.vary(rng);
+    }
+
+    fn ai_loop(&mut self, spawns: &mut SpawnQueue, enemy: &Enemy, player: (f32, f32), _rng: &mut StdRng) {
+        let mut tick = Tick {
+            spawns,
+            enemy,
+            player,
+            velocity: (0.0, 0.0),
+            telegraphs: &mut self.telegraphs,
+        };
+        self.root.tick(&mut tick);
+        self.velocity = tick.velocity;
+    }
+
+    fn velocity(&self) -> Option<(f32, f32)> {
+        Some(self.velocity)
+    }
+
+    fn take_telegraphs(&mut self) -> Vec<Telegraph> {
+        std::mem::take(&mut self.telegraphs)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::fixtures::{self, enemy_bullets};
+    use crate::simulation::{simulate, FrameInput, SimState};
+
+    #[test]
+    fn a_tree_from_data_moves_then_fires_and_loops() {
+        let tree: Node = ron::from_str(
+            "Sequence(children: [
+                Action(MoveTo(pos: (260.0, 690.0), speed: 4.0)),
+                Action(Fire(Ring(bullets: 8, speed: 3.0))),
+                Action(Wait(frames: 10)),
+            ])",
+        )
+        .unwrap();
+        let mut state = fixtures::boss_running(BehaviorTreeAI::new(tree));
+
+        // 8 units away at 4 a frame: two frames of flying, then the ring.
+        state = simulate(state, FrameInput::default());
+        state = simulate(state, FrameInput::default());
+        assert_eq!(state.enemy.enemy.body.pos, (260.0, 690.0));
+        assert_eq!(enemy_bullets(&state), 0);
+        state = simulate(state, FrameInput::default());
+        assert_eq!(state.enemy.enemy.velocity, crate::components::Velocity::default());
+        assert_eq!(enemy_bullets(&state), 8);
+
+        // The rest of the wait, then round again: already there, so straight to
+        // another ring.
+        for _ in 0..9 {
+            state = simulate(state, FrameInput::default());
+        }
+        assert_eq!(enemy_bullets(&state), 8);
+        state = simulate(state, FrameInput::default());
+        assert_eq!(enemy_bullets(&state), 16);
+    }
+
+    fn boss_running(tree: &str) -> SimState {
+        fixtures::boss_running(BehaviorTreeAI::new(ron::from_str(tree).unwrap()))
+    }
+
+    #[test]
+    fn a_dash_is_marked_a_second_before_the_boss_moves() {
+        let mut state = boss_running("Action(Dash(pos: (100.0, 500.0), speed: 10.0))");
+        let start = state.enemy.enemy.body.pos;
+        state = simulate(state, FrameInput::default());
+        assert!(!state.telegraphs.sprite_indices().is_empty());
+        for _ in 1..TELEGRAPH_FRAMES {
+            assert_eq!(state.enemy.enemy.body.pos, start);
+            state = simulate(state, FrameInput::default());
+        }
+        assert_eq!(state.enemy.enemy.body.pos, start);
+        assert!(state.telegraphs.sprite_indices().is_empty());
+        state = simulate(state, FrameInput::default());
+        assert_ne!(state.enemy.enemy.body.pos, start);
+    }
+
+    #[test]
+    fn a_laser_is_marked_a_second_before_it_fires() {
+        let mut state = boss_running("Action(Laser(speed: 12.0, frames: 5))");
+        state = simulate(state, FrameInput::default());
+        assert!(!state.telegraphs.sprite_indices().is_empty());
+        for _ in 1..TELEGRAPH_FRAMES {
+            assert_eq!(enemy_bullets(&state), 0);
+            state = simulate(state, FrameInput::default());
+        }
+        assert_eq!(enemy_bullets(&state), 0);
+        assert!(state.telegraphs.sprite_indices().is_empty());
+        for _ in 0..5 {
+            state = simulate(state, FrameInput::default());
+        }
+        assert_eq!(enemy_bullets(&state), 5);
+    }
+}
@@ -0,0 +1,27 @@
+// Copies the internal render target onto the window.
+
+struct VertexOutput {
+    @builtin(position) clip_position: vec4<f32>,
+    @location(0) tex_coords: vec2<f32>,
+}
+
+@vertex
+fn vs_main(@builtin(vertex_index) in_vertex_index: u32) -> VertexOutput {
+    // One oversized triangle that covers the whole viewport:
+    // uv (0,0), (2,0), (0,2), with (0,0) at the top left.
+    let uv: vec2<f32> = vec2(f32((in_vertex_index << 1u) & 2u), f32(in_vertex_index & 2u));
+    return VertexOutput(
+        vec4(uv.x * 2.0 - 1.0, 1.0 - uv.y * 2.0, 0.0, 1.0),
+        uv
+    );
+}
+
+@group(0) @binding(0)
+var t_frame: texture_2d<f32>;
+@group(0) @binding(1)
+var s_frame: sampler;
+
+@fragment
+fn fs_main(in: VertexOutput) -> @location(0) vec4<f32> {
+    return textureSample(t_frame, s_frame, in.tex_coords);
+}
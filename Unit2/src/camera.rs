@@ -0,0 +1,266 @@
+// The view the world is drawn through. The level's playfield decides where it
+// rests; on top of that it can be panned, zoomed and shaken, and scripted moves
+// can play through a list of keyframes. The UI stays where the view rests. None of
+// this feeds back into the sim, and it all runs on real frames so it keeps going
+// through hit-stop.
+use std::collections::VecDeque;
+
+use crate::config::CameraConfig;
+use crate::playfield::Playfield;
+use crate::tween::{lerp, Easing};
+use crate::GPUCamera;
+
+#[derive(Debug, Clone, Copy)]
+struct Shake {
+    amplitude: f32,
+    duration: u32,
+    frames_left: u32,
+}
+
+// Where a scripted move should have the camera `frames` after the keyframe before
+// it, or after the move started for the first one.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Keyframe {
+    pub frames: u32,
+    pub pan: (f32, f32),
+    pub zoom: f32,
+    pub easing: Easing,
+}
+
+#[derive(Debug, Clone)]
+pub struct Camera {
+    // World units from where the playfield puts the view.
+    pan: (f32, f32),
+    // Above 1 shows less of the world, bigger. Zooms about the middle of the view.
+    zoom: f32,
+    shake: Option<Shake>,
+    // Keyframes still to reach, and where the view was when it set off for the first.
+    script: VecDeque<Keyframe>,
+    leg_start: ((f32, f32), f32),
+    leg_elapsed: u32,
+    // From config.toml.
+    config: CameraConfig,
+}
+
+impl Default for Camera {
+    fn default() -> Self {
+        Self::new(CameraConfig::default())
+    }
+}
+
+impl Camera {
+    pub fn new(config: CameraConfig) -> Self {
+        Self {
+            pan: (0.0, 0.0),
+            zoom: 1.0,
+            shake: None,
+            script: VecDeque::new(),
+            leg_start: ((0.0, 0.0), 1.0),
+            leg_elapsed: 0,
+            config,
+        }
+    }
+
+    // Moves the view to `pan` world units from where it rests. A scripted move
+    // that's playing takes it back over on the next frame.
+    pub fn set_position(&mut self, pan: (f32, f32)) {
+        self.pan = pan;
+    }
+
+    pub fn set_zoom(&mut self, zoom: f32) {
+        self.zoom = zoom.max(0.1);
+    }
+
+    // Opens a stage on its boss, then pulls back to the playfield.
+    pub fn boss_intro(&self, playfield: &Playfield, boss: (f32, f32)) -> [Keyframe; 3] {
+        let close = (Camera::pan_to(playfield, boss), self.config.intro_zoom);
+        [
+            Keyframe { frames: 0, pan: close.0, zoom: close.1, easing: Easing::Linear },
+            Keyframe { frames: 30, pan: close.0, zoom: close.1, easing: Easing::Linear },
+            Keyframe { frames: 50, pan: (0.0, 0.0), zoom: 1.0, easing: Easing::InOutSine },
+        ]
+    }
+
+    // Jolts the view up to `amplitude` world units either way, dying down over
+    // `duration` frames. A weaker shake doesn't cut a stronger one short.
+    pub fn shake(&mut self, amplitude: f32, duration: u32) {
+        let amplitude = amplitude * self.config.shake;
+        if amplitude <= 0.0 {
+            return;
+        }
+        if self.shake_offset_bound() <= amplitude {
+            self.shake = Some(Shake {
+                amplitude,
+                duration,
+                frames_left: duration,
+            });
+        }
+    }
+
+    // The pan that puts `point` in the middle of the view.
+    pub fn pan_to(playfield: &Playfield, point: (f32, f32)) -> (f32, f32) {
+        let rest = playfield.camera();
+        (
+            point.0 - (rest.screen_pos[0] + rest.screen_size[0] / 2.0),
+            point.1 - (rest.screen_pos[1] + rest.screen_size[1] / 2.0),
+        )
+    }
+
+    // Replaces any move already playing. It sets off from wherever the view is now.
+    pub fn play(&mut self, keyframes: &[Keyframe]) {
+        self.script = keyframes.iter().copied().collect();
+        self.leg_start = (self.pan, self.zoom);
+        self.leg_elapsed = 0;
+        self.step_script();
+    }
+
+    // Drops any scripted move and puts the view back at rest.
+    pub fn stop(&mut self) {
+        self.script.clear();
+        self.set_position((0.0, 0.0));
+        self.set_zoom(1.0);
+    }
+
+    // Sets the view for the current point in the script, moving on to the next
+    // keyframe whenever one is reached.
+    fn step_script(&mut self) {
+        while let Some(&keyframe) = self.script.front() {
+            let t = if keyframe.frames == 0 { 1.0 } else { self.leg_elapsed as f32 / keyframe.frames as f32 };
+            let eased = keyframe.easing.apply(t);
+            let ((pan_x, pan_y), zoom) = self.leg_start;
+            self.set_position((lerp(pan_x, keyframe.pan.0, eased), lerp(pan_y, keyframe.pan.1, eased)));
+            self.set_zoom(lerp(zoom, keyframe.zoom, eased));
+            if t < 1.0 {
+                return;
+            }
+            self.script.pop_front();
+            self.leg_start = (keyframe.pan, keyframe.zoom);
+            self.leg_elapsed = 0;
+        }
+    }
+
+    // The world point under a point on screen, given in the UI's coordinates.
+    pub fn screen_to_world(&self, playfield: &Playfield, point: (f32, f32)) -> (f32, f32) {
+        let (view, rest) = (self.gpu_camera(playfield), playfield.camera());
+        (
+            view.screen_pos[0] + (point.0 - rest.screen_pos[0]) / rest.screen_size[0] * view.screen_size[0],
+            view.screen_pos[1] + (point.1 - rest.screen_pos[1]) / rest.screen_size[1] * view.screen_size[1],
+        )
+    }
+
+    // How far the current shake can still move the view.
+    fn shake_offset_bound(&self) -> f32 {
+        self.shake
+            .map_or(0.0, |shake| shake.amplitude * shake.frames_left as f32 / shake.duration as f32)
+    }
+
+    pub fn update(&mut self) {
+        if !self.script.is_empty() {
+            self.leg_elapsed += 1;
+            self.step_script();
+        }
+        if let Some(shake) = &mut self.shake {
+            shake.frames_left = shake.frames_left.saturating_sub(1);
+            if shake.frames_left == 0 {
+                self.shake = None;
+            }
+        }
+    }
+
+    fn shake_offset(&self) -> (f32, f32) {
+        let Some(shake) = self.shake else {
+            return (0.0, 0.0);
+        };
+        // Two sines at unrelated rates wander without needing the RNG.
+        let t = shake.frames_left as f32;
+        let strength = self.shake_offset_bound();
+        ((t * 2.7).sin() * strength, (t * 3.9).cos() * strength)
+    }
+
+    // What to upload for this frame.
+    pub fn gpu_camera(&self, playfield: &Playfield) -> GPUCamera {
+        let rest = playfield.camera();
+        let size = [rest.screen_size[0] / self.zoom, rest.screen_size[1] / self.zoom];
+        let offset = self.shake_offset();
+        GPUCamera {
+            screen_pos: [
+                rest.screen_pos[0] + (rest.screen_size[0] - size[0]) / 2.0 + self.pan.0 + offset.0,
+                rest.screen_pos[1] + (rest.screen_size[1] - size[1]) / 2.0 + self.pan.1 + offset.1,
+            ],
+            screen_size: size,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn shake_dies_down_back_to_the_resting_view() {
+        let playfield = Playfield::default();
+        let rest = playfield.camera();
+        let mut camera = Camera::default();
+        camera.shake(8.0, 10);
+        camera.update();
+        let shaken = camera.gpu_camera(&playfield);
+        assert_ne!(shaken.screen_pos, rest.screen_pos);
+        assert!((shaken.screen_pos[0] - rest.screen_pos[0]).abs() <= 8.0);
+        assert_eq!(shaken.screen_size, rest.screen_size);
+
+        // A small knock doesn't replace the big one.
+        camera.shake(1.0, 30);
+        for _ in 0..9 {
+            camera.update();
+        }
+        assert_eq!(camera.gpu_camera(&playfield).screen_pos, rest.screen_pos);
+
+        camera.set_zoom(2.0);
+        let zoomed = camera.gpu_camera(&playfield);
+        assert_eq!(zoomed.screen_size, [rest.screen_size[0] / 2.0, rest.screen_size[1] / 2.0]);
+    }
+
+    #[test]
+    fn scripted_moves_pass_through_each_keyframe_and_come_to_rest() {
+        let playfield = Playfield::default();
+        let rest = playfield.camera();
+        let mut camera = Camera::default();
+        let boss = (rest.screen_pos[0] + 100.0, rest.screen_pos[1] + 600.0);
+        camera.play(&camera.boss_intro(&playfield, boss));
+        // Starts right on the boss.
+        let close = camera.gpu_camera(&playfield);
+        assert_eq!(close.screen_pos[0] + close.screen_size[0] / 2.0, boss.0);
+        assert_eq!(close.screen_pos[1] + close.screen_size[1] / 2.0, boss.1);
+        for _ in 0..30 {
+            camera.update();
+        }
+        assert_eq!(camera.gpu_camera(&playfield).screen_pos, close.screen_pos);
+        for _ in 0..25 {
+            camera.update();
+        }
+        assert!(camera.zoom > 1.0 && camera.zoom < CameraConfig::default().intro_zoom);
+        for _ in 0..25 {
+            camera.update();
+        }
+        assert_eq!(camera.gpu_camera(&playfield).screen_pos, rest.screen_pos);
+        assert!(camera.script.is_empty());
+    }
+
+    #[test]
+    fn screen_points_follow_the_view() {
+        let playfield = Playfield::default();
+        let mut camera = Camera::default();
+        let point = (300.0, 200.0);
+        assert_eq!(camera.screen_to_world(&playfield, point), point);
+
+        camera.set_position((50.0, -20.0));
+        camera.set_zoom(2.0);
+        // Panning right puts world further right under the middle of the screen,
+        // and zooming draws the world around it in from the edges.
+        let rest = playfield.camera();
+        let middle = (rest.screen_pos[0] + rest.screen_size[0] / 2.0, rest.screen_pos[1] + rest.screen_size[1] / 2.0);
+        assert_eq!(camera.screen_to_world(&playfield, middle), (middle.0 + 50.0, middle.1 - 20.0));
+        let world = camera.screen_to_world(&playfield, point);
+        assert_eq!(world.0 - middle.0 - 50.0, (point.0 - middle.0) / 2.0);
+    }
+}
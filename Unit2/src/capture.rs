@@ -0,0 +1,180 @@
+// Keeps the last ~10 seconds of gameplay as small frames so a near miss can be
+// saved as a GIF without any external capture software. Press F9 to save.
+// Native builds only: the web has no threads to encode on or filesystem to save to.
+use std::collections::VecDeque;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::mpsc::{channel, Receiver};
+use std::sync::Arc;
+use web_time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+
+use image::codecs::gif::{GifEncoder, Repeat};
+use image::{Delay, Frame, RgbaImage};
+
+use crate::render_target::RENDER_SIZE;
+
+const CLIP_LENGTH: Duration = Duration::from_secs(10);
+// Grab every Nth rendered frame, about 15 FPS on a 60 Hz display.
+const CAPTURE_EVERY: u32 = 4;
+// Captured frames are shrunk by this factor (1024x768 -> 256x192).
+const DOWNSCALE: u32 = 4;
+const BYTES_PER_ROW: u32 = RENDER_SIZE.0 * 4;
+
+struct CapturedFrame {
+    image: RgbaImage,
+    time: Instant,
+}
+
+// Where the staging buffer is in its copy -> map -> read cycle.
+enum Readback {
+    Idle,
+    Copied,
+    Mapping(Receiver<Result<(), wgpu::BufferAsyncError>>),
+}
+
+pub struct ClipRecorder {
+    staging: wgpu::Buffer,
+    readback: Readback,
+    frames: VecDeque<CapturedFrame>,
+    frame_count: u32,
+    // The surface format decides the byte order we get back.
+    bgra: bool,
+    // Set while a GIF is being written on a background thread.
+    saving: Arc<AtomicBool>,
+}
+
+impl ClipRecorder {
+    pub fn new(device: &wgpu::Device, format: wgpu::TextureFormat) -> Self {
+        let staging = device.create_buffer(&wgpu::BufferDescriptor {
+            label: Some("clip capture"),
+            size: (BYTES_PER_ROW * RENDER_SIZE.1) as u64,
+            usage: wgpu::BufferUsages::COPY_DST | wgpu::BufferUsages::MAP_READ,
+            mapped_at_creation: false,
+        });
+        Self {
+            staging,
+            readback: Readback::Idle,
+            frames: VecDeque::new(),
+            frame_count: 0,
+            bgra: matches!(
+                format,
+                wgpu::TextureFormat::Bgra8Unorm | wgpu::TextureFormat::Bgra8UnormSrgb
+            ),
+            saving: Arc::new(AtomicBool::new(false)),
+        }
+    }
+
+    // Records a copy of the finished frame, if it's time to grab one and the
+    // previous grab has been read back already.
+    pub fn capture(&mut self, encoder: &mut wgpu::CommandEncoder, target: &wgpu::Texture) {
+        self.frame_count += 1;
+        if !self.frame_count.is_multiple_of(CAPTURE_EVERY) || !matches!(self.readback, Readback::Idle) {
+            return;
+        }
+        encoder.copy_texture_to_buffer(
+            target.as_image_copy(),
+            wgpu::ImageCopyBuffer {
+                buffer: &self.staging,
+                layout: wgpu::ImageDataLayout {
+                    offset: 0,
+                    bytes_per_row: Some(BYTES_PER_ROW),
+                    rows_per_image: Some(RENDER_SIZE.1),
+                },
+            },
+            target.size(),
+        );
+        self.readback = Readback::Copied;
+    }
+
+    // Call once the encoder passed to `capture` has been submitted.
+    pub fn after_submit(&mut self) {
+        if let Readback::Copied = self.readback {
+            let (sender, receiver) = channel();
+            self.staging
+                .slice(..)
+                .map_async(wgpu::MapMode::Read, move |result| {
+                    let _ = sender.send(result);
+                });
+            self.readback = Readback::Mapping(receiver);
+        }
+    }
+
+    // Picks up a finished readback without waiting on the GPU. Call once per frame.
+    pub fn poll(&mut self, device: &wgpu::Device) {
+        let Readback::Mapping(receiver) = &self.readback else {
+            return;
+        };
+        device.poll(wgpu::Maintain::Poll);
+        let Ok(result) = receiver.try_recv() else {
+            return;
+        };
+        if result.is_ok() {
+            let image = self.downscale(&self.staging.slice(..).get_mapped_range());
+            self.staging.unmap();
+            let now = Instant::now();
+            self.frames.push_back(CapturedFrame { image, time: now });
+            while self
+                .frames
+                .front()
+                .is_some_and(|frame| now - frame.time > CLIP_LENGTH)
+            {
+                self.frames.pop_front();
+            }
+        }
+        self.readback = Readback::Idle;
+    }
+
+    // Nearest-neighbour shrink, which keeps pixel art crisp and costs next to nothing.
+    fn downscale(&self, bytes: &[u8]) -> RgbaImage {
+        RgbaImage::from_fn(RENDER_SIZE.0 / DOWNSCALE, RENDER_SIZE.1 / DOWNSCALE, |x, y| {
+            let i = ((y * DOWNSCALE * BYTES_PER_ROW) + (x * DOWNSCALE * 4)) as usize;
+            let px = &bytes[i..i + 4];
+            if self.bgra {
+                image::Rgba([px[2], px[1], px[0], 255])
+            } else {
+                image::Rgba([px[0], px[1], px[2], 255])
+            }
+        })
+    }
+
+    // Writes everything in the buffer to `clips/clip-<unix time>.gif`. Encoding is
+    // slow, so it happens on a background thread while the game keeps running.
+    pub fn save_gif(&self) {
+        if self.frames.len() < 2 || self.saving.swap(true, Ordering::SeqCst) {
+            return;
+        }
+        let frames: Vec<Frame> = self
+            .frames
+            .iter()
+            .zip(self.frames.iter().skip(1))
+            .map(|(frame, next)| {
+                Frame::from_parts(
+                    frame.image.clone(),
+                    0,
+                    0,
+                    Delay::from_saturating_duration(next.time - frame.time),
+                )
+            })
+            .collect();
+        let saving = self.saving.clone();
+        std::thread::spawn(move || {
+            let stamp = SystemTime::now()
+                .duration_since(UNIX_EPOCH)
+                .map(|d| d.as_secs())
+                .unwrap_or(0);
+            let path = format!("clips/clip-{}.gif", stamp);
+            let result = std::fs::create_dir_all("clips")
+                .and_then(|_| std::fs::File::create(&path))
+                .map_err(image::ImageError::from)
+                .and_then(|file| {
+                    let mut encoder = GifEncoder::new_with_speed(file, 10);
+                    encoder.set_repeat(Repeat::Infinite)?;
+                    encoder.encode_frames(frames)
+                });
+            match result {
+                Ok(()) => log::info!("Saved clip to {}", path),
+                Err(e) => log::error!("Couldn't save clip {}: {}", path, e),
+            }
+            saving.store(false, Ordering::SeqCst);
+        });
+    }
+}
@@ -0,0 +1,72 @@
+// The danmaku level's other special, picked in the trial room instead of the time
+// freeze. It's the fruit-catching idea from the first level played for score:
+// grazes fill a meter, and a full meter catches every enemy bullet on screen and
+// throws it back at the boss. Where they go depends on the shot type.
+use crate::render_target::LEFT_GAUGE_X;
+use crate::{atlas, GPUSprite, HealthBar, Layer, SpriteHandle, SpriteHolder};
+
+const METER_TINT: [f32; 4] = [1.6, 0.9, 0.3, 1.0];
+
+pub struct CatchReturn {
+    // 0.0 to 1.0; releases at 1.0.
+    pub charge: f32,
+    // How many times it has been released this level.
+    pub uses: u32,
+    meter: HealthBar,
+}
+
+impl CatchReturn {
+    // Starts empty: the first release has to be earned.
+    pub fn new(sprite_holder: &mut SpriteHolder) -> Self {
+        Self {
+            charge: 0.0,
+            uses: 0,
+            meter: HealthBar {
+                currval: 0.0,
+                maxval: 1.0,
+                body: crate::components::Body::new((LEFT_GAUGE_X, 64.0), (128.0, 16.0)),
+                units_per_pixel: 4.0,
+                sprite_border: GPUSprite {
+                    screen_region: [LEFT_GAUGE_X, 64.0, 128.0, 16.0],
+                    sheet_region: atlas::sheet_region("bar_border"),
+                },
+                sprite_index_border: sprite_holder.get_next_index_on(Layer::Ui),
+                sprite_bar: GPUSprite {
+                    screen_region: [LEFT_GAUGE_X, 68.0, 128.0, 8.0],
+                    sheet_region: atlas::sheet_region("player_bar_fill"),
+                },
+                sprite_index_bar: sprite_holder.get_next_index_on(Layer::Ui),
+                ghost: None,
+            },
+        }
+    }
+
+    pub fn add_charge(&mut self, amount: f32) {
+        self.charge = (self.charge + amount).min(1.0);
+    }
+
+    // True if the player asked for it with a full meter, which empties it.
+    pub fn try_release(&mut self, activate: bool) -> bool {
+        if !activate || self.charge < 1.0 {
+            return false;
+        }
+        self.charge = 0.0;
+        self.uses += 1;
+        true
+    }
+
+    pub fn sync_sprites(&mut self, sprite_holder: &mut SpriteHolder) {
+        self.meter.currval = self.charge;
+        self.meter.health_bar_loop(sprite_holder);
+        sprite_holder.set_tint(self.meter.sprite_index_bar, METER_TINT);
+    }
+
+    pub fn sprite_indices(&self) -> Vec<SpriteHandle> {
+        vec![self.meter.sprite_index_bar, self.meter.sprite_index_border]
+    }
+
+    pub fn remove(&self, sprite_holder: &mut SpriteHolder) {
+        sprite_holder.remove_sprite(self.meter.sprite_index_bar);
+        sprite_holder.remove_sprite(self.meter.sprite_index_border);
+    }
+}
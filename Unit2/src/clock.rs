@@ -0,0 +1,169 @@
+// Two timelines for the game loop. Real time counts every fixed step; gameplay
+// time only moves while the game is actually being played, so a pause overlay or
+// a hit-stop freezes the boss timer, AI cooldowns and animations together instead
+// of each of them having to know about it.
+use web_time::{Duration, Instant};
+
+// Logic steps at this rate whatever the display's refresh rate is. Rendering
+// happens once per redraw and just shows the latest step.
+pub const FRAMES_PER_SECOND: u64 = 60;
+const STEP: Duration = Duration::from_nanos(1_000_000_000 / FRAMES_PER_SECOND);
+// After a long stall (a dragged window, a breakpoint) the game slows down
+// instead of running this many steps back to back and dropping the rest.
+const MAX_STEPS_PER_REDRAW: u32 = 4;
+
+// Turns wall-clock time between redraws into a whole number of logic steps,
+// carrying the leftover over to the next redraw.
+pub struct FixedStep {
+    last: Instant,
+    accumulator: Duration,
+}
+
+impl FixedStep {
+    pub fn new() -> Self {
+        Self {
+            last: Instant::now(),
+            accumulator: Duration::ZERO,
+        }
+    }
+
+    // Call once per redraw.
+    pub fn steps_due(&mut self) -> u32 {
+        let now = Instant::now();
+        let elapsed = now - self.last;
+        self.last = now;
+        self.advance(elapsed)
+    }
+
+    // Starts counting from now, for when the loop has been stopped on purpose and
+    // the time away shouldn't be made up.
+    pub fn resume(&mut self) {
+        self.last = Instant::now();
+        self.accumulator = Duration::ZERO;
+    }
+
+    fn advance(&mut self, elapsed: Duration) -> u32 {
+        self.accumulator += elapsed;
+        let steps = (self.accumulator.as_nanos() / STEP.as_nanos()) as u32;
+        self.accumulator -= STEP * steps;
+        if steps > MAX_STEPS_PER_REDRAW {
+            self.accumulator = Duration::ZERO;
+            return MAX_STEPS_PER_REDRAW;
+        }
+        steps
+    }
+}
+
+// Redraws are at least this far apart in low-power mode: 30 a second, each
+// running two logic steps.
+const LOW_POWER_INTERVAL: Duration = Duration::from_nanos(1_000_000_000 / 30);
+
+// When to redraw. Normally that's as soon as the last frame is out, paced by
+// vsync. In low-power mode the event loop sleeps until the next redraw is due.
+pub struct FramePacer {
+    next_redraw: Instant,
+}
+
+impl FramePacer {
+    pub fn new() -> Self {
+        Self { next_redraw: Instant::now() }
+    }
+
+    // Call when a redraw starts. Keeps to the cadence, unless it's fallen more
+    // than a whole interval behind.
+    pub fn redrawn(&mut self, now: Instant) {
+        self.next_redraw = (self.next_redraw + LOW_POWER_INTERVAL).max(now);
+    }
+
+    pub fn next_redraw(&self) -> Instant {
+        self.next_redraw
+    }
+
+    pub fn due(&self, now: Instant) -> bool {
+        now >= self.next_redraw
+    }
+}
+
+#[derive(Debug, Default)]
+pub struct GameClock {
+    real_frames: u64,
+    game_frames: u64,
+    // Frames left in the current hit-stop.
+    hit_stop: u32,
+}
+
+impl GameClock {
+    // Call once per fixed step, whatever state the game is in.
+    pub fn tick_real(&mut self) {
+        self.real_frames += 1;
+    }
+
+    // Call once per frame from the gameplay loops, which don't run at all while
+    // something is pushed on top of them. Returns true if gameplay should step.
+    pub fn tick_game(&mut self) -> bool {
+        if self.hit_stop > 0 {
+            self.hit_stop -= 1;
+            return false;
+        }
+        self.game_frames += 1;
+        true
+    }
+
+    pub fn real_frames(&self) -> u64 {
+        self.real_frames
+    }
+
+    pub fn game_frames(&self) -> u64 {
+        self.game_frames
+    }
+
+    // Freezes gameplay for a few frames. Overlapping hit-stops don't stack.
+    pub fn hit_stop(&mut self, frames: u32) {
+        self.hit_stop = self.hit_stop.max(frames);
+    }
+
+    // Back to a clean slate for a new run. Real time keeps going.
+    pub fn reset_game(&mut self) {
+        self.game_frames = 0;
+        self.hit_stop = 0;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn fast_and_slow_displays_step_at_the_same_rate() {
+        let second = |refresh_rate: u32| {
+            let mut fixed_step = FixedStep::new();
+            (0..refresh_rate)
+                .map(|_| fixed_step.advance(Duration::from_secs(1) / refresh_rate))
+                .sum::<u32>()
+        };
+        for refresh_rate in [30, 60, 144, 240] {
+            let steps = second(refresh_rate);
+            assert!((FRAMES_PER_SECOND as u32 - 1..=FRAMES_PER_SECOND as u32).contains(&steps), "{} Hz ran {} steps", refresh_rate, steps);
+        }
+    }
+
+    #[test]
+    fn a_long_stall_does_not_spiral() {
+        let mut fixed_step = FixedStep::new();
+        assert_eq!(fixed_step.advance(Duration::from_secs(2)), MAX_STEPS_PER_REDRAW);
+        assert_eq!(fixed_step.advance(Duration::ZERO), 0);
+    }
+
+    #[test]
+    fn a_late_redraw_resets_the_cadence_instead_of_bunching_up() {
+        let mut pacer = FramePacer::new();
+        let start = pacer.next_redraw();
+        pacer.redrawn(start);
+        assert!(!pacer.due(start));
+        assert_eq!(pacer.next_redraw(), start + LOW_POWER_INTERVAL);
+        let late = start + LOW_POWER_INTERVAL * 5;
+        pacer.redrawn(late);
+        pacer.redrawn(late);
+        assert_eq!(pacer.next_redraw(), late + LOW_POWER_INTERVAL);
+    }
+}
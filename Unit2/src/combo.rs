@@ -0,0 +1,106 @@
+// The score multiplier for keeping a chain going. The player's chain counts
+// catches, grazes and hits in a row; every new link restarts a short timer, and
+// the chain drops if the timer runs out before the next one. Taking a hit drops
+// it straight away. While the multiplier is up it's shown over the player's head.
+use crate::text::TextLabel;
+use crate::{Layer, Player, SpriteHandle, SpriteHolder};
+
+// Long enough to bridge the gap between level 1's fruit.
+const CHAIN_WINDOW: u32 = 150;
+// Links per step of the multiplier.
+const CHAIN_STEP: u32 = 10;
+const MAX_MULTIPLIER: u64 = 4;
+const SCALE: f32 = 2.0;
+const LABEL_GAP: f32 = 8.0;
+const TINT: [f32; 4] = [1.6, 1.3, 0.3, 1.0];
+// Fades out over the chain's last second.
+const FADE_FRAMES: u32 = 60;
+
+pub fn multiplier(chain: u32) -> u64 {
+    (1 + (chain / CHAIN_STEP) as u64).min(MAX_MULTIPLIER)
+}
+
+#[derive(Debug, Default)]
+pub struct Combo {
+    // Until the chain drops, if nothing adds to it.
+    frames_left: u32,
+    // The chain as of last frame, to tell when it's grown.
+    last_chain: u32,
+    // Only there while the multiplier is above 1.
+    label: Option<(u64, TextLabel)>,
+}
+
+impl Combo {
+    // Call once a frame, after everything that can add to or break the chain.
+    pub fn update(&mut self, chain: &mut u32) {
+        if *chain > self.last_chain {
+            self.frames_left = CHAIN_WINDOW;
+        } else if *chain > 0 {
+            self.frames_left = self.frames_left.saturating_sub(1);
+            if self.frames_left == 0 {
+                *chain = 0;
+            }
+        }
+        self.last_chain = *chain;
+    }
+
+    pub fn frames_left(&self) -> u32 {
+        self.frames_left
+    }
+
+    pub fn sync_sprites(&mut self, player: &Player, sprite_holder: &mut SpriteHolder) {
+        let shown = multiplier(player.chain);
+        if self.label.as_ref().map(|(value, _)| *value) != Some(shown) {
+            if let Some((_, label)) = self.label.take() {
+                label.remove(sprite_holder);
+            }
+            if shown > 1 {
+                // It follows the player, so it's in the world rather than on the UI.
+                let label = TextLabel::new_on(&format!("X{}", shown), Layer::Labels, sprite_holder);
+                self.label = Some((shown, label));
+            }
+        }
+        if let Some((_, label)) = &self.label {
+            let [r, g, b, _] = TINT;
+            let alpha = (self.frames_left as f32 / FADE_FRAMES as f32).min(1.0);
+            let x = player.body.center().0 - label.width(SCALE) / 2.0;
+            let y = player.body.pos.1 + player.body.size.1 + LABEL_GAP;
+            label.draw((x, y), SCALE, [r, g, b, alpha], sprite_holder);
+        }
+    }
+
+    pub fn sprite_indices(&self) -> Vec<SpriteHandle> {
+        self.label.as_ref().map_or(vec![], |(_, label)| label.sprite_indices())
+    }
+
+    pub fn clear(&mut self, sprite_holder: &mut SpriteHolder) {
+        if let Some((_, label)) = self.label.take() {
+            label.remove(sprite_holder);
+        }
+        self.frames_left = 0;
+        self.last_chain = 0;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn the_chain_drops_once_its_timer_runs_out() {
+        let mut combo = Combo::default();
+        let mut chain = 0;
+        for _ in 0..25 {
+            chain += 1;
+            combo.update(&mut chain);
+        }
+        assert_eq!(multiplier(chain), 3);
+        for _ in 1..CHAIN_WINDOW {
+            combo.update(&mut chain);
+        }
+        assert_eq!(chain, 25);
+        combo.update(&mut chain);
+        assert_eq!((chain, multiplier(chain)), (0, 1));
+        assert_eq!(multiplier(1000), MAX_MULTIPLIER);
+    }
+}
@@ -0,0 +1,58 @@
+// The parts game objects have in common, and the systems that work on those parts
+// alone. An object kind owns the components it needs instead of its own copies of
+// the same fields, so moving, colliding and drawing it doesn't need code of its
+// own, and a new kind of object is mostly a new combination of components. There's
+// no central storage: each kind keeps its components as fields, and the systems
+// take them by reference.
+use crate::{GPUSprite, SpriteHandle, SpriteHolder};
+
+// Where something is and how much room it takes up, from its bottom-left corner.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Body {
+    pub pos: (f32, f32),
+    pub size: (f32, f32),
+}
+
+impl Body {
+    pub const fn new(pos: (f32, f32), size: (f32, f32)) -> Self {
+        Self { pos, size }
+    }
+
+    pub fn center(&self) -> (f32, f32) {
+        (self.pos.0 + self.size.0 / 2.0, self.pos.1 + self.size.1 / 2.0)
+    }
+
+    pub fn screen_region(&self) -> [f32; 4] {
+        [self.pos.0, self.pos.1, self.size.0, self.size.1]
+    }
+}
+
+// How far something moves each frame. Projectiles keep a speed and a direction
+// instead, so either can change on its own, and hand `advance` the product.
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub struct Velocity(pub f32, pub f32);
+
+impl From<(f32, f32)> for Velocity {
+    fn from(velocity: (f32, f32)) -> Self {
+        Self(velocity.0, velocity.1)
+    }
+}
+
+// Movement: one frame at `velocity`.
+pub fn advance(body: &mut Body, velocity: Velocity) {
+    body.pos = (body.pos.0 + velocity.0, body.pos.1 + velocity.1);
+}
+
+// Collision: bounding boxes overlap. Touching edges count.
+pub fn touching(a: &Body, b: &Body) -> bool {
+    a.pos.1 <= b.pos.1 + b.size.1
+        && a.pos.1 + a.size.1 >= b.pos.1
+        && a.pos.0 <= b.pos.0 + b.size.0
+        && a.pos.0 + a.size.0 >= b.pos.0
+}
+
+// Sprite sync: puts the sprite where the body is and hands it to the renderer.
+pub fn sync_sprite(body: &Body, sprite: &mut GPUSprite, sprite_index: SpriteHandle, sprite_holder: &mut SpriteHolder) {
+    sprite.screen_region = body.screen_region();
+    sprite_holder.set_sprite(sprite_index, *sprite);
+}
@@ -0,0 +1,139 @@
+// Settings for players to edit by hand, read once from `config.toml` at startup.
+// Anything missing keeps its default, and a file that doesn't parse is ignored
+// with a warning. The game never writes it; the in-game toggles live in
+// options.txt. For example:
+//
+//     [window]
+//     size = [1600, 1200]
+//     fullscreen = false
+//
+//     [audio]
+//     master_volume = 0.8
+//
+//     [camera]
+//     shake = 0.5
+//     intro_zoom = 1.0
+//
+//     [controls]
+//     left = ["Left", "Q"]
+//     ability = ["C", "Mouse4"]
+use std::collections::BTreeMap;
+
+use serde::Deserialize;
+
+use crate::input::{Binding, Control};
+
+const CONFIG_PATH: &str = "config.toml";
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(default)]
+pub struct WindowConfig {
+    // Physical pixels. Without it the window scale from options.txt decides.
+    pub size: Option<(u32, u32)>,
+    // Borderless, on whichever monitor the window opens on.
+    pub fullscreen: bool,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(default)]
+pub struct AudioConfig {
+    // 0 is silent, 1 is full volume.
+    pub master_volume: f64,
+}
+
+impl Default for AudioConfig {
+    fn default() -> Self {
+        Self { master_volume: 1.0 }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Deserialize)]
+#[serde(default)]
+pub struct CameraConfig {
+    // Scales every screen shake. 0 turns shaking off.
+    pub shake: f32,
+    // How close the boss intro starts on the boss. 1 leaves the view where it is.
+    pub intro_zoom: f32,
+}
+
+impl Default for CameraConfig {
+    fn default() -> Self {
+        Self {
+            shake: 1.0,
+            intro_zoom: 1.6,
+        }
+    }
+}
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(default)]
+pub struct Config {
+    pub window: WindowConfig,
+    pub audio: AudioConfig,
+    pub camera: CameraConfig,
+    // Keys that replace a control's usual ones, by control name. Keys are named
+    // the way the pause menu lists them, plus Mouse4 and Mouse5 for the thumb
+    // buttons. Replaced controls aren't affected by "keys by position".
+    pub controls: BTreeMap<String, Vec<String>>,
+}
+
+impl Config {
+    pub fn load() -> Self {
+        let Ok(text) = std::fs::read_to_string(CONFIG_PATH) else {
+            return Self::default();
+        };
+        match toml::from_str(&text) {
+            Ok(config) => {
+                log::info!("Loaded {}", CONFIG_PATH);
+                config
+            }
+            Err(e) => {
+                log::warn!("Couldn't read {}, using the defaults: {}", CONFIG_PATH, e);
+                Self::default()
+            }
+        }
+    }
+
+    // The controls the file rebinds. Unknown controls and keys are skipped.
+    pub fn control_bindings(&self) -> Vec<(Control, Vec<Binding>)> {
+        let mut rebound = vec![];
+        for (name, keys) in self.controls.iter() {
+            let Some(control) = Control::ALL.into_iter().find(|control| control.config_name() == name) else {
+                log::warn!("Ignoring unknown control in {}: {}", CONFIG_PATH, name);
+                continue;
+            };
+            let bindings: Vec<Binding> = keys
+                .iter()
+                .filter_map(|key| {
+                    let binding = Binding::from_name(key);
+                    if binding.is_none() && key.starts_with("pos:") {
+                        log::warn!("Ignoring {} for {} in {}: only letters and digits can be bound by position", key, name, CONFIG_PATH);
+                    } else if binding.is_none() {
+                        log::warn!("Ignoring unknown key for {} in {}: {}", name, CONFIG_PATH, key);
+                    }
+                    binding
+                })
+                .collect();
+            if !bindings.is_empty() {
+                rebound.push((control, bindings));
+            }
+        }
+        rebound
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::input::Key;
+
+    #[test]
+    fn missing_sections_keep_their_defaults_and_bad_keys_are_skipped() {
+        let config: Config = toml::from_str("[window]\nfullscreen = true\n\n[controls]\nleft = [\"Q\", \"Nope\"]\njump = [\"Z\"]\n").unwrap();
+        assert!(config.window.fullscreen);
+        assert_eq!(config.window.size, None);
+        assert_eq!(config.audio.master_volume, 1.0);
+        assert_eq!(config.camera, CameraConfig::default());
+        assert_eq!(config.control_bindings(), [(Control::Left, vec![Binding::Key(Key::Q)])]);
+    }
+}
@@ -0,0 +1,124 @@
+// What got the player, for the death screen: the pattern that landed the last
+// hit and how far into the fight it happened. It's taken from the sim on the
+// frame the run ends, before the level is unloaded, and shown once the death
+// card is up.
+use crate::clock::FRAMES_PER_SECOND;
+use crate::projectile_tags::{Pattern, ProjectileTag};
+use crate::simulation::SimState;
+use crate::text::TextLabel;
+use crate::{SpriteHandle, SpriteHolder};
+
+const SCALE: f32 = 3.0;
+// Above the score graph, a line apart.
+const TOP_Y: f32 = 336.0;
+const LINE_GAP: f32 = 36.0;
+const SCREEN_WIDTH: f32 = 1024.0;
+const TINT: [f32; 4] = [1.4, 0.6, 0.5, 1.0];
+
+// The hit that took the player's last health.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum KilledBy {
+    Bullet(ProjectileTag),
+    // Level 1's fruit hurt by landing, not by touching the player.
+    DroppedFruit,
+}
+
+// M:SS
+fn format_time(frames: u64) -> String {
+    let seconds = frames / FRAMES_PER_SECOND;
+    format!("{}:{:02}", seconds / 60, seconds % 60)
+}
+
+fn report_lines(sim: &SimState) -> Vec<String> {
+    let Some(killed_by) = sim.killed_by else {
+        return vec![];
+    };
+    // Old phases' bullets are cancelled as the boss moves on, so whatever hit the
+    // player came from the phase it's in now.
+    let name = match killed_by {
+        KilledBy::DroppedFruit => "A DROPPED FRUIT",
+        KilledBy::Bullet(tag) => match tag.pattern {
+            Pattern::Boss => sim.enemy.ai.phase_name(),
+            Pattern::MidBoss => sim
+                .midboss
+                .as_ref()
+                .and_then(|midboss| midboss.entity.as_ref())
+                .map_or("THE MIDBOSS", |entity| entity.ai.phase_name()),
+            Pattern::Minion => "MINION FIRE",
+            Pattern::None => "A STRAY SHOT",
+        },
+    };
+    let mut lines = vec![
+        format!("KILLED BY {}", name),
+        format!("PHASE {} AT {}", sim.phase + 1, format_time(sim.frame)),
+    ];
+    if let Some(frames) = sim.enemy.ai.phase_frames_left() {
+        lines.push(format!("{} SECONDS LEFT IN THE PHASE", frames as u64 / FRAMES_PER_SECOND));
+    } else if let Some(survival_timer) = &sim.survival_timer {
+        lines.push(format!("{} SECONDS LEFT TO SURVIVE", survival_timer.frames_left() as u64 / FRAMES_PER_SECOND));
+    } else {
+        let boss_bar = &sim.enemy.enemy.health_bar;
+        lines.push(format!("BOSS AT {}%", (boss_bar.currval / boss_bar.maxval * 100.0).ceil().max(0.0)));
+    }
+    lines
+}
+
+#[derive(Default)]
+pub struct DeathReport {
+    // The boss phase the run ended in, for retrying from it.
+    phase: usize,
+    lines: Vec<String>,
+    labels: Vec<TextLabel>,
+}
+
+impl DeathReport {
+    // Call on the frame the run ends, while the level is still loaded.
+    pub fn record(&mut self, sim: &SimState) {
+        self.phase = sim.phase;
+        self.lines = report_lines(sim);
+    }
+
+    pub fn phase(&self) -> usize {
+        self.phase
+    }
+
+    pub fn show(&mut self, sprite_holder: &mut SpriteHolder) {
+        self.clear(sprite_holder);
+        for (i, line) in self.lines.iter().enumerate() {
+            let label = TextLabel::new(line, sprite_holder);
+            let x = (SCREEN_WIDTH - label.width(SCALE)) / 2.0;
+            label.draw((x, TOP_Y - i as f32 * LINE_GAP), SCALE, TINT, sprite_holder);
+            self.labels.push(label);
+        }
+    }
+
+    pub fn sprite_indices(&self) -> Vec<SpriteHandle> {
+        self.labels.iter().flat_map(TextLabel::sprite_indices).collect()
+    }
+
+    pub fn clear(&mut self, sprite_holder: &mut SpriteHolder) {
+        for label in self.labels.drain(..) {
+            label.remove(sprite_holder);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::fixtures;
+    use crate::simulation::{simulate, FrameInput};
+
+    #[test]
+    fn the_boss_pattern_that_lands_the_last_hit_is_named() {
+        let mut state = fixtures::loaded(crate::load_level_6);
+        state.player_health_bar.currval = 1.0;
+        fixtures::bullet_above_player(&mut state, 40.0);
+        state.projectiles[0].tag = ProjectileTag::new(Pattern::Boss, 0);
+        state = simulate(state, FrameInput::default());
+        assert_eq!(
+            report_lines(&state),
+            ["KILLED BY Swaying Stream", "PHASE 1 AT 0:00", "10 SECONDS LEFT IN THE PHASE"]
+        );
+    }
+}
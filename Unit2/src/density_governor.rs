@@ -0,0 +1,95 @@
+// A fairness net for bullet walls. Each frame it counts the enemy bullets within
+// `radius` of the player and, past `max_bullets`, pops the newest of them until
+// it's back down to the limit, so there's always a gap to find. A level can turn
+// it on for patterns that stack up worse than intended, and the thin-walls
+// modifier turns it on anywhere at ASSIST's settings.
+use serde::Deserialize;
+
+use crate::{Player, Projectile};
+
+#[derive(Debug, Clone, Copy, PartialEq, Deserialize)]
+pub struct DensityGovernor {
+    pub radius: f32,
+    pub max_bullets: usize,
+}
+
+impl DensityGovernor {
+    pub const ASSIST: Self = Self {
+        radius: 160.0,
+        max_bullets: 12,
+    };
+
+    // Whichever of the two thins more: the wider radius and the lower limit.
+    pub fn tightest(self, other: Self) -> Self {
+        Self {
+            radius: self.radius.max(other.radius),
+            max_bullets: self.max_bullets.min(other.max_bullets),
+        }
+    }
+
+    // Kills the bullets over the limit and returns where they were.
+    pub fn thin(&self, projectiles: &mut [Projectile], player: &Player) -> Vec<(f32, f32)> {
+        let center = player.center();
+        let mut crowding: Vec<usize> = (0..projectiles.len())
+            .filter(|&i| {
+                let proj = &projectiles[i];
+                let (x, y) = proj.body.center();
+                !proj.is_dead && !proj.player_spawned && (x - center.0).hypot(y - center.1) <= self.radius
+            })
+            .collect();
+        let excess = crowding.len().saturating_sub(self.max_bullets);
+        // Youngest first; among bullets fired together, the later ones in the list.
+        crowding.sort_by_key(|&i| (projectiles[i].age, std::cmp::Reverse(i)));
+        crowding
+            .into_iter()
+            .take(excess)
+            .map(|i| {
+                projectiles[i].kill();
+                projectiles[i].body.center()
+            })
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{fixtures, level, make_projectile, SpriteHandle};
+
+    #[test]
+    fn only_the_newest_bullets_near_the_player_are_thinned() {
+        let (player, ..) = level::between_stages();
+        let (x, y) = player.center();
+        let mut projectiles = vec![];
+        // Five around the player and one far off, then the first two age a frame.
+        for dx in [-80.0, -40.0, 0.0, 40.0, 80.0, 600.0] {
+            make_projectile(&mut projectiles, SpriteHandle::NONE, (x + dx - 32.0, y + 60.0), (0.0, -4.0));
+        }
+        projectiles[0].age = 1;
+        projectiles[1].age = 1;
+        let governor = DensityGovernor {
+            radius: 160.0,
+            max_bullets: 2,
+        };
+        let thinned = governor.thin(&mut projectiles, &player);
+        assert_eq!(thinned.len(), 3);
+        let dead: Vec<bool> = projectiles.iter().map(|proj| proj.is_dead).collect();
+        assert_eq!(dead, [false, false, true, true, true, false]);
+        assert!(governor.thin(&mut projectiles, &player).is_empty());
+    }
+
+    #[test]
+    fn thin_walls_tightens_level_6_and_any_governor_it_brings() {
+        let mut sim = fixtures::loaded_with(crate::load_level_6, |sim| sim.modifiers.thin_walls = true);
+        assert_eq!(sim.density_governor, Some(DensityGovernor::ASSIST));
+
+        crate::unload_level(&mut sim);
+        let mut def = level::load("level6");
+        def.density_governor = Some(DensityGovernor {
+            radius: 128.0,
+            max_bullets: 18,
+        });
+        level::start(&mut sim, &def);
+        assert_eq!(sim.density_governor, Some(DensityGovernor::ASSIST));
+    }
+}
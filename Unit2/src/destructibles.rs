@@ -0,0 +1,93 @@
+// Breakable cover. Each block soaks up enemy bullets until its health runs out,
+// then plays a short break animation and is cleaned up like a dead projectile.
+use crate::components::{self, Body};
+use crate::{atlas, GPUSprite, Projectile, SpriteHandle, SpriteHolder};
+
+// Sandy brown at full health, darkening as it takes hits.
+const BLOCK_TINT: [f32; 3] = [1.4, 1.0, 0.6];
+const DAMAGED_BRIGHTNESS: f32 = 0.45;
+const BREAK_FRAMES: u32 = 20;
+// How much bigger the block gets as it bursts apart.
+const BREAK_GROWTH: f32 = 0.5;
+
+#[derive(Debug, Clone)]
+pub struct DestructibleBlock {
+    pub body: Body,
+    pub health: u32,
+    max_health: u32,
+    // Frames into the break animation, once health has run out.
+    breaking: Option<u32>,
+    pub is_dead: bool,
+    sprite_index: SpriteHandle,
+}
+
+impl DestructibleBlock {
+    pub fn new(pos: (f32, f32), size: (f32, f32), health: u32, sprite_holder: &mut SpriteHolder) -> Self {
+        Self {
+            body: Body::new(pos, size),
+            health,
+            max_health: health,
+            breaking: None,
+            is_dead: false,
+            sprite_index: sprite_holder.get_next_index(),
+        }
+    }
+
+    // Player shots fly straight through; enemy bullets are stopped while the block stands.
+    pub fn absorbs(&self, proj: &Projectile) -> bool {
+        self.breaking.is_none()
+            && !proj.player_spawned
+            && components::touching(&proj.body, &self.body)
+    }
+
+    pub fn hit(&mut self) {
+        self.health = self.health.saturating_sub(1);
+        if self.health == 0 && self.breaking.is_none() {
+            self.breaking = Some(0);
+        }
+    }
+
+    pub fn update(&mut self, sprite_holder: &mut SpriteHolder) {
+        let mut region = self.body.screen_region();
+        let brightness = DAMAGED_BRIGHTNESS
+            + (1.0 - DAMAGED_BRIGHTNESS) * self.health as f32 / self.max_health as f32;
+        let mut alpha = 1.0;
+
+        if let Some(frame) = &mut self.breaking {
+            *frame += 1;
+            let t = *frame as f32 / BREAK_FRAMES as f32;
+            let (pos, size) = (self.body.pos, self.body.size);
+            let grow = (size.0 * BREAK_GROWTH * t / 2.0, size.1 * BREAK_GROWTH * t / 2.0);
+            region = [pos.0 - grow.0, pos.1 - grow.1, size.0 + 2.0 * grow.0, size.1 + 2.0 * grow.1];
+            alpha = 1.0 - t;
+            if *frame >= BREAK_FRAMES {
+                self.is_dead = true;
+            }
+        }
+
+        sprite_holder.set_sprite(
+            self.sprite_index,
+            GPUSprite {
+                screen_region: region,
+                sheet_region: atlas::sheet_region("block"),
+            },
+        );
+        sprite_holder.set_tint(
+            self.sprite_index,
+            [
+                BLOCK_TINT[0] * brightness,
+                BLOCK_TINT[1] * brightness,
+                BLOCK_TINT[2] * brightness,
+                alpha,
+            ],
+        );
+    }
+
+    pub fn sprite_indices(&self) -> Vec<SpriteHandle> {
+        vec![self.sprite_index]
+    }
+
+    pub fn clean_dead(&self, sprite_holder: &mut SpriteHolder) {
+        sprite_holder.remove_sprite(self.sprite_index);
+    }
+}
@@ -0,0 +1,114 @@
+// Yes/No confirmation box for anything that can't be undone. While one is open
+// it takes over the frame loop, so whatever is underneath stays frozen until the
+// player answers. Left/Right picks, Enter confirms, Escape backs out. Space is
+// left alone since it's held down to shoot and would carry over into whatever
+// screen comes next. "No" is selected to start with, so the safe answer is the default.
+use crate::generated_sprites::SOLID_CELL;
+use crate::input::{Input, Key};
+use crate::text::TextLabel;
+use crate::{GPUSprite, Layer, SpriteHandle, SpriteHolder, SPRITE_SHEET_RESOLUTION};
+
+const BOX: (f32, f32, f32, f32) = (232.0, 300.0, 560.0, 168.0);
+const BACKING_TINT: [f32; 4] = [0.05, 0.05, 0.1, 0.85];
+const QUESTION_SCALE: f32 = 4.0;
+const CHOICE_SCALE: f32 = 3.0;
+const SELECTED_TINT: [f32; 4] = [1.6, 1.3, 0.3, 1.0];
+const UNSELECTED_TINT: [f32; 4] = [0.5, 0.5, 0.5, 1.0];
+
+// What happens if the player says yes.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DialogAction {
+    QuitRun,
+    ResetSave,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DialogResult {
+    Open,
+    Confirmed(DialogAction),
+    Cancelled,
+}
+
+pub struct ConfirmDialog {
+    action: DialogAction,
+    yes_selected: bool,
+    backing_index: SpriteHandle,
+    question: TextLabel,
+    yes: TextLabel,
+    no: TextLabel,
+}
+
+impl ConfirmDialog {
+    pub fn new(question: &str, action: DialogAction, sprite_holder: &mut SpriteHolder) -> Self {
+        let backing_index = sprite_holder.get_next_index_on(Layer::Ui);
+        sprite_holder.set_sprite(
+            backing_index,
+            GPUSprite {
+                screen_region: [BOX.0, BOX.1, BOX.2, BOX.3],
+                sheet_region: [
+                    SOLID_CELL.0 / SPRITE_SHEET_RESOLUTION.0,
+                    SOLID_CELL.1 / SPRITE_SHEET_RESOLUTION.1,
+                    1.0 / SPRITE_SHEET_RESOLUTION.0,
+                    1.0 / SPRITE_SHEET_RESOLUTION.1,
+                ],
+            },
+        );
+        sprite_holder.set_tint(backing_index, BACKING_TINT);
+        Self {
+            action,
+            yes_selected: false,
+            backing_index,
+            question: TextLabel::new(question, sprite_holder),
+            yes: TextLabel::new("YES", sprite_holder),
+            no: TextLabel::new("NO", sprite_holder),
+        }
+    }
+
+    // Handles this frame's keys. Once this returns anything but `Open`, the
+    // caller should remove the dialog.
+    pub fn handle_input(&mut self, input: &Input) -> DialogResult {
+        if input.is_key_pressed(Key::Left) {
+            self.yes_selected = true;
+        }
+        if input.is_key_pressed(Key::Right) {
+            self.yes_selected = false;
+        }
+        if input.is_key_pressed(Key::Escape) {
+            return DialogResult::Cancelled;
+        }
+        if input.is_key_pressed(Key::Return) {
+            return match self.yes_selected {
+                true => DialogResult::Confirmed(self.action),
+                false => DialogResult::Cancelled,
+            };
+        }
+        DialogResult::Open
+    }
+
+    pub fn draw(&self, sprite_holder: &mut SpriteHolder) {
+        let center = BOX.0 + BOX.2 / 2.0;
+        let question_x = center - self.question.width(QUESTION_SCALE) / 2.0;
+        self.question.draw((question_x, BOX.1 + 96.0), QUESTION_SCALE, crate::TINT_NONE, sprite_holder);
+        let (yes_tint, no_tint) = match self.yes_selected {
+            true => (SELECTED_TINT, UNSELECTED_TINT),
+            false => (UNSELECTED_TINT, SELECTED_TINT),
+        };
+        self.yes.draw((center - 120.0, BOX.1 + 32.0), CHOICE_SCALE, yes_tint, sprite_holder);
+        self.no.draw((center + 72.0, BOX.1 + 32.0), CHOICE_SCALE, no_tint, sprite_holder);
+    }
+
+    pub fn sprite_indices(&self) -> Vec<SpriteHandle> {
+        let mut indices = vec![self.backing_index];
+        indices.extend(self.question.sprite_indices());
+        indices.extend(self.yes.sprite_indices());
+        indices.extend(self.no.sprite_indices());
+        indices
+    }
+
+    pub fn remove(self, sprite_holder: &mut SpriteHolder) {
+        sprite_holder.remove_sprite(self.backing_index);
+        self.question.remove(sprite_holder);
+        self.yes.remove(sprite_holder);
+        self.no.remove(sprite_holder);
+    }
+}
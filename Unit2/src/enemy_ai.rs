@@ -1,12 +1,88 @@
+use crate::projectile_behavior::ProjectileBehavior;
+use crate::spawn_queue::SpawnQueue;
+use crate::telegraphs::Telegraph;
 use crate::Enemy;
-use rand::{thread_rng, Rng};
+use rand::rngs::StdRng;
+use rand::Rng;
 use std::f32::consts::PI;
 
-use super::Projectile;
-use super::SpriteHolder;
-
 pub trait AI {
-    fn ai_loop(&mut self, projectiles: &mut Vec<Projectile>, sprite_holder: &mut SpriteHolder, enemy: &Enemy);
+    // `player` is the player's center, for patterns that aim.
+    fn ai_loop(&mut self, spawns: &mut SpawnQueue, enemy: &Enemy, player: (f32, f32), rng: &mut StdRng);
+
+    // How the enemy should move, set after every ai_loop. None leaves its
+    // velocity alone.
+    fn velocity(&self) -> Option<(f32, f32)> {
+        None
+    }
+
+    // Called instead of ai_loop on frames the enemy is too far off-screen to be
+    // worth thinking about. Timers should keep counting so it comes back in step,
+    // but nothing gets fired.
+    fn idle_loop(&mut self) {}
+
+    // Which attack phase the AI is in. Counts up as phases are cleared.
+    fn phase(&self) -> usize {
+        0
+    }
+
+    // Display name of the current phase, for overlays and results screens.
+    fn phase_name(&self) -> &'static str {
+        ""
+    }
+
+    // Frames until the current phase ends on its own, for phases on a clock.
+    fn phase_frames_left(&self) -> Option<usize> {
+        None
+    }
+
+    // Puts a pooled enemy's AI back the way it was when new, so it can go on
+    // another enemy. False if it can't be, and the enemy needs a new one.
+    fn reset(&mut self) -> bool {
+        false
+    }
+
+    // Jumps straight to the start of a later phase, for retrying from it. Returns
+    // how many frames that skipped, so clocks running alongside can keep up.
+    fn skip_to_phase(&mut self, _phase: usize) -> usize {
+        0
+    }
+
+    // Minions this AI wants spawned. The simulation drains this after every ai_loop.
+    fn take_summons(&mut self) -> Vec<Summon> {
+        vec![]
+    }
+
+    // Warnings to draw ahead of attacks. Drained after every ai_loop, like summons.
+    fn take_telegraphs(&mut self) -> Vec<Telegraph> {
+        vec![]
+    }
+
+    // The hooks below let a pattern react to what happens to its enemy without
+    // the simulation knowing about it. Summons and telegraphs queued from them are
+    // collected with the next ai_loop's.
+
+    // Called once, just before the first ai_loop.
+    fn on_spawn(&mut self, _spawns: &mut SpawnQueue, _enemy: &Enemy, _rng: &mut StdRng) {}
+
+    // Called at the end of any frame the enemy was hit, with the total damage taken.
+    fn on_damage(&mut self, _amount: f32, _enemy: &Enemy) {}
+
+    // Called once, on the frame the enemy's health runs out. Bullets fired from
+    // here still go out, so an enemy can leave a last pattern behind.
+    fn on_death(&mut self, _spawns: &mut SpawnQueue, _enemy: &Enemy, _rng: &mut StdRng) {}
+}
+
+// How long before an attack its telegraph goes up.
+pub const TELEGRAPH_FRAMES: usize = 60;
+
+// A minion to spawn: top-left corner and the velocity it drifts at.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Summon {
+    pub pos: (f32, f32),
+    pub velocity: (f32, f32),
+    // Tougher, and recoloured so the player can tell.
+    pub strong: bool,
 }
 
 pub struct Level0AI {
@@ -14,7 +90,7 @@ pub struct Level0AI {
 }
 
 impl AI for Level0AI {
-    fn ai_loop(&mut self, _projectiles: &mut Vec<Projectile>, _sprite_holder: &mut SpriteHolder, _enemy: &Enemy) {
+    fn ai_loop(&mut self, _spawns: &mut SpawnQueue, _enemy: &Enemy, _player: (f32, f32), _rng: &mut StdRng) {
         // Do nothing, used for Empty AI
     }
 }
@@ -25,53 +101,307 @@ pub struct Level1AI {
 }
 
 impl AI for Level1AI {
-    fn ai_loop(&mut self, projectiles: &mut Vec<Projectile>, sprite_holder: &mut SpriteHolder, enemy: &Enemy) {
+    fn ai_loop(&mut self, spawns: &mut SpawnQueue, enemy: &Enemy, _player: (f32, f32), rng: &mut StdRng) {
         if self.cooldown > 0 {
             self.cooldown -= 1;
         }
         else {
             self.cooldown = self.max_cooldown;
-            let angle: f32 = thread_rng().gen_range((11.0 * PI / 8.0)..=(13.0 * PI / 8.0));
+            let angle: f32 = rng.gen_range((11.0 * PI / 8.0)..=(13.0 * PI / 8.0));
             let velocity = (angle.cos() * 6.0, angle.sin() * 6.0);
-            enemy.spawn_new_projectile(projectiles, sprite_holder, velocity);
+            enemy.spawn_new_projectile(spawns, fruit_jitter(rng), velocity);
         }
     }
+
+    fn phase_name(&self) -> &'static str {
+        "Fruit Rain"
+    }
 }
 
 pub struct Level6AI {
     pub cooldown: usize,
-    pub max_cooldown: usize,
+    pub telegraphs: Vec<Telegraph>,
 }
 
 impl AI for Level6AI {
-    fn ai_loop(&mut self, projectiles: &mut Vec<Projectile>, sprite_holder: &mut SpriteHolder, enemy: &Enemy) {
+    fn ai_loop(&mut self, spawns: &mut SpawnQueue, enemy: &Enemy, _player: (f32, f32), rng: &mut StdRng) {
         self.cooldown += 1;
         if self.cooldown > 0 && self.cooldown <= 600 {
             if self.cooldown % 100 < 55 {
                 let angle: f32 = (11.0 * PI / 8.0) + ((self.cooldown as f32) / 55.0).sin() * (3.0 * PI / 8.0);
                 let velocity = (angle.cos() * 6.0, angle.sin() * 6.0);
-                enemy.spawn_new_projectile(projectiles, sprite_holder, velocity);
+                enemy.spawn_new_projectile(spawns, fruit_jitter(rng), velocity);
             }
         }
         else if self.cooldown > 600 && self.cooldown <= 1200 {
-            if self.cooldown % 30 == 0 {
-                let mut angle: f32 = thread_rng().gen_range((9.0 * PI / 8.0)..=(11.0 * PI / 8.0));
+            if self.cooldown.is_multiple_of(30) {
+                let mut angle: f32 = rng.gen_range((9.0 * PI / 8.0)..=(11.0 * PI / 8.0));
                 let velocity = (angle.cos() * 6.0, angle.sin() * 6.0);
-                enemy.spawn_new_projectile(projectiles, sprite_holder, velocity);
-                angle = angle + (2.0 * PI / 8.0);
+                enemy.spawn_new_projectile(spawns, fruit_jitter(rng), velocity);
+                angle += 2.0 * PI / 8.0;
                 let velocity_2 = (angle.cos() * 6.0, angle.sin() * 6.0);
-                enemy.spawn_new_projectile(projectiles, sprite_holder, velocity_2);
-                angle = angle + (2.0 * PI / 8.0);
+                // The middle bullet of each spread chases the player.
+                let magnet = ProjectileBehavior::magnet();
+                enemy.spawn_projectile_with_behavior(spawns, fruit_jitter(rng), velocity_2, magnet);
+                angle += 2.0 * PI / 8.0;
                 let velocity_3 = (angle.cos() * 6.0, angle.sin() * 6.0);
-                enemy.spawn_new_projectile(projectiles, sprite_holder, velocity_3);
+                enemy.spawn_new_projectile(spawns, fruit_jitter(rng), velocity_3);
             }
         }
         else if self.cooldown > 1200 && self.cooldown <= 1800 {
             if self.cooldown % 20 < 3 {
                 let angle: f32 = (11.0 * PI / 8.0) + ((self.cooldown as f32) / 7.0).sin() * (3.0 * PI / 8.0);
                 let velocity = (angle.cos() * 6.0, angle.sin() * 6.0);
-                enemy.spawn_new_projectile(projectiles, sprite_holder, velocity);
+                enemy.spawn_new_projectile(spawns, fruit_jitter(rng), velocity);
+            }
+            // A slow firework down the middle every two seconds.
+            if self.cooldown.is_multiple_of(120) {
+                let firework = ProjectileBehavior::firework(12);
+                let offset = fruit_jitter(rng);
+                let velocity = (0.0, -4.0);
+                enemy.spawn_projectile_with_behavior(spawns, offset, velocity, firework);
+                // Mark where it's going to go off.
+                let center = (enemy.body.pos.0 + offset.0 + 32.0, enemy.body.pos.1 + offset.1 + 32.0);
+                self.telegraphs.push(Telegraph {
+                    pos: ProjectileBehavior::firework_burst_point(center, velocity),
+                    frames: crate::projectile_behavior::FIREWORK_FRAMES,
+                });
             }
         }
     }
-}
\ No newline at end of file
+
+    // Three 600 frame phases, one per pattern above.
+    fn phase(&self) -> usize {
+        (self.cooldown.saturating_sub(1) / 600).min(3)
+    }
+
+    fn phase_name(&self) -> &'static str {
+        match self.phase() {
+            0 => "Swaying Stream",
+            1 => "Triple Spread",
+            2 => "Rapid Sweep",
+            _ => "Survived",
+        }
+    }
+
+    fn phase_frames_left(&self) -> Option<usize> {
+        (self.phase() < 3).then(|| 600 - self.cooldown.saturating_sub(1) % 600)
+    }
+
+    fn skip_to_phase(&mut self, phase: usize) -> usize {
+        let start = phase.min(3) * 600;
+        let skipped = start.saturating_sub(self.cooldown);
+        self.cooldown = self.cooldown.max(start);
+        skipped
+    }
+
+    fn take_telegraphs(&mut self) -> Vec<Telegraph> {
+        std::mem::take(&mut self.telegraphs)
+    }
+}
+// Wraps another boss pattern and calls in a pair of minions every `interval` frames.
+pub struct SummonerAI {
+    pub pattern: Box<dyn AI>,
+    pub interval: usize,
+    pub cooldown: usize,
+    pub summons: Vec<Summon>,
+}
+
+impl AI for SummonerAI {
+    fn ai_loop(&mut self, spawns: &mut SpawnQueue, enemy: &Enemy, player: (f32, f32), rng: &mut StdRng) {
+        self.pattern.ai_loop(spawns, enemy, player, rng);
+
+        self.cooldown += 1;
+        if self.cooldown >= self.interval {
+            self.cooldown = 0;
+            // One on each side of the boss, drifting outwards and slowly down. They
+            // toughen up once the boss is past its first phase.
+            for side in [-1.0, 1.0] {
+                self.summons.push(Summon {
+                    pos: (enemy.body.pos.0 + side * 150.0, enemy.body.pos.1 - 80.0 + rng.gen_range(-20.0..=20.0)),
+                    velocity: (side * rng.gen_range(1.0..=2.0), -0.25),
+                    strong: self.pattern.phase() > 0,
+                });
+            }
+        }
+    }
+
+    fn velocity(&self) -> Option<(f32, f32)> {
+        self.pattern.velocity()
+    }
+
+    fn phase(&self) -> usize {
+        self.pattern.phase()
+    }
+
+    fn phase_name(&self) -> &'static str {
+        self.pattern.phase_name()
+    }
+
+    fn phase_frames_left(&self) -> Option<usize> {
+        self.pattern.phase_frames_left()
+    }
+
+    fn skip_to_phase(&mut self, phase: usize) -> usize {
+        self.pattern.skip_to_phase(phase)
+    }
+
+    fn take_summons(&mut self) -> Vec<Summon> {
+        std::mem::take(&mut self.summons)
+    }
+
+    fn take_telegraphs(&mut self) -> Vec<Telegraph> {
+        self.pattern.take_telegraphs()
+    }
+
+    fn on_spawn(&mut self, spawns: &mut SpawnQueue, enemy: &Enemy, rng: &mut StdRng) {
+        self.pattern.on_spawn(spawns, enemy, rng);
+    }
+
+    fn on_damage(&mut self, amount: f32, enemy: &Enemy) {
+        self.pattern.on_damage(amount, enemy);
+    }
+
+    fn on_death(&mut self, spawns: &mut SpawnQueue, enemy: &Enemy, rng: &mut StdRng) {
+        self.pattern.on_death(spawns, enemy, rng);
+    }
+}
+
+// What the summoner's minions run: drop a fruit straight down every so often.
+pub struct MinionAI {
+    pub cooldown: usize,
+    pub max_cooldown: usize,
+}
+
+impl Default for MinionAI {
+    fn default() -> Self {
+        Self {
+            cooldown: 45,
+            max_cooldown: 90,
+        }
+    }
+}
+
+impl AI for MinionAI {
+    fn ai_loop(&mut self, spawns: &mut SpawnQueue, enemy: &Enemy, _player: (f32, f32), _rng: &mut StdRng) {
+        if self.cooldown > 0 {
+            self.cooldown -= 1;
+        }
+        else {
+            self.cooldown = self.max_cooldown;
+            enemy.spawn_new_projectile(spawns, below_center(enemy), (0.0, -5.0));
+        }
+    }
+
+    fn idle_loop(&mut self) {
+        self.cooldown = self.cooldown.checked_sub(1).unwrap_or(self.max_cooldown);
+    }
+
+    fn reset(&mut self) -> bool {
+        *self = Self::default();
+        true
+    }
+}
+
+// The stage 1 mid-boss. Two short patterns on repeat, fired from wherever it is.
+// Once it's down to half health it sticks to the aimed bursts, and it goes out
+// with a ring of bullets.
+#[derive(Default)]
+pub struct MidBossAI {
+    pub cooldown: usize,
+    // Every bullet in a burst follows the first, so the player can sidestep it.
+    pub burst_velocity: (f32, f32),
+    pub telegraphs: Vec<Telegraph>,
+    // Past half health. Only the hooks set this.
+    pub enraged: bool,
+}
+
+// The mid-boss's parting shot.
+const MIDBOSS_DEATH_RING: usize = 12;
+const MIDBOSS_DEATH_SPEED: f32 = 3.0;
+
+impl AI for MidBossAI {
+    fn ai_loop(&mut self, spawns: &mut SpawnQueue, enemy: &Enemy, player: (f32, f32), _rng: &mut StdRng) {
+        self.cooldown += 1;
+        let offset = below_center(enemy);
+        // Warn a second ahead of each burst, at the spot it comes from.
+        let upcoming = self.cooldown + TELEGRAPH_FRAMES;
+        if upcoming.is_multiple_of(60) && (self.enraged || (upcoming / 240) % 2 == 1) {
+            self.telegraphs.push(Telegraph {
+                pos: (enemy.body.pos.0 + offset.0 + 32.0, enemy.body.pos.1 + offset.1 + 32.0),
+                frames: TELEGRAPH_FRAMES as u32,
+            });
+        }
+        if self.phase() == 0 {
+            // A three-way fan.
+            if self.cooldown.is_multiple_of(45) {
+                for angle in [11.0 * PI / 8.0, 12.0 * PI / 8.0, 13.0 * PI / 8.0] {
+                    let velocity = (angle.cos() * 5.0, angle.sin() * 5.0);
+                    enemy.spawn_new_projectile(spawns, offset, velocity);
+                }
+            }
+        }
+        // Short bursts at wherever the player is when each one starts.
+        else if self.cooldown % 60 < 30 && self.cooldown.is_multiple_of(10) {
+            if self.cooldown.is_multiple_of(60) {
+                let from = (enemy.body.pos.0 + offset.0 + 32.0, enemy.body.pos.1 + offset.1 + 32.0);
+                self.burst_velocity = aim_at(from, player, 7.0);
+            }
+            enemy.spawn_new_projectile(spawns, offset, self.burst_velocity);
+        }
+    }
+
+    // Alternates every 240 frames until it's enraged.
+    fn phase(&self) -> usize {
+        if self.enraged { 1 } else { (self.cooldown / 240) % 2 }
+    }
+
+    fn phase_name(&self) -> &'static str {
+        match self.phase() {
+            0 => "Fan Toss",
+            _ => "Aimed Burst",
+        }
+    }
+
+    fn take_telegraphs(&mut self) -> Vec<Telegraph> {
+        std::mem::take(&mut self.telegraphs)
+    }
+
+    // Hits taken on the way in count too.
+    fn on_spawn(&mut self, _spawns: &mut SpawnQueue, enemy: &Enemy, _rng: &mut StdRng) {
+        self.on_damage(0.0, enemy);
+    }
+
+    fn on_damage(&mut self, _amount: f32, enemy: &Enemy) {
+        let bar = &enemy.health_bar;
+        self.enraged |= bar.currval <= bar.maxval / 2.0;
+    }
+
+    fn on_death(&mut self, spawns: &mut SpawnQueue, enemy: &Enemy, _rng: &mut StdRng) {
+        for i in 0..MIDBOSS_DEATH_RING {
+            let angle = i as f32 * 2.0 * PI / MIDBOSS_DEATH_RING as f32;
+            let velocity = (angle.cos() * MIDBOSS_DEATH_SPEED, angle.sin() * MIDBOSS_DEATH_SPEED);
+            enemy.spawn_new_projectile(spawns, below_center(enemy), velocity);
+        }
+    }
+}
+
+// The stage bosses drop their fruit from a little to either side of where they stand.
+fn fruit_jitter(rng: &mut StdRng) -> (f32, f32) {
+    (rng.gen_range(-20..=20) as f32, 0.0)
+}
+
+// Fruit are 64 wide; this centres one just under the enemy.
+pub fn below_center(enemy: &Enemy) -> (f32, f32) {
+    (enemy.body.size.0 / 2.0 - 32.0, -32.0)
+}
+
+// Velocity that takes a bullet centred on `from` straight at `target`.
+pub fn aim_at(from: (f32, f32), target: (f32, f32), speed: f32) -> (f32, f32) {
+    let (dx, dy) = (target.0 - from.0, target.1 - from.1);
+    let distance = (dx * dx + dy * dy).sqrt();
+    if distance < 1.0 {
+        return (0.0, -speed);
+    }
+    (dx / distance * speed, dy / distance * speed)
+}
@@ -0,0 +1,81 @@
+// Minions that have left the fight, kept for the next summon instead of being
+// freed. A summoner calls them in all fight long, so each new one takes an old
+// one's sprite slots and, when its AI can be reset, its AI too. Idle ones are
+// blanked but keep their slots, like the particle pool's. There are never more
+// of them than were on screen at once, so the pool needs no cap.
+use bytemuck::Zeroable;
+
+use crate::enemy_ai::{MinionAI, Summon};
+use crate::{make_minion, Entity, GPUSprite, Layer, SpriteHandle, SpriteHolder};
+
+#[derive(Default)]
+pub struct EntityPool {
+    idle: Vec<Entity>,
+}
+
+impl EntityPool {
+    pub fn spawn_minion(&mut self, summon: Summon, sprite_holder: &mut SpriteHolder) -> Entity {
+        let Some(mut old) = self.idle.pop() else {
+            let indices = [
+                sprite_holder.get_next_index(),
+                sprite_holder.get_next_index(),
+                sprite_holder.get_next_index_on(Layer::Labels),
+                sprite_holder.get_next_index_on(Layer::Labels),
+            ];
+            return make_minion(summon, indices, Box::new(MinionAI::default()), sprite_holder);
+        };
+        let indices = [
+            old.enemy.sprite_index,
+            old.enemy.sprite_index_eyes,
+            old.enemy.health_bar.sprite_index_border,
+            old.enemy.health_bar.sprite_index_bar,
+        ];
+        let ai = if old.ai.reset() { old.ai } else { Box::new(MinionAI::default()) };
+        make_minion(summon, indices, ai, sprite_holder)
+    }
+
+    // Takes a minion that's been killed or left the playfield.
+    pub fn release(&mut self, minion: Entity, sprite_holder: &mut SpriteHolder) {
+        for sprite_index in minion.sprite_indices() {
+            sprite_holder.set_sprite(sprite_index, GPUSprite::zeroed());
+        }
+        self.idle.push(minion);
+    }
+
+    pub fn sprite_indices(&self) -> Vec<SpriteHandle> {
+        self.idle.iter().flat_map(Entity::sprite_indices).collect()
+    }
+
+    pub fn clear(&mut self, sprite_holder: &mut SpriteHolder) {
+        for minion in self.idle.drain(..) {
+            minion.remove_sprites(sprite_holder);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn released_minions_come_back_fresh_in_the_same_slots() {
+        let mut sprite_holder = SpriteHolder::new(16);
+        let mut pool = EntityPool::default();
+        let summon = Summon {
+            pos: (100.0, 500.0),
+            velocity: (1.0, -0.25),
+            strong: false,
+        };
+        let mut minion = pool.spawn_minion(summon, &mut sprite_holder);
+        let slots = minion.sprite_indices();
+        minion.enemy.health_bar.currval = 0.0;
+        minion.enemy.body.pos = (0.0, 0.0);
+        pool.release(minion, &mut sprite_holder);
+
+        let strong = pool.spawn_minion(Summon { strong: true, ..summon }, &mut sprite_holder);
+        assert_eq!(strong.sprite_indices(), slots);
+        assert_eq!(strong.enemy.body.pos, summon.pos);
+        assert_eq!(strong.enemy.health_bar.currval, 6.0);
+        assert!(pool.idle.is_empty());
+    }
+}
@@ -0,0 +1,44 @@
+// The setups the simulation tests share: a level loaded on a fixed seed, a boss
+// running whatever AI the test needs, and enemy bullets placed by hand.
+use crate::enemy_ai::AI;
+use crate::simulation::SimState;
+use crate::{Layer, SpriteHolder};
+
+const SEED: u64 = 5;
+
+// A fresh sim with `load` run on it.
+pub fn loaded(load: fn(&mut SimState)) -> SimState {
+    loaded_with(load, |_| {})
+}
+
+// Same, with `setup` run first for whatever the loader reads, like the shot type
+// or the modifiers.
+pub fn loaded_with(load: fn(&mut SimState), setup: impl FnOnce(&mut SimState)) -> SimState {
+    let mut state = SimState::new(SpriteHolder::new(1000), SEED);
+    setup(&mut state);
+    load(&mut state);
+    state
+}
+
+// The danmaku stage past its intro, with the boss running `ai`.
+pub fn boss_running(ai: impl AI + 'static) -> SimState {
+    let mut state = loaded(crate::load_level_6);
+    state.stage_intro = None;
+    state.enemy.ai = Box::new(ai);
+    state
+}
+
+pub fn enemy_bullet(state: &mut SimState, pos: (f32, f32), velocity: (f32, f32)) {
+    let index = state.sprite_holder.get_next_index_on(Layer::Projectiles);
+    crate::make_projectile(&mut state.projectiles, index, pos, velocity);
+}
+
+// A bullet `height` above the player's feet, falling at 6 a frame.
+pub fn bullet_above_player(state: &mut SimState, height: f32) {
+    let (x, y) = state.player.body.pos;
+    enemy_bullet(state, (x, y + height), (0.0, -6.0));
+}
+
+pub fn enemy_bullets(state: &SimState) -> usize {
+    state.projectiles.iter().filter(|proj| !proj.player_spawned).count()
+}
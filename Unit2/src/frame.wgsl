@@ -0,0 +1,38 @@
+// The frame around the playfield: dims whatever strays outside it and draws a
+// border along its edge. render_target.rs says where the playfield is.
+
+struct Frame {
+    // The playfield's corners, in render target pixels.
+    field_min: vec2<f32>,
+    field_max: vec2<f32>,
+}
+
+@group(0) @binding(0)
+var<uniform> frame: Frame;
+
+const BORDER: f32 = 6.0;
+const BORDER_COLOR: vec4<f32> = vec4<f32>(0.9, 0.75, 0.35, 1.0);
+
+@vertex
+fn vs_main(@builtin(vertex_index) in_vertex_index: u32) -> @builtin(position) vec4<f32> {
+    // One oversized triangle that covers the whole target, as in blit.wgsl.
+    let uv: vec2<f32> = vec2(f32((in_vertex_index << 1u) & 2u), f32(in_vertex_index & 2u));
+    return vec4(uv.x * 2.0 - 1.0, 1.0 - uv.y * 2.0, 0.0, 1.0);
+}
+
+@fragment
+fn fs_main(@builtin(position) position: vec4<f32>) -> @location(0) vec4<f32> {
+    let p = position.xy;
+    // How far outside the playfield this pixel is, 0 inside it.
+    let outside = max(max(frame.field_min - p, p - frame.field_max), vec2(0.0));
+    let depth = max(outside.x, outside.y);
+    if depth <= 0.0 {
+        discard;
+    }
+    if depth < BORDER {
+        return BORDER_COLOR;
+    }
+    // Faint diagonal stripes so the frame reads as a panel rather than empty space.
+    let stripe = step(0.5, fract((p.x + p.y) / 24.0));
+    return vec4(vec3(0.05 + 0.03 * stripe), 0.75);
+}
@@ -0,0 +1,40 @@
+// Things that happened during a frame that other parts of the game care about.
+// Collision and the timers only report them here; the simulation applies them
+// all in one place near the end of the frame, so the code that notices a hit
+// doesn't need the health bar, the sound list and the transition flag in hand.
+use crate::game_state::GameState;
+use crate::projectile_tags::ProjectileTag;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum GameEvent {
+    // Something touched the player. Their i-frames decide whether it hurts.
+    PlayerHit { damage: f32, tag: ProjectileTag },
+    // The player caught a falling fruit in level 1.
+    ProjectileCaught,
+    // An enemy bullet fell past the bottom of the playfield.
+    ProjectileMissed,
+    EnemyKilled(Foe),
+    StateChangeRequested(GameState),
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Foe {
+    Boss,
+    Minion,
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct EventBus {
+    events: Vec<GameEvent>,
+}
+
+impl EventBus {
+    pub fn push(&mut self, event: GameEvent) {
+        self.events.push(event);
+    }
+
+    // Everything reported since the last drain, oldest first.
+    pub fn drain(&mut self) -> Vec<GameEvent> {
+        std::mem::take(&mut self.events)
+    }
+}
@@ -0,0 +1,330 @@
+// Which screen the game is on and how it gets between them. Each state is
+// registered once in `STATES` with the scene that runs it, what it does on the way
+// in and out, and where it's allowed to go next. A new screen is a new variant
+// and a new entry; `transition_to_state` never needs touching.
+use crate::{load_dead_level, load_level_1, load_level_6, load_trial_room};
+use crate::input::Key;
+use crate::scenes::{Card, Flow, GameplayScene, ResultsAction, ResultsChoice, ResultsScene, Scene, TitleScene};
+use crate::{GameStateHolder, SpriteHandle, SpriteHolder};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum GameState {
+    Title,
+    Gameplay,
+    GameOver,
+    StageCleared,
+    YouWin,
+    Title2,
+    Danmaku,
+    DanmakuGameOver,
+    TrialRoom,
+}
+
+impl GameState {
+    // The number the sim's transition flag and the stream overlay use for it.
+    pub fn index(self) -> usize {
+        match self {
+            GameState::Title => 0,
+            GameState::Gameplay => 1,
+            GameState::GameOver => 2,
+            GameState::StageCleared => 3,
+            GameState::YouWin => 4,
+            GameState::Title2 => 5,
+            GameState::Danmaku => 6,
+            GameState::DanmakuGameOver => 7,
+            GameState::TrialRoom => 8,
+        }
+    }
+
+    pub fn from_index(index: usize) -> Option<Self> {
+        STATES.iter().map(|hooks| hooks.state).find(|state| state.index() == index)
+    }
+
+    fn hooks(self) -> &'static StateHooks {
+        STATES
+            .iter()
+            .find(|hooks| hooks.state == self)
+            .expect("every game state is registered in STATES")
+    }
+
+    pub fn is_game_over(self) -> bool {
+        matches!(self, GameState::GameOver | GameState::DanmakuGameOver)
+    }
+
+    pub fn can_transition_to(self, next: GameState) -> bool {
+        self.hooks().next.contains(&next)
+    }
+}
+
+pub struct StateHooks {
+    pub state: GameState,
+    // The scene at the bottom of the stack while this is the current state.
+    pub scene: fn() -> Box<dyn Scene>,
+    pub enter: fn(&mut GameStateHolder),
+    pub exit: fn(&mut GameStateHolder),
+    // The states it can transition to. Anything else is refused.
+    pub next: &'static [GameState],
+}
+
+// The ways on from each results screen. Stage 1 has a single phase, so only the
+// danmaku stage offers to pick up where the run ended.
+const STAGE_1_GAME_OVER: &[ResultsChoice] = &[
+    ResultsChoice { key: Key::Space, label: "SPACE RETRY", action: ResultsAction::Goto(GameState::Gameplay) },
+    ResultsChoice { key: Key::Escape, label: "ESC TITLE", action: ResultsAction::Goto(GameState::Title) },
+];
+
+const DANMAKU_GAME_OVER: &[ResultsChoice] = &[
+    ResultsChoice { key: Key::Space, label: "SPACE RETRY", action: ResultsAction::Goto(GameState::Danmaku) },
+    ResultsChoice { key: Key::Return, label: "ENTER RETRY PHASE", action: ResultsAction::RetryPhase(GameState::Danmaku) },
+    ResultsChoice { key: Key::Escape, label: "ESC TITLE", action: ResultsAction::Goto(GameState::Title2) },
+];
+
+const CLEARED: &[ResultsChoice] = &[
+    ResultsChoice { key: Key::Space, label: "SPACE PLAY AGAIN", action: ResultsAction::Goto(GameState::Gameplay) },
+];
+
+const STATES: [StateHooks; 9] = [
+    StateHooks {
+        state: GameState::Title,
+        scene: || Box::new(TitleScene::new(false)),
+        enter: |gso| show(Card::Title, gso),
+        exit: |gso| hide(Card::Title, gso),
+        next: &[GameState::Gameplay, GameState::Title2, GameState::TrialRoom],
+    },
+    StateHooks {
+        state: GameState::Gameplay,
+        scene: || Box::new(GameplayScene { trial_room: false }),
+        enter: |gso| {
+            load_level_1(&mut gso.sim);
+            boss_intro(gso);
+            stage_intro(gso);
+            gso.sounds.start_music();
+        },
+        exit: end_run,
+        next: &[GameState::Title, GameState::GameOver, GameState::StageCleared, GameState::YouWin],
+    },
+    StateHooks {
+        state: GameState::GameOver,
+        scene: || Box::new(ResultsScene::new(Card::Death, STAGE_1_GAME_OVER)),
+        enter: |gso| {
+            show(Card::Death, gso);
+            gso.score_graph.show(&mut gso.sim.sprite_holder);
+            gso.death_report.show(&mut gso.sim.sprite_holder);
+        },
+        exit: |gso| hide(Card::Death, gso),
+        next: &[GameState::Gameplay, GameState::Title],
+    },
+    StateHooks {
+        state: GameState::StageCleared,
+        scene: || Box::new(ResultsScene::new(Card::Cleared, CLEARED)),
+        enter: |gso| show(Card::Cleared, gso),
+        exit: |gso| hide(Card::Cleared, gso),
+        next: &[GameState::Gameplay],
+    },
+    StateHooks {
+        state: GameState::YouWin,
+        scene: || Box::new(ResultsScene::new(Card::Win, &[])),
+        enter: |gso| {
+            show(Card::Win, gso);
+            gso.score_graph.show(&mut gso.sim.sprite_holder);
+        },
+        exit: |gso| hide(Card::Win, gso),
+        next: &[],
+    },
+    StateHooks {
+        state: GameState::Title2,
+        scene: || Box::new(TitleScene::new(true)),
+        enter: |gso| show(Card::Title2, gso),
+        exit: |gso| hide(Card::Title2, gso),
+        next: &[GameState::Danmaku, GameState::Title, GameState::TrialRoom],
+    },
+    StateHooks {
+        state: GameState::Danmaku,
+        scene: || Box::new(GameplayScene { trial_room: false }),
+        enter: |gso| {
+            load_level_6(&mut gso.sim);
+            boss_intro(gso);
+            stage_intro(gso);
+            gso.sounds.start_music();
+        },
+        exit: end_run,
+        next: &[GameState::Title2, GameState::DanmakuGameOver, GameState::StageCleared, GameState::YouWin],
+    },
+    StateHooks {
+        state: GameState::DanmakuGameOver,
+        scene: || Box::new(ResultsScene::new(Card::DanmakuDeath, DANMAKU_GAME_OVER)),
+        enter: |gso| {
+            show(Card::DanmakuDeath, gso);
+            gso.score_graph.show(&mut gso.sim.sprite_holder);
+            gso.death_report.show(&mut gso.sim.sprite_holder);
+        },
+        exit: |gso| hide(Card::DanmakuDeath, gso),
+        next: &[GameState::Danmaku, GameState::Title2],
+    },
+    StateHooks {
+        state: GameState::TrialRoom,
+        scene: || Box::new(GameplayScene { trial_room: true }),
+        enter: |gso| load_trial_room(&mut gso.sim),
+        exit: |gso| {
+            gso.trial_room.clear(&mut gso.sim.sprite_holder);
+            load_dead_level(&mut gso.sim);
+        },
+        next: &[GameState::Title, GameState::Title2],
+    },
+];
+
+// The current state plus its scene stack. The bottom scene is the state's own;
+// anything above it is an overlay like the pause screen.
+pub struct StateMachine {
+    pub state: GameState,
+    scenes: Vec<Box<dyn Scene>>,
+}
+
+impl StateMachine {
+    pub fn new(state: GameState) -> Self {
+        Self {
+            state,
+            scenes: vec![(state.hooks().scene)()],
+        }
+    }
+
+    pub fn push(&mut self, scene: Box<dyn Scene>) {
+        self.scenes.push(scene);
+    }
+
+    fn clear(&mut self, sprite_holder: &mut SpriteHolder) {
+        for scene in self.scenes.drain(..) {
+            scene.remove(sprite_holder);
+        }
+    }
+
+    pub fn sprite_indices(&self) -> Vec<SpriteHandle> {
+        self.scenes.iter().flat_map(|scene| scene.sprite_indices()).collect()
+    }
+}
+
+// Runs the top scene for a frame. It's lifted off the stack while it runs, so it
+// can push scenes over itself or move to another state, which replaces the stack.
+pub fn run_frame(gso: &mut GameStateHolder) {
+    let Some(mut scene) = gso.game_state.scenes.pop() else {
+        return;
+    };
+    let state = gso.game_state.state;
+    let depth = gso.game_state.scenes.len();
+    let interrupted = |gso: &GameStateHolder| gso.game_state.state != state || gso.game_state.scenes.len() != depth;
+
+    let flow = scene.handle_input(gso);
+    if flow == Flow::Continue && !interrupted(gso) {
+        scene.update(gso);
+        if !interrupted(gso) {
+            scene.render_sprites(gso);
+        }
+    }
+    if flow == Flow::Close || gso.game_state.state != state {
+        scene.remove(&mut gso.sim.sprite_holder);
+    } else {
+        // Under anything it pushed.
+        gso.game_state.scenes.insert(depth, scene);
+    }
+}
+
+pub fn transition_to_state(new_state: GameState, gso: &mut GameStateHolder) {
+    let old_state = gso.game_state.state;
+    if !old_state.can_transition_to(new_state) {
+        log::warn!("Cannot transition from state {:?} to state {:?}", old_state, new_state);
+        return;
+    }
+    switch_state(new_state, gso);
+}
+
+// Straight back to the title from anywhere, whatever the table says. Any run in
+// progress is written off the same way as quitting it.
+pub fn reset_to_title(gso: &mut GameStateHolder) {
+    abandon_run(gso, "idle");
+    switch_state(GameState::Title, gso);
+}
+
+// Wraps up the run's records when it ends without a win or a death.
+pub fn abandon_run(gso: &mut GameStateHolder, outcome: &str) {
+    gso.popups.clear(&mut gso.sim.sprite_holder);
+    if let Some(telemetry) = &mut gso.telemetry {
+        telemetry.finish(outcome);
+    }
+    gso.run_log.finish(&gso.sim, outcome);
+    if let Some(heatmap) = &mut gso.heatmap {
+        heatmap.finish(&mut gso.sim.sprite_holder);
+    }
+}
+
+fn switch_state(new_state: GameState, gso: &mut GameStateHolder) {
+    let old_state = gso.game_state.state;
+    gso.clock.reset_game();
+    gso.medals.clear(&mut gso.sim.sprite_holder);
+    gso.score_graph.clear(&mut gso.sim.sprite_holder);
+    gso.death_report.clear(&mut gso.sim.sprite_holder);
+    gso.game_state.clear(&mut gso.sim.sprite_holder);
+    (old_state.hooks().exit)(gso);
+    gso.game_state.state = new_state;
+    (new_state.hooks().enter)(gso);
+    gso.game_state.push((new_state.hooks().scene)());
+}
+
+fn boss_intro(gso: &mut GameStateHolder) {
+    let keyframes = gso.camera.boss_intro(&gso.sim.playfield, gso.sim.enemy.enemy.body.center());
+    gso.camera.play(&keyframes);
+}
+
+fn stage_intro(gso: &mut GameStateHolder) {
+    if let Some(intro) = &gso.sim.stage_intro {
+        gso.intro_banner.show(intro, &mut gso.sim.sprite_holder);
+    }
+}
+
+// Leaving a level by any route. Music only plays during a run.
+fn end_run(gso: &mut GameStateHolder) {
+    gso.sim.trans_flag.val = 0;
+    gso.sim.start_phase = None;
+    gso.intro_banner.clear(&mut gso.sim.sprite_holder);
+    gso.camera.stop();
+    load_dead_level(&mut gso.sim);
+    gso.sounds.stop_music();
+}
+
+// Where the full-screen title and results cards sit.
+const SCREEN_REGION: [f32; 4] = [160.0, 32.0, 720.0, 720.0];
+
+fn show(card: Card, gso: &mut GameStateHolder) {
+    card.screen(gso).sprite.screen_region = SCREEN_REGION;
+}
+
+fn hide(card: Card, gso: &mut GameStateHolder) {
+    let screen = card.screen(gso);
+    screen.sprite.screen_region = [0.0, 0.0, 0.0, 0.0];
+    let (sprite_index, sprite) = (screen.sprite_index, screen.sprite);
+    gso.sim.sprite_holder.set_sprite(sprite_index, sprite);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn every_state_is_registered_once_under_its_own_index() {
+        for (index, hooks) in STATES.iter().enumerate() {
+            assert_eq!(GameState::from_index(index), Some(hooks.state));
+            assert_eq!(STATES.iter().filter(|other| other.state == hooks.state).count(), 1);
+        }
+        assert_eq!(GameState::from_index(STATES.len()), None);
+    }
+
+    #[test]
+    fn the_trial_room_goes_back_to_the_title_it_came_from() {
+        let mut trial_room = crate::trial_room::TrialRoom::default();
+        for (title, danmaku) in [(GameState::Title, false), (GameState::Title2, true)] {
+            trial_room.opened_from(danmaku);
+            assert_eq!(trial_room.title(), title);
+            assert!(title.can_transition_to(GameState::TrialRoom));
+            assert!(GameState::TrialRoom.can_transition_to(title));
+        }
+    }
+}
@@ -0,0 +1,36 @@
+// Sprites drawn in code instead of by hand. They're appended below the artists'
+// spritesheet at load time: first the font rows, then a row of effect cells.
+use image::RgbaImage;
+
+use crate::{atlas, text};
+
+const CELL_PIXELS: u32 = 16;
+// Thin white ring, tinted by whatever uses it. Used for bullet telegraphs.
+pub const RING_CELL: (f32, f32) = (0.0, atlas::SHEET_ROWS + 1.0);
+// Solid white, for anything drawn as tinted, stretched rectangles.
+pub const SOLID_CELL: (f32, f32) = (1.0, atlas::SHEET_ROWS + 1.0);
+
+pub fn extend_sheet(sheet: RgbaImage) -> RgbaImage {
+    let sheet = text::append_font(sheet);
+    let mut out = RgbaImage::new(sheet.width(), sheet.height() + CELL_PIXELS);
+    image::imageops::replace(&mut out, &sheet, 0, 0);
+
+    let origin = (RING_CELL.0 as u32 * CELL_PIXELS, RING_CELL.1 as u32 * CELL_PIXELS);
+    let center = (CELL_PIXELS as f32 - 1.0) / 2.0;
+    for y in 0..CELL_PIXELS {
+        for x in 0..CELL_PIXELS {
+            let distance = ((x as f32 - center).powi(2) + (y as f32 - center).powi(2)).sqrt();
+            if (center - 1.5..=center + 0.5).contains(&distance) {
+                out.put_pixel(origin.0 + x, origin.1 + y, image::Rgba([255, 255, 255, 255]));
+            }
+        }
+    }
+
+    let origin = (SOLID_CELL.0 as u32 * CELL_PIXELS, SOLID_CELL.1 as u32 * CELL_PIXELS);
+    for y in 0..CELL_PIXELS {
+        for x in 0..CELL_PIXELS {
+            out.put_pixel(origin.0 + x, origin.1 + y, image::Rgba([255, 255, 255, 255]));
+        }
+    }
+    out
+}
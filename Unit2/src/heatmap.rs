@@ -0,0 +1,239 @@
+// Where players spend their time and where they die, for tuning patterns against
+// real playtests. Launch with `--heatmap <dir>` and every run adds its player
+// positions and deaths to `heatmap-stage<mode>.txt` in that directory, so a file
+// builds up over a whole session. F11 draws the stage's totals over the playfield.
+use std::path::PathBuf;
+
+use crate::generated_sprites::SOLID_CELL;
+use crate::simulation::SimState;
+use crate::{GPUSprite, Layer, SpriteHandle, SpriteHolder, SPRITE_SHEET_RESOLUTION};
+
+// Playfield pixels per cell side.
+const CELL: f32 = 32.0;
+// The player's position is sampled this often.
+const SAMPLE_INTERVAL: u64 = 10;
+// The overlay is rebuilt this often while it's up, rather than every frame.
+const REDRAW_INTERVAL: u64 = 30;
+// Busiest cell's colour; quieter cells fade towards clear.
+const VISIT_TINT: [f32; 4] = [1.6, 0.5, 0.2, 0.6];
+const DEATH_TINT: [f32; 4] = [1.0, 1.0, 1.0, 0.9];
+// Death markers are drawn this much smaller than a cell, in the middle of it.
+const DEATH_MARKER: f32 = 12.0;
+
+#[derive(Debug, Clone, PartialEq)]
+struct Grid {
+    cols: usize,
+    rows: usize,
+    // Row-major from the bottom-left cell.
+    visits: Vec<u32>,
+    deaths: Vec<u32>,
+}
+
+impl Grid {
+    fn new(size: (f32, f32)) -> Self {
+        let cols = (size.0 / CELL).ceil() as usize;
+        let rows = (size.1 / CELL).ceil() as usize;
+        Self {
+            cols,
+            rows,
+            visits: vec![0; cols * rows],
+            deaths: vec![0; cols * rows],
+        }
+    }
+
+    fn cell(&self, pos: (f32, f32)) -> usize {
+        let col = ((pos.0 / CELL).max(0.0) as usize).min(self.cols - 1);
+        let row = ((pos.1 / CELL).max(0.0) as usize).min(self.rows - 1);
+        row * self.cols + col
+    }
+
+    fn add(&mut self, other: &Grid) {
+        for (total, count) in self.visits.iter_mut().zip(other.visits.iter()) {
+            *total += count;
+        }
+        for (total, count) in self.deaths.iter_mut().zip(other.deaths.iter()) {
+            *total += count;
+        }
+    }
+
+    fn to_text(&self, mode: usize) -> String {
+        let join = |counts: &[u32]| counts.iter().map(|n| n.to_string()).collect::<Vec<_>>().join(",");
+        format!(
+            "# unit2game1 heatmap: stage {}, {}px cells from the bottom left\ncols={}\nrows={}\nvisits={}\ndeaths={}\n",
+            mode,
+            CELL,
+            self.cols,
+            self.rows,
+            join(&self.visits),
+            join(&self.deaths),
+        )
+    }
+
+    // None if the text is damaged or was made for a different playfield size.
+    fn parse(text: &str, size: (f32, f32)) -> Option<Self> {
+        let mut grid = Self::new(size);
+        let counts = |value: &str| value.split(',').map(|n| n.parse().ok()).collect::<Option<Vec<u32>>>();
+        for line in text.lines().map(str::trim).filter(|line| !line.is_empty() && !line.starts_with('#')) {
+            match line.split_once('=')? {
+                ("cols", value) if value.parse() == Ok(grid.cols) => {}
+                ("rows", value) if value.parse() == Ok(grid.rows) => {}
+                ("visits", value) => grid.visits = counts(value)?,
+                ("deaths", value) => grid.deaths = counts(value)?,
+                _ => return None,
+            }
+        }
+        let cells = grid.cols * grid.rows;
+        (grid.visits.len() == cells && grid.deaths.len() == cells).then_some(grid)
+    }
+}
+
+pub struct Heatmap {
+    dir: PathBuf,
+    mode: usize,
+    // Every earlier run of this stage, from the file.
+    totals: Grid,
+    run: Grid,
+    dead: bool,
+    sprite_indices: Vec<SpriteHandle>,
+}
+
+impl Heatmap {
+    // Looks for `--heatmap <dir>` in the command line.
+    pub fn from_args(mut args: impl Iterator<Item = String>) -> Option<Heatmap> {
+        args.find(|arg| arg == "--heatmap")?;
+        let dir = PathBuf::from(args.next()?);
+        log::info!("Recording playtest heatmaps to {}", dir.display());
+        Some(Heatmap {
+            dir,
+            mode: 0,
+            totals: Grid::new((CELL, CELL)),
+            run: Grid::new((CELL, CELL)),
+            dead: false,
+            sprite_indices: vec![],
+        })
+    }
+
+    fn path(&self) -> PathBuf {
+        self.dir.join(format!("heatmap-stage{}.txt", self.mode))
+    }
+
+    // Call after every simulated frame. `show` is whether the overlay is wanted.
+    pub fn record(&mut self, sim: &mut SimState, show: bool) {
+        if sim.frame == 1 {
+            self.start(sim);
+        }
+        let center = sim.player.body.center();
+        if sim.frame.is_multiple_of(SAMPLE_INTERVAL) {
+            let cell = self.run.cell(center);
+            self.run.visits[cell] += 1;
+        }
+        // Counted as it happens, so deaths undone by the rewind assist still show up.
+        let dead = sim.player_health_bar.currval <= 0.0;
+        if dead && !self.dead {
+            let cell = self.run.cell(center);
+            self.run.deaths[cell] += 1;
+        }
+        self.dead = dead;
+
+        if !show {
+            self.clear(&mut sim.sprite_holder);
+        } else if self.sprite_indices.is_empty() || sim.frame.is_multiple_of(REDRAW_INTERVAL) {
+            self.draw(&mut sim.sprite_holder);
+        }
+    }
+
+    fn start(&mut self, sim: &SimState) {
+        self.mode = sim.mode;
+        self.run = Grid::new(sim.playfield.size);
+        self.dead = false;
+        let path = self.path();
+        self.totals = match std::fs::read_to_string(&path) {
+            Ok(text) => Grid::parse(&text, sim.playfield.size).unwrap_or_else(|| {
+                log::warn!("Starting {} over; it doesn't match this stage", path.display());
+                Grid::new(sim.playfield.size)
+            }),
+            Err(_) => Grid::new(sim.playfield.size),
+        };
+    }
+
+    // Adds the run that just ended to the stage's file.
+    pub fn finish(&mut self, sprite_holder: &mut SpriteHolder) {
+        self.clear(sprite_holder);
+        if self.mode == 0 {
+            return;
+        }
+        let run = std::mem::replace(&mut self.run, Grid::new((CELL, CELL)));
+        self.totals.add(&run);
+        let path = self.path();
+        let result = std::fs::create_dir_all(&self.dir).and_then(|_| std::fs::write(&path, self.totals.to_text(self.mode)));
+        match result {
+            Ok(()) => log::info!("Wrote heatmap {}", path.display()),
+            Err(e) => log::warn!("Couldn't write heatmap {}: {}", path.display(), e),
+        }
+        self.mode = 0;
+    }
+
+    fn draw(&mut self, sprite_holder: &mut SpriteHolder) {
+        self.clear(sprite_holder);
+        let mut grid = self.totals.clone();
+        grid.add(&self.run);
+        let busiest = grid.visits.iter().copied().max().unwrap_or(0).max(1) as f32;
+        for cell in 0..grid.visits.len() {
+            let x = (cell % grid.cols) as f32 * CELL;
+            let y = (cell / grid.cols) as f32 * CELL;
+            if grid.visits[cell] > 0 {
+                let heat = grid.visits[cell] as f32 / busiest;
+                let tint = [VISIT_TINT[0], VISIT_TINT[1], VISIT_TINT[2], VISIT_TINT[3] * heat];
+                self.add_rect([x, y, CELL, CELL], tint, sprite_holder);
+            }
+            if grid.deaths[cell] > 0 {
+                let inset = (CELL - DEATH_MARKER) / 2.0;
+                self.add_rect([x + inset, y + inset, DEATH_MARKER, DEATH_MARKER], DEATH_TINT, sprite_holder);
+            }
+        }
+    }
+
+    fn add_rect(&mut self, screen_region: [f32; 4], tint: [f32; 4], sprite_holder: &mut SpriteHolder) {
+        let sprite_index = sprite_holder.get_next_index_on(Layer::Ui);
+        sprite_holder.set_sprite(
+            sprite_index,
+            GPUSprite {
+                screen_region,
+                sheet_region: [
+                    SOLID_CELL.0 / SPRITE_SHEET_RESOLUTION.0,
+                    SOLID_CELL.1 / SPRITE_SHEET_RESOLUTION.1,
+                    1.0 / SPRITE_SHEET_RESOLUTION.0,
+                    1.0 / SPRITE_SHEET_RESOLUTION.1,
+                ],
+            },
+        );
+        sprite_holder.set_tint(sprite_index, tint);
+        self.sprite_indices.push(sprite_index);
+    }
+
+    pub fn sprite_indices(&self) -> Vec<SpriteHandle> {
+        self.sprite_indices.clone()
+    }
+
+    fn clear(&mut self, sprite_holder: &mut SpriteHolder) {
+        for sprite_index in self.sprite_indices.drain(..) {
+            sprite_holder.remove_sprite(sprite_index);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn a_grid_survives_the_file_and_rejects_other_sizes() {
+        let mut grid = Grid::new((600.0, 800.0));
+        let cell = grid.cell((300.0, 790.0));
+        grid.visits[cell] = 7;
+        grid.deaths[cell] = 2;
+        let text = grid.to_text(6);
+        assert_eq!(Grid::parse(&text, (600.0, 800.0)), Some(grid));
+        assert_eq!(Grid::parse(&text, (1024.0, 768.0)), None);
+    }
+}
@@ -0,0 +1,85 @@
+// What the player sees and hears when something takes damage. Every damageable
+// entity carries a HitFlash; the damage functions trigger it, and the simulation
+// applies all the flashes, sounds and sparks together at the end of the frame.
+use crate::audio::SoundId;
+use crate::particles::{self, Particles};
+use crate::{SpriteHandle, SpriteHolder};
+
+const FLASH_FRAMES: u32 = 2;
+// Tints multiply the texture, so a big one washes the sprite out to white.
+const FLASH_TINT: [f32; 4] = [4.0, 4.0, 4.0, 1.0];
+const SPARKS_PER_HIT: usize = 4;
+const SPARK_SPEED: f32 = 4.0;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Faction {
+    Player,
+    Enemy,
+}
+
+impl Faction {
+    fn sound(self) -> SoundId {
+        match self {
+            Faction::Player => SoundId::ProjectileMissed,
+            Faction::Enemy => SoundId::EnemyHit,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy)]
+pub struct HitFlash {
+    faction: Faction,
+    // The tint the sprite has when it isn't flashing.
+    base_tint: [f32; 4],
+    // Whether hits also throw off a spark.
+    sparks: bool,
+    frames_left: u32,
+}
+
+impl HitFlash {
+    pub fn new(faction: Faction, base_tint: [f32; 4], sparks: bool) -> Self {
+        Self {
+            faction,
+            base_tint,
+            sparks,
+            frames_left: 0,
+        }
+    }
+
+    // Call whenever the owner takes damage. `center` is where the spark comes from.
+    pub fn hit(&mut self, center: (f32, f32), feedback: &mut HitFeedback) {
+        self.frames_left = FLASH_FRAMES;
+        feedback.pending.push((self.faction, center, self.sparks));
+    }
+
+    // Sets the owner's sprite tint for this frame.
+    pub fn apply(&mut self, sprite_index: SpriteHandle, sprite_holder: &mut SpriteHolder) {
+        if self.frames_left > 0 {
+            self.frames_left -= 1;
+            sprite_holder.set_tint(sprite_index, FLASH_TINT);
+        } else {
+            sprite_holder.set_tint(sprite_index, self.base_tint);
+        }
+    }
+}
+
+// Hits reported this frame.
+#[derive(Default)]
+pub struct HitFeedback {
+    pending: Vec<(Faction, (f32, f32), bool)>,
+}
+
+impl HitFeedback {
+    pub fn update(&mut self, sounds: &mut Vec<SoundId>, particles: &mut Particles, sprite_holder: &mut SpriteHolder) {
+        for (faction, center, spark) in self.pending.drain(..) {
+            sounds.push(faction.sound());
+            if spark {
+                particles.burst(center, SPARKS_PER_HIT, SPARK_SPEED, particles::SPARK, sprite_holder);
+            }
+        }
+    }
+
+    pub fn clear(&mut self) {
+        self.pending.clear();
+    }
+}
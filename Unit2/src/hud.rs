@@ -0,0 +1,174 @@
+// What each gameplay state shows of the run and where. Most levels keep their bars
+// in the frame around the playfield. The danmaku level's playfield is a tall strip
+// and lists its readouts in a panel to the right of it, like the classic vertical
+// shooters.
+use crate::generated_sprites::SOLID_CELL;
+use crate::playfield::Playfield;
+use crate::simulation::SimState;
+use crate::text::TextLabel;
+use crate::{GPUSprite, Layer, SpriteHandle, SpriteHolder, SPRITE_SHEET_RESOLUTION};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum HudItem {
+    Score,
+    Lives,
+    Bombs,
+    Graze,
+    SpellCard,
+}
+
+impl HudItem {
+    fn title(self) -> &'static str {
+        match self {
+            HudItem::Score => "SCORE",
+            HudItem::Lives => "LIVES",
+            HudItem::Bombs => "BOMB",
+            HudItem::Graze => "GRAZE",
+            HudItem::SpellCard => "SPELL",
+        }
+    }
+
+    fn value(self, sim: &SimState) -> String {
+        match self {
+            HudItem::Score => sim.player.score.to_string(),
+            HudItem::Lives => (sim.player_health_bar.currval.max(0.0) as u32).to_string(),
+            // Whichever special the level has; both fill the same way.
+            HudItem::Bombs => {
+                let charge = sim
+                    .time_freeze
+                    .as_ref()
+                    .map(|time_freeze| time_freeze.charge)
+                    .or(sim.catch_return.as_ref().map(|catch_return| catch_return.charge))
+                    .unwrap_or(0.0);
+                format!("{}%", (charge * 100.0) as u32)
+            }
+            HudItem::Graze => sim.grazes.to_string(),
+            HudItem::SpellCard => format!("{} {}/{}", sim.enemy.ai.phase_name(), sim.spells_captured, sim.phases_cleared),
+        }
+    }
+}
+
+pub struct HudLayout {
+    // The side panel's readouts, top to bottom. Empty means no panel. The panel
+    // fills whatever the playfield leaves of the framed area on its right.
+    pub panel: &'static [HudItem],
+}
+
+const CLASSIC: HudLayout = HudLayout { panel: &[] };
+
+const DANMAKU: HudLayout = HudLayout {
+    panel: &[HudItem::Score, HudItem::Lives, HudItem::Bombs, HudItem::Graze, HudItem::SpellCard],
+};
+
+pub fn layout(mode: usize) -> &'static HudLayout {
+    match mode {
+        6 => &DANMAKU,
+        _ => &CLASSIC,
+    }
+}
+
+// From the top of the panel to the baseline of the first title.
+const PANEL_TOP_GAP: f32 = 32.0;
+const PANEL_MARGIN: f32 = 24.0;
+const DIVIDER_WIDTH: f32 = 4.0;
+const TEXT_SCALE: f32 = 2.0;
+// A title line and a value line per readout, then a gap.
+const LINE_HEIGHT: f32 = 24.0;
+const ROW_HEIGHT: f32 = 64.0;
+const BACKDROP_TINT: [f32; 4] = [0.06, 0.06, 0.1, 1.0];
+const DIVIDER_TINT: [f32; 4] = [0.9, 0.75, 0.35, 1.0];
+const TITLE_TINT: [f32; 4] = [0.6, 0.6, 0.75, 1.0];
+const VALUE_TINT: [f32; 4] = [1.0, 1.0, 1.0, 1.0];
+
+struct Readout {
+    item: HudItem,
+    title: TextLabel,
+    // The text `value` was made for, so it's only rebuilt when it changes.
+    shown: String,
+    value: TextLabel,
+}
+
+// The panel itself. It covers the playfield's right side, so it's drawn with the
+// rest of the HUD, over anything that strays under it.
+pub struct SidePanel {
+    left: f32,
+    top: f32,
+    backdrop_index: SpriteHandle,
+    divider_index: SpriteHandle,
+    readouts: Vec<Readout>,
+}
+
+impl SidePanel {
+    pub fn new(layout: &HudLayout, playfield: &Playfield, sprite_holder: &mut SpriteHolder) -> Self {
+        let (left, top) = playfield.size;
+        let backdrop_index = sprite_holder.get_next_index_on(Layer::Ui);
+        let divider_index = sprite_holder.get_next_index_on(Layer::Ui);
+        sprite_holder.set_sprite(backdrop_index, solid([left, 0.0, playfield.visible_width() - left, top]));
+        sprite_holder.set_tint(backdrop_index, BACKDROP_TINT);
+        sprite_holder.set_sprite(divider_index, solid([left, 0.0, DIVIDER_WIDTH, top]));
+        sprite_holder.set_tint(divider_index, DIVIDER_TINT);
+        let readouts = layout
+            .panel
+            .iter()
+            .map(|&item| Readout {
+                item,
+                title: TextLabel::new(item.title(), sprite_holder),
+                shown: String::new(),
+                value: TextLabel::new("", sprite_holder),
+            })
+            .collect();
+        Self {
+            left,
+            top,
+            backdrop_index,
+            divider_index,
+            readouts,
+        }
+    }
+
+    // Takes the whole state for the readouts, so it's lifted off it while syncing.
+    pub fn sync_sprites(&mut self, sim: &mut SimState) {
+        let x = self.left + PANEL_MARGIN;
+        for (row, readout) in self.readouts.iter_mut().enumerate() {
+            let value = readout.item.value(sim);
+            if value != readout.shown {
+                let label = std::mem::replace(&mut readout.value, TextLabel::new(&value, &mut sim.sprite_holder));
+                label.remove(&mut sim.sprite_holder);
+                readout.shown = value;
+            }
+            let y = self.top - PANEL_TOP_GAP - row as f32 * ROW_HEIGHT;
+            readout.title.draw((x, y), TEXT_SCALE, TITLE_TINT, &mut sim.sprite_holder);
+            readout.value.draw((x, y - LINE_HEIGHT), TEXT_SCALE, VALUE_TINT, &mut sim.sprite_holder);
+        }
+    }
+
+    pub fn sprite_indices(&self) -> Vec<SpriteHandle> {
+        let mut indices = vec![self.backdrop_index, self.divider_index];
+        for readout in self.readouts.iter() {
+            indices.extend(readout.title.sprite_indices());
+            indices.extend(readout.value.sprite_indices());
+        }
+        indices
+    }
+
+    pub fn remove(self, sprite_holder: &mut SpriteHolder) {
+        sprite_holder.remove_sprite(self.backdrop_index);
+        sprite_holder.remove_sprite(self.divider_index);
+        for readout in self.readouts {
+            readout.title.remove(sprite_holder);
+            readout.value.remove(sprite_holder);
+        }
+    }
+}
+
+fn solid(screen_region: [f32; 4]) -> GPUSprite {
+    GPUSprite {
+        screen_region,
+        sheet_region: [
+            SOLID_CELL.0 / SPRITE_SHEET_RESOLUTION.0,
+            SOLID_CELL.1 / SPRITE_SHEET_RESOLUTION.1,
+            1.0 / SPRITE_SHEET_RESOLUTION.0,
+            1.0 / SPRITE_SHEET_RESOLUTION.1,
+        ],
+    }
+}
@@ -1,6 +1,276 @@
 pub use winit::dpi::PhysicalPosition as MousePos;
 pub use winit::event::VirtualKeyCode as Key;
-use winit::event::{ElementState, MouseButton};
+use winit::event::{ElementState, MouseButton, MouseScrollDelta, Touch, TouchPhase};
+
+// How many frames apart two presses of a key can be and still count as a double-tap.
+pub const DOUBLE_TAP_FRAMES: u64 = 12;
+const SHIFT: &[Key] = &[Key::LShift, Key::RShift];
+
+// Sized for every scancode the PC set uses; anything past it is ignored.
+const SCANCODES: usize = 256;
+// Left, right, middle, then `Other` buttons up to this many in all.
+const MOUSE_BUTTONS: usize = 16;
+// Touchpads scroll by pixels. This many count as one notch of a wheel.
+const PIXELS_PER_NOTCH: f64 = 40.0;
+
+// How far a finger can wander, in physical pixels, and still be a tap rather
+// than a drag.
+const TAP_SLOP: f64 = 24.0;
+// A drag steers once it's this far either side of its anchor. The anchor trails
+// along at DRAG_LEASH behind, so turning back doesn't mean dragging all the way
+// back to where the finger went down.
+const DRAG_DEADZONE: f64 = 12.0;
+const DRAG_LEASH: f64 = 48.0;
+
+// The thumb buttons, numbered the way X11 does it. Other platforms' numbers are
+// changed to these as they come in.
+pub const BACK_BUTTON: MouseButton = MouseButton::Other(8);
+pub const FORWARD_BUTTON: MouseButton = MouseButton::Other(9);
+
+// This platform's numbers for the back and forward thumb buttons.
+fn thumb_buttons() -> &'static [(u16, MouseButton)] {
+    if cfg!(target_os = "windows") {
+        &[(1, BACK_BUTTON), (2, FORWARD_BUTTON)]
+    } else if cfg!(target_os = "macos") {
+        &[(3, BACK_BUTTON), (4, FORWARD_BUTTON)]
+    } else {
+        // Wayland passes on the kernel's BTN_SIDE and BTN_EXTRA.
+        &[(0x113, BACK_BUTTON), (0x114, FORWARD_BUTTON)]
+    }
+}
+
+// The gamepad buttons menus answer to, from any pad that's plugged in.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PadButton {
+    Up,
+    Down,
+    Left,
+    Right,
+    // A on most pads, cross on PlayStation ones.
+    South,
+    East,
+    Start,
+}
+
+impl PadButton {
+    const COUNT: usize = 7;
+
+    fn from_gilrs(button: gilrs::Button) -> Option<Self> {
+        match button {
+            gilrs::Button::DPadUp => Some(PadButton::Up),
+            gilrs::Button::DPadDown => Some(PadButton::Down),
+            gilrs::Button::DPadLeft => Some(PadButton::Left),
+            gilrs::Button::DPadRight => Some(PadButton::Right),
+            gilrs::Button::South => Some(PadButton::South),
+            gilrs::Button::East => Some(PadButton::East),
+            gilrs::Button::Start => Some(PadButton::Start),
+            _ => None,
+        }
+    }
+}
+
+// A gameplay key, either by what the layout calls it or by where it sits.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Binding {
+    Key(Key),
+    Mouse(MouseButton),
+    // Whatever key is in this one's place on a US board, so AZERTY and Dvorak
+    // players get the same hand position instead of the same letter.
+    Position(Key),
+}
+
+impl Binding {
+    // How config.toml and the options file write it: a key's name, Mouse4 or
+    // Mouse5, or "pos:" and a letter or digit for a key bound by position.
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name {
+            "Mouse4" => Some(Binding::Mouse(BACK_BUTTON)),
+            "Mouse5" => Some(Binding::Mouse(FORWARD_BUTTON)),
+            _ => match name.strip_prefix("pos:") {
+                Some(name) => key_from_name(name).filter(|&key| has_position(key)).map(Binding::Position),
+                None => key_from_name(name).map(Binding::Key),
+            },
+        }
+    }
+
+    // None for mouse buttons without a name.
+    pub fn config_name(self) -> Option<String> {
+        match self {
+            Binding::Key(key) => Some(format!("{:?}", key)),
+            Binding::Position(key) => Some(format!("pos:{:?}", key)),
+            Binding::Mouse(BACK_BUTTON) => Some("Mouse4".to_string()),
+            Binding::Mouse(FORWARD_BUTTON) => Some("Mouse5".to_string()),
+            Binding::Mouse(_) => None,
+        }
+    }
+}
+
+// Where the letters and digits sit on a US board, as PC scancodes. Windows and
+// Linux both report these. Only these keys can be bound by position.
+const PC_POSITIONS: &[(Key, u32)] = &[
+    (Key::Key1, 2), (Key::Key2, 3), (Key::Key3, 4), (Key::Key4, 5), (Key::Key5, 6),
+    (Key::Key6, 7), (Key::Key7, 8), (Key::Key8, 9), (Key::Key9, 10), (Key::Key0, 11),
+    (Key::Q, 16), (Key::W, 17), (Key::E, 18), (Key::R, 19), (Key::T, 20),
+    (Key::Y, 21), (Key::U, 22), (Key::I, 23), (Key::O, 24), (Key::P, 25),
+    (Key::A, 30), (Key::S, 31), (Key::D, 32), (Key::F, 33), (Key::G, 34),
+    (Key::H, 35), (Key::J, 36), (Key::K, 37), (Key::L, 38),
+    (Key::Z, 44), (Key::X, 45), (Key::C, 46), (Key::V, 47), (Key::B, 48),
+    (Key::N, 49), (Key::M, 50),
+];
+// The same keys in macOS's own numbering.
+const MAC_POSITIONS: &[(Key, u32)] = &[
+    (Key::Key1, 0x12), (Key::Key2, 0x13), (Key::Key3, 0x14), (Key::Key4, 0x15), (Key::Key5, 0x17),
+    (Key::Key6, 0x16), (Key::Key7, 0x1a), (Key::Key8, 0x1c), (Key::Key9, 0x19), (Key::Key0, 0x1d),
+    (Key::Q, 0x0c), (Key::W, 0x0d), (Key::E, 0x0e), (Key::R, 0x0f), (Key::T, 0x11),
+    (Key::Y, 0x10), (Key::U, 0x20), (Key::I, 0x22), (Key::O, 0x1f), (Key::P, 0x23),
+    (Key::A, 0x00), (Key::S, 0x01), (Key::D, 0x02), (Key::F, 0x03), (Key::G, 0x05),
+    (Key::H, 0x04), (Key::J, 0x26), (Key::K, 0x28), (Key::L, 0x25),
+    (Key::Z, 0x06), (Key::X, 0x07), (Key::C, 0x08), (Key::V, 0x09), (Key::B, 0x0b),
+    (Key::N, 0x2d), (Key::M, 0x2e),
+];
+
+fn has_position(key: Key) -> bool {
+    PC_POSITIONS.iter().any(|&(us_key, _)| us_key == key)
+}
+
+// The scancode of a key's position on a US board, for the keys bound by position.
+// The web build gets no real scancodes, so it goes by keycode instead.
+fn us_scancode(key: Key) -> Option<u32> {
+    let codes = if cfg!(target_arch = "wasm32") {
+        &[]
+    } else if cfg!(target_os = "macos") {
+        MAC_POSITIONS
+    } else {
+        PC_POSITIONS
+    };
+    codes.iter().find(|&&(us_key, _)| us_key == key).map(|&(_, code)| code)
+}
+
+// The key `binding_name` calls `name`, e.g. "Left" or "A".
+pub fn key_from_name(name: &str) -> Option<Key> {
+    use serde::Deserialize;
+    Key::deserialize(serde::de::value::StrDeserializer::<serde::de::value::Error>::new(name)).ok()
+}
+
+// The keys gameplay reads, as opposed to menu and debug keys.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Control {
+    Left,
+    Right,
+    Shoot,
+    Ability,
+    // Set off by double-tapping or Shift + whatever Left and Right are bound to,
+    // so they have no keys of their own and are down for one frame at a time.
+    DashLeft,
+    DashRight,
+}
+
+impl Control {
+    // The ones with keys of their own, which can be rebound.
+    pub const ALL: [Control; 4] = [Control::Left, Control::Right, Control::Shoot, Control::Ability];
+
+    // Arrows and Space are in the same place on every layout, so only the letters
+    // move with `by_position`.
+    pub fn bindings(self, by_position: bool) -> &'static [Binding] {
+        match (self, by_position) {
+            (Control::Left, _) => &[Binding::Key(Key::Left)],
+            (Control::Right, _) => &[Binding::Key(Key::Right)],
+            (Control::Shoot, _) => &[Binding::Key(Key::Space), Binding::Mouse(FORWARD_BUTTON)],
+            (Control::Ability, false) => &[Binding::Key(Key::X), Binding::Mouse(BACK_BUTTON)],
+            (Control::Ability, true) => &[Binding::Position(Key::X), Binding::Mouse(BACK_BUTTON)],
+            (Control::DashLeft | Control::DashRight, _) => &[],
+        }
+    }
+
+    // The control whose keys set this one off.
+    fn dashes_with(self) -> Option<Control> {
+        match self {
+            Control::DashLeft => Some(Control::Left),
+            Control::DashRight => Some(Control::Right),
+            _ => None,
+        }
+    }
+
+    // What config.toml calls it.
+    pub fn config_name(self) -> &'static str {
+        match self {
+            Control::Left => "left",
+            Control::Right => "right",
+            Control::Shoot => "shoot",
+            Control::Ability => "ability",
+            Control::DashLeft => "dash_left",
+            Control::DashRight => "dash_right",
+        }
+    }
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Control::Left => "LEFT",
+            Control::Right => "RIGHT",
+            Control::Shoot => "FIRE",
+            Control::Ability => "ABILITY",
+            Control::DashLeft => "DASH LEFT",
+            Control::DashRight => "DASH RIGHT",
+        }
+    }
+}
+
+// What a touchscreen stands in for. Dragging a finger steers; a finger that stays
+// put fires for as long as it's down.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TouchButton {
+    Left,
+    Right,
+    Shoot,
+}
+
+impl TouchButton {
+    const COUNT: usize = 3;
+
+    fn for_control(control: Control) -> Option<Self> {
+        match control {
+            Control::Left => Some(TouchButton::Left),
+            Control::Right => Some(TouchButton::Right),
+            Control::Shoot => Some(TouchButton::Shoot),
+            Control::Ability | Control::DashLeft | Control::DashRight => None,
+        }
+    }
+}
+
+// A finger on the screen.
+#[derive(Debug, Clone, Copy)]
+struct Finger {
+    id: u64,
+    start: MousePos<f64>,
+    x: f64,
+    // Where its steering is measured from: where it went down, trailing along
+    // behind it once it's far enough away.
+    anchor: f64,
+    dragging: bool,
+}
+
+impl Finger {
+    fn button(&self) -> Option<TouchButton> {
+        if !self.dragging {
+            Some(TouchButton::Shoot)
+        } else if self.x < self.anchor - DRAG_DEADZONE {
+            Some(TouchButton::Left)
+        } else if self.x > self.anchor + DRAG_DEADZONE {
+            Some(TouchButton::Right)
+        } else {
+            None
+        }
+    }
+}
+
+// A key press that only counts in combination with something else.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Trigger {
+    // `key` pressed while any of `held` is down.
+    Chord { held: &'static [Key], key: Key },
+    // `key` pressed a second time within DOUBLE_TAP_FRAMES of the first.
+    DoubleTap(Key),
+}
 
 pub struct Input {
     now_keys: Box<[bool]>,
@@ -9,16 +279,56 @@ pub struct Input {
     prev_mouse: Box<[bool]>,
     now_mouse_pos: MousePos<f64>,
     prev_mouse_pos: MousePos<f64>,
+    // Same again by scancode, for keys bound by position.
+    now_scans: Box<[bool]>,
+    // The key each scancode last came in as, which is how the layout gets learned
+    // for naming keys bound by position.
+    scan_keys: Box<[Option<Key>]>,
+    // Wheel notches this frame, up positive. Touchpads can leave part of one
+    // over for the next frame.
+    wheel: f64,
+    // The last key to go down this frame, for rebinding.
+    pressed_key: Option<Key>,
+    // Controls given other keys than their usual ones, by config.toml or the
+    // controls menu.
+    rebound: Vec<(Control, Vec<Binding>)>,
+    // Fingers down right now, and the touch buttons they hold. A tap that comes
+    // and goes between two frames still holds its button for the next one.
+    fingers: Vec<Finger>,
+    now_touch: [bool; TouchButton::COUNT],
+    prev_touch: [bool; TouchButton::COUNT],
+    lifted_touch: [bool; TouchButton::COUNT],
+    now_pad: [bool; PadButton::COUNT],
+    prev_pad: [bool; PadButton::COUNT],
+    // Counts calls to next_frame, for double-tap timing.
+    frame: u64,
+    // The frame each key was last pressed on, and whether that press was a double-tap.
+    last_press: Box<[Option<u64>]>,
+    double_tapped: Box<[bool]>,
 }
 impl Default for Input {
     fn default() -> Self {
         Self {
             now_keys: vec![false; 255].into_boxed_slice(),
             prev_keys: vec![false; 255].into_boxed_slice(),
-            now_mouse: vec![false; 16].into_boxed_slice(),
-            prev_mouse: vec![false; 16].into_boxed_slice(),
+            now_mouse: vec![false; MOUSE_BUTTONS].into_boxed_slice(),
+            prev_mouse: vec![false; MOUSE_BUTTONS].into_boxed_slice(),
             now_mouse_pos: MousePos { x: 0.0, y: 0.0 },
             prev_mouse_pos: MousePos { x: 0.0, y: 0.0 },
+            now_scans: vec![false; SCANCODES].into_boxed_slice(),
+            scan_keys: vec![None; SCANCODES].into_boxed_slice(),
+            wheel: 0.0,
+            pressed_key: None,
+            rebound: vec![],
+            fingers: vec![],
+            now_touch: [false; TouchButton::COUNT],
+            prev_touch: [false; TouchButton::COUNT],
+            lifted_touch: [false; TouchButton::COUNT],
+            now_pad: [false; PadButton::COUNT],
+            prev_pad: [false; PadButton::COUNT],
+            frame: 0,
+            last_press: vec![None; 255].into_boxed_slice(),
+            double_tapped: vec![false; 255].into_boxed_slice(),
         }
     }
 }
@@ -36,27 +346,170 @@ impl Input {
     pub fn is_key_released(&self, kc: Key) -> bool {
         !self.now_keys[kc as usize] && self.prev_keys[kc as usize]
     }
+    pub fn pressed_key(&self) -> Option<Key> {
+        self.pressed_key
+    }
+    pub fn is_bound_down(&self, binding: Binding) -> bool {
+        match binding {
+            Binding::Key(key) => self.is_key_down(key),
+            Binding::Mouse(button) => self.is_mouse_down(button),
+            Binding::Position(key) => match us_scancode(key) {
+                Some(code) => self.now_scans[code as usize],
+                None => self.is_key_down(key),
+            },
+        }
+    }
+    pub fn rebind(&mut self, control: Control, bindings: Vec<Binding>) {
+        self.rebound.retain(|(rebound, _)| *rebound != control);
+        self.rebound.push((control, bindings));
+    }
+    // The control's keys, rebound or not.
+    pub fn control_bindings(&self, control: Control, by_position: bool) -> &[Binding] {
+        self.rebound
+            .iter()
+            .find(|(rebound, _)| *rebound == control)
+            .map_or(control.bindings(by_position), |(_, bindings)| bindings.as_slice())
+    }
+    // The key a binding reads, as the player's layout has it. None for the mouse.
+    fn bound_key(&self, binding: Binding) -> Option<Key> {
+        match binding {
+            Binding::Key(key) => Some(key),
+            Binding::Position(key) => Some(self.position_key(key)),
+            Binding::Mouse(_) => None,
+        }
+    }
+    // Makes `key` the control's only key, keeping its mouse buttons. A control
+    // that had `key` already, by name or by position, gets this one's old key in
+    // exchange. Returns the controls that were swapped.
+    pub fn bind_key(&mut self, control: Control, key: Key, by_position: bool) -> Vec<Control> {
+        let old = self.primary_key(control, by_position);
+        let mut swapped = vec![];
+        for other in Control::ALL.into_iter().filter(|&other| other != control) {
+            let bindings = self.control_bindings(other, by_position);
+            if bindings.iter().any(|&binding| self.bound_key(binding) == Some(key)) {
+                let kept = bindings
+                    .iter()
+                    .filter_map(|&binding| match (self.bound_key(binding), old) {
+                        (Some(bound), Some(old)) if bound == key => Some(Binding::Key(old)),
+                        (Some(bound), None) if bound == key => None,
+                        _ => Some(binding),
+                    })
+                    .collect();
+                self.rebind(other, kept);
+                swapped.push(other);
+            }
+        }
+        let mut bindings = vec![Binding::Key(key)];
+        bindings.extend(self.control_bindings(control, by_position).iter().filter(|binding| matches!(binding, Binding::Mouse(_))));
+        self.rebind(control, bindings);
+        swapped
+    }
+    // Back to the built-in keys for everything.
+    pub fn reset_bindings(&mut self) {
+        self.rebound.clear();
+    }
+    // The control's first key, as the player's layout has it. None if it's only
+    // on the mouse.
+    pub fn primary_key(&self, control: Control, by_position: bool) -> Option<Key> {
+        self.control_bindings(control, by_position).iter().find_map(|&binding| self.bound_key(binding))
+    }
+    // What the layout has in US `key`'s place, as far as we've seen.
+    fn position_key(&self, key: Key) -> Key {
+        us_scancode(key).and_then(|code| self.scan_keys[code as usize]).unwrap_or(key)
+    }
+    // Touch counts whatever the control is bound to. Dashes are down on the frame
+    // they're set off.
+    pub fn is_control_down(&self, control: Control, by_position: bool) -> bool {
+        if control.dashes_with().is_some() {
+            return self.triggers(control, by_position).into_iter().any(|trigger| self.is_triggered(trigger));
+        }
+        self.control_bindings(control, by_position).iter().any(|&binding| self.is_bound_down(binding))
+            || TouchButton::for_control(control).is_some_and(|button| self.is_touch_down(button))
+    }
+    // What sets a dash off: a double-tap or a Shift chord on any of its
+    // direction's keys, as they're bound right now.
+    fn triggers(&self, control: Control, by_position: bool) -> Vec<Trigger> {
+        let Some(direction) = control.dashes_with() else {
+            return vec![];
+        };
+        self.control_bindings(direction, by_position)
+            .iter()
+            .filter_map(|&binding| self.bound_key(binding))
+            .flat_map(|key| [Trigger::DoubleTap(key), Trigger::Chord { held: SHIFT, key }])
+            .collect()
+    }
+    pub fn is_touch_down(&self, button: TouchButton) -> bool {
+        self.now_touch[button as usize]
+    }
+    pub fn is_touch_pressed(&self, button: TouchButton) -> bool {
+        self.now_touch[button as usize] && !self.prev_touch[button as usize]
+    }
+    pub fn is_touch_released(&self, button: TouchButton) -> bool {
+        !self.now_touch[button as usize] && self.prev_touch[button as usize]
+    }
+    // What to call a binding. Keys go by what the player's layout calls them; a
+    // position that hasn't been pressed yet goes by its US name, since there's no
+    // asking the OS for the layout.
+    pub fn binding_name(&self, binding: Binding) -> String {
+        match binding {
+            Binding::Key(key) => format!("{:?}", key),
+            Binding::Position(key) => format!("{:?}", self.position_key(key)),
+            Binding::Mouse(BACK_BUTTON) => "Mouse4".to_string(),
+            Binding::Mouse(FORWARD_BUTTON) => "Mouse5".to_string(),
+            Binding::Mouse(button) => format!("{:?}", button),
+        }
+    }
+    // Every control and its keys on one line, e.g. "LEFT Left  RIGHT Right  FIRE Space/Mouse5".
+    pub fn controls_text(&self, by_position: bool) -> String {
+        Control::ALL
+            .iter()
+            .map(|&control| {
+                let keys: Vec<String> = self.control_bindings(control, by_position).iter().map(|&binding| self.binding_name(binding)).collect();
+                format!("{} {}", control.name(), keys.join("/"))
+            })
+            .collect::<Vec<_>>()
+            .join("  ")
+    }
+    pub fn is_double_tapped(&self, kc: Key) -> bool {
+        self.is_key_pressed(kc) && self.double_tapped[kc as usize]
+    }
+    pub fn is_chord_pressed(&self, held: &[Key], kc: Key) -> bool {
+        self.is_key_pressed(kc) && held.iter().any(|&key| self.is_key_down(key))
+    }
+    pub fn is_triggered(&self, trigger: Trigger) -> bool {
+        match trigger {
+            Trigger::Chord { held, key } => self.is_chord_pressed(held, key),
+            Trigger::DoubleTap(key) => self.is_double_tapped(key),
+        }
+    }
     pub fn is_mouse_down(&self, button: MouseButton) -> bool {
-        self.now_mouse[Self::mouse_button_to_usize(button)]
+        Self::mouse_button_to_usize(button).is_some_and(|button| self.now_mouse[button])
     }
-    fn mouse_button_to_usize(button: MouseButton) -> usize {
-        match button {
+    // None for buttons past the end of the table.
+    fn mouse_button_to_usize(button: MouseButton) -> Option<usize> {
+        let index = match button {
             MouseButton::Left => 0,
             MouseButton::Right => 1,
             MouseButton::Middle => 2,
-            MouseButton::Other(n) => n as usize,
-        }
+            MouseButton::Other(n) => 3 + n as usize,
+        };
+        (index < MOUSE_BUTTONS).then_some(index)
     }
     pub fn is_mouse_up(&self, mb: MouseButton) -> bool {
-        !self.now_mouse[Self::mouse_button_to_usize(mb)]
+        !self.is_mouse_down(mb)
     }
     pub fn is_mouse_pressed(&self, mb: MouseButton) -> bool {
-        self.now_mouse[Self::mouse_button_to_usize(mb)]
-            && !self.prev_mouse[Self::mouse_button_to_usize(mb)]
+        Self::mouse_button_to_usize(mb).is_some_and(|mb| self.now_mouse[mb] && !self.prev_mouse[mb])
     }
     pub fn is_mouse_released(&self, mb: MouseButton) -> bool {
-        !self.now_mouse[Self::mouse_button_to_usize(mb)]
-            && self.prev_mouse[Self::mouse_button_to_usize(mb)]
+        Self::mouse_button_to_usize(mb).is_some_and(|mb| !self.now_mouse[mb] && self.prev_mouse[mb])
+    }
+    pub fn is_pad_pressed(&self, button: PadButton) -> bool {
+        self.now_pad[button as usize] && !self.prev_pad[button as usize]
+    }
+    // Whole wheel notches this frame, up positive.
+    pub fn wheel_steps(&self) -> i32 {
+        self.wheel.trunc() as i32
     }
     pub fn mouse_pos(&self) -> MousePos<f64> {
         self.now_mouse_pos
@@ -67,6 +520,14 @@ impl Input {
             y: self.now_mouse_pos.y - self.prev_mouse_pos.y,
         }
     }
+    // Anything held down or the mouse moving, i.e. someone is at the controls.
+    pub fn any_activity(&self) -> bool {
+        self.now_keys.iter().any(|&down| down)
+            || self.now_mouse.iter().any(|&down| down)
+            || self.now_mouse_pos != self.prev_mouse_pos
+            || !self.fingers.is_empty()
+            || self.now_pad.iter().any(|&down| down)
+    }
     pub fn key_axis(&self, down: Key, up: Key) -> f32 {
         (if self.is_key_down(down) { -1.0 } else { 0.0 })
             + (if self.is_key_down(up) { 1.0 } else { 0.0 })
@@ -75,36 +536,282 @@ impl Input {
         self.prev_keys.copy_from_slice(&self.now_keys);
         self.prev_mouse.copy_from_slice(&self.now_mouse);
         self.prev_mouse_pos = self.now_mouse_pos;
+        self.wheel = self.wheel.fract();
+        self.pressed_key = None;
+        self.prev_touch = self.now_touch;
+        self.prev_pad = self.now_pad;
+        self.lifted_touch = [false; TouchButton::COUNT];
+        self.update_touch();
+        self.frame += 1;
     }
     pub fn handle_key_event(&mut self, ke: winit::event::KeyboardInput) {
-        if let winit::event::KeyboardInput {
-            virtual_keycode: Some(keycode),
-            state,
-            ..
-        } = ke
-        {
-            match state {
-                winit::event::ElementState::Pressed => {
-                    self.now_keys[keycode as usize] = true;
-                }
-                winit::event::ElementState::Released => {
-                    self.now_keys[keycode as usize] = false;
-                }
+        let pressed = ke.state == ElementState::Pressed;
+        if let Some(scan) = self.now_scans.get_mut(ke.scancode as usize) {
+            *scan = pressed;
+            if ke.virtual_keycode.is_some() {
+                self.scan_keys[ke.scancode as usize] = ke.virtual_keycode;
             }
         }
+        let Some(keycode) = ke.virtual_keycode else {
+            return;
+        };
+        // Key repeat sends more presses while it's held.
+        if pressed && !self.now_keys[keycode as usize] {
+            self.pressed_key = Some(keycode);
+            let last = self.last_press[keycode as usize].replace(self.frame);
+            // A third quick tap starts a new pair rather than being a second double.
+            let double = last.is_some_and(|last| self.frame - last <= DOUBLE_TAP_FRAMES) && !self.double_tapped[keycode as usize];
+            self.double_tapped[keycode as usize] = double;
+        }
+        self.now_keys[keycode as usize] = pressed;
     }
     pub fn handle_mouse_button(&mut self, state: ElementState, button: MouseButton) {
-        let button = Self::mouse_button_to_usize(button);
-        match state {
-            ElementState::Pressed => {
-                self.now_mouse[button] = true;
-            }
-            ElementState::Released => {
-                self.now_mouse[button] = false;
-            }
+        let button = match button {
+            MouseButton::Other(n) => thumb_buttons().iter().find(|&&(code, _)| code == n).map_or(button, |&(_, thumb)| thumb),
+            _ => button,
+        };
+        if let Some(button) = Self::mouse_button_to_usize(button) {
+            self.now_mouse[button] = state == ElementState::Pressed;
         }
     }
+    pub fn handle_mouse_wheel(&mut self, delta: MouseScrollDelta) {
+        self.wheel += match delta {
+            MouseScrollDelta::LineDelta(_, lines) => lines as f64,
+            MouseScrollDelta::PixelDelta(pixels) => pixels.y / PIXELS_PER_NOTCH,
+        };
+    }
     pub fn handle_mouse_move(&mut self, position: MousePos<f64>) {
         self.now_mouse_pos = position;
     }
+    // Takes every gamepad event since the last call.
+    pub fn poll_gamepads(&mut self, gamepads: &mut gilrs::Gilrs) {
+        while let Some(gilrs::Event { event, .. }) = gamepads.next_event() {
+            let (button, pressed) = match event {
+                gilrs::EventType::ButtonPressed(button, _) => (button, true),
+                gilrs::EventType::ButtonReleased(button, _) => (button, false),
+                _ => continue,
+            };
+            if let Some(button) = PadButton::from_gilrs(button) {
+                self.now_pad[button as usize] = pressed;
+            }
+        }
+    }
+    pub fn handle_touch(&mut self, touch: Touch) {
+        let pos = touch.location;
+        match touch.phase {
+            TouchPhase::Started => self.fingers.push(Finger {
+                id: touch.id,
+                start: pos,
+                x: pos.x,
+                anchor: pos.x,
+                dragging: false,
+            }),
+            TouchPhase::Moved => {
+                if let Some(finger) = self.fingers.iter_mut().find(|finger| finger.id == touch.id) {
+                    finger.x = pos.x;
+                    if !finger.dragging && (pos.x - finger.start.x).hypot(pos.y - finger.start.y) > TAP_SLOP {
+                        finger.dragging = true;
+                    }
+                    finger.anchor = finger.anchor.clamp(pos.x - DRAG_LEASH, pos.x + DRAG_LEASH);
+                }
+            }
+            TouchPhase::Ended | TouchPhase::Cancelled => {
+                if let Some(i) = self.fingers.iter().position(|finger| finger.id == touch.id) {
+                    let finger = self.fingers.remove(i);
+                    if let Some(button) = finger.button() {
+                        self.lifted_touch[button as usize] = true;
+                    }
+                }
+            }
+        }
+        self.update_touch();
+    }
+    fn update_touch(&mut self) {
+        self.now_touch = self.lifted_touch;
+        for button in self.fingers.iter().filter_map(Finger::button) {
+            self.now_touch[button as usize] = true;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn key_event(input: &mut Input, key: Key, state: ElementState) {
+        scan_event(input, 0, key, state);
+    }
+
+    fn scan_event(input: &mut Input, scancode: u32, key: Key, state: ElementState) {
+        #[allow(deprecated)]
+        input.handle_key_event(winit::event::KeyboardInput {
+            scancode,
+            state,
+            virtual_keycode: Some(key),
+            modifiers: winit::event::ModifiersState::empty(),
+        });
+    }
+
+    // Presses and releases `key`, returning whether it dashed left on the press's frame.
+    fn tap(input: &mut Input, key: Key) -> bool {
+        key_event(input, key, ElementState::Pressed);
+        let dashed = input.is_control_down(Control::DashLeft, false);
+        input.next_frame();
+        key_event(input, key, ElementState::Released);
+        input.next_frame();
+        dashed
+    }
+
+    #[test]
+    fn double_taps_and_chords_become_actions() {
+        let mut input = Input::default();
+        assert!(!tap(&mut input, Key::Left));
+        assert!(tap(&mut input, Key::Left));
+        // A third tap right after starts over.
+        assert!(!tap(&mut input, Key::Left));
+
+        for _ in 0..DOUBLE_TAP_FRAMES {
+            input.next_frame();
+        }
+        assert!(!tap(&mut input, Key::Left));
+
+        // Long enough after the last tap that only the chord can count.
+        key_event(&mut input, Key::RShift, ElementState::Pressed);
+        for _ in 0..=DOUBLE_TAP_FRAMES {
+            input.next_frame();
+        }
+        assert!(tap(&mut input, Key::Left));
+        assert!(!tap(&mut input, Key::Right));
+    }
+
+    #[test]
+    fn dashes_follow_the_movement_keys() {
+        let mut input = Input::default();
+        input.bind_key(Control::Left, Key::J, false);
+        assert!(!tap(&mut input, Key::J));
+        assert!(tap(&mut input, Key::J));
+        // Left isn't a movement key any more.
+        for _ in 0..=DOUBLE_TAP_FRAMES {
+            input.next_frame();
+        }
+        key_event(&mut input, Key::LShift, ElementState::Pressed);
+        assert!(!tap(&mut input, Key::Left));
+        assert!(tap(&mut input, Key::J));
+    }
+
+    // The PC scancodes, so not on macOS.
+    #[cfg(not(target_os = "macos"))]
+    #[test]
+    fn pos_bindings_read_the_us_scancode() {
+        let mut input = Input::default();
+        let binding = Binding::from_name("pos:A").unwrap();
+        // A key called A somewhere else, e.g. Q's place on AZERTY, doesn't count.
+        scan_event(&mut input, 16, Key::A, ElementState::Pressed);
+        assert!(!input.is_bound_down(binding));
+        scan_event(&mut input, 30, Key::Q, ElementState::Pressed);
+        assert!(input.is_bound_down(binding));
+        assert_eq!(input.binding_name(binding), "Q");
+
+        assert_eq!(Binding::from_name("pos:Semicolon"), None);
+        assert_eq!(Binding::from_name("Semicolon"), Some(Binding::Key(Key::Semicolon)));
+    }
+
+    #[test]
+    fn keys_bound_by_position_follow_the_layout() {
+        let mut input = Input::default();
+        assert_eq!(input.controls_text(true), "LEFT Left  RIGHT Right  FIRE Space/Mouse5  ABILITY X/Mouse4");
+
+        // On Dvorak, US X's place is Q.
+        let x_position = us_scancode(Key::X).unwrap();
+        scan_event(&mut input, x_position, Key::Q, ElementState::Pressed);
+        assert!(input.is_control_down(Control::Ability, true));
+        assert!(!input.is_control_down(Control::Ability, false));
+        assert_eq!(input.controls_text(true), "LEFT Left  RIGHT Right  FIRE Space/Mouse5  ABILITY Q/Mouse4");
+
+        scan_event(&mut input, x_position, Key::Q, ElementState::Released);
+        assert!(!input.is_control_down(Control::Ability, true));
+    }
+
+    #[test]
+    fn binding_a_taken_key_swaps_it_with_the_control_that_had_it() {
+        let mut input = Input::default();
+        input.bind_key(Control::Shoot, Key::Z, false);
+        input.bind_key(Control::Left, Key::Z, false);
+        assert_eq!(input.controls_text(false), "LEFT Z  RIGHT Right  FIRE Left/Mouse5  ABILITY X/Mouse4");
+        assert_eq!(key_from_name("Left"), Some(Key::Left));
+
+        input.reset_bindings();
+        assert_eq!(input.primary_key(Control::Shoot, false), Some(Key::Space));
+
+        // The ability's X is bound by position, which clashes all the same.
+        let swapped = input.bind_key(Control::Shoot, Key::X, true);
+        assert_eq!(swapped, [Control::Ability]);
+        assert_eq!(input.controls_text(true), "LEFT Left  RIGHT Right  FIRE X/Mouse5  ABILITY Space/Mouse4");
+
+        // What the options file keeps them as.
+        for &binding in Control::Ability.bindings(true).iter().chain(Control::Shoot.bindings(true)) {
+            assert_eq!(Binding::from_name(&binding.config_name().unwrap()), Some(binding));
+        }
+    }
+
+    fn touch(input: &mut Input, id: u64, phase: TouchPhase, x: f64) {
+        input.handle_touch(Touch {
+            device_id: unsafe { winit::event::DeviceId::dummy() },
+            phase,
+            location: MousePos::new(x, 500.0),
+            force: None,
+            id,
+        });
+    }
+
+    #[test]
+    fn dragging_steers_and_tapping_fires() {
+        let mut input = Input::default();
+        // Down and up between two frames still fires for one.
+        touch(&mut input, 0, TouchPhase::Started, 300.0);
+        touch(&mut input, 0, TouchPhase::Ended, 300.0);
+        assert!(input.is_control_down(Control::Shoot, false));
+        assert!(input.is_touch_pressed(TouchButton::Shoot));
+        input.next_frame();
+        assert!(input.is_touch_released(TouchButton::Shoot));
+
+        touch(&mut input, 1, TouchPhase::Started, 300.0);
+        touch(&mut input, 1, TouchPhase::Moved, 260.0);
+        assert!(input.is_control_down(Control::Left, false));
+        assert!(!input.is_control_down(Control::Shoot, false));
+        // Well past the leash, so turning back steers right straight away.
+        touch(&mut input, 1, TouchPhase::Moved, 100.0);
+        touch(&mut input, 1, TouchPhase::Moved, 170.0);
+        assert!(input.is_control_down(Control::Right, false));
+        // A second finger fires while the first steers.
+        touch(&mut input, 2, TouchPhase::Started, 800.0);
+        assert!(input.is_control_down(Control::Shoot, false));
+        assert!(input.is_control_down(Control::Right, false));
+
+        touch(&mut input, 1, TouchPhase::Ended, 170.0);
+        touch(&mut input, 2, TouchPhase::Cancelled, 800.0);
+        input.next_frame();
+        assert!(!Control::ALL.into_iter().any(|control| input.is_control_down(control, false)));
+    }
+
+    #[test]
+    fn thumb_buttons_and_the_wheel() {
+        let mut input = Input::default();
+        let (back, _) = thumb_buttons()[0];
+        input.handle_mouse_button(ElementState::Pressed, MouseButton::Other(back));
+        assert!(input.is_control_down(Control::Ability, false));
+        assert!(!input.is_control_down(Control::Shoot, false));
+        // Past the end of the table, so it's dropped rather than indexing out of bounds.
+        input.handle_mouse_button(ElementState::Pressed, MouseButton::Other(600));
+        assert!(!input.is_mouse_down(MouseButton::Other(600)));
+
+        input.handle_mouse_wheel(MouseScrollDelta::PixelDelta(winit::dpi::PhysicalPosition::new(0.0, -60.0)));
+        assert_eq!(input.wheel_steps(), -1);
+        input.next_frame();
+        // Half a notch carried over.
+        input.handle_mouse_wheel(MouseScrollDelta::PixelDelta(winit::dpi::PhysicalPosition::new(0.0, -20.0)));
+        assert_eq!(input.wheel_steps(), -1);
+        input.next_frame();
+        assert_eq!(input.wheel_steps(), 0);
+    }
 }
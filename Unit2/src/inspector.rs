@@ -0,0 +1,242 @@
+// Dev tool for poking at a running level, turned on with `--inspector`. Left click
+// picks the topmost object under the cursor and lists its fields in the corner of
+// the playfield; right click lets go. While something is picked, Page Up/Page Down
+// give or take a point of its health and Delete removes a bullet or item. Editing
+// anything makes the run ineligible for leaderboards.
+use std::collections::HashMap;
+
+use winit::event::MouseButton;
+
+use crate::camera::Camera;
+use crate::input::{Input, Key};
+use crate::render_target;
+use crate::simulation::SimState;
+use crate::text::TextLabel;
+use crate::{HealthBar, SpriteHandle, SpriteHolder};
+
+const TEXT_SCALE: f32 = 2.0;
+const LINE_HEIGHT: f32 = 20.0;
+const TEXT_TINT: [f32; 4] = [0.6, 1.6, 0.6, 1.0];
+
+// An object in the sim. The number is its place in its list, which shifts as
+// earlier ones are removed, so it's only good for the frame it was looked up in.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Picked {
+    Player,
+    Boss,
+    Minion(usize),
+    MidBoss,
+    Projectile(usize),
+    Item(usize),
+}
+
+// Which object owns each sprite slot. Built from the same per-object sprite lists
+// the sprite audit checks, so it can't drift from what's really allocated.
+pub fn registry(sim: &SimState) -> HashMap<SpriteHandle, Picked> {
+    let mut owners = HashMap::new();
+    // Between levels the player and boss are the between-stages ones.
+    if sim.mode == 0 {
+        return owners;
+    }
+    owners.insert(sim.player.sprite_index, Picked::Player);
+    owners.extend(sim.player_health_bar.sprite_indices().into_iter().map(|index| (index, Picked::Player)));
+    owners.extend(sim.enemy.sprite_indices().into_iter().map(|index| (index, Picked::Boss)));
+    for (i, minion) in sim.minions.iter().enumerate() {
+        owners.extend(minion.sprite_indices().into_iter().map(|index| (index, Picked::Minion(i))));
+    }
+    if let Some(midboss) = &sim.midboss {
+        owners.extend(midboss.sprite_indices().into_iter().map(|index| (index, Picked::MidBoss)));
+    }
+    for (i, proj) in sim.projectiles.iter().enumerate() {
+        owners.extend(proj.sprite_indices().into_iter().map(|index| (index, Picked::Projectile(i))));
+    }
+    for (i, item) in sim.items.iter().enumerate() {
+        owners.extend(item.sprite_indices().into_iter().map(|index| (index, Picked::Item(i))));
+    }
+    owners
+}
+
+// The owned sprite drawn on top at `point`, in world units. Higher layers draw
+// over lower ones, and later slots over earlier ones on the same layer.
+fn pick(
+    owners: &HashMap<SpriteHandle, Picked>,
+    sprite_holder: &SpriteHolder,
+    point: (f32, f32),
+) -> Option<(SpriteHandle, Picked)> {
+    owners
+        .iter()
+        .filter(|(&handle, _)| {
+            let [x, y, w, h] = sprite_holder.sprites[handle.index()].screen_region;
+            point.0 >= x && point.0 <= x + w && point.1 >= y && point.1 <= y + h
+        })
+        .max_by_key(|(&handle, _)| (sprite_holder.layers[handle.index()] as usize, handle.index()))
+        .map(|(&handle, &picked)| (handle, picked))
+}
+
+fn health_bar(sim: &mut SimState, picked: Picked) -> Option<&mut HealthBar> {
+    match picked {
+        Picked::Player => Some(&mut sim.player_health_bar),
+        Picked::Boss => Some(&mut sim.enemy.enemy.health_bar),
+        Picked::Minion(i) => Some(&mut sim.minions[i].enemy.health_bar),
+        Picked::MidBoss => Some(&mut sim.midboss.as_mut()?.entity.as_mut()?.enemy.health_bar),
+        Picked::Projectile(_) | Picked::Item(_) => None,
+    }
+}
+
+fn describe(sim: &SimState, picked: Picked) -> Vec<String> {
+    let pos = |pos: (f32, f32)| format!("POS {:.0} {:.0}", pos.0, pos.1);
+    let health = |bar: &HealthBar| format!("HP {:.0}/{:.0}", bar.currval, bar.maxval);
+    let enemy = |name: &str, entity: &crate::Entity| {
+        vec![name.to_string(), pos(entity.enemy.body.pos), health(&entity.enemy.health_bar)]
+    };
+    match picked {
+        Picked::Player => vec![
+            "PLAYER".to_string(),
+            pos(sim.player.body.pos),
+            health(&sim.player_health_bar),
+            format!("SCORE {}", sim.player.score),
+            format!("CHAIN {}", sim.player.chain),
+        ],
+        Picked::Boss => enemy("BOSS", &sim.enemy),
+        Picked::Minion(i) => enemy("MINION", &sim.minions[i]),
+        Picked::MidBoss => match sim.midboss.as_ref().and_then(|midboss| midboss.entity.as_ref()) {
+            Some(entity) => enemy("MIDBOSS", entity),
+            None => vec![],
+        },
+        Picked::Projectile(i) => {
+            let proj = &sim.projectiles[i];
+            vec![
+                if proj.player_spawned { "SHOT" } else { "BULLET" }.to_string(),
+                pos(proj.body.pos),
+                format!("SPEED {:.1}", proj.speed),
+                format!("AGE {}", proj.age),
+            ]
+        }
+        Picked::Item(i) => {
+            let item = &sim.items[i];
+            vec!["ITEM".to_string(), pos(item.body.pos), format!("{:?}", item.kind).to_uppercase()]
+        }
+    }
+}
+
+pub struct Inspector {
+    // The sprite that was clicked, and what owned it this frame.
+    selected: Option<(SpriteHandle, Picked)>,
+    lines: Vec<(String, TextLabel)>,
+}
+
+impl Inspector {
+    pub fn from_args(mut args: impl Iterator<Item = String>) -> Option<Inspector> {
+        args.find(|arg| arg == "--inspector")?;
+        log::info!("Inspector on: click to pick, right click to let go");
+        Some(Inspector {
+            selected: None,
+            lines: vec![],
+        })
+    }
+
+    // `viewport` is where the frame sits on the window, from `blit_viewport`.
+    pub fn update(&mut self, input: &Input, viewport: [f32; 4], camera: &Camera, sim: &mut SimState) {
+        let owners = registry(sim);
+        // Follow the pick to its new place in its list, and drop it once its
+        // sprite is gone.
+        self.selected = self.selected.and_then(|(handle, _)| Some((handle, *owners.get(&handle)?)));
+
+        if input.is_mouse_pressed(MouseButton::Left) {
+            let cursor = input.mouse_pos();
+            if let Some(render_pos) = render_target::render_pos((cursor.x as f32, cursor.y as f32), viewport) {
+                let screen_pos = sim.playfield.screen_pos(render_pos);
+                self.selected = pick(&owners, &sim.sprite_holder, camera.screen_to_world(&sim.playfield, screen_pos));
+                log::info!("Inspector picked {:?}", self.selected.map(|(_, picked)| picked));
+            }
+        }
+        if input.is_mouse_pressed(MouseButton::Right) {
+            self.selected = None;
+        }
+
+        if let Some((_, picked)) = self.selected {
+            self.edit(input, picked, sim);
+        }
+        let text = self.selected.map_or(vec![], |(_, picked)| describe(sim, picked));
+        self.show(text, &mut sim.sprite_holder);
+        self.draw(sim);
+    }
+
+    fn edit(&mut self, input: &Input, picked: Picked, sim: &mut SimState) {
+        let change = if input.is_key_pressed(Key::PageUp) {
+            1.0
+        } else if input.is_key_pressed(Key::PageDown) {
+            -1.0
+        } else {
+            0.0
+        };
+        let mut edited = false;
+        if let Some(bar) = health_bar(sim, picked).filter(|_| change != 0.0) {
+            bar.currval = (bar.currval + change).clamp(0.0, bar.maxval);
+            edited = true;
+        }
+        if input.is_key_pressed(Key::Delete) {
+            match picked {
+                Picked::Projectile(i) => sim.projectiles[i].kill(),
+                Picked::Item(i) => sim.items[i].is_dead = true,
+                _ => {}
+            }
+            edited |= matches!(picked, Picked::Projectile(_) | Picked::Item(_));
+        }
+        if edited {
+            log::info!("Inspector edited {:?}", picked);
+            sim.leaderboard_eligible = false;
+        }
+    }
+
+    // Rebuilds only the lines whose text changed.
+    fn show(&mut self, text: Vec<String>, sprite_holder: &mut SpriteHolder) {
+        for (_, label) in self.lines.drain(text.len().min(self.lines.len())..) {
+            label.remove(sprite_holder);
+        }
+        for (row, line) in text.into_iter().enumerate() {
+            match self.lines.get_mut(row) {
+                Some((shown, _)) if *shown == line => {}
+                Some(entry) => {
+                    let old = std::mem::replace(entry, (line.clone(), TextLabel::new(&line, sprite_holder)));
+                    old.1.remove(sprite_holder);
+                }
+                None => self.lines.push((line.clone(), TextLabel::new(&line, sprite_holder))),
+            }
+        }
+    }
+
+    fn draw(&self, sim: &mut SimState) {
+        let top = sim.playfield.size.1;
+        for (row, (_, label)) in self.lines.iter().enumerate() {
+            let pos = (8.0, top - (row + 1) as f32 * LINE_HEIGHT);
+            label.draw(pos, TEXT_SCALE, TEXT_TINT, &mut sim.sprite_holder);
+        }
+    }
+
+    pub fn sprite_indices(&self) -> Vec<SpriteHandle> {
+        self.lines.iter().flat_map(|(_, label)| label.sprite_indices()).collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::fixtures;
+
+    #[test]
+    fn clicking_a_bullet_over_the_player_picks_the_bullet() {
+        let mut sim = fixtures::loaded(crate::load_level_1);
+        sim.stage_intro = None;
+        let player = sim.player.body.center();
+        fixtures::enemy_bullet(&mut sim, player, (0.0, -6.0));
+        sim = crate::simulation::simulate(sim, Default::default());
+
+        let owners = registry(&sim);
+        assert_eq!(owners.get(&sim.player.sprite_index), Some(&Picked::Player));
+        let bullet = sim.projectiles.iter().position(|proj| !proj.player_spawned).unwrap();
+        let picked = pick(&owners, &sim.sprite_holder, sim.projectiles[bullet].body.center());
+        assert_eq!(picked.map(|(_, picked)| picked), Some(Picked::Projectile(bullet)));
+        assert_eq!(pick(&owners, &sim.sprite_holder, (-50.0, -50.0)), None);
+    }
+}
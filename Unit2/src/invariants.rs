@@ -0,0 +1,74 @@
+// Sanity checks on the sim between frames, run in debug builds only. Each one is
+// something that should never happen however the level plays out, so a failure
+// means a logic bug, and it's caught on the frame it first shows up instead of
+// whenever it finally becomes visible.
+use crate::game_state::GameState;
+use crate::simulation::SimState;
+
+use std::sync::atomic::{AtomicBool, Ordering};
+
+// How far outside the playfield a sprite may be before it counts as lost.
+const MARGIN: f32 = 512.0;
+// No pattern needs more than this, but versus garbage can add more and the sprite
+// holder grows to fit them, so going over is only worth a warning.
+const CROWDED_PROJECTILES: usize = 600;
+// Whether the warning's been given since the count last dropped back under.
+static WARNED_CROWDED: AtomicBool = AtomicBool::new(false);
+
+// Everything wrong with the sim right now, one line each.
+pub fn find_violations(sim: &SimState) -> Vec<String> {
+    let mut violations = vec![];
+    // Between levels the player and boss are the between-stages ones.
+    if sim.mode == 0 {
+        return violations;
+    }
+
+    let (width, height) = sim.playfield.size;
+    let mut check_region = |name: &str, [x, y, w, h]: [f32; 4], culled: bool| {
+        if [x, y, w, h].iter().any(|value| !value.is_finite()) {
+            violations.push(format!("{} has a non-finite region {:?}", name, [x, y, w, h]));
+        } else if culled && (x + w < -MARGIN || x > width + MARGIN || y + h < -MARGIN || y > height + MARGIN) {
+            violations.push(format!("{} is far outside the playfield at {:?}", name, [x, y, w, h]));
+        }
+    };
+    check_region("player", sim.player.sprite.screen_region, true);
+    let midboss = sim.midboss.as_ref().and_then(|midboss| midboss.entity.as_ref());
+    for (i, entity) in std::iter::once(&sim.enemy).chain(sim.minions.iter()).chain(midboss).enumerate() {
+        check_region(&format!("enemy {}", i), entity.enemy.sprite.screen_region, true);
+    }
+    // Bullets leaving through the sides keep going, so only their numbers are checked.
+    for (i, proj) in sim.projectiles.iter().enumerate() {
+        check_region(&format!("projectile {}", i), proj.sprite.screen_region, false);
+    }
+    for (i, item) in sim.items.iter().enumerate() {
+        check_region(&format!("item {}", i), [item.body.pos.0, item.body.pos.1, 0.0, 0.0], true);
+    }
+
+    let midboss = sim.midboss.as_ref().and_then(|midboss| midboss.entity.as_ref());
+    let enemy_bars = std::iter::once(&sim.enemy).chain(sim.minions.iter()).chain(midboss);
+    let bars = std::iter::once(("player", &sim.player_health_bar)).chain(enemy_bars.map(|entity| ("enemy", &entity.enemy.health_bar)));
+    for (name, bar) in bars {
+        if !(0.0..=bar.maxval).contains(&bar.currval) {
+            violations.push(format!("{} health {} is outside 0..={}", name, bar.currval, bar.maxval));
+        }
+    }
+    violations
+}
+
+// Warns about a crowded screen. Logs any violations, then panics so the bug gets looked at where it starts.
+pub fn check(sim: &SimState, game_state: GameState) {
+    let crowded = sim.projectiles.len() > CROWDED_PROJECTILES;
+    if crowded && !WARNED_CROWDED.swap(true, Ordering::Relaxed) {
+        log::warn!("{} projectiles on frame {}, over {}", sim.projectiles.len(), sim.frame, CROWDED_PROJECTILES);
+    } else if !crowded {
+        WARNED_CROWDED.store(false, Ordering::Relaxed);
+    }
+    let violations = find_violations(sim);
+    if violations.is_empty() {
+        return;
+    }
+    for violation in violations.iter() {
+        log::error!("Invariant broken on frame {} in state {:?}: {}", sim.frame, game_state, violation);
+    }
+    panic!("{} invariant(s) broken on frame {}", violations.len(), sim.frame);
+}
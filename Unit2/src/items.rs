@@ -0,0 +1,70 @@
+// Pickups dropped by defeated minions. They fall toward the player's row and are
+// collected by touching them.
+use crate::components::{self, Body, Velocity};
+use crate::{atlas, GPUSprite, Player, SpriteHandle, SpriteHolder};
+
+const ITEM_SIZE: f32 = 32.0;
+const FALL: Velocity = Velocity(0.0, -3.0);
+const POINTS_TINT: [f32; 4] = [1.6, 1.4, 0.4, 1.0];
+const CHARGE_TINT: [f32; 4] = [0.6, 1.6, 0.6, 1.0];
+pub const ITEM_POINTS: u64 = 500;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ItemKind {
+    Points,
+    // One fruit's worth of charge toward the player's next shot.
+    Charge,
+}
+
+#[derive(Debug, Clone)]
+pub struct Item {
+    pub body: Body,
+    pub kind: ItemKind,
+    pub is_dead: bool,
+    sprite_index: SpriteHandle,
+}
+
+impl Item {
+    // `center` is where the item should start, usually the middle of whatever dropped it.
+    pub fn new(center: (f32, f32), kind: ItemKind, sprite_holder: &mut SpriteHolder) -> Self {
+        Self {
+            body: Body::new((center.0 - ITEM_SIZE / 2.0, center.1 - ITEM_SIZE / 2.0), (ITEM_SIZE, ITEM_SIZE)),
+            kind,
+            is_dead: false,
+            sprite_index: sprite_holder.get_next_index(),
+        }
+    }
+
+    // Falls one frame. Returns true if the player picked the item up this frame.
+    pub fn update(&mut self, player: &Player, sprite_holder: &mut SpriteHolder) -> bool {
+        components::advance(&mut self.body, FALL);
+        if self.body.pos.1 + ITEM_SIZE < 0.0 {
+            self.is_dead = true;
+            return false;
+        }
+        if components::touching(&self.body, &player.body) {
+            self.is_dead = true;
+            return true;
+        }
+
+        let mut sprite = GPUSprite {
+            screen_region: self.body.screen_region(),
+            sheet_region: atlas::sheet_region("player_bullet"),
+        };
+        components::sync_sprite(&self.body, &mut sprite, self.sprite_index, sprite_holder);
+        let tint = match self.kind {
+            ItemKind::Points => POINTS_TINT,
+            ItemKind::Charge => CHARGE_TINT,
+        };
+        sprite_holder.set_tint(self.sprite_index, tint);
+        false
+    }
+
+    pub fn sprite_indices(&self) -> Vec<SpriteHandle> {
+        vec![self.sprite_index]
+    }
+
+    pub fn clean_dead(&self, sprite_holder: &mut SpriteHolder) {
+        sprite_holder.remove_sprite(self.sprite_index);
+    }
+}
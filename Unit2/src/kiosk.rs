@@ -0,0 +1,69 @@
+// Demo-station mode for showcases. Launch with `--kiosk [idle seconds]` and
+// Escape no longer quits a run, the function key toggles and the save reset are
+// switched off, and the game goes back to the title screen once nobody has
+// touched it for a while, ready for the next visitor. `--kiosk 0` never goes back.
+use crate::clock::FRAMES_PER_SECOND;
+use crate::input::Input;
+
+const DEFAULT_IDLE_SECONDS: u64 = 60;
+
+pub struct Kiosk {
+    // None to never time out.
+    idle_limit: Option<u64>,
+    // Steps since the last key, button or mouse movement.
+    idle: u64,
+}
+
+impl Kiosk {
+    // Looks for `--kiosk` in the command line, optionally followed by the idle
+    // timeout in seconds.
+    pub fn from_args(mut args: impl Iterator<Item = String>) -> Option<Kiosk> {
+        args.find(|arg| arg == "--kiosk")?;
+        let seconds = args.next().and_then(|arg| arg.parse().ok()).unwrap_or(DEFAULT_IDLE_SECONDS);
+        if seconds == 0 {
+            log::info!("Kiosk mode: no idle timeout");
+        } else {
+            log::info!("Kiosk mode: back to the title after {} idle seconds", seconds);
+        }
+        Some(Kiosk {
+            idle_limit: (seconds > 0).then_some(seconds * FRAMES_PER_SECOND),
+            idle: 0,
+        })
+    }
+
+    // Call once per step. True on the step the idle timeout runs out, after which
+    // the count starts over.
+    pub fn timed_out(&mut self, input: &Input) -> bool {
+        if input.any_activity() {
+            self.idle = 0;
+            return false;
+        }
+        let Some(limit) = self.idle_limit else {
+            return false;
+        };
+        self.idle += 1;
+        if self.idle < limit {
+            return false;
+        }
+        self.idle = 0;
+        true
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn times_out_once_after_the_idle_limit() {
+        let mut kiosk = Kiosk::from_args(["unit2", "--kiosk", "2"].map(String::from).into_iter()).unwrap();
+        let input = Input::default();
+        let steps: Vec<bool> = (0..3 * FRAMES_PER_SECOND).map(|_| kiosk.timed_out(&input)).collect();
+        assert_eq!(steps.iter().filter(|&&timed_out| timed_out).count(), 1);
+        assert!(steps[(2 * FRAMES_PER_SECOND - 1) as usize]);
+
+        // Zero turns the timeout off rather than firing straight away.
+        let mut kiosk = Kiosk::from_args(["unit2", "--kiosk", "0"].map(String::from).into_iter()).unwrap();
+        assert!((0..3 * FRAMES_PER_SECOND).all(|_| !kiosk.timed_out(&input)));
+    }
+}
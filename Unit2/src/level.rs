@@ -0,0 +1,342 @@
+// Stage layouts, read from `src/content/levels/<name>.ron` each time a stage
+// starts, so a stage can be retuned or rearranged without rebuilding. The path is
+// under the crate root, not the working directory. Every file there is also
+// compiled in by build.rs, and a missing or broken file falls back to that copy.
+use std::sync::OnceLock;
+
+use serde::Deserialize;
+
+use crate::catch_return::CatchReturn;
+use crate::components::{Body, Velocity};
+use crate::density_governor::DensityGovernor;
+use crate::destructibles::DestructibleBlock;
+use crate::hit_feedback::{Faction, HitFlash};
+use crate::midboss::MidBoss;
+use crate::obstacles::{self, Motion, Obstacle};
+use crate::playfield::Playfield;
+use crate::render_target::LEFT_GAUGE_X;
+use crate::scene::Attachment;
+use crate::score_zones::ScoreZone;
+use crate::shot_types::{Special, Trigger};
+use crate::simulation::SimState;
+use crate::stage_intro::StageIntro;
+use crate::survival_timer::SurvivalTimer;
+use crate::time_freeze::TimeFreeze;
+use crate::vulnerability::{self, Vulnerability};
+use crate::{atlas, behavior_tree, enemy_ai, hud, Enemy, Entity, GPUSprite, HealthBar, Layer, Player, SpriteHandle, TINT_NONE};
+
+const LEVEL_DIR: &str = concat!(env!("CARGO_MANIFEST_DIR"), "/src/content/levels");
+const BUILT_IN: &[(&str, &str)] = &include!(concat!(env!("OUT_DIR"), "/levels.rs"));
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct LevelDef {
+    // The sim's mode number while it's running.
+    pub mode: usize,
+    #[serde(default)]
+    pub trigger: Trigger,
+    #[serde(default)]
+    pub playfield: Playfield,
+    // Whether the modifiers picked on the title screen apply.
+    pub modifiers: bool,
+    // The readouts panel beside the playfield.
+    #[serde(default)]
+    pub side_panel: bool,
+    // The shot type's special meter, time freeze or catch-and-return.
+    #[serde(default)]
+    pub special: bool,
+    #[serde(default)]
+    pub survival_frames: Option<u32>,
+    #[serde(default)]
+    pub obstacles: Vec<ObstacleDef>,
+    #[serde(default)]
+    pub blocks: Vec<BlockDef>,
+    #[serde(default)]
+    pub score_zones: Vec<ScoreZoneDef>,
+    pub player: ActorDef,
+    pub player_health: f32,
+    pub boss: ActorDef,
+    pub boss_health: f32,
+    pub ai: AiDef,
+    #[serde(default)]
+    pub midboss: Option<MidBossDef>,
+    // Caps how many bullets can crowd the player, for patterns that can stack up
+    // into walls. The thin-walls modifier adds one where there isn't.
+    #[serde(default)]
+    pub density_governor: Option<DensityGovernor>,
+    // The stage's number and name for its opening banner and countdown.
+    #[serde(default)]
+    pub intro: Option<StageIntro>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Deserialize)]
+pub struct ActorDef {
+    pub pos: (f32, f32),
+    pub size: (f32, f32),
+    pub speed: f32,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub enum ObstacleDef {
+    // Arms evenly spaced around `center`, turning at `speed` radians per frame.
+    Spinner { center: (f32, f32), radius: f32, arms: usize, speed: f32 },
+    Slab { pos: (f32, f32), size: (f32, f32), motion: Motion },
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct BlockDef {
+    pub pos: (f32, f32),
+    pub size: (f32, f32),
+    pub health: u32,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct ScoreZoneDef {
+    pub x_range: (f32, f32),
+    pub multiplier: u64,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct MidBossDef {
+    pub appears_at: u64,
+    pub duration: u32,
+}
+
+// The boss's attack patterns, by name.
+#[derive(Debug, Clone, Deserialize)]
+pub enum AiDef {
+    Idle,
+    FruitRain { max_cooldown: usize },
+    Danmaku,
+    // Another pattern, plus a wave of minions every `interval` frames.
+    Summoner { pattern: Box<AiDef>, interval: usize },
+    // Behaviour written out as a tree; see behavior_tree.rs.
+    Tree(behavior_tree::Node),
+}
+
+impl AiDef {
+    fn build(&self) -> Box<dyn enemy_ai::AI> {
+        match self {
+            AiDef::Idle => Box::new(enemy_ai::Level0AI {}),
+            AiDef::FruitRain { max_cooldown } => Box::new(enemy_ai::Level1AI {
+                max_cooldown: *max_cooldown,
+                cooldown: 0,
+            }),
+            AiDef::Danmaku => Box::new(enemy_ai::Level6AI {
+                cooldown: 0,
+                telegraphs: vec![],
+            }),
+            AiDef::Summoner { pattern, interval } => Box::new(enemy_ai::SummonerAI {
+                pattern: pattern.build(),
+                interval: *interval,
+                cooldown: 0,
+                summons: vec![],
+            }),
+            AiDef::Tree(root) => Box::new(behavior_tree::BehaviorTreeAI::new(root.clone())),
+        }
+    }
+}
+
+fn built_in(name: &str) -> Option<&'static str> {
+    BUILT_IN.iter().find(|(built_in, _)| *built_in == name).map(|(_, text)| *text)
+}
+
+pub fn load(name: &str) -> LevelDef {
+    let path = std::path::Path::new(LEVEL_DIR).join(format!("{}.ron", name));
+    let parsed = std::fs::read_to_string(&path)
+        .map_err(|e| e.to_string())
+        .and_then(|text| ron::from_str(&text).map_err(|e| e.to_string()));
+    match parsed {
+        Ok(def) => return def,
+        Err(e) => log::warn!("Couldn't load {}, using the built-in copy: {}", path.display(), e),
+    }
+    let text = built_in(name).unwrap_or_else(|| panic!("no built-in level named {}", name));
+    ron::from_str(text).expect("built-in levels parse")
+}
+
+// Sets the sim up for a stage. Expects the previous one to have been unloaded.
+pub fn start(sim: &mut SimState, def: &LevelDef) {
+    sim.start_level(def.mode);
+    sim.shot.trigger = def.trigger;
+    sim.playfield = def.playfield;
+    if def.side_panel {
+        sim.side_panel = Some(hud::SidePanel::new(hud::layout(def.mode), &sim.playfield, &mut sim.sprite_holder));
+    }
+    for obstacle in def.obstacles.iter() {
+        match *obstacle {
+            ObstacleDef::Spinner { center, radius, arms, speed } => {
+                sim.obstacles.extend(obstacles::spinner(center, radius, arms, speed, &mut sim.sprite_holder));
+            }
+            ObstacleDef::Slab { pos, size, motion } => {
+                sim.obstacles.push(Obstacle::new(pos, size, motion, &mut sim.sprite_holder));
+            }
+        }
+    }
+    if def.special {
+        match sim.shot.special {
+            Special::TimeFreeze => sim.time_freeze = Some(TimeFreeze::new(&mut sim.sprite_holder)),
+            Special::CatchReturn => sim.catch_return = Some(CatchReturn::new(&mut sim.sprite_holder)),
+        }
+    }
+    if let Some(frames) = def.survival_frames {
+        sim.survival_timer = Some(SurvivalTimer::new(frames, &mut sim.sprite_holder));
+    }
+    sim.blocks = def
+        .blocks
+        .iter()
+        .map(|block| DestructibleBlock::new(block.pos, block.size, block.health, &mut sim.sprite_holder))
+        .collect();
+    sim.score_zones = def
+        .score_zones
+        .iter()
+        .map(|zone| ScoreZone::new(zone.x_range, zone.multiplier, &mut sim.sprite_holder))
+        .collect();
+    sim.player = player(&def.player, sim.sprite_holder.get_next_index());
+    let boss_indices = [Layer::Entities, Layer::Entities, Layer::Labels, Layer::Labels].map(|layer| sim.sprite_holder.get_next_index_on(layer));
+    sim.enemy = boss(&def.boss, def.boss_health, def.ai.build(), boss_indices);
+    let bar_indices = [(); 2].map(|_| sim.sprite_holder.get_next_index_on(Layer::Ui));
+    sim.player_health_bar = player_health_bar(def.player_health, bar_indices);
+    if let Some(midboss) = &def.midboss {
+        sim.midboss = Some(MidBoss::new(midboss.appears_at, midboss.duration));
+    }
+    sim.density_governor = def.density_governor;
+    sim.stage_intro = def.intro.clone();
+    if def.modifiers {
+        let modifiers = sim.modifiers;
+        modifiers.apply_to_level(sim);
+    }
+    if let Some(phase) = sim.start_phase {
+        skip_to_phase(sim, phase);
+    }
+    // Last, so they start from whatever health the modifiers and phase left.
+    sim.enemy.enemy.health_bar.add_ghost(sim.sprite_holder.get_next_index_on(Layer::Labels));
+    sim.player_health_bar.add_ghost(sim.sprite_holder.get_next_index_on(Layer::Ui));
+}
+
+// Puts a freshly started stage's boss at the start of `phase`, with the survival
+// clock run down to match. It's practice, so the run doesn't count for records.
+fn skip_to_phase(sim: &mut SimState, phase: usize) {
+    let skipped = sim.enemy.ai.skip_to_phase(phase);
+    if let Some(survival_timer) = &mut sim.survival_timer {
+        survival_timer.skip(skipped as u32);
+    }
+    // Already there, so it isn't counted as a phase cleared.
+    sim.phase = phase;
+    sim.leaderboard_eligible = false;
+    log::info!("Retrying from phase {} ({} frames in)", phase + 1, skipped);
+}
+
+// The player, boss and health bar left between stages, from the compiled-in
+// `between_stages.ron`. They own no sprite slots, so nothing is drawn.
+pub fn between_stages() -> (Player, Entity, HealthBar) {
+    static DEF: OnceLock<LevelDef> = OnceLock::new();
+    let def = DEF.get_or_init(|| ron::from_str(built_in("between_stages").expect("between_stages.ron is compiled in")).expect("built-in levels parse"));
+    (
+        player(&def.player, SpriteHandle::NONE),
+        boss(&def.boss, def.boss_health, def.ai.build(), [SpriteHandle::NONE; 4]),
+        player_health_bar(def.player_health, [SpriteHandle::NONE; 2]),
+    )
+}
+
+fn player(def: &ActorDef, sprite_index: SpriteHandle) -> Player {
+    Player {
+        body: Body::new(def.pos, def.size),
+        speed: def.speed,
+        velocity: Velocity::default(),
+        sprite_index,
+        facing_right: true,
+        sprite: GPUSprite {
+            screen_region: [32.0, 128.0, 64.0, 64.0],
+            sheet_region: atlas::sheet_region("player_right"),
+        },
+        charges: 0,
+        fire_cooldown: 0,
+        score: 0,
+        chain: 0,
+        flash: HitFlash::new(Faction::Player, TINT_NONE, false),
+        vulnerability: Vulnerability::default(),
+        dash_frames: 0,
+        dash_cooldown: 0,
+    }
+}
+
+// `indices` are the body, eyes, bar border and bar slots.
+fn boss(def: &ActorDef, health: f32, ai: Box<dyn enemy_ai::AI>, indices: [SpriteHandle; 4]) -> Entity {
+    Entity {
+        enemy: Enemy {
+            body: Body::new(def.pos, def.size),
+            speed: def.speed,
+            velocity: Velocity::default(),
+            sprite_index: indices[0],
+            sprite_index_eyes: indices[1],
+            frame: 0.0,
+            sprite: GPUSprite {
+                screen_region: [32.0, 128.0, 64.0, 64.0],
+                sheet_region: atlas::sheet_region("spikey"),
+            },
+            sprite_eyes: GPUSprite {
+                screen_region: [32.0, 128.0, 64.0, 64.0],
+                sheet_region: atlas::sheet_region("spikey_eyes"),
+            },
+            eyes_attachment: Attachment::default(),
+            health_bar_attachment: Some(Attachment::above((64.0, 64.0), (128.0, 24.0), 8.0)),
+            flash: HitFlash::new(Faction::Enemy, TINT_NONE, true),
+            vulnerability: Vulnerability::new(vulnerability::ENEMY_COOLDOWN),
+            health_bar: HealthBar {
+                currval: health,
+                maxval: health,
+                body: Body::new((32.0, 600.0), (128.0, 24.0)),
+                units_per_pixel: 4.0,
+                sprite_border: GPUSprite {
+                    screen_region: [32.0, 32.0, 128.0, 24.0],
+                    sheet_region: atlas::sheet_region("bar_border"),
+                },
+                sprite_index_border: indices[2],
+                sprite_bar: GPUSprite {
+                    screen_region: [32.0, 36.0, 128.0, 16.0],
+                    sheet_region: atlas::sheet_region("bar_fill"),
+                },
+                sprite_index_bar: indices[3],
+                ghost: None,
+            },
+            damage_taken: 0.0,
+        },
+        ai,
+        spawned: false,
+        dead: false,
+    }
+}
+
+// `indices` are the border and bar slots. Sits off the left of the playfield.
+fn player_health_bar(health: f32, indices: [SpriteHandle; 2]) -> HealthBar {
+    HealthBar {
+        currval: health,
+        maxval: health,
+        body: Body::new((LEFT_GAUGE_X, 32.0), (128.0, 24.0)),
+        units_per_pixel: 4.0,
+        sprite_border: GPUSprite {
+            screen_region: [LEFT_GAUGE_X, 32.0, 128.0, 24.0],
+            sheet_region: atlas::sheet_region("bar_border"),
+        },
+        sprite_index_border: indices[0],
+        sprite_bar: GPUSprite {
+            screen_region: [LEFT_GAUGE_X, 36.0, 128.0, 16.0],
+            sheet_region: atlas::sheet_region("player_bar_fill"),
+        },
+        sprite_index_bar: indices[1],
+        ghost: None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn every_built_in_level_parses() {
+        for (name, text) in BUILT_IN {
+            if let Err(e) = ron::from_str::<LevelDef>(text) {
+                panic!("{}: {}", name, e);
+            }
+        }
+    }
+}
@@ -1,20 +1,98 @@
 use bytemuck::{Pod, Zeroable};
-use kira::{
-    manager::{backend::DefaultBackend, AudioManager, AudioManagerSettings},
-    sound::static_sound::{StaticSoundData, StaticSoundSettings},
-};
-use rand::{thread_rng, Rng};
+use kira::manager::{backend::DefaultBackend, AudioManager, AudioManagerSettings};
+use rand::rngs::StdRng;
+use rand::{thread_rng, Rng, SeedableRng};
 use std::borrow::Cow;
 use winit::{
     event::{Event, WindowEvent},
     event_loop::{ControlFlow, EventLoop},
     window::Window,
 };
+#[cfg(test)]
+mod ai_harness;
+mod atlas;
+mod audio;
+mod behavior_tree;
+mod camera;
+#[cfg(not(target_arch = "wasm32"))]
+mod capture;
+mod catch_return;
+mod clock;
+mod combo;
+mod components;
+mod config;
+mod death_report;
+mod density_governor;
+mod destructibles;
+mod dialog;
 mod enemy_ai;
+mod entity_pool;
+#[cfg(test)]
+mod fixtures;
+mod game_events;
+mod game_state;
+mod generated_sprites;
+mod heatmap;
+mod hit_feedback;
+mod hud;
 mod input;
-
-// Sprite Sheet Resolution
-const SPRITE_SHEET_RESOLUTION: (f32, f32) = (12.0, 16.0);
+mod inspector;
+mod invariants;
+mod items;
+mod kiosk;
+mod level;
+mod medals;
+mod midboss;
+mod modifiers;
+mod netplay;
+mod obstacles;
+mod options;
+mod overlay;
+mod palettes;
+mod particles;
+mod pattern_mutator;
+mod playfield;
+mod popups;
+mod projectile_behavior;
+mod projectile_tags;
+mod render_target;
+mod respawn_bubble;
+mod rewind;
+mod run_log;
+mod save;
+mod scene;
+mod scenes;
+mod score_graph;
+mod score_zones;
+mod sheet_viewer;
+mod shot_types;
+mod simulation;
+mod spawn_queue;
+mod sprite_audit;
+mod sprite_buffers;
+mod stage_intro;
+mod survival_timer;
+mod telegraphs;
+mod telemetry;
+mod text;
+mod time_freeze;
+mod trial_room;
+mod tween;
+mod vulnerability;
+mod widgets;
+
+use audio::SoundId;
+use game_state::{transition_to_state, GameState};
+use simulation::SimState;
+use vulnerability::Vulnerability;
+
+const WINDOW_TITLE: &str = "Unit 2";
+
+// Sprite Sheet Resolution, including the rows added by `generated_sprites::extend_sheet`.
+const SPRITE_SHEET_RESOLUTION: (f32, f32) = (12.0, atlas::SHEET_ROWS + 2.0);
+// Mip levels for the sheet: 16px cells down to 4px. Any smaller and neighbouring
+// cells in the atlas bleed into each other.
+const SHEET_MIP_LEVELS: u32 = 3;
 
 #[repr(C)]
 #[derive(Clone, Copy, Zeroable, Pod)]
@@ -36,265 +114,515 @@ struct TransitionFlag {
 
 // A massive struct used to hold every major variable in the game.
 struct GameStateHolder {
-    player: Player,
-    enemy: Entity,
-    sprite_holder: SpriteHolder,
-    projectiles: Vec<Projectile>,
+    sim: SimState,
     input: input::Input,
-    player_health_bar: HealthBar,
-    game_state: GameState,
+    options: options::Options,
+    // Fraction of a frame banked up while the slowdown assist is active.
+    slowdown_credit: f32,
+    game_state: game_state::StateMachine,
+    clock: clock::GameClock,
+    camera: camera::Camera,
+    rewind: rewind::RewindLog,
+    save: save::SaveData,
+    medals: medals::MedalBoard,
+    score_graph: score_graph::ScoreGraph,
+    death_report: death_report::DeathReport,
     background: Screen,
     title_screen: Screen,
     death_screen: Screen,
+    danmaku_death_screen: Screen,
     cleared_screen: Screen,
     win_screen: Screen,
     title_screen_2: Screen,
-    sound_manager: AudioManager,
-    trans_flag: TransitionFlag,
+    sounds: audio::SoundDispatcher,
+    popups: popups::Popups,
+    run_log: run_log::RunLog,
+    intro_banner: stage_intro::IntroBanner,
+    trial_room: trial_room::TrialRoom,
+    // Only set when launched with `--versus`.
+    versus: Option<netplay::VersusSession>,
+    // Only set when launched with `--overlay`.
+    overlay: Option<overlay::StreamOverlay>,
+    // Only set when launched with `--telemetry`.
+    telemetry: Option<telemetry::Telemetry>,
+    // Only set when launched with `--heatmap`.
+    heatmap: Option<heatmap::Heatmap>,
+    // Only set when launched with `--inspector`.
+    inspector: Option<inspector::Inspector>,
+    // Only set when launched with `--kiosk`.
+    kiosk: Option<kiosk::Kiosk>,
 }
 
-struct GameState {
-    // This should be done better... but it isn't.
-    /*
-       0 = Title Screen
-       1 = Gameplay
-       2 = Game Over
-       3 = Stage Cleared
-       4 = You Win!
-       5 = Title 2
-       6 = Danmaku Game
-       7 = Danmaku Game Death Screen
-    */
-    state: usize,
+impl GameStateHolder {
+    // Every sprite slot that's supposed to be in use right now, for the sprite audit.
+    fn sprite_indices(&self) -> Vec<SpriteHandle> {
+        let mut indices: Vec<SpriteHandle> = [
+            &self.background,
+            &self.title_screen,
+            &self.death_screen,
+            &self.danmaku_death_screen,
+            &self.cleared_screen,
+            &self.win_screen,
+            &self.title_screen_2,
+        ]
+        .iter()
+        .map(|screen| screen.sprite_index)
+        .collect();
+        indices.extend(self.popups.sprite_indices());
+        indices.extend(self.intro_banner.sprite_indices());
+        indices.extend(self.medals.sprite_indices());
+        indices.extend(self.score_graph.sprite_indices());
+        indices.extend(self.death_report.sprite_indices());
+        indices.extend(self.game_state.sprite_indices());
+        indices.extend(self.trial_room.sprite_indices());
+        if let Some(heatmap) = &self.heatmap {
+            indices.extend(heatmap.sprite_indices());
+        }
+        if let Some(inspector) = &self.inspector {
+            indices.extend(inspector.sprite_indices());
+        }
+        indices.extend(self.sim.sprite_indices());
+        indices
+    }
 }
 
 struct Screen {
     sprite: GPUSprite,
-    sprite_index: usize,
+    sprite_index: SpriteHandle,
+}
+
+// Multiplies a sprite's colour by nothing at all.
+const TINT_NONE: [f32; 4] = [1.0, 1.0, 1.0, 1.0];
+
+// One sprite slot, as handed out by `SpriteHolder`. Slots are reused once freed,
+// so a handle also remembers which use of the slot it was for. One kept past its
+// sprite's removal no longer matches, and can't touch whatever has the slot now.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct SpriteHandle {
+    index: usize,
+    generation: u32,
+}
+
+impl SpriteHandle {
+    // Never valid. For objects that don't own a sprite, like the between-stages
+    // player between levels, and for when the holder is full.
+    const NONE: SpriteHandle = SpriteHandle {
+        index: 0,
+        generation: u32::MAX,
+    };
+
+    // Its slot in the sprite holder. The renderer draws slots in layer order, so
+    // this isn't where it sits in the GPU buffers; see `SpriteBuffers::position`.
+    fn index(self) -> usize {
+        self.index
+    }
+}
+
+// The sprite holder doubles in size when it runs out, up to this many slots. Past
+// that new sprites get `SpriteHandle::NONE` and simply aren't drawn; nothing that
+// is already on screen is ever evicted for them.
+const MAX_SPRITES: usize = 16384;
+// Clean runs shorter than this between two dirty ones are uploaded anyway, since
+// one bigger write is cheaper than two small ones.
+const DIRTY_MERGE_GAP: usize = 8;
+
+// Draw order, back to front. Sprites on the same layer are drawn in slot order.
+// Everything but the UI is in world coordinates and moves with the camera; the UI
+// is in screen coordinates, where the world would be with the camera at rest.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Layer {
+    Background,
+    #[default]
+    Entities,
+    Projectiles,
+    // Text and health bars that belong to something in the world, over everything
+    // else in it.
+    Labels,
+    Ui,
 }
 
 #[derive(Debug, Clone)]
 pub struct SpriteHolder {
     sprites: Vec<GPUSprite>,
+    // Colour multiplier for each sprite, uploaded alongside `sprites`.
+    tints: Vec<[f32; 4]>,
+    // Recolour for each sprite, as a `palettes::Palette` number.
+    palettes: Vec<u32>,
     active: Vec<bool>,
+    // Bumped every time a slot is freed, which retires the handles to it.
+    generations: Vec<u32>,
+    // Slots changed since the last upload to the GPU.
+    dirty: Vec<bool>,
+    layers: Vec<Layer>,
+    // Set whenever a slot changes layer, so the renderer knows to re-sort.
+    layers_changed: bool,
 }
 
 impl SpriteHolder {
-    // Gets the next free index for adding a new sprite.
-    fn get_next_index(&mut self) -> usize {
+    fn new(capacity: usize) -> Self {
+        Self {
+            sprites: vec![GPUSprite::zeroed(); capacity],
+            tints: vec![TINT_NONE; capacity],
+            palettes: vec![palettes::Palette::Sheet as u32; capacity],
+            active: vec![false; capacity],
+            generations: vec![0; capacity],
+            dirty: vec![true; capacity],
+            layers: vec![Layer::default(); capacity],
+            layers_changed: true,
+        }
+    }
+
+    // Gets the next free slot for adding a new sprite.
+    fn get_next_index(&mut self) -> SpriteHandle {
+        self.get_next_index_on(Layer::default())
+    }
+
+    // Like get_next_index, for sprites that belong in front of or behind the
+    // entities.
+    fn get_next_index_on(&mut self, layer: Layer) -> SpriteHandle {
         for i in 0..self.active.len() {
             // Optionals are great.
-            match self.active.get(i) {
-                Some(b) => {
-                    if !b {
-                        self.active[i] = true;
-                        return i;
-                    }
+            if let Some(false) = self.active.get(i) {
+                self.active[i] = true;
+                if self.layers[i] != layer {
+                    self.layers[i] = layer;
+                    self.layers_changed = true;
                 }
-                None => {}
+                return SpriteHandle {
+                    index: i,
+                    generation: self.generations[i],
+                };
             }
         }
 
-        // This case will never happen but rust thinks it might.
-        return 0;
+        // Full: grow rather than hand out a slot that's already drawing something.
+        // The renderer reallocates its buffers when it sees the new size.
+        let capacity = self.active.len();
+        if capacity >= MAX_SPRITES {
+            log::warn!("Out of sprite slots, all {} are in use", capacity);
+            return SpriteHandle::NONE;
+        }
+        let grown = (capacity * 2).clamp(1, MAX_SPRITES);
+        log::info!("Growing the sprite holder from {} to {} slots", capacity, grown);
+        self.sprites.resize(grown, GPUSprite::zeroed());
+        self.tints.resize(grown, TINT_NONE);
+        self.palettes.resize(grown, palettes::Palette::Sheet as u32);
+        self.active.resize(grown, false);
+        self.generations.resize(grown, 0);
+        self.dirty.resize(grown, true);
+        self.layers.resize(grown, Layer::default());
+        self.layers_changed = true;
+        self.get_next_index_on(layer)
+    }
+
+    // Whether the handle is for the slot's current sprite.
+    fn is_live(&self, handle: SpriteHandle) -> bool {
+        self.active.get(handle.index) == Some(&true) && self.generations[handle.index] == handle.generation
     }
 
     // When an object dies, remove its sprite to prevent lingering graphics
-    fn remove_sprite(&mut self, sprite_index: usize) {
+    fn remove_sprite(&mut self, handle: SpriteHandle) {
+        if !self.is_live(handle) {
+            if handle != SpriteHandle::NONE {
+                log::warn!("Ignoring removal of stale sprite {:?}", handle);
+            }
+            return;
+        }
+        let sprite_index = handle.index;
         // Open up the sprite to be used by a future object.
         self.active[sprite_index] = false;
+        self.generations[sprite_index] = self.generations[sprite_index].wrapping_add(1) % u32::MAX;
         // And disable rendering for the sprite (by zeroing all its values)
         self.sprites[sprite_index] = GPUSprite::zeroed();
+        self.tints[sprite_index] = TINT_NONE;
+        self.palettes[sprite_index] = palettes::Palette::Sheet as u32;
+        self.dirty[sprite_index] = true;
     }
 
-    fn set_sprite(&mut self, sprite_index: usize, sprite: GPUSprite) {
-        // Flag the sprite as in use.
-        self.active[sprite_index] = true;
-        // Set the sprite data as passed.
-        self.sprites[sprite_index] = sprite;
+    // Writes to a removed sprite are dropped, so a stale handle can't draw over
+    // the slot's next owner.
+    fn set_sprite(&mut self, handle: SpriteHandle, sprite: GPUSprite) {
+        if self.is_live(handle) && self.sprites[handle.index] != sprite {
+            self.sprites[handle.index] = sprite;
+            self.dirty[handle.index] = true;
+        }
+    }
+
+    fn set_tint(&mut self, handle: SpriteHandle, tint: [f32; 4]) {
+        if self.is_live(handle) && self.tints[handle.index] != tint {
+            self.tints[handle.index] = tint;
+            self.dirty[handle.index] = true;
+        }
+    }
+
+    fn set_palette(&mut self, handle: SpriteHandle, palette: palettes::Palette) {
+        if self.is_live(handle) && self.palettes[handle.index] != palette as u32 {
+            self.palettes[handle.index] = palette as u32;
+            self.dirty[handle.index] = true;
+        }
+    }
+
+    // The sprite data behind a live handle. Assumed changed, since there's no
+    // telling what the caller does with it.
+    fn sprite_mut(&mut self, handle: SpriteHandle) -> Option<&mut GPUSprite> {
+        match self.is_live(handle) {
+            true => {
+                self.dirty[handle.index] = true;
+                Some(&mut self.sprites[handle.index])
+            }
+            false => None,
+        }
+    }
+
+    // The runs of slots changed since the last call, in order, with small gaps
+    // between them merged in. Everything comes back clean afterwards.
+    fn take_dirty_spans(&mut self) -> Vec<std::ops::Range<usize>> {
+        let dirty = self.dirty.iter_mut().enumerate().filter_map(|(index, dirty)| std::mem::take(dirty).then_some(index));
+        merge_spans(dirty)
+    }
+
+    // Whether any slot has changed layer since the last call.
+    fn take_layers_changed(&mut self) -> bool {
+        std::mem::take(&mut self.layers_changed)
     }
 }
 
+// Groups ascending indices into runs, bridging gaps of up to DIRTY_MERGE_GAP.
+fn merge_spans(indices: impl IntoIterator<Item = usize>) -> Vec<std::ops::Range<usize>> {
+    let mut spans: Vec<std::ops::Range<usize>> = vec![];
+    for index in indices {
+        match spans.last_mut() {
+            Some(span) if index - span.end <= DIRTY_MERGE_GAP => span.end = index + 1,
+            _ => spans.push(index..index + 1),
+        }
+    }
+    spans
+}
+
 #[derive(Debug, Clone, PartialEq)]
 pub struct Projectile {
-    pos: (f32, f32),
-    size: (f32, f32),
+    body: components::Body,
+    // Where it was before this frame's move, so same-frame hits can be ordered.
+    prev_pos: (f32, f32),
+    // Motion is a speed along a unit direction, so speed can be scaled or changed
+    // without touching where the bullet is headed.
     speed: f32,
-    velocity: (f32, f32),
-    sprite_index: usize,
+    direction: (f32, f32),
+    sprite_index: SpriteHandle,
     sprite: GPUSprite,
     is_dead: bool,
     player_spawned: bool,
+    // Set once the bullet has passed close to the player, so each one only grazes once.
+    grazed: bool,
+    behavior: projectile_behavior::ProjectileBehavior,
+    // Frames since it was made, and how many of those it spends fading in. It
+    // can't hit or be hit until it's fully there.
+    age: u32,
+    warm_up: u32,
+    // What fired it, for cancelling bullets in groups.
+    tag: projectile_tags::ProjectileTag,
 }
 
 impl Projectile {
+    // A stopped bullet keeps its old direction.
+    fn set_velocity(&mut self, velocity: (f32, f32)) {
+        self.speed = (velocity.0 * velocity.0 + velocity.1 * velocity.1).sqrt();
+        if self.speed > 0.0 {
+            self.direction = (velocity.0 / self.speed, velocity.1 / self.speed);
+        }
+    }
+
+    fn collidable(&self) -> bool {
+        self.age >= self.warm_up
+    }
+
+    // 0.0 when just made, up to 1.0 once warmed up.
+    fn warm_up_alpha(&self) -> f32 {
+        if self.collidable() {
+            return 1.0;
+        }
+        self.age as f32 / self.warm_up as f32
+    }
+
     // Called each frame to move the projectile
-    fn move_proj(&mut self, player_health_bar: &mut HealthBar, sound_manager: &mut AudioManager, trans_flag: &mut TransitionFlag, game_state: usize) {
+    // `time_scale` changes the projectile's speed for this frame only, e.g. inside a time-freeze bubble.
+    fn move_proj(&mut self, events: &mut game_events::EventBus, time_scale: f32) {
+        self.age = self.age.saturating_add(1);
         // Move down by <speed> amount
-        self.pos = (self.pos.0 + self.velocity.0, self.pos.1 + self.velocity.1);
+        self.prev_pos = self.body.pos;
+        let step = self.speed * time_scale;
+        components::advance(&mut self.body, components::Velocity(self.direction.0 * step, self.direction.1 * step));
 
-        if self.pos.1 < 0.0 {
+        if self.body.pos.1 < 0.0 {
             self.kill();
-            if game_state == 1 {
-                let sound_data =
-                StaticSoundData::from_file("src/content/projectile_missed.ogg", StaticSoundSettings::default())
-                    .unwrap();
-    
-                let _ = sound_manager.play(sound_data);
-                Player::damage(1.0, player_health_bar, trans_flag, 1);
+            if !self.player_spawned {
+                events.push(game_events::GameEvent::ProjectileMissed);
             }
         }
         // Remove if too high
-        else if self.pos.1 > 1000.0 {
+        else if self.body.pos.1 > 1000.0 {
             self.kill();
         }
-
-        // Update sprite location.
-        self.sprite.screen_region = [self.pos.0, self.pos.1, self.size.0, self.size.1];
     }
 
     fn check_collision(
         &mut self,
         player: &mut Player,
         enemy: &mut Enemy,
-        sound_manager: &mut AudioManager,
-        trans_flag: &mut TransitionFlag,
-        player_health_bar: &mut HealthBar,
+        events: &mut game_events::EventBus,
+        feedback: &mut hit_feedback::HitFeedback,
         game_state: usize,
+        score_multiplier: u64,
     ) {
         if self.player_spawned {
-            // Check for collision
-            if self.pos.1 <= enemy.pos.1 + enemy.size.1
-                && self.pos.1 + self.size.1 >= enemy.pos.1
-                && self.pos.0 <= enemy.pos.0 + enemy.size.0
-                && self.pos.0 + self.size.0 >= enemy.pos.0
-            {
-                let sound_data =
-                    StaticSoundData::from_file("src/content/enemy_hit.ogg", StaticSoundSettings::default())
-                        .unwrap();
-
-                let _ = sound_manager.play(sound_data);
-
+            if components::touching(&self.body, &enemy.body) {
                 // Handle logic.
-                enemy.damage(1.0, trans_flag);
+                if enemy.hit(1.0, feedback) {
+                    events.push(game_events::GameEvent::EnemyKilled(game_events::Foe::Boss));
+                }
+                player.score += 100 * score_multiplier;
+                player.chain += 1;
                 // If colliding, remove projectile
                 self.kill();
             }
         } else {
-            // Check for collision
-            if self.pos.1 <= player.pos.1 + player.size.1
-                && self.pos.1 + self.size.1 >= player.pos.1
-                && self.pos.0 <= player.pos.0 + player.size.0
-                && self.pos.0 + self.size.0 >= player.pos.0
-            {
+            if components::touching(&self.body, &player.body) {
                 if game_state == 1 {
-                    let sound_data =
-                    StaticSoundData::from_file("src/content/player_hit.ogg", StaticSoundSettings::default())
-                        .unwrap();
-
-                    let _ = sound_manager.play(sound_data);
-                    // Handle logic.
-                    player.charges += 1;
-                }
-                if game_state == 6 {
-                    Player::damage(1.0, player_health_bar, trans_flag, 6);
+                    events.push(game_events::GameEvent::ProjectileCaught);
                 }
+                // Hits in state 6 are resolved before this, in time-of-impact order.
                 // If colliding, remove projectile
                 self.kill();
-            } else {
-                // TODO: player missed
             }
         }
     }
 
+    // Makes an enemy bullet the player's, for the catch-and-return special.
+    fn turn_around(&mut self, velocity: (f32, f32), sprite_holder: &mut SpriteHolder) {
+        self.behavior.remove_sprites(sprite_holder);
+        self.behavior = projectile_behavior::ProjectileBehavior::Straight;
+        self.player_spawned = true;
+        self.warm_up = 0;
+        self.set_velocity(velocity);
+        self.sprite.sheet_region = atlas::sheet_region("player_bullet");
+    }
+
     fn kill(&mut self) {
         self.is_dead = true;
     }
 
     fn clean_dead(&mut self, sprite_holder: &mut SpriteHolder) {
         sprite_holder.remove_sprite(self.sprite_index);
+        self.behavior.remove_sprites(sprite_holder);
+    }
+
+    fn sprite_indices(&self) -> Vec<SpriteHandle> {
+        let mut indices = vec![self.sprite_index];
+        indices.extend(self.behavior.sprite_indices());
+        indices
     }
 }
 
 pub struct Player {
-    pos: (f32, f32),
-    size: (f32, f32),
+    body: components::Body,
     speed: f32,
-    velocity: (f32, f32),
-    sprite_index: usize,
+    velocity: components::Velocity,
+    sprite_index: SpriteHandle,
     facing_right: bool,
     sprite: GPUSprite,
     charges: usize,
+    // Frames until a Held trigger can fire again.
+    fire_cooldown: u32,
+    score: u64,
+    // Catches, grazes and boss hits in a row without taking damage.
+    chain: u32,
+    flash: hit_feedback::HitFlash,
+    // At most one hit per frame, however many bullets overlap at once.
+    vulnerability: Vulnerability,
+    // Frames left of the current dash, and until the next one is allowed.
+    dash_frames: u32,
+    dash_cooldown: u32,
 }
 
+const DASH_FRAMES: u32 = 8;
+const DASH_SPEED_SCALE: f32 = 3.0;
+// Counted from the start of the dash.
+const DASH_COOLDOWN_FRAMES: u32 = 40;
+
 impl Player {
-    fn player_loop(&mut self, sprite_holder: &mut SpriteHolder) {
+    fn center(&self) -> (f32, f32) {
+        self.body.center()
+    }
+
+    // Starts a dash unless one was used too recently.
+    fn dash(&mut self) {
+        if self.dash_cooldown == 0 {
+            self.dash_frames = DASH_FRAMES;
+            self.dash_cooldown = DASH_COOLDOWN_FRAMES;
+        }
+    }
+
+    fn move_step(&mut self, playfield: &playfield::Playfield, obstacles: &[obstacles::Obstacle]) {
+        let start = self.body.pos;
+        let speed = if self.dash_frames > 0 { self.speed * DASH_SPEED_SCALE } else { self.speed };
+        self.dash_frames = self.dash_frames.saturating_sub(1);
+        self.dash_cooldown = self.dash_cooldown.saturating_sub(1);
         if self.velocity.0 > 0.0 {
-            self.pos = (self.pos.0 + self.speed, self.pos.1);
-            if self.pos.0 > 960.0 {
-                self.pos.0 = 960.0;
-            }
+            self.body.pos = (playfield.confine_x(self.body.pos.0 + speed), self.body.pos.1);
             self.facing_right = true;
         }
         if self.velocity.0 < 0.0 {
-            self.pos = (self.pos.0 - self.speed, self.pos.1);
-            if self.pos.0 < 0.0 {
-                self.pos.0 = 0.0;
-            }
+            self.body.pos = (playfield.confine_x(self.body.pos.0 - speed), self.body.pos.1);
             self.facing_right = false;
         }
-
-        self.sprite.screen_region = [self.pos.0, self.pos.1, self.size.0, self.size.1];
-
-        if self.facing_right {
-            set_sprite(&mut self.sprite, (0.0, 0.0))
-        } else {
-            set_sprite(&mut self.sprite, (2.0, 0.0))
+        // Obstacles are solid, unless one has moved on top of the player. Then any
+        // step is allowed so they can get back out.
+        if obstacles::blocks(obstacles, self.body.pos, self.body.size) && !obstacles::blocks(obstacles, start, self.body.size) {
+            self.body.pos = start;
         }
-
-        // Sync sprite to Sprite Holder.
-        sprite_holder.set_sprite(self.sprite_index, self.sprite);
     }
 
-    fn damage(amount: f32, player_health_bar: &mut HealthBar, trans_flag: &mut TransitionFlag, game_state: usize) {
-        player_health_bar.currval -= amount;
-        if player_health_bar.currval <= 0.0 {
-            if game_state == 1 {
-                trans_flag.val = 2;
-            }
-            else if game_state == 6 {
-                trans_flag.val = 7;
-            }
-        }
+    fn sync_sprite(&mut self, sprite_holder: &mut SpriteHolder) {
+        self.sprite.sheet_region = atlas::sheet_region(if self.facing_right { "player_right" } else { "player_left" });
+        components::sync_sprite(&self.body, &mut self.sprite, self.sprite_index, sprite_holder);
     }
 
-    fn add_speed(&mut self, new_velocity: (f32, f32)) {
-        self.velocity = (
-            self.velocity.0 + new_velocity.0,
-            self.velocity.1 + new_velocity.1,
-        );
+    // Returns false if i-frames turned the hit away.
+    fn damage(&mut self, amount: f32, player_health_bar: &mut HealthBar, feedback: &mut hit_feedback::HitFeedback) -> bool {
+        if !self.vulnerability.try_hit() {
+            return false;
+        }
+        player_health_bar.currval = (player_health_bar.currval - amount).max(0.0);
+        let center = self.body.center();
+        self.flash.hit(center, feedback);
+        true
     }
 
     fn spawn_new_projectile(
         &mut self,
         speed: f32,
+        shot: &shot_types::ShotConfig,
         projectiles: &mut Vec<Projectile>,
         sprite_holder: &mut SpriteHolder,
-        sound_manager: &mut AudioManager,
+        sounds: &mut Vec<SoundId>,
     ) {
         // Shoot if player has enough juice. 3 Apples = 1 Orange, ofc.
-        if self.charges >= 3 {
-            let sound_data =
-                StaticSoundData::from_file("src/content/player_shoot.ogg", StaticSoundSettings::default())
-                    .unwrap();
-            let _ = sound_manager.play(sound_data);
-            // Set velocity based on a random angle.
-            let velocity = (0.0, speed);
-            let pos = (self.pos.0, self.pos.1 + self.size.1);
-            make_player_projectile(projectiles, sprite_holder.get_next_index(), pos, velocity);
+        let ready = match shot.trigger {
+            shot_types::Trigger::Charge => self.charges >= shot.charge_cost(),
+            shot_types::Trigger::Held => self.fire_cooldown == 0,
+        };
+        if ready {
+            sounds.push(SoundId::PlayerShoot);
+            for (offset, velocity) in shot.volley(speed) {
+                let pos = (self.body.pos.0 + offset, self.body.pos.1 + self.body.size.1);
+                make_player_projectile(projectiles, sprite_holder.get_next_index_on(Layer::Projectiles), pos, velocity);
+            }
 
             // Reset juice.
-            self.charges = 0;
+            match shot.trigger {
+                shot_types::Trigger::Charge => self.charges = 0,
+                shot_types::Trigger::Held => self.fire_cooldown = shot.cooldown_frames(),
+            }
         }
     }
 }
@@ -302,118 +630,266 @@ impl Player {
 // Speed would matter if the enemy were able to move, but it doesn't in our current levels.
 #[allow(dead_code)]
 pub struct Enemy {
-    pos: (f32, f32),
-    size: (f32, f32),
+    body: components::Body,
     speed: f32,
-    velocity: (f32, f32),
+    velocity: components::Velocity,
     frame: f32,
-    sprite_index: usize,
-    sprite_index_eyes: usize,
+    sprite_index: SpriteHandle,
+    sprite_index_eyes: SpriteHandle,
     sprite: GPUSprite,
     sprite_eyes: GPUSprite,
+    // Where the eyes and health bar sit relative to the body. An enemy whose bar
+    // has no attachment keeps it wherever it was put, e.g. pinned to a screen corner.
+    eyes_attachment: scene::Attachment,
+    health_bar_attachment: Option<scene::Attachment>,
+    flash: hit_feedback::HitFlash,
+    // Enemies use no cooldown: every player shot counts, even several in one frame.
+    vulnerability: Vulnerability,
     health_bar: HealthBar,
+    // Damage from hits that landed since the AI last heard about them.
+    damage_taken: f32,
 }
 
 impl Enemy {
-    fn spawn_new_projectile(
+    // Fires from wherever the enemy is now. `offset` is from its bottom-left corner
+    // to the bullet's, and is up to the AI.
+    fn spawn_new_projectile(&self, spawns: &mut spawn_queue::SpawnQueue, offset: (f32, f32), velocity: (f32, f32)) {
+        self.spawn_projectile_with_behavior(spawns, offset, velocity, projectile_behavior::ProjectileBehavior::Straight);
+    }
+
+    // Like spawn_new_projectile, for bullets that do more than fly straight.
+    fn spawn_projectile_with_behavior(
         &self,
-        projectiles: &mut Vec<Projectile>,
-        sprite_holder: &mut SpriteHolder,
+        spawns: &mut spawn_queue::SpawnQueue,
+        offset: (f32, f32),
         velocity: (f32, f32),
-    ) {                 
-        // let sound_data =
-        // StaticSoundData::from_file("src/content/enemy_shoot.ogg", StaticSoundSettings::default())
-        //     .unwrap();
-
-        // sound_manager.play(sound_data);
-        // Set velocity based on a random angle.
-        let pos = (450.0 + thread_rng().gen_range(-20..=20) as f32, 650.0);
-        make_projectile(projectiles, sprite_holder.get_next_index(), pos, velocity)
+        behavior: projectile_behavior::ProjectileBehavior,
+    ) {
+        let pos = (self.body.pos.0 + offset.0, self.body.pos.1 + offset.1);
+        spawns.push_with_behavior(pos, velocity, behavior);
     }
 
-    fn damage(&mut self, amount: f32, trans_flag: &mut TransitionFlag) {
-        self.health_bar.currval -= amount;
-        if self.health_bar.currval <= 0.0 {
-            trans_flag.val = 4;
+    // Every hit on an enemy goes through here. Returns true if it was the killing blow.
+    fn hit(&mut self, amount: f32, feedback: &mut hit_feedback::HitFeedback) -> bool {
+        if !self.vulnerability.try_hit() {
+            return false;
         }
+        // Several shots can land on the frame it dies. Health stops at zero.
+        self.health_bar.currval = (self.health_bar.currval - amount).max(0.0);
+        self.damage_taken += amount;
+        let center = self.body.center();
+        self.flash.hit(center, feedback);
+        self.health_bar.currval <= 0.0
     }
 }
 
 struct Entity {
     enemy: Enemy,
     ai: Box<dyn enemy_ai::AI>,
+    // Whether the AI's on_spawn has run.
+    spawned: bool,
+    // Whether the AI has heard about its death. Health stops at zero, so later
+    // hits can't be told apart from the killing one by health alone.
+    dead: bool,
 }
 
 impl Entity {
-    fn enemy_loop(&mut self, projectiles: &mut Vec<Projectile>, sprite_holder: &mut SpriteHolder) {
-        self.enemy.pos = (
-            self.enemy.pos.0 + self.enemy.velocity.0,
-            self.enemy.pos.1 + self.enemy.velocity.1,
-        );
+    // Lets the AI fire this frame's bullets and summons.
+    fn think(&mut self, spawns: &mut spawn_queue::SpawnQueue, player: (f32, f32), rng: &mut StdRng) {
+        if !self.spawned {
+            self.spawned = true;
+            self.ai.on_spawn(spawns, &self.enemy, rng);
+        }
+        self.ai.ai_loop(spawns, &self.enemy, player, rng);
+        if let Some(velocity) = self.ai.velocity() {
+            self.enemy.velocity = velocity.into();
+        }
+    }
 
-        // Sync the base sprite to screen position.
-        self.enemy.sprite.screen_region = [
-            self.enemy.pos.0,
-            self.enemy.pos.1,
-            self.enemy.size.0,
-            self.enemy.size.1,
-        ];
+    // Swaps in a new pattern, which gets its own on_spawn.
+    fn set_ai(&mut self, ai: Box<dyn enemy_ai::AI>) {
+        self.ai = ai;
+        self.spawned = false;
+    }
+
+    // Tells the AI about this frame's hits, and about its death if they finished it.
+    fn react(&mut self, spawns: &mut spawn_queue::SpawnQueue, rng: &mut StdRng) {
+        let damage = std::mem::take(&mut self.enemy.damage_taken);
+        if damage <= 0.0 {
+            return;
+        }
+        self.ai.on_damage(damage, &self.enemy);
+        if self.enemy.health_bar.currval <= 0.0 && !self.dead {
+            self.dead = true;
+            self.ai.on_death(spawns, &self.enemy, rng);
+        }
+    }
+
+    fn move_step(&mut self) {
+        components::advance(&mut self.enemy.body, self.enemy.velocity);
+        if let Some(attachment) = self.enemy.health_bar_attachment {
+            self.enemy.health_bar.body.pos = attachment.resolve(self.enemy.body.pos);
+        }
+        self.enemy.frame += 0.05;
+    }
 
+    fn sync_sprites(&mut self, sprite_holder: &mut SpriteHolder) {
         // Animate the spikes of the spikey boi.
-        if (self.enemy.frame * 20.0) as usize % 20 == 0 {
-            self.enemy.sprite.sheet_region = [
-                1.0 / SPRITE_SHEET_RESOLUTION.0,
-                1.0 / SPRITE_SHEET_RESOLUTION.1,
-                1.0 / SPRITE_SHEET_RESOLUTION.0,
-                1.0 / SPRITE_SHEET_RESOLUTION.1,
-            ];
-        } else if (self.enemy.frame * 20.0) as usize % 10 == 0 {
-            self.enemy.sprite.sheet_region = [
-                2.0 / SPRITE_SHEET_RESOLUTION.0,
-                1.0 / SPRITE_SHEET_RESOLUTION.1,
-                1.0 / SPRITE_SHEET_RESOLUTION.0,
-                1.0 / SPRITE_SHEET_RESOLUTION.1,
-            ];
+        if ((self.enemy.frame * 20.0) as usize).is_multiple_of(20) {
+            self.enemy.sprite.sheet_region = atlas::sheet_region("spikey");
+        } else if ((self.enemy.frame * 20.0) as usize).is_multiple_of(10) {
+            self.enemy.sprite.sheet_region = atlas::sheet_region("spikey_spiked");
         }
 
         // Sync the eyes sprite to the screen pos and animate bob.
-        self.enemy.sprite_eyes.screen_region = [
-            self.enemy.pos.0,
-            self.enemy.pos.1 - 2.0 + 4.0 * self.enemy.frame.sin(),
-            self.enemy.size.0,
-            self.enemy.size.1,
-        ];
+        let bob = (0.0, -2.0 + 4.0 * self.enemy.frame.sin());
+        self.enemy.sprite_eyes.screen_region = self
+            .enemy
+            .eyes_attachment
+            .place((self.enemy.body.pos.0 + bob.0, self.enemy.body.pos.1 + bob.1), self.enemy.body.size);
 
-        self.ai.ai_loop(projectiles, sprite_holder, &self.enemy);
+        components::sync_sprite(&self.enemy.body, &mut self.enemy.sprite, self.enemy.sprite_index, sprite_holder);
+        sprite_holder.set_sprite(self.enemy.sprite_index_eyes, self.enemy.sprite_eyes);
 
-        self.enemy.health_bar.bar_pos = (
-            self.enemy.pos.0 - 32.0,
-            self.enemy.pos.1 + 72.0,
-            self.enemy.health_bar.bar_pos.2,
-            self.enemy.health_bar.bar_pos.3,
-        );
+        self.enemy.health_bar.health_bar_loop(sprite_holder);
+    }
 
-        self.enemy.frame += 0.05;
+    fn sprite_indices(&self) -> Vec<SpriteHandle> {
+        let mut indices = vec![self.enemy.sprite_index, self.enemy.sprite_index_eyes];
+        indices.extend(self.enemy.health_bar.sprite_indices());
+        indices
+    }
 
-        sprite_holder.set_sprite(self.enemy.sprite_index, self.enemy.sprite);
-        sprite_holder.set_sprite(self.enemy.sprite_index_eyes, self.enemy.sprite_eyes);
+    fn remove_sprites(&self, sprite_holder: &mut SpriteHolder) {
+        sprite_holder.remove_sprite(self.enemy.sprite_index);
+        sprite_holder.remove_sprite(self.enemy.sprite_index_eyes);
+        self.enemy.health_bar.remove_sprites(sprite_holder);
+    }
+}
 
-        self.enemy.health_bar.health_bar_loop(sprite_holder);
+// A small spikey boi called in by a summoner. Same sprites as the boss, scaled down.
+// `indices` are for its body, eyes, health bar border and health bar.
+fn make_minion(summon: enemy_ai::Summon, indices: [SpriteHandle; 4], ai: Box<dyn enemy_ai::AI>, sprite_holder: &mut SpriteHolder) -> Entity {
+    let (health, tint, palette) = if summon.strong {
+        (6.0, TINT_NONE, palettes::Palette::Crimson)
+    } else {
+        // Purple, so they don't get mistaken for the boss.
+        (3.0, [1.2, 0.6, 1.4, 1.0], palettes::Palette::Sheet)
+    };
+    let minion = Entity {
+        enemy: Enemy {
+            body: components::Body::new(summon.pos, (40.0, 40.0)),
+            speed: 0.0,
+            velocity: summon.velocity.into(),
+            sprite_index: indices[0],
+            sprite_index_eyes: indices[1],
+            frame: 0.0,
+            sprite: GPUSprite {
+                screen_region: [summon.pos.0, summon.pos.1, 40.0, 40.0],
+                sheet_region: atlas::sheet_region("spikey"),
+            },
+            sprite_eyes: GPUSprite {
+                screen_region: [summon.pos.0, summon.pos.1, 40.0, 40.0],
+                sheet_region: atlas::sheet_region("spikey_eyes"),
+            },
+            eyes_attachment: scene::Attachment::default(),
+            health_bar_attachment: Some(scene::Attachment::above((40.0, 40.0), (48.0, 12.0), 8.0)),
+            flash: hit_feedback::HitFlash::new(hit_feedback::Faction::Enemy, tint, true),
+            vulnerability: Vulnerability::new(vulnerability::ENEMY_COOLDOWN),
+            health_bar: HealthBar {
+                currval: health,
+                maxval: health,
+                body: components::Body::new(summon.pos, (48.0, 12.0)),
+                units_per_pixel: 2.0,
+                sprite_border: GPUSprite {
+                    screen_region: [summon.pos.0, summon.pos.1, 48.0, 12.0],
+                    sheet_region: atlas::sheet_region("bar_border"),
+                },
+                sprite_index_border: indices[2],
+                sprite_bar: GPUSprite {
+                    screen_region: [summon.pos.0, summon.pos.1, 48.0, 8.0],
+                    sheet_region: atlas::sheet_region("bar_fill"),
+                },
+                sprite_index_bar: indices[3],
+                ghost: None,
+            },
+            damage_taken: 0.0,
+        },
+        ai,
+        spawned: false,
+        dead: false,
+    };
+    sprite_holder.set_palette(minion.enemy.sprite_index, palette);
+    minion
+}
+
+// How long the chunk of health just lost takes to drain away behind the bar.
+const GHOST_FRAMES: u32 = 40;
+const GHOST_TINT: [f32; 4] = [1.8, 1.8, 1.8, 0.6];
+
+// The health just lost, left showing past the end of the bar and then drained
+// away, so a big hit reads as big. Only for show; the sim never looks at it.
+struct Ghost {
+    sprite_index: SpriteHandle,
+    value: f32,
+    // Where the drain is headed, so another hit can restart it.
+    target: f32,
+    drain: Option<tween::Tween>,
+}
+
+impl Ghost {
+    // Follows `currval` down, slowly at first, and jumps straight up with it.
+    fn update(&mut self, currval: f32, sprite_holder: &mut SpriteHolder) -> f32 {
+        if currval >= self.value {
+            self.value = currval;
+            self.target = currval;
+            self.drain = None;
+        } else if currval != self.target {
+            self.target = currval;
+            self.drain = Some(tween::Tween::new(self.value, currval, GHOST_FRAMES, tween::Easing::InCubic));
+        }
+        if let Some(drain) = &mut self.drain {
+            self.value = drain.step(sprite_holder);
+        }
+        self.value
     }
 }
 
 struct HealthBar {
     currval: f32,
     maxval: f32,
-    bar_pos: (f32, f32, f32, f32),
+    body: components::Body,
     units_per_pixel: f32,
     sprite_bar: GPUSprite,
     sprite_border: GPUSprite,
-    sprite_index_bar: usize,
-    sprite_index_border: usize,
+    sprite_index_bar: SpriteHandle,
+    sprite_index_border: SpriteHandle,
+    ghost: Option<Ghost>,
 }
 
 impl HealthBar {
+    // Drawn on top of the bar, so `sprite_index` should come after the bar's.
+    fn add_ghost(&mut self, sprite_index: SpriteHandle) {
+        self.ghost = Some(Ghost {
+            sprite_index,
+            value: self.currval,
+            target: self.currval,
+            drain: None,
+        });
+    }
+
+    fn sprite_indices(&self) -> Vec<SpriteHandle> {
+        let mut indices = vec![self.sprite_index_bar, self.sprite_index_border];
+        indices.extend(self.ghost.as_ref().map(|ghost| ghost.sprite_index));
+        indices
+    }
+
+    fn remove_sprites(&self, sprite_holder: &mut SpriteHolder) {
+        for sprite_index in self.sprite_indices() {
+            sprite_holder.remove_sprite(sprite_index);
+        }
+    }
+
     fn health_bar_loop(&mut self, sprite_holder: &mut SpriteHolder) {
         // Prevent Health Bar Underflow
         if self.currval < 0.0 {
@@ -421,21 +897,28 @@ impl HealthBar {
         }
 
         self.sprite_bar.screen_region = [
-            self.bar_pos.0,
-            self.bar_pos.1 + self.units_per_pixel,
-            self.bar_pos.2 * (self.currval / self.maxval),
-            self.bar_pos.3 - (2.0 * self.units_per_pixel),
+            self.body.pos.0,
+            self.body.pos.1 + self.units_per_pixel,
+            self.body.size.0 * (self.currval / self.maxval),
+            self.body.size.1 - (2.0 * self.units_per_pixel),
         ];
-
-        self.sprite_border.screen_region = [
-            self.bar_pos.0,
-            self.bar_pos.1,
-            self.bar_pos.2,
-            self.bar_pos.3,
-        ];
-
         sprite_holder.set_sprite(self.sprite_index_bar, self.sprite_bar);
-        sprite_holder.set_sprite(self.sprite_index_border, self.sprite_border);
+        components::sync_sprite(&self.body, &mut self.sprite_border, self.sprite_index_border, sprite_holder);
+
+        if let Some(ghost) = &mut self.ghost {
+            let lost = ghost.update(self.currval, sprite_holder) - self.currval;
+            let ghost_sprite = GPUSprite {
+                screen_region: [
+                    self.sprite_bar.screen_region[0] + self.sprite_bar.screen_region[2],
+                    self.sprite_bar.screen_region[1],
+                    self.body.size.0 * (lost / self.maxval),
+                    self.sprite_bar.screen_region[3],
+                ],
+                sheet_region: self.sprite_bar.sheet_region,
+            };
+            sprite_holder.set_sprite(ghost.sprite_index, ghost_sprite);
+            sprite_holder.set_tint(ghost.sprite_index, GHOST_TINT);
+        }
     }
 }
 
@@ -445,9 +928,24 @@ const USE_STORAGE: bool = true;
 const USE_STORAGE: bool = false;
 
 async fn run(event_loop: EventLoop<()>, window: Window) {
-    // Initial game state. This object controls the state of the game.
-    let game_state = GameState { state: 0 };
+    let versus = netplay::VersusSession::from_args(std::env::args());
 
+    // Initial game state. This object controls the state of the game.
+    // Versus players, and anyone who's already cleared stage 1, skip straight to
+    // the danmaku title.
+    let save = save::SaveData::load();
+    let skip_stage_1 = versus.is_some() || save.has_cleared(1);
+    let game_state = game_state::StateMachine::new(if skip_stage_1 { GameState::Title2 } else { GameState::Title });
+
+    let settings = config::Config::load();
+    let options = options::Options::load();
+    match settings.window.size {
+        Some((width, height)) => window.set_inner_size(winit::dpi::PhysicalSize::new(width, height)),
+        None => window.set_inner_size(options.window_scale.window_size()),
+    }
+    if settings.window.fullscreen {
+        window.set_fullscreen(Some(winit::window::Fullscreen::Borderless(None)));
+    }
     let size = window.inner_size();
 
     log::info!("Use storage? {:?}", USE_STORAGE);
@@ -567,6 +1065,28 @@ async fn run(event_loop: EventLoop<()>, window: Window) {
                     // No count, not a buffer array binding
                     count: None,
                 },
+                // Per-sprite tints, same indices as the sprites
+                wgpu::BindGroupLayoutEntry {
+                    binding: 2,
+                    visibility: wgpu::ShaderStages::VERTEX,
+                    ty: wgpu::BindingType::Buffer {
+                        ty: wgpu::BufferBindingType::Storage { read_only: true },
+                        has_dynamic_offset: false,
+                        min_binding_size: None,
+                    },
+                    count: None,
+                },
+                // Per-sprite palettes, same indices again
+                wgpu::BindGroupLayoutEntry {
+                    binding: 3,
+                    visibility: wgpu::ShaderStages::VERTEX,
+                    ty: wgpu::BindingType::Buffer {
+                        ty: wgpu::BufferBindingType::Storage { read_only: true },
+                        has_dynamic_offset: false,
+                        min_binding_size: None,
+                    },
+                    count: None,
+                },
             ],
         })
     } else {
@@ -584,47 +1104,82 @@ async fn run(event_loop: EventLoop<()>, window: Window) {
     let swapchain_capabilities = surface.get_capabilities(&adapter);
     let swapchain_format = swapchain_capabilities.formats[0];
 
-    let render_pipeline = device.create_render_pipeline(&wgpu::RenderPipelineDescriptor {
-        label: None,
-        layout: Some(&pipeline_layout),
-        vertex: wgpu::VertexState {
-            module: &shader,
-            entry_point: if USE_STORAGE {
-                "vs_storage_main"
-            } else {
-                "vs_vbuf_main"
-            },
-            buffers: if USE_STORAGE {
-                &[]
-            } else {
-                &[wgpu::VertexBufferLayout {
-                    array_stride: std::mem::size_of::<GPUSprite>() as u64,
-                    step_mode: wgpu::VertexStepMode::Instance,
-                    attributes: &[
-                        wgpu::VertexAttribute {
-                            format: wgpu::VertexFormat::Float32x4,
-                            offset: 0,
-                            shader_location: 0,
+    // Every sprite pipeline shares the vertex stage and only swaps the fragment shader.
+    let create_sprite_pipeline = |fragment_entry_point: &str| {
+        device.create_render_pipeline(&wgpu::RenderPipelineDescriptor {
+            label: None,
+            layout: Some(&pipeline_layout),
+            vertex: wgpu::VertexState {
+                module: &shader,
+                entry_point: if USE_STORAGE {
+                    "vs_storage_main"
+                } else {
+                    "vs_vbuf_main"
+                },
+                buffers: if USE_STORAGE {
+                    &[]
+                } else {
+                    &[
+                        wgpu::VertexBufferLayout {
+                            array_stride: std::mem::size_of::<GPUSprite>() as u64,
+                            step_mode: wgpu::VertexStepMode::Instance,
+                            attributes: &[
+                                wgpu::VertexAttribute {
+                                    format: wgpu::VertexFormat::Float32x4,
+                                    offset: 0,
+                                    shader_location: 0,
+                                },
+                                wgpu::VertexAttribute {
+                                    format: wgpu::VertexFormat::Float32x4,
+                                    offset: std::mem::size_of::<[f32; 4]>() as u64,
+                                    shader_location: 1,
+                                },
+                            ],
+                        },
+                        // Per-sprite tints
+                        wgpu::VertexBufferLayout {
+                            array_stride: std::mem::size_of::<[f32; 4]>() as u64,
+                            step_mode: wgpu::VertexStepMode::Instance,
+                            attributes: &[wgpu::VertexAttribute {
+                                format: wgpu::VertexFormat::Float32x4,
+                                offset: 0,
+                                shader_location: 2,
+                            }],
                         },
-                        wgpu::VertexAttribute {
-                            format: wgpu::VertexFormat::Float32x4,
-                            offset: std::mem::size_of::<[f32; 4]>() as u64,
-                            shader_location: 1,
+                        // Per-sprite palettes
+                        wgpu::VertexBufferLayout {
+                            array_stride: std::mem::size_of::<u32>() as u64,
+                            step_mode: wgpu::VertexStepMode::Instance,
+                            attributes: &[wgpu::VertexAttribute {
+                                format: wgpu::VertexFormat::Uint32,
+                                offset: 0,
+                                shader_location: 3,
+                            }],
                         },
-                    ],
-                }]
+                    ]
+                },
             },
-        },
-        fragment: Some(wgpu::FragmentState {
-            module: &shader,
-            entry_point: "fs_main",
-            targets: &[Some(swapchain_format.into())],
-        }),
-        primitive: wgpu::PrimitiveState::default(),
-        depth_stencil: None,
-        multisample: wgpu::MultisampleState::default(),
-        multiview: None,
-    });
+            fragment: Some(wgpu::FragmentState {
+                module: &shader,
+                entry_point: fragment_entry_point,
+                // Blended so tints with alpha below 1 show what's underneath.
+                targets: &[Some(wgpu::ColorTargetState {
+                    format: swapchain_format,
+                    blend: Some(wgpu::BlendState::ALPHA_BLENDING),
+                    write_mask: wgpu::ColorWrites::ALL,
+                })],
+            }),
+            primitive: wgpu::PrimitiveState::default(),
+            depth_stencil: None,
+            multisample: wgpu::MultisampleState::default(),
+            multiview: None,
+        })
+    };
+    let render_pipeline = create_sprite_pipeline("fs_main");
+    // Draws a dark outline around the player and enemy, see Options::sprite_outlines.
+    let outline_pipeline = create_sprite_pipeline("fs_outline");
+    // Draws the player's silhouette on top, see Options::player_silhouette.
+    let silhouette_pipeline = create_sprite_pipeline("fs_silhouette");
 
     let mut config = wgpu::SurfaceConfiguration {
         usage: wgpu::TextureUsages::RENDER_ATTACHMENT,
@@ -638,12 +1193,29 @@ async fn run(event_loop: EventLoop<()>, window: Window) {
 
     surface.configure(&device, &config);
 
+    // Sprites are drawn into this fixed size texture, which is then stretched onto the window.
+    let render_target =
+        render_target::RenderTarget::new(&device, swapchain_format, &texture_bind_group_layout);
+    // No clips on the web, see capture.rs.
+    #[cfg(not(target_arch = "wasm32"))]
+    let mut clip_recorder = capture::ClipRecorder::new(&device, swapchain_format);
+    // Menus can be driven from a gamepad. Without one, or without gamepad support
+    // on this platform, the keyboard still works.
+    let mut gamepads = gilrs::Gilrs::new()
+        .map_err(|e| log::warn!("No gamepad support: {}", e))
+        .ok();
+
     let (sprite_tex, _sprite_img) =
-        load_texture("src/content/spritesheet.png", None, &device, &queue)
-            .await
+        load_texture(atlas::SHEET_PNG, None, Some(generated_sprites::extend_sheet), SHEET_MIP_LEVELS, &device, &queue)
             .expect("Couldn't load spritesheet texture");
     let view_sprite = sprite_tex.create_view(&wgpu::TextureViewDescriptor::default());
-    let sampler_sprite = device.create_sampler(&wgpu::SamplerDescriptor::default());
+    // Enlarged sprites stay sharp pixel art; shrunk ones blend between mip levels
+    // instead of shimmering as they move.
+    let sampler_sprite = device.create_sampler(&wgpu::SamplerDescriptor {
+        min_filter: wgpu::FilterMode::Linear,
+        mipmap_filter: wgpu::FilterMode::Linear,
+        ..Default::default()
+    });
     let texture_bind_group = device.create_bind_group(&wgpu::BindGroupDescriptor {
         label: None,
         layout: &texture_bind_group_layout,
@@ -659,247 +1231,111 @@ async fn run(event_loop: EventLoop<()>, window: Window) {
             },
         ],
     });
-    let camera = GPUCamera {
-        screen_pos: [0.0, 0.0],
-        screen_size: [1024.0, 768.0],
-    };
-    let buffer_camera = device.create_buffer(&wgpu::BufferDescriptor {
-        label: None,
-        size: bytemuck::bytes_of(&camera).len() as u64,
-        usage: wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST,
-        mapped_at_creation: false,
-    });
-    let mut sprite_holder = SpriteHolder {
-        sprites: vec![GPUSprite::zeroed(); 1000],
-        active: vec![false; 1000],
-    };
-    let buffer_sprite = device.create_buffer(&wgpu::BufferDescriptor {
-        label: None,
-        size: sprite_holder.sprites.len() as u64 * std::mem::size_of::<GPUSprite>() as u64,
-        usage: if USE_STORAGE {
-            wgpu::BufferUsages::STORAGE
-        } else {
-            wgpu::BufferUsages::VERTEX
-        } | wgpu::BufferUsages::COPY_DST,
-        mapped_at_creation: false,
-    });
-    let sprite_bind_group = if USE_STORAGE {
-        device.create_bind_group(&wgpu::BindGroupDescriptor {
-            label: None,
-            layout: &sprite_bind_group_layout,
-            entries: &[
-                wgpu::BindGroupEntry {
-                    binding: 0,
-                    resource: buffer_camera.as_entire_binding(),
-                },
-                wgpu::BindGroupEntry {
-                    binding: 1,
-                    resource: buffer_sprite.as_entire_binding(),
-                },
-            ],
-        })
-    } else {
-        device.create_bind_group(&wgpu::BindGroupDescriptor {
-            label: None,
-            layout: &sprite_bind_group_layout,
-            entries: &[wgpu::BindGroupEntry {
-                binding: 0,
-                resource: buffer_camera.as_entire_binding(),
-            }],
-        })
+    // Replaced every frame by the current level's cameras.
+    let camera = playfield::Playfield::default().camera();
+    let camera_buffers = sprite_buffers::CameraBuffers::new(&device);
+    let mut sprite_holder = SpriteHolder::new(1000);
+    let mut sprite_buffers = sprite_buffers::SpriteBuffers::new(&device, &sprite_bind_group_layout, &camera_buffers, sprite_holder.sprites.len());
+    camera_buffers.write(&queue, camera, camera);
+    sprite_buffers.upload(&device, &queue, &sprite_bind_group_layout, &camera_buffers, &mut sprite_holder);
+
+    let audio_settings = AudioManagerSettings {
+        main_track_builder: kira::track::TrackBuilder::new().volume(settings.audio.master_volume.clamp(0.0, 1.0)),
+        ..AudioManagerSettings::default()
     };
-    queue.write_buffer(&buffer_camera, 0, bytemuck::bytes_of(&camera));
-    queue.write_buffer(
-        &buffer_sprite,
-        0,
-        bytemuck::cast_slice(&sprite_holder.sprites),
-    );
-
-    let sound_manager =
-        AudioManager::<DefaultBackend>::new(AudioManagerSettings::default()).unwrap();
+    let sounds = audio::SoundDispatcher::new(AudioManager::<DefaultBackend>::new(audio_settings).unwrap());
 
     // No one should read this mess of a declaration.
     // Contains a bunch of initial data for starting the game.
     let mut gso = GameStateHolder {
-        game_state: game_state,
-        player: Player {
-            pos: (400.0, 100.0),
-            size: (64.0, 64.0),
-            speed: 6.0,
-            velocity: (0.0, 0.0),
-            sprite_index: 0,
-            facing_right: true,
-            sprite: GPUSprite {
-                screen_region: [32.0, 128.0, 64.0, 64.0],
-                sheet_region: [
-                    0.0 / SPRITE_SHEET_RESOLUTION.0,
-                    0.0 / SPRITE_SHEET_RESOLUTION.1,
-                    1.0 / SPRITE_SHEET_RESOLUTION.0,
-                    1.0 / SPRITE_SHEET_RESOLUTION.1,
-                ],
-            },
-            charges: 0,
-        },
-        enemy: Entity {
-            enemy: Enemy {
-                pos: (450.0, 650.0),
-                size: (64.0, 64.0),
-                speed: 6.0,
-                velocity: (0.0, 0.0),
-                sprite_index: 0,
-                sprite_index_eyes: 0,
-                frame: 0.0,
-                sprite: GPUSprite {
-                    screen_region: [32.0, 128.0, 64.0, 64.0],
-                    sheet_region: [
-                        1.0 / SPRITE_SHEET_RESOLUTION.0,
-                        1.0 / SPRITE_SHEET_RESOLUTION.1,
-                        1.0 / SPRITE_SHEET_RESOLUTION.0,
-                        1.0 / SPRITE_SHEET_RESOLUTION.1,
-                    ],
-                },
-                sprite_eyes: GPUSprite {
-                    screen_region: [32.0, 128.0, 64.0, 64.0],
-                    sheet_region: [
-                        3.0 / SPRITE_SHEET_RESOLUTION.0,
-                        1.0 / SPRITE_SHEET_RESOLUTION.1,
-                        1.0 / SPRITE_SHEET_RESOLUTION.0,
-                        1.0 / SPRITE_SHEET_RESOLUTION.1,
-                    ],
-                },
-                health_bar: HealthBar {
-                    currval: 10.0,
-                    maxval: 10.0,
-                    bar_pos: (32.0, 600.0, 128.0, 24.0),
-                    units_per_pixel: 4.0,
-                    sprite_border: GPUSprite {
-                        screen_region: [32.0, 32.0, 128.0, 24.0],
-                        sheet_region: [
-                            0.0 / SPRITE_SHEET_RESOLUTION.0,
-                            2.0 / SPRITE_SHEET_RESOLUTION.1,
-                            2.0 / SPRITE_SHEET_RESOLUTION.0,
-                            (6.0 / 16.0) / SPRITE_SHEET_RESOLUTION.1,
-                        ],
-                    },
-                    sprite_index_border: 0,
-                    sprite_bar: GPUSprite {
-                        screen_region: [32.0, 36.0, 128.0, 16.0],
-                        sheet_region: [
-                            0.0 / SPRITE_SHEET_RESOLUTION.0,
-                            (2.0 + (12.0 / 16.0)) / SPRITE_SHEET_RESOLUTION.1,
-                            2.0 / SPRITE_SHEET_RESOLUTION.0,
-                            (4.0 / 16.0) / SPRITE_SHEET_RESOLUTION.1,
-                        ],
-                    },
-                    sprite_index_bar: 0,
-                },
-            },
-            ai: Box::new(enemy_ai::Level0AI {}),
-        },
-        projectiles: vec![],
+        game_state,
+        clock: clock::GameClock::default(),
+        camera: camera::Camera::new(settings.camera),
+        rewind: rewind::RewindLog::default(),
+        save,
+        medals: medals::MedalBoard::default(),
+        score_graph: score_graph::ScoreGraph::default(),
+        death_report: death_report::DeathReport::default(),
         input: input::Input::default(),
-        player_health_bar: HealthBar {
-            currval: 10.0,
-            maxval: 10.0,
-            bar_pos: (32.0, 32.0, 128.0, 24.0),
-            units_per_pixel: 4.0,
-            sprite_border: GPUSprite {
-                screen_region: [32.0, 32.0, 128.0, 24.0],
-                sheet_region: [
-                    0.0 / SPRITE_SHEET_RESOLUTION.0,
-                    2.0 / SPRITE_SHEET_RESOLUTION.1,
-                    2.0 / SPRITE_SHEET_RESOLUTION.0,
-                    (6.0 / 16.0) / SPRITE_SHEET_RESOLUTION.1,
-                ],
-            },
-            sprite_index_border: 0,
-            sprite_bar: GPUSprite {
-                screen_region: [32.0, 36.0, 128.0, 16.0],
-                sheet_region: [
-                    0.0 / SPRITE_SHEET_RESOLUTION.0,
-                    (2.0 + (7.0 / 16.0)) / SPRITE_SHEET_RESOLUTION.1,
-                    2.0 / SPRITE_SHEET_RESOLUTION.0,
-                    (4.0 / 16.0) / SPRITE_SHEET_RESOLUTION.1,
-                ],
-            },
-            sprite_index_bar: 0,
-        },
+        options,
+        slowdown_credit: 0.0,
+        popups: popups::Popups::default(),
+        run_log: run_log::RunLog::default(),
+        intro_banner: stage_intro::IntroBanner::default(),
+        trial_room: trial_room::TrialRoom::default(),
         background: Screen {
             sprite: GPUSprite {
                 screen_region: [0.0, 0.0, 1024.0, 760.0],
-                sheet_region: [
-                    0.0 / SPRITE_SHEET_RESOLUTION.0,
-                    8.0 / SPRITE_SHEET_RESOLUTION.1,
-                    12.0 / SPRITE_SHEET_RESOLUTION.0,
-                    8.0 / SPRITE_SHEET_RESOLUTION.1,
-                ],
+                sheet_region: atlas::sheet_region("background"),
             },
-            sprite_index: sprite_holder.get_next_index(),
+            sprite_index: sprite_holder.get_next_index_on(Layer::Background),
         },
         title_screen: Screen {
             sprite: GPUSprite {
                 screen_region: [160.0, 32.0, 720.0, 720.0],
-                sheet_region: [
-                    0.0 / SPRITE_SHEET_RESOLUTION.0,
-                    4.0 / SPRITE_SHEET_RESOLUTION.1,
-                    4.0 / SPRITE_SHEET_RESOLUTION.0,
-                    4.0 / SPRITE_SHEET_RESOLUTION.1,
-                ],
+                sheet_region: atlas::sheet_region("title_screen"),
             },
-            sprite_index: sprite_holder.get_next_index(),
+            sprite_index: sprite_holder.get_next_index_on(Layer::Ui),
         },
         death_screen: Screen {
             sprite: GPUSprite {
                 screen_region: [160.0, 32.0, 720.0, 720.0],
-                sheet_region: [
-                    8.0 / SPRITE_SHEET_RESOLUTION.0,
-                    4.0 / SPRITE_SHEET_RESOLUTION.1,
-                    4.0 / SPRITE_SHEET_RESOLUTION.0,
-                    4.0 / SPRITE_SHEET_RESOLUTION.1,
-                ],
+                sheet_region: atlas::sheet_region("death_screen"),
             },
-            sprite_index: sprite_holder.get_next_index(),
+            sprite_index: sprite_holder.get_next_index_on(Layer::Ui),
+        },
+        danmaku_death_screen: Screen {
+            sprite: GPUSprite {
+                screen_region: [160.0, 32.0, 720.0, 720.0],
+                sheet_region: atlas::sheet_region("danmaku_death_screen"),
+            },
+            sprite_index: sprite_holder.get_next_index_on(Layer::Ui),
         },
         win_screen: Screen {
             sprite: GPUSprite {
                 screen_region: [160.0, 32.0, 720.0, 720.0],
-                sheet_region: [
-                    4.0 / SPRITE_SHEET_RESOLUTION.0,
-                    0.0 / SPRITE_SHEET_RESOLUTION.1,
-                    4.0 / SPRITE_SHEET_RESOLUTION.0,
-                    4.0 / SPRITE_SHEET_RESOLUTION.1,
-                ],
+                sheet_region: atlas::sheet_region("win_screen"),
             },
-            sprite_index: sprite_holder.get_next_index(),
+            sprite_index: sprite_holder.get_next_index_on(Layer::Ui),
         },
         title_screen_2: Screen {
             sprite: GPUSprite {
                 screen_region: [160.0, 32.0, 720.0, 720.0],
-                sheet_region: [
-                    8.0 / SPRITE_SHEET_RESOLUTION.0,
-                    0.0 / SPRITE_SHEET_RESOLUTION.1,
-                    4.0 / SPRITE_SHEET_RESOLUTION.0,
-                    4.0 / SPRITE_SHEET_RESOLUTION.1,
-                ],
+                sheet_region: atlas::sheet_region("title_screen_2"),
             },
-            sprite_index: sprite_holder.get_next_index(),
+            sprite_index: sprite_holder.get_next_index_on(Layer::Ui),
         },
         cleared_screen: Screen {
             sprite: GPUSprite {
                 screen_region: [160.0, 32.0, 720.0, 720.0],
-                sheet_region: [
-                    8.0 / SPRITE_SHEET_RESOLUTION.0,
-                    4.0 / SPRITE_SHEET_RESOLUTION.1,
-                    4.0 / SPRITE_SHEET_RESOLUTION.0,
-                    4.0 / SPRITE_SHEET_RESOLUTION.1,
-                ],
+                sheet_region: atlas::sheet_region("death_screen"),
             },
-            sprite_index: sprite_holder.get_next_index(),
+            sprite_index: sprite_holder.get_next_index_on(Layer::Ui),
         },
-        sprite_holder: sprite_holder,
-        sound_manager: sound_manager,
-        trans_flag: TransitionFlag { val: 0 },
+        sim: SimState::new(sprite_holder, thread_rng().gen()),
+        sounds,
+        versus,
+        overlay: overlay::StreamOverlay::from_args(std::env::args()),
+        telemetry: telemetry::Telemetry::from_args(std::env::args()),
+        heatmap: heatmap::Heatmap::from_args(std::env::args()),
+        inspector: inspector::Inspector::from_args(std::env::args()),
+        kiosk: kiosk::Kiosk::from_args(std::env::args()),
     };
+    for (control, bindings) in settings.control_bindings() {
+        gso.input.rebind(control, bindings);
+    }
+    // Keys picked in the pause menu go on top.
+    for (control, bindings) in gso.options.bound_keys.iter() {
+        gso.input.rebind(*control, bindings.clone());
+    }
+
+    let mut shown_modifiers = modifiers::Modifiers::default();
+    let mut fixed_step = clock::FixedStep::new();
+    let mut frame_pacer = clock::FramePacer::new();
+    // Nothing is drawn or simulated while the window can't be seen.
+    let mut minimized = false;
+    let mut occluded = false;
+    let mut suspended = false;
 
     event_loop.run(move |event, _, control_flow| {
         //*control_flow = ControlFlow::Wait;
@@ -908,53 +1344,92 @@ async fn run(event_loop: EventLoop<()>, window: Window) {
                 event: WindowEvent::Resized(size),
                 ..
             } => {
-                // Reconfigure the surface with the new size
-                config.width = size.width;
-                config.height = size.height;
-                surface.configure(&device, &config);
-                // On macos the window needs to be redrawn manually after resizing
-                window.request_redraw();
+                // Minimizing shrinks the window to nothing on some platforms, and
+                // a surface can't be that size.
+                minimized = size.width == 0 || size.height == 0;
+                if !minimized {
+                    // Reconfigure the surface with the new size
+                    config.width = size.width;
+                    config.height = size.height;
+                    surface.configure(&device, &config);
+                    // On macos the window needs to be redrawn manually after resizing
+                    window.request_redraw();
+                }
+            }
+            Event::WindowEvent {
+                event: WindowEvent::Occluded(now_occluded),
+                ..
+            } => {
+                occluded = now_occluded;
             }
+            Event::RedrawRequested(_) if suspended => {}
             Event::RedrawRequested(_) => {
-                // Control the event loop in each state
-                match gso.game_state.state {
-                    0 => {
-                        title_screen_loop(&mut gso);
+                frame_pacer.redrawn(web_time::Instant::now());
+                #[cfg(not(target_arch = "wasm32"))]
+                clip_recorder.poll(&device);
+                // Logic runs on its own fixed clock, so a fast display just
+                // redraws the same step, and a slow one runs a few per redraw.
+                for _ in 0..fixed_step.steps_due() {
+                    gso.clock.tick_real();
+                    gso.sounds.update();
+                    if let Some(gamepads) = &mut gamepads {
+                        gso.input.poll_gamepads(gamepads);
                     }
-                    1 => {
-                        main_event_loop(&mut gso);
+                    if let Some(kiosk) = &mut gso.kiosk {
+                        if kiosk.timed_out(&gso.input) && gso.game_state.state != GameState::Title {
+                            log::info!("Nobody's playing; back to the title");
+                            game_state::reset_to_title(&mut gso);
+                        }
                     }
-                    2 => {
-                        death_screen_loop(&mut gso, 1);
+                    // Control the event loop in each state
+                    game_state::run_frame(&mut gso);
+                    gso.camera.update();
+
+                    if let Some(inspector) = &mut gso.inspector {
+                        let viewport = render_target::blit_viewport((config.width, config.height), gso.options.integer_scaling);
+                        inspector.update(&gso.input, viewport, &gso.camera, &mut gso.sim);
+                    }
+
+                    // There's no text rendering yet, so active modifiers go in the title bar.
+                    if gso.sim.modifiers != shown_modifiers {
+                        shown_modifiers = gso.sim.modifiers;
+                        match shown_modifiers.tag().as_str() {
+                            "" => window.set_title(WINDOW_TITLE),
+                            tag => window.set_title(&format!("{} [{}]", WINDOW_TITLE, tag)),
+                        }
                     }
-                    3 => {
-                        cleared_screen_loop(&mut gso);
+
+                    if let Some(overlay) = &mut gso.overlay {
+                        overlay.update(gso.game_state.state.index(), &gso.sim, &gso.clock);
                     }
-                    4 => {
-                        win_screen_loop(&mut gso);
+
+                    if gso.kiosk.is_none() && (gso.options.sprite_audit || cfg!(debug_assertions)) {
+                        sprite_audit::check(&gso.sim.sprite_holder, &gso.sprite_indices(), gso.game_state.state);
                     }
-                    5 => {
-                        title_screen_2_loop(&mut gso);
+                    if cfg!(debug_assertions) {
+                        invariants::check(&gso.sim, gso.game_state.state);
                     }
-                    6 => {
-                        main_event_loop(&mut gso);
+
+                    let window_scale = gso.options.window_scale;
+                    // The station's options are set up front; visitors don't get the F keys.
+                    if gso.kiosk.is_none() {
+                        gso.options.handle_hotkeys(&gso.input);
+                        gso.options.handle_debug_hotkeys(&gso.input);
                     }
-                    7 => {
-                        death_screen_loop(&mut gso, 6);
+                    if gso.options.window_scale != window_scale {
+                        window.set_inner_size(gso.options.window_scale.window_size());
                     }
-                    _ => {
-                        println!("INVALID STATE {} REACHED!", gso.game_state.state);
+                    #[cfg(not(target_arch = "wasm32"))]
+                    if gso.kiosk.is_none() && gso.input.is_key_pressed(input::Key::F9) {
+                        clip_recorder.save_gif();
                     }
+                    // Presses are only seen by the step they land in.
+                    gso.input.next_frame();
                 }
 
                 // Then send the data to the GPU!
-                gso.input.next_frame();
-                queue.write_buffer(&buffer_camera, 0, bytemuck::bytes_of(&camera));
-                queue.write_buffer(
-                    &buffer_sprite,
-                    0,
-                    bytemuck::cast_slice(&gso.sprite_holder.sprites),
-                );
+                camera_buffers.write(&queue, gso.camera.gpu_camera(&gso.sim.playfield), gso.sim.playfield.camera());
+                sprite_buffers.upload(&device, &queue, &sprite_bind_group_layout, &camera_buffers, &mut gso.sim.sprite_holder);
 
                 let frame = surface
                     .get_current_texture()
@@ -968,7 +1443,7 @@ async fn run(event_loop: EventLoop<()>, window: Window) {
                     let mut rpass = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
                         label: None,
                         color_attachments: &[Some(wgpu::RenderPassColorAttachment {
-                            view: &view,
+                            view: &render_target.view,
                             resolve_target: None,
                             ops: wgpu::Operations {
                                 load: wgpu::LoadOp::Clear(wgpu::Color::BLACK),
@@ -979,25 +1454,63 @@ async fn run(event_loop: EventLoop<()>, window: Window) {
                     });
                     rpass.set_pipeline(&render_pipeline);
                     if !USE_STORAGE {
-                        rpass.set_vertex_buffer(0, buffer_sprite.slice(..));
+                        rpass.set_vertex_buffer(0, sprite_buffers.sprites.slice(..));
+                        rpass.set_vertex_buffer(1, sprite_buffers.tints.slice(..));
+                        rpass.set_vertex_buffer(2, sprite_buffers.palettes.slice(..));
                     }
-                    rpass.set_bind_group(0, &sprite_bind_group, &[]);
+                    rpass.set_bind_group(0, &sprite_buffers.bind_group, &[]);
                     rpass.set_bind_group(1, &texture_bind_group, &[]);
                     // draw two triangles per sprite, and sprites-many sprites.
                     // this uses instanced drawing, but it would also be okay
                     // to draw 6 * sprites.len() vertices and use modular arithmetic
                     // to figure out which sprite we're drawing.
-                    rpass.draw(0..6, 0..(gso.sprite_holder.sprites.len() as u32));
+                    rpass.draw(0..6, sprite_buffers.world_range());
+                    rpass.set_bind_group(0, &sprite_buffers.screen_bind_group, &[]);
+                    rpass.draw(0..6, sprite_buffers.screen_range());
+                    rpass.set_bind_group(0, &sprite_buffers.bind_group, &[]);
+
+                    // Outlines go over the finished sprites. They only ever touch
+                    // pixels just outside each sprite, so the sprite itself is untouched.
+                    if gso.options.sprite_outlines && (gso.sim.mode == 1 || gso.sim.mode == 6) {
+                        rpass.set_pipeline(&outline_pipeline);
+                        for handle in [gso.sim.player.sprite_index, gso.sim.enemy.enemy.sprite_index] {
+                            let index = sprite_buffers.position(handle);
+                            rpass.draw(0..6, index..index + 1);
+                        }
+                    }
+
+                    render_target.draw_frame(&mut rpass);
+                    rpass.set_pipeline(&render_pipeline);
+                    rpass.set_bind_group(0, &sprite_buffers.screen_bind_group, &[]);
+                    rpass.set_bind_group(1, &texture_bind_group, &[]);
+                    for handle in gso.sim.hud_sprite_indices() {
+                        let index = sprite_buffers.position(handle);
+                        rpass.draw(0..6, index..index + 1);
+                    }
+
+                    if gso.options.wants_silhouette(&gso.sim) {
+                        rpass.set_bind_group(0, &sprite_buffers.bind_group, &[]);
+                        rpass.set_pipeline(&silhouette_pipeline);
+                        let index = sprite_buffers.position(gso.sim.player.sprite_index);
+                        rpass.draw(0..6, index..index + 1);
+                    }
                 }
+                #[cfg(not(target_arch = "wasm32"))]
+                clip_recorder.capture(&mut encoder, &render_target.texture);
+                let viewport = render_target::blit_viewport((config.width, config.height), gso.options.integer_scaling);
+                render_target.blit(&mut encoder, &view, viewport);
                 queue.submit(Some(encoder.finish()));
+                #[cfg(not(target_arch = "wasm32"))]
+                clip_recorder.after_submit();
                 frame.present();
-
-                window.request_redraw();
             }
             Event::WindowEvent {
                 event: WindowEvent::CloseRequested,
                 ..
-            } => *control_flow = ControlFlow::Exit,
+            } => {
+                gso.run_log.finish(&gso.sim, "closed");
+                *control_flow = ControlFlow::Exit;
+            }
             // WindowEvent->KeyboardInput: Keyboard input!
             Event::WindowEvent {
                 // Note this deeply nested pattern match
@@ -1018,8 +1531,38 @@ async fn run(event_loop: EventLoop<()>, window: Window) {
             } => {
                 gso.input.handle_mouse_move(position);
             }
+            Event::WindowEvent {
+                event: WindowEvent::MouseWheel { delta, .. },
+                ..
+            } => {
+                gso.input.handle_mouse_wheel(delta);
+            }
+            Event::WindowEvent {
+                event: WindowEvent::Touch(touch),
+                ..
+            } => {
+                gso.input.handle_touch(touch);
+            }
+            // Redraws back to back normally. In low-power mode the loop sleeps
+            // until the next one is due; the fixed step makes up the steps. While
+            // the window is hidden it only wakes up for events.
             Event::MainEventsCleared => {
-                window.request_redraw();
+                if suspended != (minimized || occluded) {
+                    suspended = minimized || occluded;
+                    log::info!("Window {}", if suspended { "hidden; suspending" } else { "visible again; resuming" });
+                    // Picks up on the step it stopped at instead of catching up.
+                    if !suspended {
+                        fixed_step.resume();
+                    }
+                }
+                if suspended {
+                    *control_flow = ControlFlow::Wait;
+                } else if !gso.options.low_power || frame_pacer.due(web_time::Instant::now()) {
+                    *control_flow = ControlFlow::Poll;
+                    window.request_redraw();
+                } else {
+                    *control_flow = ControlFlow::WaitUntil(frame_pacer.next_redraw());
+                }
             }
             _ => {}
         }
@@ -1029,6 +1572,7 @@ async fn run(event_loop: EventLoop<()>, window: Window) {
 fn main() {
     let event_loop = EventLoop::new();
     let window = winit::window::Window::new(&event_loop).unwrap();
+    window.set_title(WINDOW_TITLE);
     #[cfg(not(target_arch = "wasm32"))]
     {
         env_logger::init();
@@ -1039,51 +1583,35 @@ fn main() {
         std::panic::set_hook(Box::new(console_error_panic_hook::hook));
         console_log::init_with_level(log::Level::Trace).expect("could not initialize logger");
         use winit::platform::web::WindowExtWebSys;
-        // On wasm, append the canvas to the document body
+        // On wasm, append the canvas to the document body. Touches on it are the
+        // game's, not the page's to scroll or zoom with.
         web_sys::window()
             .and_then(|win| win.document())
             .and_then(|doc| doc.body())
             .and_then(|body| {
-                body.append_child(&web_sys::Element::from(window.canvas()))
-                    .ok()
+                let canvas = web_sys::Element::from(window.canvas());
+                canvas.set_attribute("style", "touch-action: none").ok()?;
+                body.append_child(&canvas).ok()
             })
             .expect("couldn't append canvas to document body");
         wasm_bindgen_futures::spawn_local(run(event_loop, window));
     }
 }
-async fn load_texture(
-    path: impl AsRef<std::path::Path>,
+fn load_texture(
+    png: &[u8],
     label: Option<&str>,
+    // Lets the caller change the image before it's uploaded.
+    process: Option<fn(image::RgbaImage) -> image::RgbaImage>,
+    // 1 for just the image itself. Each level after that is half the size of the last.
+    mip_level_count: u32,
     device: &wgpu::Device,
     queue: &wgpu::Queue,
 ) -> Result<(wgpu::Texture, image::RgbaImage), Box<dyn std::error::Error>> {
-    #[cfg(target_arch = "wasm32")]
-    let img = {
-        let fetch = web_sys::window()
-            .map(|win| win.fetch_with_str(path.as_ref().to_str().unwrap()))
-            .unwrap();
-        let resp: web_sys::Response = wasm_bindgen_futures::JsFuture::from(fetch)
-            .await
-            .unwrap()
-            .into();
-        log::debug!("{:?} {:?}", &resp, resp.status());
-        let buf: js_sys::ArrayBuffer =
-            wasm_bindgen_futures::JsFuture::from(resp.array_buffer().unwrap())
-                .await
-                .unwrap()
-                .into();
-        log::debug!("{:?} {:?}", &buf, buf.byte_length());
-        let u8arr = js_sys::Uint8Array::new(&buf);
-        log::debug!("{:?}, {:?}", &u8arr, u8arr.length());
-        let mut bytes = vec![0; u8arr.length() as usize];
-        log::debug!("{:?}", &bytes);
-        u8arr.copy_to(&mut bytes);
-        image::load_from_memory_with_format(&bytes, image::ImageFormat::Png)
-            .map_err(|e| e.to_string())?
-            .to_rgba8()
+    let img = image::load_from_memory_with_format(png, image::ImageFormat::Png)?.to_rgba8();
+    let img = match process {
+        Some(process) => process(img),
+        None => img,
     };
-    #[cfg(not(target_arch = "wasm32"))]
-    let img = image::open(path.as_ref())?.to_rgba8();
     let (width, height) = img.dimensions();
     let size = wgpu::Extent3d {
         width,
@@ -1093,570 +1621,340 @@ async fn load_texture(
     let texture = device.create_texture(&wgpu::TextureDescriptor {
         label,
         size,
-        mip_level_count: 1,
+        mip_level_count,
         sample_count: 1,
         dimension: wgpu::TextureDimension::D2,
         format: wgpu::TextureFormat::Rgba8UnormSrgb,
         usage: wgpu::TextureUsages::TEXTURE_BINDING | wgpu::TextureUsages::COPY_DST,
         view_formats: &[],
     });
-    queue.write_texture(
-        texture.as_image_copy(),
-        &img,
-        wgpu::ImageDataLayout {
-            offset: 0,
-            bytes_per_row: Some(4 * width),
-            rows_per_image: Some(height),
-        },
-        size,
-    );
+    for level in 0..mip_level_count {
+        let level_size = size.mip_level_size(level, wgpu::TextureDimension::D2);
+        let level_img = if level == 0 {
+            img.clone()
+        } else {
+            image::imageops::resize(&img, level_size.width, level_size.height, image::imageops::FilterType::Triangle)
+        };
+        queue.write_texture(
+            wgpu::ImageCopyTexture {
+                texture: &texture,
+                mip_level: level,
+                origin: wgpu::Origin3d::ZERO,
+                aspect: wgpu::TextureAspect::All,
+            },
+            &level_img,
+            wgpu::ImageDataLayout {
+                offset: 0,
+                bytes_per_row: Some(4 * level_size.width),
+                rows_per_image: Some(level_size.height),
+            },
+            level_size,
+        );
+    }
     Ok((texture, img))
 }
 
-fn set_sprite(sprite: &mut GPUSprite, index: (f32, f32)) {
-    sprite.sheet_region = [
-        index.0 / SPRITE_SHEET_RESOLUTION.0,
-        index.1 / SPRITE_SHEET_RESOLUTION.1,
-        1.0 / SPRITE_SHEET_RESOLUTION.0,
-        1.0 / SPRITE_SHEET_RESOLUTION.1,
-    ];
-}
-
 fn make_projectile(
     projectiles: &mut Vec<Projectile>,
-    index: usize,
+    index: SpriteHandle,
     spawn_pos: (f32, f32),
     velocity: (f32, f32),
 ) {
-    let projectile = Projectile {
-        pos: (spawn_pos.0, spawn_pos.1),
-        size: (64.0, 64.0),
-        speed: 10.0,
-        velocity: (velocity.0, velocity.1),
+    let mut projectile = Projectile {
+        body: components::Body::new(spawn_pos, (64.0, 64.0)),
+        prev_pos: spawn_pos,
+        speed: 0.0,
+        direction: (0.0, -1.0),
         sprite_index: index,
         sprite: GPUSprite {
             screen_region: [2.0, 32.0, 64.0, 64.0],
-            sheet_region: [
-                0.0 / SPRITE_SHEET_RESOLUTION.0,
-                1.0 / SPRITE_SHEET_RESOLUTION.1,
-                1.0 / SPRITE_SHEET_RESOLUTION.0,
-                1.0 / SPRITE_SHEET_RESOLUTION.1,
-            ],
+            sheet_region: atlas::sheet_region("bullet"),
         },
         is_dead: false,
         player_spawned: false,
+        grazed: false,
+        age: 0,
+        warm_up: 0,
+        behavior: projectile_behavior::ProjectileBehavior::Straight,
+        tag: projectile_tags::ProjectileTag::default(),
     };
+    projectile.set_velocity(velocity);
     projectiles.push(projectile);
 }
 
 fn make_player_projectile(
     projectiles: &mut Vec<Projectile>,
-    index: usize,
+    index: SpriteHandle,
     spawn_pos: (f32, f32),
     velocity: (f32, f32),
 ) {
-    let projectile = Projectile {
-        pos: (spawn_pos.0, spawn_pos.1),
-        size: (64.0, 64.0),
-        speed: 10.0,
-        velocity: (velocity.0, velocity.1),
+    let mut projectile = Projectile {
+        body: components::Body::new(spawn_pos, (64.0, 64.0)),
+        prev_pos: spawn_pos,
+        speed: 0.0,
+        direction: (0.0, -1.0),
         sprite_index: index,
         sprite: GPUSprite {
             screen_region: [2.0, 32.0, 64.0, 64.0],
-            sheet_region: [
-                3.0 / SPRITE_SHEET_RESOLUTION.0,
-                2.0 / SPRITE_SHEET_RESOLUTION.1,
-                1.0 / SPRITE_SHEET_RESOLUTION.0,
-                1.0 / SPRITE_SHEET_RESOLUTION.1,
-            ],
+            sheet_region: atlas::sheet_region("player_bullet"),
         },
         is_dead: false,
         player_spawned: true,
+        grazed: false,
+        age: 0,
+        warm_up: 0,
+        behavior: projectile_behavior::ProjectileBehavior::Straight,
+        tag: projectile_tags::ProjectileTag::default(),
     };
+    projectile.set_velocity(velocity);
     projectiles.push(projectile);
 }
 
-fn main_event_loop(gso: &mut GameStateHolder) {
-    // Player movement!
-    if gso
-        .input
-        .is_key_pressed(winit::event::VirtualKeyCode::Right)
-    {
-        gso.player.add_speed((gso.player.speed, 0.0))
-    }
-    if gso.input.is_key_pressed(winit::event::VirtualKeyCode::Left) {
-        gso.player.add_speed((-gso.player.speed, 0.0))
-    }
-    if gso
-        .input
-        .is_key_released(winit::event::VirtualKeyCode::Right)
-    {
-        gso.player.add_speed((-gso.player.speed, 0.0))
-    }
-    if gso
-        .input
-        .is_key_released(winit::event::VirtualKeyCode::Left)
-    {
-        gso.player.add_speed((gso.player.speed, 0.0))
-    }
-
-    gso.sprite_holder.set_sprite(gso.background.sprite_index, gso.background.sprite);
+// A "N HIT!" popup is shown every time the chain passes a multiple of this.
+const POPUP_CHAIN_STEP: u32 = 10;
+// And a "GREAT!" one every time the boss's health drops past another of this many
+// even steps, e.g. 75%, 50% and 25% left.
+const POPUP_BOSS_HEALTH_STEPS: f32 = 4.0;
 
-    // Shoot!
-    if gso.input.is_key_down(winit::event::VirtualKeyCode::Space) {
-        gso.player.spawn_new_projectile(
-            10.0,
-            &mut gso.projectiles,
-            &mut gso.sprite_holder,
-            &mut gso.sound_manager,
-        )
+fn boss_health_step(sim: &SimState) -> f32 {
+    let health_bar = &sim.enemy.enemy.health_bar;
+    (health_bar.currval / health_bar.maxval * POPUP_BOSS_HEALTH_STEPS).ceil()
+}
+// How long gameplay freezes when the player is hit.
+const HIT_STOP_FRAMES: u32 = 6;
+// How hard and how long the view shakes when the player is hit.
+const HIT_SHAKE: (f32, u32) = (6.0, 14);
+
+// One frame of a level, once the gameplay clock says it's due.
+fn gameplay_frame(gso: &mut GameStateHolder) {
+    // Everything that touches gameplay happens in here.
+    let input = simulation::FrameInput::from_input(&gso.input, gso.options.bind_by_position);
+
+    // Each run gets a seed of its own, so the one in runs.log replays it. Versus
+    // uses the same seed on both sides, so both bosses fire the same patterns. A
+    // rewind back to the first frame is still the same run.
+    if gso.sim.frame == 0 && !gso.run_log.in_run() {
+        let seed = if gso.versus.is_some() && gso.sim.mode == 6 { netplay::VERSUS_SEED } else { thread_rng().gen() };
+        gso.sim.rng = StdRng::seed_from_u64(seed);
+        gso.run_log.start(&gso.sim, seed);
     }
 
-    // Loop for the player
-    gso.player.player_loop(&mut gso.sprite_holder);
-
-    gso.player_health_bar
-        .health_bar_loop(&mut gso.sprite_holder);
-
-    if gso.game_state.state == 6 {
-        gso.enemy.enemy.damage(1.0, &mut gso.trans_flag);
+    // Versus mode: trade garbage bullets with the other player before stepping.
+    if let (Some(versus), 6) = (&mut gso.versus, gso.sim.mode) {
+        let garbage = versus.receive();
+        simulation::spawn_garbage(&mut gso.sim, garbage);
     }
-    
-    // Loop for the enemy
-    gso.enemy
-        .enemy_loop(&mut gso.projectiles, &mut gso.sprite_holder);
 
-    // Move projectile
-    for proj in gso.projectiles.iter_mut() {
-        proj.move_proj(&mut gso.player_health_bar, &mut gso.sound_manager, &mut gso.trans_flag, gso.game_state.state);
-        proj.check_collision(
-            &mut gso.player,
-            &mut gso.enemy.enemy,
-            &mut gso.sound_manager,
-            &mut gso.trans_flag,
-            &mut gso.player_health_bar,
-            gso.game_state.state,
-        );
-        gso.sprite_holder.set_sprite(proj.sprite_index, proj.sprite);
-    }
-    // Code to remove projectiles. Not very optimal but rust likes it.
-    gso.projectiles.iter_mut().for_each(|proj| {
-        if proj.is_dead {
-            proj.clean_dead(&mut gso.sprite_holder)
+    // Practice assist: run at reduced speed while a bullet is about to hit.
+    if gso.options.slowdown_assist && simulation::bullet_near_player(&gso.sim, options::SLOWDOWN_RADIUS) {
+        gso.sim.leaderboard_eligible = false;
+        gso.slowdown_credit += options::SLOWDOWN_SPEED;
+        if gso.slowdown_credit < 1.0 {
+            return;
         }
-    });
-    gso.projectiles.retain(|proj| !proj.is_dead);
-
-    // Watch for updating gamestate
-    if gso.trans_flag.val != 0 {
-        transition_to_state(gso.trans_flag.val, gso);
+        gso.slowdown_credit -= 1.0;
     }
-}
 
-fn title_screen_loop(gso: &mut GameStateHolder) {
-    if gso.input.is_key_down(winit::event::VirtualKeyCode::Space) {
-        transition_to_state(1, gso);
-        gso.title_screen.sprite.screen_region = [0.0, 0.0, 0.0, 0.0];
-        gso.sprite_holder.set_sprite(gso.title_screen.sprite_index, gso.title_screen.sprite);
+    let chain_before = gso.sim.player.chain;
+    let boss_step_before = boss_health_step(&gso.sim);
+    let spells_before = gso.sim.spells_captured;
+    let player_health_before = gso.sim.player_health_bar.currval;
+    let phase_before = gso.sim.phase;
+
+    gso.rewind.record(&gso.sim, input);
+    let sim = std::mem::replace(&mut gso.sim, SimState::new(SpriteHolder::new(0), 0));
+    gso.sim = simulation::simulate(sim, input);
+    log::trace!("frame {} checksum {:016x}", gso.sim.frame, gso.sim.checksum());
+    if let Some(telemetry) = &mut gso.telemetry {
+        telemetry.record(&gso.sim);
     }
-    else if gso.input.is_key_down(winit::event::VirtualKeyCode::Right) {
-        transition_to_state(5, gso);
-        gso.title_screen.sprite.screen_region = [0.0, 0.0, 0.0, 0.0];
-        gso.sprite_holder.set_sprite(gso.title_screen.sprite_index, gso.title_screen.sprite);
+    if let Some(heatmap) = &mut gso.heatmap {
+        heatmap.record(&mut gso.sim, gso.options.heatmap_overlay);
     }
+    gso.score_graph.record(&gso.sim);
+    gso.run_log.record(&gso.sim);
 
-    gso.sprite_holder
-        .set_sprite(gso.title_screen.sprite_index, gso.title_screen.sprite);
-}
-
-fn death_screen_loop (gso: &mut GameStateHolder, next_state: usize) {
-    if gso.input.is_key_down(winit::event::VirtualKeyCode::Space) {
-        transition_to_state(next_state, gso);
-        gso.death_screen.sprite.screen_region = [0.0, 0.0, 0.0, 0.0];
-        gso.sprite_holder.set_sprite(gso.death_screen.sprite_index, gso.death_screen.sprite);
+    if let (Some(versus), 6) = (&mut gso.versus, gso.sim.mode) {
+        versus.send(gso.sim.frame, input, gso.sim.phases_cleared);
     }
 
-    gso.sprite_holder.set_sprite(gso.death_screen.sprite_index, gso.death_screen.sprite);
-}
-
-fn cleared_screen_loop (gso: &mut GameStateHolder) {
-    if gso.input.is_key_down(winit::event::VirtualKeyCode::Space) {
-        transition_to_state(1, gso);
-        gso.cleared_screen.sprite.screen_region = [0.0, 0.0, 0.0, 0.0];
-        gso.sprite_holder.set_sprite(gso.cleared_screen.sprite_index, gso.cleared_screen.sprite);
+    // Announcer popups for whatever the player just pulled off.
+    if gso.sim.player.chain / POPUP_CHAIN_STEP > chain_before / POPUP_CHAIN_STEP {
+        gso.popups.push(format!("{} HIT!", gso.sim.player.chain / POPUP_CHAIN_STEP * POPUP_CHAIN_STEP));
     }
-
-    gso.sprite_holder.set_sprite(gso.cleared_screen.sprite_index, gso.cleared_screen.sprite);
-}
-
-fn win_screen_loop (gso: &mut GameStateHolder) {
-    gso.sprite_holder.set_sprite(gso.win_screen.sprite_index, gso.win_screen.sprite);
-}
-
-fn title_screen_2_loop (gso: &mut GameStateHolder) {
-    if gso.input.is_key_down(winit::event::VirtualKeyCode::Space) {
-        transition_to_state(6, gso);
-        gso.title_screen_2.sprite.screen_region = [0.0, 0.0, 0.0, 0.0];
-        gso.sprite_holder.set_sprite(gso.title_screen_2.sprite_index, gso.title_screen_2.sprite);
+    if boss_health_step(&gso.sim) < boss_step_before {
+        gso.popups.push("GREAT!");
     }
-    else if gso.input.is_key_down(winit::event::VirtualKeyCode::Left) {
-        transition_to_state(0, gso);
-        gso.title_screen_2.sprite.screen_region = [0.0, 0.0, 0.0, 0.0];
-        gso.sprite_holder.set_sprite(gso.title_screen_2.sprite_index, gso.title_screen_2.sprite);
+    if gso.sim.spells_captured > spells_before {
+        gso.popups.push("SPELL CAPTURED");
+        gso.sounds.handle_event(audio::MusicEvent::SpellCaptured);
+    } else if gso.sim.phase > phase_before {
+        gso.sounds.handle_event(audio::MusicEvent::PhaseChanged);
     }
+    if gso.sim.player_health_bar.currval < player_health_before {
+        gso.clock.hit_stop(HIT_STOP_FRAMES);
+        gso.camera.shake(HIT_SHAKE.0, HIT_SHAKE.1);
+    }
+    gso.popups.update(&mut gso.sim.sprite_holder);
+    gso.intro_banner.update(gso.sim.stage_intro.as_ref(), &mut gso.sim.sprite_holder);
+
+    // The music builds up as the boss goes down, or as the survival clock runs out.
+    let boss_bar = &gso.sim.enemy.enemy.health_bar;
+    let mut intensity = 1.0 - (boss_bar.currval / boss_bar.maxval).clamp(0.0, 1.0);
+    if let Some(survival_timer) = &gso.sim.survival_timer {
+        intensity = intensity.max(survival_timer.progress());
+    }
+    gso.sounds.set_intensity(intensity);
+    gso.sounds.play_sounds(&mut gso.sim.sounds);
+    gso.options.apply_visuals(&mut gso.sim);
+
+    // The sim asks for a new state by its index; 0 means stay put.
+    let next_state = match gso.sim.trans_flag.val {
+        0 => None,
+        val => GameState::from_index(val),
+    };
 
-    gso.sprite_holder
-        .set_sprite(gso.title_screen_2.sprite_index, gso.title_screen_2.sprite);
-}
-
-
-fn transition_to_state(new_state: usize, gso: &mut GameStateHolder) {
-    match gso.game_state.state{
-        0 => {
-            match new_state {
-                1 => {
-                    gso.game_state.state = new_state;
-                    load_level_1(gso);
-                }
-                5 => {
-                    gso.game_state.state = new_state;
-                    gso.title_screen_2.sprite.screen_region = [160.0, 32.0, 720.0, 720.0];
-                    gso.game_state.state = new_state;
-                }
-                _ => {
-                    println!("Cannot transition from state {} to state {}", gso.game_state.state, new_state);
-                }
-            }
-        }
-        1 => {
-            // Reset Transition Flag
-            gso.trans_flag.val = 0;
-            match new_state {
-                // Game Over
-                2 => {
-                    gso.death_screen.sprite.screen_region = [160.0, 32.0, 720.0, 720.0];
-                    gso.game_state.state = new_state;
-                    load_dead_level(gso);
-                }
-                // Stage Cleared
-                3 => {
-                    gso.cleared_screen.sprite.screen_region = [160.0, 32.0, 720.0, 720.0];
-                    gso.game_state.state = new_state;
-                    load_dead_level(gso);
-                }
-                // You  Win
-                4 => {
-                    gso.win_screen.sprite.screen_region = [160.0, 32.0, 720.0, 720.0];
-                    gso.game_state.state = new_state;
-                    load_dead_level(gso);
-                }
-                _ => {
-                    println!("Cannot transition from state {} to state {}", gso.game_state.state, new_state);
-                }
-            }
+    // Practice rewind: undo the last few seconds instead of ending the run.
+    if gso.options.rewind_on_death && gso.versus.is_none() && next_state.is_some_and(GameState::is_game_over) {
+        let load = if gso.sim.mode == 6 { load_level_6 } else { load_level_1 };
+        if gso.rewind.rewind(&mut gso.sim, load) {
+            gso.score_graph.rewind_to(gso.sim.frame);
+            gso.sim.leaderboard_eligible = false;
+            gso.popups.clear(&mut gso.sim.sprite_holder);
+            gso.popups.push("REWIND");
+            return;
         }
-        2 => {
-            match new_state {
-                1 => {
-                    gso.game_state.state = new_state;
-                    load_level_1(gso);
-                }
-                _ => {
-                    println!("Cannot transition from state {} to state {}", gso.game_state.state, new_state);
-                }
-            }
+    }
+
+    // Watch for updating gamestate
+    if let Some(next_state) = next_state {
+        gso.popups.clear(&mut gso.sim.sprite_holder);
+        if next_state.is_game_over() {
+            gso.sounds.handle_event(audio::MusicEvent::GameOver);
+            gso.death_report.record(&gso.sim);
         }
-        3 => {
-            match new_state {
-                1 => {
-                    gso.game_state.state = new_state;
-                    load_level_1(gso);
-                }
-                _ => {
-                    println!("Cannot transition from state {} to state {}", gso.game_state.state, new_state);
-                }
-            }
+        let won = next_state == GameState::YouWin;
+        if let Some(telemetry) = &mut gso.telemetry {
+            telemetry.finish(if won { "win" } else { "death" });
         }
-        5 => {
-            match new_state {
-                6 => {
-                    gso.game_state.state = new_state;
-                    load_level_6(gso);
-                }
-                0 => {
-                    gso.game_state.state = new_state;
-                    gso.title_screen.sprite.screen_region = [160.0, 32.0, 720.0, 720.0];
-                    gso.game_state.state = new_state;
-                }
-                _ => {
-                    println!("Cannot transition from state {} to state {}", gso.game_state.state, new_state);
-                }
-            }
+        let outcome = match next_state {
+            GameState::YouWin => "win",
+            GameState::StageCleared => "cleared",
+            _ => "death",
+        };
+        gso.run_log.finish(&gso.sim, outcome);
+        if let Some(heatmap) = &mut gso.heatmap {
+            heatmap.finish(&mut gso.sim.sprite_holder);
         }
-        6 => {
-            // Reset Transition Flag
-            gso.trans_flag.val = 0;
-            match new_state {
-                // Game Over
-                7 => {
-                    gso.death_screen.sprite.screen_region = [160.0, 32.0, 720.0, 720.0];
-                    gso.game_state.state = new_state;
-                    load_dead_level(gso);
-                }
-                // Stage Cleared
-                3 => {
-                    gso.cleared_screen.sprite.screen_region = [160.0, 32.0, 720.0, 720.0];
-                    gso.game_state.state = new_state;
-                    load_dead_level(gso);
-                }
-                // You  Win
-                4 => {
-                    gso.win_screen.sprite.screen_region = [160.0, 32.0, 720.0, 720.0];
-                    gso.game_state.state = new_state;
-                    load_dead_level(gso);
-                }
-                _ => {
-                    println!("Cannot transition from state {} to state {}", gso.game_state.state, new_state);
-                }
-            }
+        // Progress counts however the stage was cleared.
+        if matches!(next_state, GameState::StageCleared | GameState::YouWin) && gso.save.record_stage_cleared(gso.sim.mode) {
+            gso.save.store();
         }
-        7 => {
-            match new_state {
-                6 => {
-                    gso.game_state.state = new_state;
-                    load_level_6(gso);
-                }
-                _ => {
-                    println!("Cannot transition from state {} to state {}", gso.game_state.state, new_state);
-                }
+        // Assisted runs don't earn medals or records.
+        let cleared = (won && gso.sim.leaderboard_eligible).then_some((gso.sim.mode, gso.sim.frame));
+        transition_to_state(next_state, gso);
+        if let Some((mode, frames)) = cleared {
+            let new_best = gso.save.record_clear(mode, frames);
+            if new_best {
+                gso.save.store();
             }
-        }
-        _ => {
-            println!("Cannot transition from state {}", gso.game_state.state);
+            gso.medals.show_result(mode, frames, new_best, &mut gso.sim.sprite_holder);
         }
     }
 }
 
-fn load_dead_level(gso : &mut GameStateHolder) {
-    // Clear out old sprites.
-    gso.sprite_holder.remove_sprite(gso.player.sprite_index);
-    gso.sprite_holder.remove_sprite(gso.enemy.enemy.sprite_index);
-    gso.sprite_holder.remove_sprite(gso.enemy.enemy.sprite_index_eyes);
-    gso.sprite_holder.remove_sprite(gso.enemy.enemy.health_bar.sprite_index_bar);
-    gso.sprite_holder.remove_sprite(gso.enemy.enemy.health_bar.sprite_index_border);
-    gso.sprite_holder.remove_sprite(gso.player_health_bar.sprite_index_bar);
-    gso.sprite_holder.remove_sprite(gso.player_health_bar.sprite_index_border);
+// One frame of the trial room, once the gameplay clock says it's due.
+fn trial_room_frame(gso: &mut GameStateHolder) {
+    let shot = gso.sim.shot;
+    gso.sim.shot.handle_hotkeys(&gso.input);
+    if gso.sim.shot != shot {
+        gso.trial_room.reset_meter();
+    }
 
-    // Purge Projectiles
-    gso.projectiles.iter_mut().for_each(|proj| {proj.kill(); if proj.is_dead {proj.clean_dead(&mut gso.sprite_holder)}});
-    gso.projectiles.retain(|proj| !proj.is_dead);
-
-    // Set values to dead state values.
-    gso.player = Player {
-        pos: (400.0, 100.0),
-        size: (64.0, 64.0),
-        speed: 6.0,
-        velocity: (0.0, 0.0),
-        sprite_index: 0,
-        facing_right: true,
-        sprite: GPUSprite {
-            screen_region: [32.0, 128.0, 64.0, 64.0],
-            sheet_region: [0.0 / SPRITE_SHEET_RESOLUTION.0, 0.0 / SPRITE_SHEET_RESOLUTION.1, 1.0 / SPRITE_SHEET_RESOLUTION.0, 1.0 / SPRITE_SHEET_RESOLUTION.1],
-        },
-        charges: 0,
-    };
-    gso.enemy = Entity {
-        enemy: Enemy {
-            pos: (450.0, 650.0),
-            size: (64.0, 64.0),
-            speed: 6.0,
-            velocity: (0.0, 0.0),
-            sprite_index: 0,
-            sprite_index_eyes: 0,
-            frame: 0.0,
-            sprite: GPUSprite {
-                screen_region: [32.0, 128.0, 64.0, 64.0],
-                sheet_region: [1.0 / SPRITE_SHEET_RESOLUTION.0, 1.0 / SPRITE_SHEET_RESOLUTION.1, 1.0 / SPRITE_SHEET_RESOLUTION.0, 1.0 / SPRITE_SHEET_RESOLUTION.1],
-            },
-            sprite_eyes: GPUSprite {
-                screen_region: [32.0, 128.0, 64.0, 64.0],
-                sheet_region: [3.0 / SPRITE_SHEET_RESOLUTION.0, 1.0 / SPRITE_SHEET_RESOLUTION.1, 1.0 / SPRITE_SHEET_RESOLUTION.0, 1.0 / SPRITE_SHEET_RESOLUTION.1],
-            },
-            health_bar: HealthBar {
-                currval: 10.0,
-                maxval: 10.0,
-                bar_pos: (32.0, 600.0, 128.0, 24.0),
-                units_per_pixel: 4.0,
-                sprite_border: GPUSprite {
-                    screen_region: [32.0, 32.0, 128.0, 24.0],
-                    sheet_region: [0.0 / SPRITE_SHEET_RESOLUTION.0, 2.0 / SPRITE_SHEET_RESOLUTION.1, 2.0 / SPRITE_SHEET_RESOLUTION.0, (6.0 / 16.0) / SPRITE_SHEET_RESOLUTION.1],
-                },
-                sprite_index_border: 0,
-                sprite_bar: GPUSprite {
-                    screen_region: [32.0, 36.0, 128.0, 16.0],
-                    sheet_region: [0.0 / SPRITE_SHEET_RESOLUTION.0, (2.0  + (12.0 / 16.0)) / SPRITE_SHEET_RESOLUTION.1, 2.0 / SPRITE_SHEET_RESOLUTION.0, (4.0 / 16.0) / SPRITE_SHEET_RESOLUTION.1],
-                },
-                sprite_index_bar: 0,
-            },
-        },
-        ai: Box::new(enemy_ai::Level0AI {})
-    };
-    gso.player_health_bar = HealthBar {
-        currval: 10.0,
-        maxval: 10.0,
-        bar_pos: (32.0, 32.0, 128.0, 24.0),
-        units_per_pixel: 4.0,
-        sprite_border: GPUSprite {
-            screen_region: [32.0, 32.0, 128.0, 24.0],
-            sheet_region: [0.0 / SPRITE_SHEET_RESOLUTION.0, 2.0 / SPRITE_SHEET_RESOLUTION.1, 2.0 / SPRITE_SHEET_RESOLUTION.0, (6.0 / 16.0) / SPRITE_SHEET_RESOLUTION.1],
-        },
-        sprite_index_border: 0,
-        sprite_bar: GPUSprite {
-            screen_region: [32.0, 36.0, 128.0, 16.0],
-            sheet_region: [0.0 / SPRITE_SHEET_RESOLUTION.0, (2.0  + (7.0 / 16.0)) / SPRITE_SHEET_RESOLUTION.1, 2.0 / SPRITE_SHEET_RESOLUTION.0, (4.0 / 16.0) / SPRITE_SHEET_RESOLUTION.1],
-        },
-        sprite_index_bar: 0,
-    };
+    let input = simulation::FrameInput::from_input(&gso.input, gso.options.bind_by_position);
+    let sim = std::mem::replace(&mut gso.sim, SimState::new(SpriteHolder::new(0), 0));
+    gso.sim = simulation::simulate(sim, input);
+    gso.trial_room.update(&mut gso.sim);
+
+    gso.sounds.play_sounds(&mut gso.sim.sounds);
+    gso.options.apply_visuals(&mut gso.sim);
 }
 
-fn load_level_1(gso : &mut GameStateHolder) {
-    gso.player = Player {
-            pos: (400.0, 100.0),
-            size: (64.0, 64.0),
-            speed: 6.0,
-            velocity: (0.0, 0.0),
-            sprite_index: gso.sprite_holder.get_next_index(),
-            facing_right: true,
-            sprite: GPUSprite {
-                screen_region: [32.0, 128.0, 64.0, 64.0],
-                sheet_region: [0.0 / SPRITE_SHEET_RESOLUTION.0, 0.0 / SPRITE_SHEET_RESOLUTION.1, 1.0 / SPRITE_SHEET_RESOLUTION.0, 1.0 / SPRITE_SHEET_RESOLUTION.1],
-            },
-            charges: 0,
-        };
-    gso.enemy = Entity {
-            enemy: Enemy {
-                pos: (450.0, 650.0),
-                size: (64.0, 64.0),
-                speed: 6.0,
-                velocity: (0.0, 0.0),
-                sprite_index: gso.sprite_holder.get_next_index(),
-                sprite_index_eyes: gso.sprite_holder.get_next_index(),
-                frame: 0.0,
-                sprite: GPUSprite {
-                    screen_region: [32.0, 128.0, 64.0, 64.0],
-                    sheet_region: [1.0 / SPRITE_SHEET_RESOLUTION.0, 1.0 / SPRITE_SHEET_RESOLUTION.1, 1.0 / SPRITE_SHEET_RESOLUTION.0, 1.0 / SPRITE_SHEET_RESOLUTION.1],
-                },
-                sprite_eyes: GPUSprite {
-                    screen_region: [32.0, 128.0, 64.0, 64.0],
-                    sheet_region: [3.0 / SPRITE_SHEET_RESOLUTION.0, 1.0 / SPRITE_SHEET_RESOLUTION.1, 1.0 / SPRITE_SHEET_RESOLUTION.0, 1.0 / SPRITE_SHEET_RESOLUTION.1],
-                },
-                health_bar: HealthBar {
-                    currval: 10.0,
-                    maxval: 10.0,
-                    bar_pos: (32.0, 600.0, 128.0, 24.0),
-                    units_per_pixel: 4.0,
-                    sprite_border: GPUSprite {
-                        screen_region: [32.0, 32.0, 128.0, 24.0],
-                        sheet_region: [0.0 / SPRITE_SHEET_RESOLUTION.0, 2.0 / SPRITE_SHEET_RESOLUTION.1, 2.0 / SPRITE_SHEET_RESOLUTION.0, (6.0 / 16.0) / SPRITE_SHEET_RESOLUTION.1],
-                    },
-                    sprite_index_border: gso.sprite_holder.get_next_index(),
-                    sprite_bar: GPUSprite {
-                        screen_region: [32.0, 36.0, 128.0, 16.0],
-                        sheet_region: [0.0 / SPRITE_SHEET_RESOLUTION.0, (2.0  + (12.0 / 16.0)) / SPRITE_SHEET_RESOLUTION.1, 2.0 / SPRITE_SHEET_RESOLUTION.0, (4.0 / 16.0) / SPRITE_SHEET_RESOLUTION.1],
-                    },
-                    sprite_index_bar: gso.sprite_holder.get_next_index(),
-                },
-            },
-            ai: Box::new(enemy_ai::Level1AI {
-                max_cooldown: 40,
-                cooldown: 0,
-            }),
-        };
-    gso.player_health_bar = HealthBar {
-        currval: 10.0,
-        maxval: 10.0,
-        bar_pos: (32.0, 32.0, 128.0, 24.0),
-        units_per_pixel: 4.0,
-        sprite_border: GPUSprite {
-            screen_region: [32.0, 32.0, 128.0, 24.0],
-            sheet_region: [0.0 / SPRITE_SHEET_RESOLUTION.0, 2.0 / SPRITE_SHEET_RESOLUTION.1, 2.0 / SPRITE_SHEET_RESOLUTION.0, (6.0 / 16.0) / SPRITE_SHEET_RESOLUTION.1],
-        },
-        sprite_index_border: gso.sprite_holder.get_next_index(),
-        sprite_bar: GPUSprite {
-            screen_region: [32.0, 36.0, 128.0, 16.0],
-            sheet_region: [0.0 / SPRITE_SHEET_RESOLUTION.0, (2.0  + (7.0 / 16.0)) / SPRITE_SHEET_RESOLUTION.1, 2.0 / SPRITE_SHEET_RESOLUTION.0, (4.0 / 16.0) / SPRITE_SHEET_RESOLUTION.1],
-        },
-        sprite_index_bar: gso.sprite_holder.get_next_index(),
+fn load_dead_level(sim: &mut SimState) {
+    if sim.mode != 8 {
+        log::info!("Run over: {} points, modifiers: {}", sim.player.score, sim.modifiers.describe());
     }
+    unload_level(sim);
 }
 
-fn load_level_6(gso : &mut GameStateHolder) {
-    gso.player = Player {
-            pos: (400.0, 100.0),
-            size: (64.0, 64.0),
-            speed: 6.0,
-            velocity: (0.0, 0.0),
-            sprite_index: gso.sprite_holder.get_next_index(),
-            facing_right: true,
-            sprite: GPUSprite {
-                screen_region: [32.0, 128.0, 64.0, 64.0],
-                sheet_region: [0.0 / SPRITE_SHEET_RESOLUTION.0, 0.0 / SPRITE_SHEET_RESOLUTION.1, 1.0 / SPRITE_SHEET_RESOLUTION.0, 1.0 / SPRITE_SHEET_RESOLUTION.1],
-            },
-            charges: 0,
-        };
-    gso.enemy = Entity {
-            enemy: Enemy {
-                pos: (450.0, 650.0),
-                size: (64.0, 64.0),
-                speed: 6.0,
-                velocity: (0.0, 0.0),
-                sprite_index: gso.sprite_holder.get_next_index(),
-                sprite_index_eyes: gso.sprite_holder.get_next_index(),
-                frame: 0.0,
-                sprite: GPUSprite {
-                    screen_region: [32.0, 128.0, 64.0, 64.0],
-                    sheet_region: [1.0 / SPRITE_SHEET_RESOLUTION.0, 1.0 / SPRITE_SHEET_RESOLUTION.1, 1.0 / SPRITE_SHEET_RESOLUTION.0, 1.0 / SPRITE_SHEET_RESOLUTION.1],
-                },
-                sprite_eyes: GPUSprite {
-                    screen_region: [32.0, 128.0, 64.0, 64.0],
-                    sheet_region: [3.0 / SPRITE_SHEET_RESOLUTION.0, 1.0 / SPRITE_SHEET_RESOLUTION.1, 1.0 / SPRITE_SHEET_RESOLUTION.0, 1.0 / SPRITE_SHEET_RESOLUTION.1],
-                },
-                health_bar: HealthBar {
-                    currval: 1800.0,
-                    maxval: 1800.0,
-                    bar_pos: (32.0, 600.0, 128.0, 24.0),
-                    units_per_pixel: 4.0,
-                    sprite_border: GPUSprite {
-                        screen_region: [32.0, 32.0, 128.0, 24.0],
-                        sheet_region: [0.0 / SPRITE_SHEET_RESOLUTION.0, 2.0 / SPRITE_SHEET_RESOLUTION.1, 2.0 / SPRITE_SHEET_RESOLUTION.0, (6.0 / 16.0) / SPRITE_SHEET_RESOLUTION.1],
-                    },
-                    sprite_index_border: gso.sprite_holder.get_next_index(),
-                    sprite_bar: GPUSprite {
-                        screen_region: [32.0, 36.0, 128.0, 16.0],
-                        sheet_region: [0.0 / SPRITE_SHEET_RESOLUTION.0, (2.0  + (12.0 / 16.0)) / SPRITE_SHEET_RESOLUTION.1, 2.0 / SPRITE_SHEET_RESOLUTION.0, (4.0 / 16.0) / SPRITE_SHEET_RESOLUTION.1],
-                    },
-                    sprite_index_bar: gso.sprite_holder.get_next_index(),
-                },
-            },
-            ai: Box::new(enemy_ai::Level6AI {
-                max_cooldown: 40,
-                cooldown: 0,
-            }),
-        };
-    gso.player_health_bar = HealthBar {
-        currval: 1.0,
-        maxval: 1.0,
-        bar_pos: (32.0, 32.0, 128.0, 24.0),
-        units_per_pixel: 4.0,
-        sprite_border: GPUSprite {
-            screen_region: [32.0, 32.0, 128.0, 24.0],
-            sheet_region: [0.0 / SPRITE_SHEET_RESOLUTION.0, 2.0 / SPRITE_SHEET_RESOLUTION.1, 2.0 / SPRITE_SHEET_RESOLUTION.0, (6.0 / 16.0) / SPRITE_SHEET_RESOLUTION.1],
-        },
-        sprite_index_border: gso.sprite_holder.get_next_index(),
-        sprite_bar: GPUSprite {
-            screen_region: [32.0, 36.0, 128.0, 16.0],
-            sheet_region: [0.0 / SPRITE_SHEET_RESOLUTION.0, (2.0  + (7.0 / 16.0)) / SPRITE_SHEET_RESOLUTION.1, 2.0 / SPRITE_SHEET_RESOLUTION.0, (4.0 / 16.0) / SPRITE_SHEET_RESOLUTION.1],
-        },
-        sprite_index_bar: gso.sprite_holder.get_next_index(),
+// Tears down everything the current level spawned and leaves the between-stages player and boss.
+fn unload_level(sim: &mut SimState) {
+    sim.mode = 0;
+    sim.frame = 0;
+    // The title and results screens are drawn for the default camera.
+    sim.playfield = playfield::Playfield::default();
+
+    // Clear out old sprites.
+    sim.sprite_holder.remove_sprite(sim.player.sprite_index);
+    sim.enemy.remove_sprites(&mut sim.sprite_holder);
+    sim.player_health_bar.remove_sprites(&mut sim.sprite_holder);
+    for zone in sim.score_zones.drain(..) {
+        zone.remove(&mut sim.sprite_holder);
+    }
+    for obstacle in sim.obstacles.drain(..) {
+        obstacle.remove(&mut sim.sprite_holder);
+    }
+    for block in sim.blocks.drain(..) {
+        block.clean_dead(&mut sim.sprite_holder);
+    }
+    for minion in sim.minions.drain(..) {
+        minion.remove_sprites(&mut sim.sprite_holder);
+    }
+    sim.entity_pool.clear(&mut sim.sprite_holder);
+    for item in sim.items.drain(..) {
+        item.clean_dead(&mut sim.sprite_holder);
+    }
+    if let Some(mut midboss) = sim.midboss.take() {
+        midboss.remove(&mut sim.sprite_holder);
+    }
+    sim.hit_feedback.clear();
+    sim.particles.clear(&mut sim.sprite_holder);
+    sim.telegraphs.clear(&mut sim.sprite_holder);
+    sim.combo.clear(&mut sim.sprite_holder);
+    if let Some(time_freeze) = sim.time_freeze.take() {
+        time_freeze.remove(&mut sim.sprite_holder);
+    }
+    if let Some(catch_return) = sim.catch_return.take() {
+        catch_return.remove(&mut sim.sprite_holder);
     }
-}
\ No newline at end of file
+    if let Some(respawn_bubble) = sim.respawn_bubble.take() {
+        respawn_bubble.remove(&mut sim.sprite_holder);
+    }
+    if let Some(survival_timer) = sim.survival_timer.take() {
+        survival_timer.remove(&mut sim.sprite_holder);
+    }
+    if let Some(side_panel) = sim.side_panel.take() {
+        side_panel.remove(&mut sim.sprite_holder);
+    }
+
+    // Purge Projectiles
+    sim.projectiles.iter_mut().for_each(|proj| {proj.kill(); if proj.is_dead {proj.clean_dead(&mut sim.sprite_holder)}});
+    sim.projectiles.retain(|proj| !proj.is_dead);
+
+    // Set values to dead state values, keeping the final score around for the results screens.
+    let score = sim.player.score;
+    (sim.player, sim.enemy, sim.player_health_bar) = level::between_stages();
+    sim.player.score = score;
+}
+
+fn load_level_1(sim: &mut SimState) {
+    level::start(sim, &level::load("level1"));
+}
+
+fn load_trial_room(sim: &mut SimState) {
+    level::start(sim, &level::load("trial_room"));
+}
+
+fn load_level_6(sim: &mut SimState) {
+    level::start(sim, &level::load("level6"));
+}
@@ -0,0 +1,112 @@
+// Par clear times for each stage. Beating a par earns a medal, shown on the
+// results screen after the clear and next to the stage on its title screen.
+use crate::clock::FRAMES_PER_SECOND;
+use crate::save::SaveData;
+use crate::text::TextLabel;
+use crate::{SpriteHandle, SpriteHolder};
+
+const SCALE: f32 = 3.0;
+const LABEL_Y: f32 = 48.0;
+const SCREEN_WIDTH: f32 = 1024.0;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Medal {
+    Bronze,
+    Silver,
+    Gold,
+}
+
+impl Medal {
+    fn name(self) -> &'static str {
+        match self {
+            Medal::Bronze => "BRONZE",
+            Medal::Silver => "SILVER",
+            Medal::Gold => "GOLD",
+        }
+    }
+
+    fn tint(self) -> [f32; 4] {
+        match self {
+            Medal::Bronze => [1.3, 0.8, 0.5, 1.0],
+            Medal::Silver => [1.2, 1.2, 1.3, 1.0],
+            Medal::Gold => [1.6, 1.3, 0.3, 1.0],
+        }
+    }
+}
+
+// Gold, silver and bronze par times in seconds, by sim mode.
+fn par_seconds(mode: usize) -> Option<[u64; 3]> {
+    match mode {
+        1 => Some([90, 150, 240]),
+        6 => Some([120, 180, 300]),
+        _ => None,
+    }
+}
+
+pub fn medal_for(mode: usize, frames: u64) -> Option<Medal> {
+    let [gold, silver, bronze] = par_seconds(mode)?;
+    [(gold, Medal::Gold), (silver, Medal::Silver), (bronze, Medal::Bronze)]
+        .into_iter()
+        .find(|&(par, _)| frames <= par * FRAMES_PER_SECOND)
+        .map(|(_, medal)| medal)
+}
+
+// M:SS.CC
+fn format_time(frames: u64) -> String {
+    let hundredths = frames * 100 / FRAMES_PER_SECOND;
+    format!("{}:{:02}.{:02}", hundredths / 6000, hundredths / 100 % 60, hundredths % 100)
+}
+
+// The one line of medal text currently on screen, if any.
+#[derive(Default)]
+pub struct MedalBoard {
+    label: Option<TextLabel>,
+    // Stage whose best time is showing, so the title screens don't rebuild it every frame.
+    showing_best: Option<usize>,
+}
+
+impl MedalBoard {
+    // For the title screens: the stage's best time and medal, if it's been cleared.
+    pub fn show_best(&mut self, mode: usize, save: &SaveData, sprite_holder: &mut SpriteHolder) {
+        if self.showing_best == Some(mode) {
+            return;
+        }
+        self.clear(sprite_holder);
+        self.showing_best = Some(mode);
+        let Some(&frames) = save.best_clear_frames.get(&mode) else {
+            return;
+        };
+        let medal = medal_for(mode, frames);
+        let text = format!("BEST {} {}", format_time(frames), medal.map_or("", Medal::name));
+        self.show(text.trim_end(), medal, sprite_holder);
+    }
+
+    // For the results screen right after a clear.
+    pub fn show_result(&mut self, mode: usize, frames: u64, new_best: bool, sprite_holder: &mut SpriteHolder) {
+        self.clear(sprite_holder);
+        let medal = medal_for(mode, frames);
+        let mut text = format!("TIME {} {}", format_time(frames), medal.map_or("", Medal::name));
+        if new_best {
+            text.push_str(" NEW BEST!");
+        }
+        self.show(&text, medal, sprite_holder);
+    }
+
+    fn show(&mut self, text: &str, medal: Option<Medal>, sprite_holder: &mut SpriteHolder) {
+        let label = TextLabel::new(text, sprite_holder);
+        let x = (SCREEN_WIDTH - label.width(SCALE)) / 2.0;
+        label.draw((x, LABEL_Y), SCALE, medal.map_or(crate::TINT_NONE, Medal::tint), sprite_holder);
+        self.label = Some(label);
+    }
+
+    pub fn sprite_indices(&self) -> Vec<SpriteHandle> {
+        self.label.as_ref().map_or(vec![], |label| label.sprite_indices())
+    }
+
+    pub fn clear(&mut self, sprite_holder: &mut SpriteHolder) {
+        self.showing_best = None;
+        if let Some(label) = self.label.take() {
+            label.remove(sprite_holder);
+        }
+    }
+}
@@ -0,0 +1,213 @@
+// A scripted mid-boss for stage 1. It flies in partway through the fight, shoots
+// a short pattern set for a while and then leaves on its own. Killing it first is
+// worth a bonus, but it never has to be killed.
+use rand::rngs::StdRng;
+
+use crate::components::{Body, Velocity};
+use crate::hit_feedback::{Faction, HitFlash};
+use crate::scene::Attachment;
+use crate::spawn_queue::SpawnQueue;
+use crate::tween::{Easing, Tween};
+use crate::vulnerability::{self, Vulnerability};
+use crate::{atlas, enemy_ai, Enemy, Entity, GPUSprite, HealthBar, Layer, SpriteHandle, SpriteHolder};
+
+const SIZE: f32 = 56.0;
+// Enters from above the screen and stops at this height.
+const ENTRY_POS: (f32, f32) = (180.0, 800.0);
+const HOLD_HEIGHT: f32 = 520.0;
+const EXIT_HEIGHT: f32 = 820.0;
+const ENTRY_FRAMES: u32 = 90;
+const RETREAT_FRAMES: u32 = 60;
+// Pinned in the frame right of the playfield, level with the top of it.
+const BAR: Body = Body::new((1040.0, 720.0), (128.0, 24.0));
+const TINT: [f32; 4] = [1.5, 0.9, 0.4, 1.0];
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Stage {
+    Waiting,
+    Entering,
+    Fighting,
+    Retreating,
+    Gone,
+}
+
+// What happened to the mid-boss this frame, for scoring.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MidBossEvent {
+    None,
+    Killed,
+    Retreated,
+}
+
+pub struct MidBoss {
+    // Fight frame it shows up on, counted from GO.
+    appears_at: u64,
+    // How long it sticks around once it's in place.
+    frames_left: u32,
+    stage: Stage,
+    // Height over time while flying in or out.
+    flight: Option<Tween>,
+    pub entity: Option<Entity>,
+}
+
+impl MidBoss {
+    pub fn new(appears_at: u64, duration: u32) -> Self {
+        Self {
+            appears_at,
+            frames_left: duration,
+            stage: Stage::Waiting,
+            flight: None,
+            entity: None,
+        }
+    }
+
+    // Runs the script, AI and movement for a frame. Sprites are left to `sync_sprites`.
+    pub fn update(
+        &mut self,
+        frame: u64,
+        player: (f32, f32),
+        spawns: &mut SpawnQueue,
+        sprite_holder: &mut SpriteHolder,
+        rng: &mut StdRng,
+    ) -> MidBossEvent {
+        if self.stage == Stage::Waiting && frame >= self.appears_at {
+            self.entity = Some(spawn(sprite_holder));
+            self.flight = Some(Tween::new(ENTRY_POS.1, HOLD_HEIGHT, ENTRY_FRAMES, Easing::OutCubic));
+            self.stage = Stage::Entering;
+        }
+        // Its AI has already had on_death, so any parting shot is out.
+        if self.entity.as_ref().is_some_and(|entity| entity.dead) {
+            self.remove(sprite_holder);
+            self.stage = Stage::Gone;
+            return MidBossEvent::Killed;
+        }
+        let Some(entity) = &mut self.entity else {
+            return MidBossEvent::None;
+        };
+
+        if let Some(flight) = &mut self.flight {
+            entity.enemy.body.pos.1 = flight.step(sprite_holder);
+        }
+        let landed = self.flight.as_ref().is_some_and(|flight| flight.is_done());
+        match self.stage {
+            Stage::Entering if landed => {
+                self.flight = None;
+                entity.set_ai(Box::new(enemy_ai::MidBossAI {
+                    burst_velocity: (0.0, -7.0),
+                    ..Default::default()
+                }));
+                self.stage = Stage::Fighting;
+            }
+            Stage::Fighting => {
+                // A level can give it no time at all, which sends it straight back.
+                self.frames_left = self.frames_left.saturating_sub(1);
+                if self.frames_left == 0 {
+                    // Time's up: stop shooting and fly back out the top.
+                    self.flight = Some(Tween::new(HOLD_HEIGHT, EXIT_HEIGHT, RETREAT_FRAMES, Easing::InCubic));
+                    entity.set_ai(Box::new(enemy_ai::Level0AI {}));
+                    self.stage = Stage::Retreating;
+                }
+            }
+            Stage::Retreating if landed => {
+                self.remove(sprite_holder);
+                self.stage = Stage::Gone;
+                return MidBossEvent::Retreated;
+            }
+            _ => {}
+        }
+
+        entity.think(spawns, player, rng);
+        entity.move_step();
+        MidBossEvent::None
+    }
+
+    pub fn sync_sprites(&mut self, sprite_holder: &mut SpriteHolder) {
+        if let Some(entity) = &mut self.entity {
+            entity.sync_sprites(sprite_holder);
+        }
+    }
+
+    pub fn sprite_indices(&self) -> Vec<SpriteHandle> {
+        self.entity.as_ref().map_or(vec![], |entity| entity.sprite_indices())
+    }
+
+    // Just its health bar, which lives in the frame.
+    pub fn hud_sprite_indices(&self) -> Vec<SpriteHandle> {
+        self.entity.as_ref().map_or(vec![], |entity| entity.enemy.health_bar.sprite_indices())
+    }
+
+    pub fn remove(&mut self, sprite_holder: &mut SpriteHolder) {
+        self.flight = None;
+        if let Some(entity) = self.entity.take() {
+            entity.remove_sprites(sprite_holder);
+        }
+    }
+}
+
+fn spawn(sprite_holder: &mut SpriteHolder) -> Entity {
+    Entity {
+        enemy: Enemy {
+            body: Body::new(ENTRY_POS, (SIZE, SIZE)),
+            speed: 0.0,
+            // Moved by its flight tween rather than by velocity.
+            velocity: Velocity::default(),
+            sprite_index: sprite_holder.get_next_index(),
+            sprite_index_eyes: sprite_holder.get_next_index(),
+            frame: 0.0,
+            sprite: GPUSprite {
+                screen_region: [ENTRY_POS.0, ENTRY_POS.1, SIZE, SIZE],
+                sheet_region: atlas::sheet_region("spikey"),
+            },
+            sprite_eyes: GPUSprite {
+                screen_region: [ENTRY_POS.0, ENTRY_POS.1, SIZE, SIZE],
+                sheet_region: atlas::sheet_region("spikey_eyes"),
+            },
+            eyes_attachment: Attachment::default(),
+            // Not attached: it stays pinned where BAR puts it.
+            health_bar_attachment: None,
+            flash: HitFlash::new(Faction::Enemy, TINT, true),
+            vulnerability: Vulnerability::new(vulnerability::ENEMY_COOLDOWN),
+            health_bar: HealthBar {
+                currval: 6.0,
+                maxval: 6.0,
+                body: BAR,
+                units_per_pixel: 4.0,
+                sprite_border: GPUSprite {
+                    screen_region: BAR.screen_region(),
+                    sheet_region: atlas::sheet_region("bar_border"),
+                },
+                sprite_index_border: sprite_holder.get_next_index_on(Layer::Labels),
+                sprite_bar: GPUSprite {
+                    screen_region: [BAR.pos.0, BAR.pos.1 + 4.0, BAR.size.0, 16.0],
+                    sheet_region: atlas::sheet_region("bar_fill"),
+                },
+                sprite_index_bar: sprite_holder.get_next_index_on(Layer::Labels),
+                ghost: None,
+            },
+            damage_taken: 0.0,
+        },
+        // Holds fire until it's in place.
+        ai: Box::new(enemy_ai::Level0AI {}),
+        spawned: false,
+        dead: false,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rand::SeedableRng;
+
+    #[test]
+    fn a_zero_duration_midboss_retreats_as_soon_as_it_lands() {
+        let mut midboss = MidBoss::new(0, 0);
+        let mut spawns = SpawnQueue::default();
+        let mut sprite_holder = SpriteHolder::new(64);
+        let mut rng = StdRng::seed_from_u64(1);
+        let events: Vec<MidBossEvent> = (0..ENTRY_FRAMES + RETREAT_FRAMES + 10)
+            .map(|frame| midboss.update(frame as u64, (400.0, 100.0), &mut spawns, &mut sprite_holder, &mut rng))
+            .collect();
+        assert_eq!(events.iter().filter(|&&event| event == MidBossEvent::Retreated).count(), 1);
+        assert_eq!(midboss.stage, Stage::Gone);
+    }
+}
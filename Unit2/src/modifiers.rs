@@ -0,0 +1,128 @@
+// Optional challenge mutators, plus two easy-mode assists. Toggle them from either
+// title screen's menu, or with 1-6, before starting a run. They live on the SimState, so they're part of the checksum and
+// every score is reported together with the modifiers it was earned under.
+use crate::density_governor::DensityGovernor;
+use crate::input::{Input, Key};
+use crate::simulation::SimState;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct Modifiers {
+    // 1: enemy bullets fly twice as fast.
+    pub double_bullet_speed: bool,
+    // 2: the player moves at half speed.
+    pub half_player_speed: bool,
+    // 3: the first point of damage ends the run.
+    pub one_hit: bool,
+    // 4: left and right are swapped.
+    pub mirrored_controls: bool,
+    // 5: easy assist. A hit taken with a full special meter sets the special off
+    // instead of costing a life, however late it was.
+    pub auto_bomb: bool,
+    // 6: easy assist. Bullets crowding the player past a limit are popped, newest first.
+    pub thin_walls: bool,
+}
+
+impl Modifiers {
+    pub fn handle_hotkeys(&mut self, input: &Input) {
+        let before = *self;
+        if input.is_key_pressed(Key::Key1) {
+            self.double_bullet_speed = !self.double_bullet_speed;
+        }
+        if input.is_key_pressed(Key::Key2) {
+            self.half_player_speed = !self.half_player_speed;
+        }
+        if input.is_key_pressed(Key::Key3) {
+            self.one_hit = !self.one_hit;
+        }
+        if input.is_key_pressed(Key::Key4) {
+            self.mirrored_controls = !self.mirrored_controls;
+        }
+        if input.is_key_pressed(Key::Key5) {
+            self.auto_bomb = !self.auto_bomb;
+        }
+        if input.is_key_pressed(Key::Key6) {
+            self.thin_walls = !self.thin_walls;
+        }
+        if *self != before {
+            log::info!("Modifiers: {}", self.describe());
+        }
+    }
+
+    // Each flag with its title menu name, in hotkey order.
+    pub fn flags(&self) -> [(&'static str, bool); 6] {
+        [
+            ("2X BULLETS", self.double_bullet_speed),
+            ("HALF SPEED", self.half_player_speed),
+            ("ONE HIT", self.one_hit),
+            ("MIRRORED", self.mirrored_controls),
+            ("AUTO BOMB", self.auto_bomb),
+            ("THIN WALLS", self.thin_walls),
+        ]
+    }
+
+    // Sets the flag at `index` in `flags`.
+    pub fn set(&mut self, index: usize, on: bool) {
+        let flag = match index {
+            0 => &mut self.double_bullet_speed,
+            1 => &mut self.half_player_speed,
+            2 => &mut self.one_hit,
+            3 => &mut self.mirrored_controls,
+            4 => &mut self.auto_bomb,
+            5 => &mut self.thin_walls,
+            _ => return,
+        };
+        *flag = on;
+        log::info!("Modifiers: {}", self.describe());
+    }
+
+    pub fn bullet_speed_scale(&self) -> f32 {
+        if self.double_bullet_speed { 2.0 } else { 1.0 }
+    }
+
+    // Called by the level loaders once the player and health bar are set up.
+    pub fn apply_to_level(&self, sim: &mut SimState) {
+        if self.half_player_speed {
+            sim.player.speed *= 0.5;
+        }
+        if self.one_hit {
+            sim.player_health_bar.maxval = 1.0;
+            sim.player_health_bar.currval = 1.0;
+        }
+        if self.thin_walls {
+            let governor = sim.density_governor.map_or(DensityGovernor::ASSIST, |own| own.tightest(DensityGovernor::ASSIST));
+            sim.density_governor = Some(governor);
+        }
+    }
+
+    pub fn to_bits(self) -> u8 {
+        (self.double_bullet_speed as u8)
+            | (self.half_player_speed as u8) << 1
+            | (self.one_hit as u8) << 2
+            | (self.mirrored_controls as u8) << 3
+            | (self.auto_bomb as u8) << 4
+            | (self.thin_walls as u8) << 5
+    }
+
+    // Short tag stored next to scores, e.g. "2xBullets+Mirrored". Empty for a normal run.
+    pub fn tag(&self) -> String {
+        let names = [
+            (self.double_bullet_speed, "2xBullets"),
+            (self.half_player_speed, "HalfSpeed"),
+            (self.one_hit, "OneHit"),
+            (self.mirrored_controls, "Mirrored"),
+            (self.auto_bomb, "AutoBomb"),
+            (self.thin_walls, "ThinWalls"),
+        ];
+        names
+            .iter()
+            .filter(|(on, _)| *on)
+            .map(|(_, name)| *name)
+            .collect::<Vec<_>>()
+            .join("+")
+    }
+
+    pub fn describe(&self) -> String {
+        let tag = self.tag();
+        if tag.is_empty() { "none".to_string() } else { tag }
+    }
+}
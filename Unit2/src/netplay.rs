@@ -0,0 +1,190 @@
+// Experimental LAN versus mode. Each player fights their own copy of the danmaku
+// boss; every phase you clear sends a handful of extra bullets to the other screen.
+//
+// Launch both games with `--versus <local addr> <peer addr>`, for example
+//   sprite-renderer --versus 0.0.0.0:4747 192.168.1.20:4747
+use std::net::{SocketAddr, UdpSocket};
+
+use crate::simulation::FrameInput;
+
+// Bullets the opponent receives each time you clear a phase.
+pub const BULLETS_PER_PHASE: u16 = 12;
+// Both sides use the same seed so the bosses are mirrored.
+pub const VERSUS_SEED: u64 = 47;
+
+const MAGIC: [u8; 2] = *b"U2";
+const PACKET_LEN: usize = 9;
+// Complain in the log if we haven't heard from the peer for this many ticks.
+const TIMEOUT_TICKS: u32 = 120;
+
+// One tick's worth of data.
+// Layout: magic (2) | frame u32 | input bits u8 | garbage total u16, little endian.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+struct Packet {
+    frame: u32,
+    input: u8,
+    // Running total of bullets ever sent. Sending the total instead of a delta means
+    // a lost or reordered packet can't lose or duplicate garbage.
+    garbage_total: u16,
+}
+
+impl Packet {
+    fn encode(&self) -> [u8; PACKET_LEN] {
+        let mut buf = [0; PACKET_LEN];
+        buf[0..2].copy_from_slice(&MAGIC);
+        buf[2..6].copy_from_slice(&self.frame.to_le_bytes());
+        buf[6] = self.input;
+        buf[7..9].copy_from_slice(&self.garbage_total.to_le_bytes());
+        buf
+    }
+
+    fn decode(buf: &[u8]) -> Option<Packet> {
+        if buf.len() != PACKET_LEN || buf[0..2] != MAGIC {
+            return None;
+        }
+        Some(Packet {
+            frame: u32::from_le_bytes([buf[2], buf[3], buf[4], buf[5]]),
+            input: buf[6],
+            garbage_total: u16::from_le_bytes([buf[7], buf[8]]),
+        })
+    }
+}
+
+pub struct VersusSession {
+    socket: UdpSocket,
+    peer: SocketAddr,
+    garbage_sent: u16,
+    garbage_received: u16,
+    peer_frame: u32,
+    ticks_since_heard: u32,
+    // Phases cleared in the current level, as of the last tick.
+    last_phases_cleared: u32,
+}
+
+impl VersusSession {
+    // Looks for `--versus <local> <peer>` in the command line.
+    pub fn from_args(mut args: impl Iterator<Item = String>) -> Option<VersusSession> {
+        args.find(|arg| arg == "--versus")?;
+        let local = args.next()?;
+        let peer = args.next()?;
+        match VersusSession::connect(&local, &peer) {
+            Ok(session) => Some(session),
+            Err(e) => {
+                log::error!("Couldn't start versus mode: {}", e);
+                None
+            }
+        }
+    }
+
+    fn connect(local: &str, peer: &str) -> std::io::Result<VersusSession> {
+        let socket = UdpSocket::bind(local)?;
+        socket.set_nonblocking(true)?;
+        let peer = peer
+            .parse()
+            .map_err(|e| std::io::Error::new(std::io::ErrorKind::InvalidInput, e))?;
+        log::info!("Versus mode: {} <-> {}", socket.local_addr()?, peer);
+        Ok(VersusSession {
+            socket,
+            peer,
+            garbage_sent: 0,
+            garbage_received: 0,
+            peer_frame: 0,
+            ticks_since_heard: 0,
+            last_phases_cleared: 0,
+        })
+    }
+
+    // Sends this tick's packet. `phases_cleared` is the simulation's count for the
+    // current level; it drops back to 0 when a level is reloaded.
+    pub fn send(&mut self, frame: u64, input: FrameInput, phases_cleared: u32) {
+        if phases_cleared < self.last_phases_cleared {
+            self.last_phases_cleared = 0;
+        }
+        let newly_cleared = (phases_cleared - self.last_phases_cleared) as u16;
+        self.last_phases_cleared = phases_cleared;
+        self.garbage_sent = self
+            .garbage_sent
+            .wrapping_add(newly_cleared * BULLETS_PER_PHASE);
+
+        let packet = Packet {
+            frame: frame as u32,
+            input: input.to_bits(),
+            garbage_total: self.garbage_sent,
+        };
+        // UDP is fire and forget; the next tick carries the same totals anyway.
+        let _ = self.socket.send_to(&packet.encode(), self.peer);
+    }
+
+    // Drains every packet that arrived since last tick and returns how many
+    // bullets the opponent has sent us in the meantime.
+    pub fn receive(&mut self) -> u16 {
+        let mut buf = [0; 64];
+        let mut incoming: u16 = 0;
+        let mut heard = false;
+        while let Ok((len, from)) = self.socket.recv_from(&mut buf) {
+            if from != self.peer {
+                continue;
+            }
+            let Some(packet) = Packet::decode(&buf[..len]) else {
+                continue;
+            };
+            heard = true;
+            self.peer_frame = packet.frame;
+            log::trace!(
+                "peer frame {} input {:?}",
+                self.peer_frame,
+                FrameInput::from_bits(packet.input)
+            );
+
+            let delta = packet.garbage_total.wrapping_sub(self.garbage_received);
+            // A "negative" difference means the packet is older than one we already used.
+            if delta < 0x8000 {
+                incoming += delta;
+                self.garbage_received = packet.garbage_total;
+            }
+        }
+
+        if heard {
+            self.ticks_since_heard = 0;
+        } else {
+            self.ticks_since_heard += 1;
+            if self.ticks_since_heard == TIMEOUT_TICKS {
+                log::warn!("No packets from {} for {} ticks", self.peer, TIMEOUT_TICKS);
+            }
+        }
+        incoming
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn packets_survive_the_wire() {
+        let packet = Packet {
+            frame: 0x0102_0304,
+            input: 0b101,
+            garbage_total: 0xfffe,
+        };
+        let buf = packet.encode();
+        assert_eq!(buf[0..2], MAGIC);
+        assert_eq!(Packet::decode(&buf), Some(packet));
+    }
+
+    #[test]
+    fn short_or_foreign_packets_are_dropped() {
+        let buf = Packet {
+            frame: 7,
+            input: 1,
+            garbage_total: 24,
+        }
+        .encode();
+        assert_eq!(Packet::decode(&buf[..PACKET_LEN - 1]), None);
+        assert_eq!(Packet::decode(&[]), None);
+
+        let mut foreign = buf;
+        foreign[0] = b'X';
+        assert_eq!(Packet::decode(&foreign), None);
+    }
+}
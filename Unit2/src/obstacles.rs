@@ -0,0 +1,111 @@
+// Level hazards that aren't the player, the boss or a bullet. Obstacles stop the
+// player from walking through them and swallow any bullet that touches them,
+// from either side.
+use std::f32::consts::PI;
+
+use crate::tween::{lerp, Easing};
+use crate::{atlas, GPUSprite, SpriteHandle, SpriteHolder};
+
+#[derive(Debug, Clone, Copy, PartialEq, serde::Deserialize)]
+pub enum Motion {
+    Static,
+    // Moves back and forth between two points, `period` frames per round trip.
+    Patrol { from: (f32, f32), to: (f32, f32), period: u32 },
+    // Circles `center` (the obstacle's center, not its corner) at `speed` radians per frame.
+    Orbit { center: (f32, f32), radius: f32, speed: f32, start_angle: f32 },
+}
+
+#[derive(Debug, Clone)]
+pub struct Obstacle {
+    pub pos: (f32, f32),
+    pub size: (f32, f32),
+    motion: Motion,
+    age: u32,
+    sprite_index: SpriteHandle,
+}
+
+impl Obstacle {
+    pub fn new(pos: (f32, f32), size: (f32, f32), motion: Motion, sprite_holder: &mut SpriteHolder) -> Self {
+        let mut obstacle = Self {
+            pos,
+            size,
+            motion,
+            age: 0,
+            sprite_index: sprite_holder.get_next_index(),
+        };
+        obstacle.pos = obstacle.position_at(0);
+        obstacle
+    }
+
+    fn position_at(&self, age: u32) -> (f32, f32) {
+        match self.motion {
+            Motion::Static => self.pos,
+            Motion::Patrol { from, to, period } => {
+                // There and back again over one period, easing at both ends.
+                let phase = (age % period) as f32 / period as f32;
+                let leg = if phase < 0.5 { phase * 2.0 } else { 2.0 - phase * 2.0 };
+                let t = Easing::InOutSine.apply(leg);
+                (lerp(from.0, to.0, t), lerp(from.1, to.1, t))
+            }
+            Motion::Orbit { center, radius, speed, start_angle } => {
+                let angle = start_angle + speed * age as f32;
+                (
+                    center.0 + angle.cos() * radius - self.size.0 / 2.0,
+                    center.1 + angle.sin() * radius - self.size.1 / 2.0,
+                )
+            }
+        }
+    }
+
+    pub fn update(&mut self, sprite_holder: &mut SpriteHolder) {
+        self.age += 1;
+        self.pos = self.position_at(self.age);
+        sprite_holder.set_sprite(
+            self.sprite_index,
+            GPUSprite {
+                screen_region: [self.pos.0, self.pos.1, self.size.0, self.size.1],
+                sheet_region: atlas::sheet_region("block"),
+            },
+        );
+    }
+
+    pub fn overlaps(&self, pos: (f32, f32), size: (f32, f32)) -> bool {
+        pos.0 < self.pos.0 + self.size.0
+            && pos.0 + size.0 > self.pos.0
+            && pos.1 < self.pos.1 + self.size.1
+            && pos.1 + size.1 > self.pos.1
+    }
+
+    pub fn sprite_indices(&self) -> Vec<SpriteHandle> {
+        vec![self.sprite_index]
+    }
+
+    pub fn remove(&self, sprite_holder: &mut SpriteHolder) {
+        sprite_holder.remove_sprite(self.sprite_index);
+    }
+}
+
+// A rotating spinner: `arms` blocks evenly spaced around a circle.
+pub fn spinner(
+    center: (f32, f32),
+    radius: f32,
+    arms: usize,
+    speed: f32,
+    sprite_holder: &mut SpriteHolder,
+) -> Vec<Obstacle> {
+    (0..arms)
+        .map(|arm| {
+            let motion = Motion::Orbit {
+                center,
+                radius,
+                speed,
+                start_angle: arm as f32 * 2.0 * PI / arms as f32,
+            };
+            Obstacle::new((0.0, 0.0), (32.0, 32.0), motion, sprite_holder)
+        })
+        .collect()
+}
+
+pub fn blocks(obstacles: &[Obstacle], pos: (f32, f32), size: (f32, f32)) -> bool {
+    obstacles.iter().any(|obstacle| obstacle.overlaps(pos, size))
+}
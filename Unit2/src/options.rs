@@ -0,0 +1,304 @@
+use std::path::PathBuf;
+
+use crate::input::{Binding, Control, Input, Key};
+use crate::render_target::RENDER_SIZE;
+use crate::simulation::SimState;
+
+// The display settings that are kept between sessions, in the same `key=value`
+// format as the save file.
+const OPTIONS_PATH: &str = "options.txt";
+
+// How much bigger enemy bullets are drawn with `bullet_boost` on. Hitboxes don't change.
+const BULLET_BOOST_SCALE: f32 = 1.5;
+// Brightens bullets past their normal colours; the framebuffer clamps at white.
+const BULLET_BOOST_TINT: [f32; 4] = [1.6, 1.6, 1.6, 1.0];
+// With `slowdown_assist` on, the game runs at this speed while an enemy bullet
+// is within this many pixels of the player's hurtbox.
+pub const SLOWDOWN_SPEED: f32 = 0.7;
+pub const SLOWDOWN_RADIUS: f32 = 48.0;
+// With `player_silhouette` on, the player is redrawn on top of everything once
+// this many enemy bullets overlap their sprite.
+const SILHOUETTE_OVERLAPS: usize = 3;
+// Enemy bullets slower than the first speed are "slow", faster than the second "fast".
+const SPEED_TIERS: (f32, f32) = (4.5, 6.5);
+
+// How enemy bullets are coloured by speed, so fast ones stand out in a dense pattern.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum BulletColors {
+    #[default]
+    Off,
+    // Slow blue, fast red.
+    BlueRed,
+    // Slow blue, fast yellow, for players who can't tell red from the fruit.
+    BlueYellow,
+}
+
+impl BulletColors {
+    fn next(self) -> Self {
+        match self {
+            BulletColors::Off => BulletColors::BlueRed,
+            BulletColors::BlueRed => BulletColors::BlueYellow,
+            BulletColors::BlueYellow => BulletColors::Off,
+        }
+    }
+
+    fn tint(self, speed: f32) -> [f32; 4] {
+        let (slow, fast) = match self {
+            BulletColors::Off => return crate::TINT_NONE,
+            BulletColors::BlueRed => ([0.5, 0.8, 1.6, 1.0], [1.6, 0.4, 0.4, 1.0]),
+            BulletColors::BlueYellow => ([0.5, 0.8, 1.6, 1.0], [1.6, 1.4, 0.3, 1.0]),
+        };
+        if speed < SPEED_TIERS.0 {
+            slow
+        } else if speed > SPEED_TIERS.1 {
+            fast
+        } else {
+            crate::TINT_NONE
+        }
+    }
+}
+
+// Window size as a multiple of the internal render size.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum WindowScale {
+    #[default]
+    One,
+    OneAndAHalf,
+    Two,
+}
+
+impl WindowScale {
+    fn next(self) -> Self {
+        match self {
+            WindowScale::One => WindowScale::OneAndAHalf,
+            WindowScale::OneAndAHalf => WindowScale::Two,
+            WindowScale::Two => WindowScale::One,
+        }
+    }
+
+    fn name(self) -> &'static str {
+        match self {
+            WindowScale::One => "1x",
+            WindowScale::OneAndAHalf => "1.5x",
+            WindowScale::Two => "2x",
+        }
+    }
+
+    fn parse(name: &str) -> Option<Self> {
+        [WindowScale::One, WindowScale::OneAndAHalf, WindowScale::Two]
+            .into_iter()
+            .find(|scale| scale.name() == name)
+    }
+
+    pub fn window_size(self) -> winit::dpi::PhysicalSize<u32> {
+        let factor = match self {
+            WindowScale::One => 1.0,
+            WindowScale::OneAndAHalf => 1.5,
+            WindowScale::Two => 2.0,
+        };
+        winit::dpi::PhysicalSize::new(
+            (RENDER_SIZE.0 as f32 * factor) as u32,
+            (RENDER_SIZE.1 as f32 * factor) as u32,
+        )
+    }
+}
+
+// Player-facing display and accessibility settings.
+// There's no options menu yet, so each one has a function key toggle.
+#[derive(Debug, Clone, Default)]
+pub struct Options {
+    // F2: dark 1px outline around the player and enemy so they stay readable in busy patterns.
+    pub sprite_outlines: bool,
+    // F3: draw enemy bullets bigger and brighter. Purely visual.
+    pub bullet_boost: bool,
+    // F4: practice assist that slows the game when a bullet gets close.
+    // Using it makes the run ineligible for leaderboards.
+    pub slowdown_assist: bool,
+    // F6: check for leaked sprite slots every frame. Always on in debug builds.
+    pub sprite_audit: bool,
+    // F7: practice assist that rewinds five seconds on death instead of ending the run.
+    // Also makes the run ineligible for leaderboards once it kicks in.
+    pub rewind_on_death: bool,
+    // F8: cycles through the bullet colour schemes.
+    pub bullet_colors: BulletColors,
+    // F1: redraw the player as a bright silhouette over everything when they're buried in bullets.
+    pub player_silhouette: bool,
+    // F5: cycles the window size presets. Remembered between sessions.
+    pub window_scale: WindowScale,
+    // F10: only scale the frame up by whole multiples, with black around it, so
+    // every pixel of the art stays the same size. Remembered between sessions.
+    pub integer_scaling: bool,
+    // F11: draw the playtest heatmap over the playfield. Only does anything when
+    // launched with `--heatmap`.
+    pub heatmap_overlay: bool,
+    // F12: redraw at a capped rate and sleep in between, for laptops and the web
+    // build. Logic still steps at the usual rate. Remembered between sessions.
+    pub low_power: bool,
+    // Pause menu: read the letter keys by where they sit rather than what the
+    // layout calls them, for AZERTY and Dvorak. Remembered between sessions.
+    pub bind_by_position: bool,
+    // Pause menu: every control changed on the controls page, whether it was
+    // picked or swapped, with all of its keys. Remembered between sessions.
+    pub bound_keys: Vec<(Control, Vec<Binding>)>,
+}
+
+impl Options {
+    // Defaults, with the remembered display settings from the options file if there is one.
+    pub fn load() -> Self {
+        let mut options = Self::default();
+        let path = PathBuf::from(OPTIONS_PATH);
+        let Ok(text) = std::fs::read_to_string(&path) else {
+            return options;
+        };
+        for line in text.lines().map(str::trim).filter(|line| !line.is_empty() && !line.starts_with('#')) {
+            let parsed = match line.split_once('=') {
+                Some(("window_scale", value)) => WindowScale::parse(value).map(|scale| options.window_scale = scale),
+                Some(("integer_scaling", value)) => value.parse().ok().map(|on| options.integer_scaling = on),
+                Some(("low_power", value)) => value.parse().ok().map(|on| options.low_power = on),
+                Some(("bind_by_position", value)) => value.parse().ok().map(|on| options.bind_by_position = on),
+                Some((setting, value)) if setting.starts_with("key.") => {
+                    let control = Control::ALL.into_iter().find(|control| setting == format!("key.{}", control.config_name()));
+                    let bindings: Option<Vec<Binding>> = value.split('/').map(Binding::from_name).collect();
+                    control.zip(bindings).map(|bound| options.bound_keys.push(bound))
+                }
+                _ => None,
+            };
+            if parsed.is_none() {
+                log::warn!("Ignoring unknown line in {}: {}", path.display(), line);
+            }
+        }
+        options
+    }
+
+    fn store(&self) {
+        let mut text = format!(
+            "# unit2game1 options\nwindow_scale={}\ninteger_scaling={}\nlow_power={}\nbind_by_position={}\n",
+            self.window_scale.name(),
+            self.integer_scaling,
+            self.low_power,
+            self.bind_by_position
+        );
+        for (control, bindings) in self.bound_keys.iter() {
+            let names: Vec<String> = bindings.iter().filter_map(|binding| binding.config_name()).collect();
+            text += &format!("key.{}={}\n", control.config_name(), names.join("/"));
+        }
+        if let Err(e) = std::fs::write(OPTIONS_PATH, text) {
+            log::warn!("Couldn't write options file {}: {}", OPTIONS_PATH, e);
+        }
+    }
+
+    pub fn set_bind_by_position(&mut self, on: bool) {
+        self.bind_by_position = on;
+        log::info!("Keys by position: {}", on);
+        self.store();
+    }
+
+    // Remembers the controls' keys as they are now, replacing whatever was kept
+    // for them before.
+    pub fn set_bound_keys(&mut self, controls: &[Control], input: &Input, by_position: bool) {
+        for &control in controls {
+            let bindings = input.control_bindings(control, by_position).to_vec();
+            log::info!("{} bound to {}", control.name(), bindings.iter().map(|&binding| input.binding_name(binding)).collect::<Vec<_>>().join("/"));
+            self.bound_keys.retain(|(bound, _)| *bound != control);
+            self.bound_keys.push((control, bindings));
+        }
+        self.store();
+    }
+
+    pub fn clear_bound_keys(&mut self) {
+        self.bound_keys.clear();
+        log::info!("Controls back to the defaults");
+        self.store();
+    }
+
+    pub fn handle_hotkeys(&mut self, input: &Input) {
+        if input.is_key_pressed(Key::F1) {
+            self.player_silhouette = !self.player_silhouette;
+            log::info!("Player silhouette: {}", self.player_silhouette);
+        }
+        if input.is_key_pressed(Key::F2) {
+            self.sprite_outlines = !self.sprite_outlines;
+            log::info!("Sprite outlines: {}", self.sprite_outlines);
+        }
+        if input.is_key_pressed(Key::F3) {
+            self.bullet_boost = !self.bullet_boost;
+            log::info!("Bullet visibility boost: {}", self.bullet_boost);
+        }
+        if input.is_key_pressed(Key::F4) {
+            self.slowdown_assist = !self.slowdown_assist;
+            log::info!("Slowdown assist: {}", self.slowdown_assist);
+        }
+        if input.is_key_pressed(Key::F7) {
+            self.rewind_on_death = !self.rewind_on_death;
+            log::info!("Rewind on death: {}", self.rewind_on_death);
+        }
+        if input.is_key_pressed(Key::F8) {
+            self.bullet_colors = self.bullet_colors.next();
+            log::info!("Bullet colours: {:?}", self.bullet_colors);
+        }
+        if input.is_key_pressed(Key::F5) {
+            self.window_scale = self.window_scale.next();
+            log::info!("Window scale: {}", self.window_scale.name());
+            self.store();
+        }
+        if input.is_key_pressed(Key::F10) {
+            self.integer_scaling = !self.integer_scaling;
+            log::info!("Integer scaling: {}", self.integer_scaling);
+            self.store();
+        }
+        if input.is_key_pressed(Key::F12) {
+            self.low_power = !self.low_power;
+            log::info!("Low power frame pacing: {}", self.low_power);
+            self.store();
+        }
+    }
+
+    // Developer toggles, kept apart from the player-facing ones above.
+    pub fn handle_debug_hotkeys(&mut self, input: &Input) {
+        if input.is_key_pressed(Key::F6) {
+            self.sprite_audit = !self.sprite_audit;
+            log::info!("Sprite audit: {}", self.sprite_audit);
+        }
+        if input.is_key_pressed(Key::F11) {
+            self.heatmap_overlay = !self.heatmap_overlay;
+            log::info!("Heatmap overlay: {}", self.heatmap_overlay);
+        }
+    }
+
+    // Whether to draw the player's silhouette this frame.
+    pub fn wants_silhouette(&self, sim: &SimState) -> bool {
+        if !self.player_silhouette || sim.mode == 0 {
+            return false;
+        }
+        let overlaps = |a: [f32; 4], b: [f32; 4]| {
+            a[0] < b[0] + b[2] && b[0] < a[0] + a[2] && a[1] < b[1] + b[3] && b[1] < a[1] + a[3]
+        };
+        let player = sim.player.sprite.screen_region;
+        sim.projectiles
+            .iter()
+            .filter(|proj| !proj.player_spawned && overlaps(proj.sprite.screen_region, player))
+            .count()
+            >= SILHOUETTE_OVERLAPS
+    }
+
+    // Adjusts sprites after the simulation has synced them for this frame.
+    // Nothing in here may feed back into gameplay.
+    pub fn apply_visuals(&self, sim: &mut SimState) {
+        // Colours go by the bullet's own speed, before any run modifier scales it.
+        for proj in sim.projectiles.iter().filter(|proj| !proj.player_spawned) {
+            let mut tint = self.bullet_colors.tint(proj.speed);
+            tint[3] *= proj.warm_up_alpha();
+            if self.bullet_boost {
+                let [x, y, w, h] = proj.sprite.screen_region;
+                let grow = (w * (BULLET_BOOST_SCALE - 1.0) / 2.0, h * (BULLET_BOOST_SCALE - 1.0) / 2.0);
+                let mut sprite = proj.sprite;
+                sprite.screen_region = [x - grow.0, y - grow.1, w + 2.0 * grow.0, h + 2.0 * grow.1];
+                sim.sprite_holder.set_sprite(proj.sprite_index, sprite);
+                for (channel, boost) in tint.iter_mut().zip(BULLET_BOOST_TINT) {
+                    *channel *= boost;
+                }
+            }
+            sim.sprite_holder.set_tint(proj.sprite_index, tint);
+        }
+    }
+}
@@ -0,0 +1,60 @@
+// Optional live data for stream overlays (OBS text sources, browser sources, ...).
+// Launch with `--overlay <file>` and the game rewrites that file with a small
+// JSON object once a second.
+use std::path::PathBuf;
+use web_time::{Duration, Instant};
+
+use crate::clock::{GameClock, FRAMES_PER_SECOND};
+use crate::simulation::SimState;
+
+const WRITE_INTERVAL: Duration = Duration::from_secs(1);
+
+pub struct StreamOverlay {
+    path: PathBuf,
+    last_write: Option<Instant>,
+}
+
+impl StreamOverlay {
+    // Looks for `--overlay <file>` in the command line.
+    pub fn from_args(mut args: impl Iterator<Item = String>) -> Option<StreamOverlay> {
+        args.find(|arg| arg == "--overlay")?;
+        let path = PathBuf::from(args.next()?);
+        log::info!("Writing stream overlay data to {}", path.display());
+        Some(StreamOverlay {
+            path,
+            last_write: None,
+        })
+    }
+
+    // Called every frame; only touches the disk once per interval.
+    pub fn update(&mut self, game_state: usize, sim: &SimState, clock: &GameClock) {
+        if self
+            .last_write
+            .is_some_and(|last| last.elapsed() < WRITE_INTERVAL)
+        {
+            return;
+        }
+        self.last_write = Some(Instant::now());
+
+        let json = format!(
+            "{{\"state\":{},\"score\":{},\"lives\":{},\"boss_hp\":{},\"boss_max_hp\":{},\"phase\":\"{}\",\"leaderboard_eligible\":{},\"modifiers\":\"{}\",\"play_seconds\":{},\"session_seconds\":{}}}\n",
+            game_state,
+            sim.player.score,
+            sim.player_health_bar.currval.max(0.0),
+            sim.enemy.enemy.health_bar.currval.max(0.0),
+            sim.enemy.enemy.health_bar.maxval,
+            sim.enemy.ai.phase_name(),
+            sim.leaderboard_eligible,
+            sim.modifiers.tag(),
+            clock.game_frames() / FRAMES_PER_SECOND,
+            clock.real_frames() / FRAMES_PER_SECOND,
+        );
+
+        // Write then rename so the overlay never reads a half written file.
+        let tmp = self.path.with_extension("tmp");
+        let result = std::fs::write(&tmp, json).and_then(|_| std::fs::rename(&tmp, &self.path));
+        if let Err(e) = result {
+            log::warn!("Couldn't write overlay file {}: {}", self.path.display(), e);
+        }
+    }
+}
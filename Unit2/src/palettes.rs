@@ -0,0 +1,13 @@
+// Recolours the sprite shader can apply on its own, so a variant of an enemy or a
+// bullet can reuse the original art instead of needing a copy in the sheet. The
+// numbers are what `palette_color` in shader.wgsl switches on.
+#[repr(u32)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Palette {
+    // The sheet's own colours.
+    #[default]
+    Sheet = 0,
+    Crimson = 1,
+    Frost = 2,
+    Gold = 3,
+}
@@ -0,0 +1,184 @@
+// Short-lived sprites that are only there to look at: sparks off a hit, a burst
+// when something dies, a flash at the gun when the player fires. Each particle
+// drifts, slows, shrinks and fades on its own, and none of it touches gameplay or
+// the sim's RNG. Finished particles keep their sprite slot, blanked, for the next
+// one, so a busy fight doesn't churn the sprite holder.
+use bytemuck::Zeroable;
+
+use crate::generated_sprites::SOLID_CELL;
+use crate::{GPUSprite, Layer, SpriteHandle, SpriteHolder, SPRITE_SHEET_RESOLUTION};
+
+// Past this the oldest particle makes way for the new one.
+const MAX_PARTICLES: usize = 512;
+// Idle slots kept for reuse. Any beyond this go back to the holder.
+const MAX_POOLED: usize = 128;
+// Turns each burst by this much from the last, so they don't all line up.
+const GOLDEN_ANGLE: f32 = 2.399_963;
+
+#[derive(Debug, Clone, Copy)]
+pub struct Style {
+    lifetime: u32,
+    // Size when it appears and when it's gone.
+    size: (f32, f32),
+    tint: [f32; 3],
+    // The share of its speed it keeps each frame.
+    drag: f32,
+}
+
+pub const SPARK: Style = Style {
+    lifetime: 8,
+    size: (12.0, 4.0),
+    tint: [1.6, 1.5, 1.0],
+    drag: 0.85,
+};
+
+pub const DEATH_BURST: Style = Style {
+    lifetime: 24,
+    size: (18.0, 4.0),
+    tint: [1.8, 0.9, 0.5],
+    drag: 0.92,
+};
+
+pub const MUZZLE_FLASH: Style = Style {
+    lifetime: 4,
+    size: (20.0, 8.0),
+    tint: [1.4, 1.4, 1.8],
+    drag: 0.5,
+};
+
+struct Particle {
+    pos: (f32, f32),
+    velocity: (f32, f32),
+    age: u32,
+    style: Style,
+    sprite_index: SpriteHandle,
+}
+
+impl Particle {
+    fn is_done(&self) -> bool {
+        self.age >= self.style.lifetime
+    }
+
+    fn sync_sprite(&self, sprite_holder: &mut SpriteHolder) {
+        let t = self.age as f32 / self.style.lifetime as f32;
+        let size = self.style.size.0 + (self.style.size.1 - self.style.size.0) * t;
+        sprite_holder.set_sprite(
+            self.sprite_index,
+            GPUSprite {
+                screen_region: [self.pos.0 - size / 2.0, self.pos.1 - size / 2.0, size, size],
+                sheet_region: [
+                    SOLID_CELL.0 / SPRITE_SHEET_RESOLUTION.0,
+                    SOLID_CELL.1 / SPRITE_SHEET_RESOLUTION.1,
+                    1.0 / SPRITE_SHEET_RESOLUTION.0,
+                    1.0 / SPRITE_SHEET_RESOLUTION.1,
+                ],
+            },
+        );
+        let [r, g, b] = self.style.tint;
+        sprite_holder.set_tint(self.sprite_index, [r, g, b, 1.0 - t]);
+    }
+}
+
+#[derive(Default)]
+pub struct Particles {
+    live: Vec<Particle>,
+    // Slots left by finished particles, blanked and waiting to be reused.
+    pool: Vec<SpriteHandle>,
+    bursts: u32,
+}
+
+impl Particles {
+    pub fn emit(&mut self, pos: (f32, f32), velocity: (f32, f32), style: Style, sprite_holder: &mut SpriteHolder) {
+        if self.live.len() >= MAX_PARTICLES {
+            let oldest = self.live.remove(0);
+            self.pool.push(oldest.sprite_index);
+        }
+        let sprite_index = self
+            .pool
+            .pop()
+            .unwrap_or_else(|| sprite_holder.get_next_index_on(Layer::Projectiles));
+        let particle = Particle {
+            pos,
+            velocity,
+            age: 0,
+            style,
+            sprite_index,
+        };
+        particle.sync_sprite(sprite_holder);
+        self.live.push(particle);
+    }
+
+    // `count` particles flying out from `center`, evenly spread around it.
+    pub fn burst(&mut self, center: (f32, f32), count: usize, speed: f32, style: Style, sprite_holder: &mut SpriteHolder) {
+        let start = self.bursts as f32 * GOLDEN_ANGLE;
+        self.bursts = self.bursts.wrapping_add(1);
+        for i in 0..count {
+            let angle = start + std::f32::consts::TAU * i as f32 / count as f32;
+            self.emit(center, (angle.cos() * speed, angle.sin() * speed), style, sprite_holder);
+        }
+    }
+
+    pub fn update(&mut self, sprite_holder: &mut SpriteHolder) {
+        for particle in self.live.iter_mut() {
+            particle.age += 1;
+            particle.pos.0 += particle.velocity.0;
+            particle.pos.1 += particle.velocity.1;
+            particle.velocity.0 *= particle.style.drag;
+            particle.velocity.1 *= particle.style.drag;
+            if particle.is_done() {
+                sprite_holder.set_sprite(particle.sprite_index, GPUSprite::zeroed());
+                self.pool.push(particle.sprite_index);
+            } else {
+                particle.sync_sprite(sprite_holder);
+            }
+        }
+        self.live.retain(|particle| !particle.is_done());
+        while self.pool.len() > MAX_POOLED {
+            if let Some(sprite_index) = self.pool.pop() {
+                sprite_holder.remove_sprite(sprite_index);
+            }
+        }
+    }
+
+    pub fn sprite_indices(&self) -> Vec<SpriteHandle> {
+        self.live.iter().map(|particle| particle.sprite_index).chain(self.pool.iter().copied()).collect()
+    }
+
+    pub fn clear(&mut self, sprite_holder: &mut SpriteHolder) {
+        for sprite_index in self.sprite_indices() {
+            sprite_holder.remove_sprite(sprite_index);
+        }
+        self.live.clear();
+        self.pool.clear();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn finished_particles_hand_their_slots_to_new_ones() {
+        let mut sprite_holder = SpriteHolder::new(16);
+        let mut particles = Particles::default();
+        particles.burst((100.0, 100.0), 4, 2.0, MUZZLE_FLASH, &mut sprite_holder);
+        let first = particles.sprite_indices();
+        assert_eq!(first.len(), 4);
+        let start = particles.live[0].pos;
+        particles.update(&mut sprite_holder);
+        assert_ne!(particles.live[0].pos, start);
+
+        for _ in 1..MUZZLE_FLASH.lifetime {
+            particles.update(&mut sprite_holder);
+        }
+        assert!(particles.live.is_empty());
+        // Still owned, just blank.
+        assert_eq!(particles.sprite_indices().len(), 4);
+        assert!(first.iter().all(|&handle| sprite_holder.is_live(handle) && sprite_holder.sprites[handle.index()].screen_region == [0.0; 4]));
+
+        particles.emit((0.0, 0.0), (0.0, 0.0), SPARK, &mut sprite_holder);
+        assert!(first.contains(&particles.live[0].sprite_index));
+        particles.clear(&mut sprite_holder);
+        assert!(first.iter().all(|&handle| !sprite_holder.is_live(handle)));
+    }
+}
@@ -0,0 +1,107 @@
+// Run-to-run variety for behaviour tree volleys. A designer gives a volley bounds
+// to wander within, and each run draws its own values from the sim's seeded RNG
+// when the boss spawns, so a replay or a rewind gets the same ones back. The
+// pressure band keeps the result about as hard as the volley as written: more
+// bullets come slower, fewer come faster.
+use rand::rngs::StdRng;
+use rand::Rng;
+use serde::Deserialize;
+
+use crate::behavior_tree::Volley;
+
+// Inclusive ranges. Anything left out keeps the volley's own value.
+#[derive(Debug, Clone, Copy, Default, Deserialize)]
+#[serde(default)]
+pub struct Variation {
+    pub bullets: Option<(usize, usize)>,
+    pub speed: Option<(f32, f32)>,
+    // Fans only.
+    pub spread: Option<(f32, f32)>,
+    // Turns rings and fans by up to this many radians either way.
+    pub turn: Option<f32>,
+    // How far bullets times speed may stray from the written volley's, as a
+    // fraction of it. Speed gives way to stay inside.
+    pub pressure_band: Option<f32>,
+}
+
+fn draw_f32(rng: &mut StdRng, (low, high): (f32, f32)) -> f32 {
+    if low < high {
+        rng.gen_range(low..=high)
+    } else {
+        low
+    }
+}
+
+pub fn vary(volley: Volley, variation: &Variation, rng: &mut StdRng) -> Volley {
+    let (bullets, speed) = match volley {
+        Volley::Ring { bullets, speed, .. } | Volley::Fan { bullets, speed, .. } => (bullets, speed),
+        Volley::Aimed { speed } => (1, speed),
+    };
+    let new_bullets = match (volley, variation.bullets) {
+        (Volley::Aimed { .. }, _) | (_, None) => bullets,
+        (_, Some((low, high))) => rng.gen_range(low.min(high)..=low.max(high)).max(1),
+    };
+    let (mut low, mut high) = variation.speed.unwrap_or((speed, speed));
+    if let Some(band) = variation.pressure_band {
+        let pressure = bullets as f32 * speed;
+        let (band_low, band_high) = (pressure * (1.0 - band) / new_bullets as f32, pressure * (1.0 + band) / new_bullets as f32);
+        (low, high) = (low.max(band_low), high.min(band_high));
+        // The bounds and the band don't overlap; the band wins.
+        if low > high {
+            let speed = speed.clamp(band_low, band_high);
+            (low, high) = (speed, speed);
+        }
+    }
+    let new_speed = draw_f32(rng, (low, high));
+    let turn = variation.turn.map_or(0.0, |turn| draw_f32(rng, (-turn, turn)));
+    match volley {
+        Volley::Ring { turn: written, .. } => Volley::Ring {
+            bullets: new_bullets,
+            speed: new_speed,
+            turn: written + turn,
+        },
+        Volley::Aimed { .. } => Volley::Aimed { speed: new_speed },
+        Volley::Fan { spread, turn: written, .. } => Volley::Fan {
+            bullets: new_bullets,
+            spread: variation.spread.map_or(spread, |bounds| draw_f32(rng, bounds)),
+            speed: new_speed,
+            turn: written + turn,
+        },
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rand::SeedableRng;
+
+    #[test]
+    fn varied_volleys_stay_in_bounds_and_in_the_pressure_band() {
+        let written = Volley::Ring { bullets: 10, speed: 4.0, turn: 0.0 };
+        let variation = Variation {
+            bullets: Some((6, 16)),
+            speed: Some((2.0, 7.0)),
+            turn: Some(0.5),
+            pressure_band: Some(0.2),
+            ..Default::default()
+        };
+        let mut rng = StdRng::seed_from_u64(9);
+        let mut counts = std::collections::BTreeSet::new();
+        for _ in 0..200 {
+            let Volley::Ring { bullets, speed, turn } = vary(written, &variation, &mut rng) else {
+                panic!("a ring stays a ring");
+            };
+            counts.insert(bullets);
+            assert!((6..=16).contains(&bullets));
+            assert!((2.0..=7.0).contains(&speed));
+            assert!(turn.abs() <= 0.5);
+            let pressure = bullets as f32 * speed;
+            assert!((31.9..=48.1).contains(&pressure), "{} bullets at {}", bullets, speed);
+        }
+        assert!(counts.len() > 5);
+
+        // Same seed, same volley.
+        let first = vary(written, &variation, &mut StdRng::seed_from_u64(3));
+        assert_eq!(format!("{:?}", first), format!("{:?}", vary(written, &variation, &mut StdRng::seed_from_u64(3))));
+    }
+}
@@ -0,0 +1,111 @@
+// The level's world: how big it is, the area the player is allowed to move in, and
+// what happens at its edges. Each level loader sets one up on the SimState.
+use crate::components::Body;
+use crate::render_target::{PLAYFIELD_ORIGIN, PLAYFIELD_SIZE, RENDER_SIZE};
+use crate::GPUCamera;
+
+// How far past the edges something has to be before it's too far away to
+// bother thinking or animating.
+const IDLE_MARGIN: f32 = 256.0;
+
+// Levels may leave out any field; it keeps its default.
+#[derive(Debug, Clone, Copy, PartialEq, serde::Deserialize)]
+#[serde(default)]
+pub struct Playfield {
+    // World units, from (0, 0) at the bottom left. The camera fits this to the
+    // render target, so a tall one is shown smaller with room beside it.
+    pub size: (f32, f32),
+    // Range of the player's x position (their left edge).
+    pub min_x: f32,
+    pub max_x: f32,
+    // Leaving one side brings the player in on the other instead of stopping them.
+    pub wrap_x: bool,
+}
+
+impl Default for Playfield {
+    fn default() -> Self {
+        Self {
+            size: (1024.0, 768.0),
+            min_x: 0.0,
+            max_x: 960.0,
+            wrap_x: false,
+        }
+    }
+}
+
+impl Playfield {
+    pub fn confine_x(&self, x: f32) -> f32 {
+        if !self.wrap_x {
+            return x.clamp(self.min_x, self.max_x);
+        }
+        let span = self.max_x - self.min_x;
+        if x > self.max_x {
+            x - span
+        } else if x < self.min_x {
+            x + span
+        } else {
+            x
+        }
+    }
+
+    // Render target pixels per world unit, the same in both directions.
+    fn scale(&self) -> f32 {
+        (PLAYFIELD_SIZE.0 as f32 / self.size.0).min(PLAYFIELD_SIZE.1 as f32 / self.size.1)
+    }
+
+    // Shows the playfield as large as fits in the framed area, from its bottom left.
+    pub fn camera(&self) -> GPUCamera {
+        let scale = self.scale();
+        GPUCamera {
+            screen_pos: [-(PLAYFIELD_ORIGIN.0 as f32) / scale, -(PLAYFIELD_ORIGIN.1 as f32) / scale],
+            screen_size: [RENDER_SIZE.0 as f32 / scale, RENDER_SIZE.1 as f32 / scale],
+        }
+    }
+
+    // The screen point under a render target pixel, counted from its bottom left.
+    // Screen points are in world units, where the world is with the camera at rest.
+    pub fn screen_pos(&self, render_pos: (f32, f32)) -> (f32, f32) {
+        let camera = self.camera();
+        let scale = self.scale();
+        (camera.screen_pos[0] + render_pos.0 / scale, camera.screen_pos[1] + render_pos.1 / scale)
+    }
+
+    // Whether `body` is so far outside that its AI and animation can be skipped.
+    // It still moves, so it turns up where and when it would have anyway.
+    pub fn is_far_outside(&self, body: &Body) -> bool {
+        body.pos.0 + body.size.0 < -IDLE_MARGIN
+            || body.pos.0 > self.size.0 + IDLE_MARGIN
+            || body.pos.1 + body.size.1 < -IDLE_MARGIN
+            || body.pos.1 > self.size.1 + IDLE_MARGIN
+    }
+
+    // How wide the framed area is in world units. More than `size.0` for a tall
+    // playfield; a side panel can fill the difference.
+    pub fn visible_width(&self) -> f32 {
+        PLAYFIELD_SIZE.0 as f32 / self.scale()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn portrait_playfield_fits_the_framed_height() {
+        let default = Playfield::default().camera();
+        assert_eq!(default.screen_pos, [-(PLAYFIELD_ORIGIN.0 as f32), -(PLAYFIELD_ORIGIN.1 as f32)]);
+        assert_eq!(default.screen_size, [RENDER_SIZE.0 as f32, RENDER_SIZE.1 as f32]);
+
+        let portrait = Playfield {
+            size: (600.0, 800.0),
+            ..Default::default()
+        };
+        // The top edge of the playfield lands on the top edge of the framed area.
+        let camera = portrait.camera();
+        let top = (800.0 - camera.screen_pos[1]) / camera.screen_size[1] * RENDER_SIZE.1 as f32;
+        assert!((top - (PLAYFIELD_ORIGIN.1 + PLAYFIELD_SIZE.1) as f32).abs() < 0.01);
+        assert!(portrait.visible_width() > 600.0);
+        let origin = portrait.screen_pos((PLAYFIELD_ORIGIN.0 as f32, PLAYFIELD_ORIGIN.1 as f32));
+        assert!(origin.0.abs() < 0.01 && origin.1.abs() < 0.01);
+    }
+}
@@ -0,0 +1,89 @@
+// Announcer banners ("10 HIT!", "GREAT!", "SPELL CAPTURED") that slide in from the
+// right, hold for a moment and slide out to the left. Only one is on screen at a
+// time so they never draw over each other; the rest wait their turn in a queue.
+use std::collections::VecDeque;
+
+use crate::text::TextLabel;
+use crate::tween::{Easing, Tween};
+use crate::{SpriteHandle, SpriteHolder};
+
+const SLIDE_FRAMES: u32 = 18;
+const HOLD_FRAMES: u32 = 50;
+// Anything older than this is dropped rather than shown late.
+const MAX_QUEUED: usize = 3;
+const SCALE: f32 = 5.0;
+const POPUP_Y: f32 = 440.0;
+const SCREEN_WIDTH: f32 = 1024.0;
+const TINT: [f32; 4] = [1.0, 0.85, 0.3, 1.0];
+
+struct ActivePopup {
+    label: TextLabel,
+    // Slide in, hold, slide out; the front one is playing.
+    moves: VecDeque<Tween>,
+}
+
+#[derive(Default)]
+pub struct Popups {
+    queue: VecDeque<String>,
+    active: Option<ActivePopup>,
+}
+
+impl Popups {
+    pub fn push(&mut self, text: impl Into<String>) {
+        if self.queue.len() == MAX_QUEUED {
+            self.queue.pop_front();
+        }
+        self.queue.push_back(text.into());
+    }
+
+    // Advances the current banner by one frame, starting the next one when it's done.
+    pub fn update(&mut self, sprite_holder: &mut SpriteHolder) {
+        if self.active.is_none() {
+            let Some(text) = self.queue.pop_front() else {
+                return;
+            };
+            let label = TextLabel::new(&text, sprite_holder);
+            let width = label.width(SCALE);
+            let centered = (SCREEN_WIDTH - width) / 2.0;
+            let glyphs = label.sprite_indices();
+            let moves = VecDeque::from([
+                Tween::new(SCREEN_WIDTH, centered, SLIDE_FRAMES, Easing::OutCubic),
+                Tween::new(centered, centered, HOLD_FRAMES, Easing::Linear),
+                Tween::new(centered, -width, SLIDE_FRAMES, Easing::InCubic).on_complete(move |sprite_holder| {
+                    for index in glyphs {
+                        sprite_holder.remove_sprite(index);
+                    }
+                }),
+            ]);
+            self.active = Some(ActivePopup { label, moves });
+        }
+        let Some(popup) = &mut self.active else {
+            return;
+        };
+        let Some(tween) = popup.moves.front_mut() else {
+            return;
+        };
+
+        popup.label.draw((tween.value(), POPUP_Y), SCALE, TINT, sprite_holder);
+        tween.step(sprite_holder);
+        if tween.is_done() {
+            popup.moves.pop_front();
+        }
+        // The last slide cleans up its own glyphs.
+        if popup.moves.is_empty() {
+            self.active = None;
+        }
+    }
+
+    pub fn sprite_indices(&self) -> Vec<SpriteHandle> {
+        self.active.as_ref().map_or(vec![], |popup| popup.label.sprite_indices())
+    }
+
+    // Drops everything, e.g. when the level is torn down.
+    pub fn clear(&mut self, sprite_holder: &mut SpriteHolder) {
+        self.queue.clear();
+        if let Some(popup) = self.active.take() {
+            popup.label.remove(sprite_holder);
+        }
+    }
+}
@@ -0,0 +1,165 @@
+// Per-bullet movement rules beyond flying in a straight line. Every projectile has
+// one; the simulation lets it change the bullet's speed and direction each frame
+// before moving.
+use std::f32::consts::PI;
+
+use crate::generated_sprites::RING_CELL;
+use crate::palettes::Palette;
+use crate::scene::Attachment;
+use crate::spawn_queue::{SpawnQueue, SpawnRequest};
+use crate::{GPUSprite, Layer, SpriteHandle, SpriteHolder, SPRITE_SHEET_RESOLUTION};
+
+// Magnet bullets give up homing after this long, so they can't circle forever.
+const MAGNET_FRAMES: u32 = 150;
+const MAGNET_ACCEL: f32 = 0.09;
+const MAGNET_MAX_SPEED: f32 = 6.5;
+const MAGNET_RING_SCALE: f32 = 1.6;
+const MAGNET_RING_TINT: [f32; 4] = [1.0, 0.3, 1.0, 0.7];
+const FIREWORK_FUSE_FRAMES: u32 = 50;
+const FIREWORK_PAUSE_FRAMES: u32 = 24;
+const FIREWORK_CHILD_SPEED: f32 = 3.5;
+// Frames from launch until a firework bursts.
+pub const FIREWORK_FRAMES: u32 = FIREWORK_FUSE_FRAMES + FIREWORK_PAUSE_FRAMES;
+// Half a bullet, so children start centred on the burst.
+const CHILD_HALF_SIZE: f32 = 32.0;
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum ProjectileBehavior {
+    Straight,
+    // Curves toward the player with capped acceleration, wearing a ring so it's
+    // obvious which bullets will follow you.
+    Magnet { frames_left: u32, ring_sprite_index: SpriteHandle },
+    // Flies for a while, hangs in place, then bursts into a ring of `children` bullets.
+    Firework { age: u32, children: u32 },
+}
+
+impl ProjectileBehavior {
+    // The ring's sprite is claimed when the spawn queue turns this into a bullet.
+    pub fn magnet() -> Self {
+        ProjectileBehavior::Magnet {
+            frames_left: MAGNET_FRAMES,
+            ring_sprite_index: SpriteHandle::NONE,
+        }
+    }
+
+    pub fn firework(children: u32) -> Self {
+        ProjectileBehavior::Firework { age: 0, children }
+    }
+
+    // Where a firework launched centred on `center` will be when it bursts.
+    pub fn firework_burst_point(center: (f32, f32), velocity: (f32, f32)) -> (f32, f32) {
+        let flying = (FIREWORK_FUSE_FRAMES - 1) as f32;
+        (center.0 + velocity.0 * flying, center.1 + velocity.1 * flying)
+    }
+
+    // Adjusts `speed` and `direction` for this frame. Returns false once the bullet
+    // has used itself up. `center` is the bullet's center and `target` the player's.
+    // New bullets go through `spawns` rather than straight into the projectile list.
+    pub fn update(
+        &mut self,
+        center: (f32, f32),
+        speed: &mut f32,
+        direction: &mut (f32, f32),
+        target: (f32, f32),
+        spawns: &mut SpawnQueue,
+    ) -> bool {
+        match self {
+            ProjectileBehavior::Straight => true,
+            ProjectileBehavior::Firework { age, children } => {
+                *age += 1;
+                if *age < FIREWORK_FUSE_FRAMES {
+                    true
+                } else if *age < FIREWORK_FUSE_FRAMES + FIREWORK_PAUSE_FRAMES {
+                    *speed = 0.0;
+                    true
+                } else {
+                    // No warm-up: they come out of a bullet that was already there
+                    // and marked with a telegraph, so they're no surprise.
+                    for i in 0..*children {
+                        let angle = i as f32 * 2.0 * PI / *children as f32;
+                        spawns.push_request(SpawnRequest {
+                            pos: (center.0 - CHILD_HALF_SIZE, center.1 - CHILD_HALF_SIZE),
+                            velocity: (angle.cos() * FIREWORK_CHILD_SPEED, angle.sin() * FIREWORK_CHILD_SPEED),
+                            behavior: ProjectileBehavior::Straight,
+                            warm_up: 0,
+                        });
+                    }
+                    false
+                }
+            }
+            ProjectileBehavior::Magnet { frames_left, .. } => {
+                if *frames_left == 0 {
+                    return true;
+                }
+                *frames_left -= 1;
+                let to_target = (target.0 - center.0, target.1 - center.1);
+                let distance = (to_target.0.powi(2) + to_target.1.powi(2)).sqrt().max(1.0);
+                let velocity = (
+                    direction.0 * *speed + to_target.0 / distance * MAGNET_ACCEL,
+                    direction.1 * *speed + to_target.1 / distance * MAGNET_ACCEL,
+                );
+                let new_speed = (velocity.0.powi(2) + velocity.1.powi(2)).sqrt();
+                if new_speed > 0.0 {
+                    *direction = (velocity.0 / new_speed, velocity.1 / new_speed);
+                }
+                *speed = new_speed.min(MAGNET_MAX_SPEED);
+                true
+            }
+        }
+    }
+
+    // How the bullet itself is recoloured, so the special ones stand out from the
+    // plain fruit without needing their own art.
+    pub fn palette(&self) -> Palette {
+        match self {
+            ProjectileBehavior::Straight => Palette::Sheet,
+            ProjectileBehavior::Magnet { .. } => Palette::Gold,
+            ProjectileBehavior::Firework { .. } => Palette::Frost,
+        }
+    }
+
+    // Takes sprite slots for anything the behavior draws besides the bullet.
+    pub fn claim_sprites(&mut self, sprite_holder: &mut SpriteHolder) {
+        if let ProjectileBehavior::Magnet { ring_sprite_index, .. } = self {
+            *ring_sprite_index = sprite_holder.get_next_index_on(Layer::Projectiles);
+        }
+    }
+
+    // Draws any extra sprites the behavior owns around the bullet's screen region.
+    pub fn sync_sprites(&self, frame: u64, region: [f32; 4], sprite_holder: &mut SpriteHolder) {
+        if let ProjectileBehavior::Magnet { frames_left, ring_sprite_index } = self {
+            // The ring pulses while homing and fades out once the bullet lets go.
+            let pulse = 1.0 + 0.15 * (frame as f32 * 0.3).sin();
+            let size = (region[2] * MAGNET_RING_SCALE * pulse, region[3] * MAGNET_RING_SCALE * pulse);
+            let mut tint = MAGNET_RING_TINT;
+            tint[3] *= (*frames_left as f32 / 30.0).min(1.0);
+            sprite_holder.set_sprite(
+                *ring_sprite_index,
+                GPUSprite {
+                    screen_region: Attachment::centered((region[2], region[3]), size)
+                        .place((region[0], region[1]), size),
+                    sheet_region: [
+                        RING_CELL.0 / SPRITE_SHEET_RESOLUTION.0,
+                        RING_CELL.1 / SPRITE_SHEET_RESOLUTION.1,
+                        1.0 / SPRITE_SHEET_RESOLUTION.0,
+                        1.0 / SPRITE_SHEET_RESOLUTION.1,
+                    ],
+                },
+            );
+            sprite_holder.set_tint(*ring_sprite_index, tint);
+        }
+    }
+
+    pub fn sprite_indices(&self) -> Vec<SpriteHandle> {
+        match self {
+            ProjectileBehavior::Magnet { ring_sprite_index, .. } => vec![*ring_sprite_index],
+            _ => vec![],
+        }
+    }
+
+    pub fn remove_sprites(&self, sprite_holder: &mut SpriteHolder) {
+        if let ProjectileBehavior::Magnet { ring_sprite_index, .. } = self {
+            sprite_holder.remove_sprite(*ring_sprite_index);
+        }
+    }
+}
@@ -0,0 +1,94 @@
+// Labels on enemy bullets saying what fired them and when, so a whole group can be
+// cancelled at once: the old phase's bullets when the boss moves on, say, without
+// touching the minions' bullets alongside them.
+use crate::items::{Item, ItemKind};
+use crate::playfield::Playfield;
+use crate::{Projectile, SpriteHolder};
+
+// Which attack pattern a bullet came from. `None` is the player's own shots, and
+// anything fired outside an enemy's turn.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Pattern {
+    #[default]
+    None,
+    Boss,
+    Minion,
+    MidBoss,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct ProjectileTag {
+    pub pattern: Pattern,
+    // The firing AI's phase at the time.
+    pub phase: usize,
+}
+
+impl ProjectileTag {
+    pub fn new(pattern: Pattern, phase: usize) -> Self {
+        Self { pattern, phase }
+    }
+}
+
+// What cancelled bullets turn into.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Cancel {
+    Vanish,
+    // A points item where each bullet was, for those still anywhere near the
+    // playfield. Bullets that drifted far off just vanish.
+    Points,
+}
+
+// Kills every live enemy bullet whose tag passes `matches`. They're removed with
+// the rest of the dead in cleanup. Returns how many were cancelled.
+pub fn cancel_tagged(
+    projectiles: &mut [Projectile],
+    items: &mut Vec<Item>,
+    sprite_holder: &mut SpriteHolder,
+    playfield: &Playfield,
+    matches: impl Fn(ProjectileTag) -> bool,
+    into: Cancel,
+) -> usize {
+    let mut cancelled = 0;
+    for proj in projectiles.iter_mut().filter(|proj| !proj.player_spawned && !proj.is_dead && matches(proj.tag)) {
+        proj.kill();
+        if into == Cancel::Points && !playfield.is_far_outside(&proj.body) {
+            items.push(Item::new(proj.body.center(), ItemKind::Points, sprite_holder));
+        }
+        cancelled += 1;
+    }
+    cancelled
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::projectile_behavior::ProjectileBehavior;
+    use crate::spawn_queue::SpawnQueue;
+
+    #[test]
+    fn only_matching_bullets_are_cancelled() {
+        let mut sprite_holder = SpriteHolder::new(16);
+        let mut projectiles = vec![];
+        let mut items = vec![];
+        let mut spawns = SpawnQueue::default();
+        for tag in [
+            ProjectileTag::new(Pattern::Boss, 0),
+            ProjectileTag::new(Pattern::Minion, 0),
+            ProjectileTag::new(Pattern::Boss, 1),
+        ] {
+            spawns.set_tag(tag);
+            spawns.push_with_behavior((0.0, 0.0), (0.0, -1.0), ProjectileBehavior::Straight);
+        }
+        spawns.flush(&mut projectiles, &mut sprite_holder);
+
+        let old_boss_phase = |tag: ProjectileTag| tag.pattern == Pattern::Boss && tag.phase < 1;
+        let cancelled = cancel_tagged(&mut projectiles, &mut items, &mut sprite_holder, &Playfield::default(), old_boss_phase, Cancel::Points);
+        assert_eq!(cancelled, 1);
+        assert_eq!(projectiles.iter().map(|proj| proj.is_dead).collect::<Vec<_>>(), [true, false, false]);
+        assert_eq!(items.len(), 1);
+        assert_eq!(items[0].kind, ItemKind::Points);
+        // Already dead, so it isn't counted twice.
+        assert_eq!(cancel_tagged(&mut projectiles, &mut items, &mut sprite_holder, &Playfield::default(), |_| true, Cancel::Vanish), 2);
+        assert_eq!(items.len(), 1);
+    }
+}
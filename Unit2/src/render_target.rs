@@ -0,0 +1,286 @@
+use std::borrow::Cow;
+
+// The playfield is 1024x768 and sits in the middle of the render target, with a
+// frame around it for the HUD. frame.wgsl is handed these as a uniform.
+pub const PLAYFIELD_ORIGIN: (u32, u32) = (160, 120);
+pub const PLAYFIELD_SIZE: (u32, u32) = (1024, 768);
+
+// Left edge of the gauges stacked in the frame left of the playfield, in the
+// playfield's units: 16 in from the edge of the render target.
+pub const LEFT_GAUGE_X: f32 = 16.0 - PLAYFIELD_ORIGIN.0 as f32;
+
+// Where frame.wgsl finds the playfield, in render target pixels.
+#[repr(C)]
+#[derive(Clone, Copy, bytemuck::Zeroable, bytemuck::Pod)]
+struct FrameUniform {
+    field_min: [f32; 2],
+    field_max: [f32; 2],
+}
+
+const FRAME_UNIFORM: FrameUniform = FrameUniform {
+    field_min: [PLAYFIELD_ORIGIN.0 as f32, PLAYFIELD_ORIGIN.1 as f32],
+    field_max: [
+        (PLAYFIELD_ORIGIN.0 + PLAYFIELD_SIZE.0) as f32,
+        (PLAYFIELD_ORIGIN.1 + PLAYFIELD_SIZE.1) as f32,
+    ],
+};
+
+// The game always renders at this size, no matter how big the window is.
+// The finished frame is then scaled onto the window by `blit`.
+pub const RENDER_SIZE: (u32, u32) = (
+    PLAYFIELD_SIZE.0 + 2 * PLAYFIELD_ORIGIN.0,
+    PLAYFIELD_SIZE.1 + 2 * PLAYFIELD_ORIGIN.1,
+);
+
+pub struct RenderTarget {
+    pub texture: wgpu::Texture,
+    pub view: wgpu::TextureView,
+    bind_group: wgpu::BindGroup,
+    pipeline: wgpu::RenderPipeline,
+    frame_pipeline: wgpu::RenderPipeline,
+    frame_bind_group: wgpu::BindGroup,
+}
+
+impl RenderTarget {
+    // `texture_bind_group_layout` is the same texture + sampler layout the sprites use.
+    pub fn new(
+        device: &wgpu::Device,
+        format: wgpu::TextureFormat,
+        texture_bind_group_layout: &wgpu::BindGroupLayout,
+    ) -> Self {
+        let texture = device.create_texture(&wgpu::TextureDescriptor {
+            label: Some("render target"),
+            size: wgpu::Extent3d {
+                width: RENDER_SIZE.0,
+                height: RENDER_SIZE.1,
+                depth_or_array_layers: 1,
+            },
+            mip_level_count: 1,
+            sample_count: 1,
+            dimension: wgpu::TextureDimension::D2,
+            format,
+            // Drawn into, sampled by the blit, and copied out for clip capture.
+            usage: wgpu::TextureUsages::RENDER_ATTACHMENT
+                | wgpu::TextureUsages::TEXTURE_BINDING
+                | wgpu::TextureUsages::COPY_SRC,
+            view_formats: &[],
+        });
+        let view = texture.create_view(&wgpu::TextureViewDescriptor::default());
+        let sampler = device.create_sampler(&wgpu::SamplerDescriptor::default());
+        let bind_group = device.create_bind_group(&wgpu::BindGroupDescriptor {
+            label: None,
+            layout: texture_bind_group_layout,
+            entries: &[
+                wgpu::BindGroupEntry {
+                    binding: 0,
+                    resource: wgpu::BindingResource::TextureView(&view),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 1,
+                    resource: wgpu::BindingResource::Sampler(&sampler),
+                },
+            ],
+        });
+
+        let shader = device.create_shader_module(wgpu::ShaderModuleDescriptor {
+            label: None,
+            source: wgpu::ShaderSource::Wgsl(Cow::Borrowed(include_str!("blit.wgsl"))),
+        });
+        let pipeline_layout = device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+            label: None,
+            bind_group_layouts: &[texture_bind_group_layout],
+            push_constant_ranges: &[],
+        });
+        let pipeline = device.create_render_pipeline(&wgpu::RenderPipelineDescriptor {
+            label: Some("blit"),
+            layout: Some(&pipeline_layout),
+            vertex: wgpu::VertexState {
+                module: &shader,
+                entry_point: "vs_main",
+                buffers: &[],
+            },
+            fragment: Some(wgpu::FragmentState {
+                module: &shader,
+                entry_point: "fs_main",
+                targets: &[Some(format.into())],
+            }),
+            primitive: wgpu::PrimitiveState::default(),
+            depth_stencil: None,
+            multisample: wgpu::MultisampleState::default(),
+            multiview: None,
+        });
+
+        let frame_shader = device.create_shader_module(wgpu::ShaderModuleDescriptor {
+            label: None,
+            source: wgpu::ShaderSource::Wgsl(Cow::Borrowed(include_str!("frame.wgsl"))),
+        });
+        // Never changes, so it's filled in once here.
+        let frame_buffer = device.create_buffer(&wgpu::BufferDescriptor {
+            label: Some("playfield frame"),
+            size: std::mem::size_of::<FrameUniform>() as u64,
+            usage: wgpu::BufferUsages::UNIFORM,
+            mapped_at_creation: true,
+        });
+        frame_buffer
+            .slice(..)
+            .get_mapped_range_mut()
+            .copy_from_slice(bytemuck::bytes_of(&FRAME_UNIFORM));
+        frame_buffer.unmap();
+        let frame_bind_group_layout = device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+            label: None,
+            entries: &[wgpu::BindGroupLayoutEntry {
+                binding: 0,
+                visibility: wgpu::ShaderStages::FRAGMENT,
+                ty: wgpu::BindingType::Buffer {
+                    ty: wgpu::BufferBindingType::Uniform,
+                    has_dynamic_offset: false,
+                    min_binding_size: None,
+                },
+                count: None,
+            }],
+        });
+        let frame_bind_group = device.create_bind_group(&wgpu::BindGroupDescriptor {
+            label: None,
+            layout: &frame_bind_group_layout,
+            entries: &[wgpu::BindGroupEntry {
+                binding: 0,
+                resource: frame_buffer.as_entire_binding(),
+            }],
+        });
+        let frame_layout = device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+            label: None,
+            bind_group_layouts: &[&frame_bind_group_layout],
+            push_constant_ranges: &[],
+        });
+        let frame_pipeline = device.create_render_pipeline(&wgpu::RenderPipelineDescriptor {
+            label: Some("playfield frame"),
+            layout: Some(&frame_layout),
+            vertex: wgpu::VertexState {
+                module: &frame_shader,
+                entry_point: "vs_main",
+                buffers: &[],
+            },
+            fragment: Some(wgpu::FragmentState {
+                module: &frame_shader,
+                entry_point: "fs_main",
+                targets: &[Some(wgpu::ColorTargetState {
+                    format,
+                    blend: Some(wgpu::BlendState::ALPHA_BLENDING),
+                    write_mask: wgpu::ColorWrites::ALL,
+                })],
+            }),
+            primitive: wgpu::PrimitiveState::default(),
+            depth_stencil: None,
+            multisample: wgpu::MultisampleState::default(),
+            multiview: None,
+        });
+
+        Self {
+            texture,
+            view,
+            bind_group,
+            pipeline,
+            frame_pipeline,
+            frame_bind_group,
+        }
+    }
+
+    // Dims everything outside the playfield and borders it. Goes into the main pass
+    // after the sprites; the HUD is drawn again on top of it afterwards.
+    pub fn draw_frame<'a>(&'a self, rpass: &mut wgpu::RenderPass<'a>) {
+        rpass.set_pipeline(&self.frame_pipeline);
+        rpass.set_bind_group(0, &self.frame_bind_group, &[]);
+        rpass.draw(0..3, 0..1);
+    }
+
+    // Draws the finished frame onto the window surface, into `viewport` from `blit_viewport`.
+    pub fn blit(&self, encoder: &mut wgpu::CommandEncoder, surface_view: &wgpu::TextureView, viewport: [f32; 4]) {
+        let mut rpass = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
+            label: Some("blit"),
+            color_attachments: &[Some(wgpu::RenderPassColorAttachment {
+                view: surface_view,
+                resolve_target: None,
+                ops: wgpu::Operations {
+                    load: wgpu::LoadOp::Clear(wgpu::Color::BLACK),
+                    store: true,
+                },
+            })],
+            depth_stencil_attachment: None,
+        });
+        rpass.set_viewport(viewport[0], viewport[1], viewport[2], viewport[3], 0.0, 1.0);
+        rpass.set_pipeline(&self.pipeline);
+        rpass.set_bind_group(0, &self.bind_group, &[]);
+        rpass.draw(0..3, 0..1);
+    }
+}
+
+// Where the frame goes on a surface of this size, as x, y, width, height. It's
+// scaled as large as fits without changing its shape, centred, with black bars
+// on whichever sides are left over. With integer scaling it's the largest whole
+// multiple that fits instead, unless not even 1x fits.
+pub fn blit_viewport(surface_size: (u32, u32), integer_scaling: bool) -> [f32; 4] {
+    let multiple = (surface_size.0 / RENDER_SIZE.0).min(surface_size.1 / RENDER_SIZE.1);
+    let scale = if integer_scaling && multiple > 0 {
+        multiple as f32
+    } else {
+        (surface_size.0 as f32 / RENDER_SIZE.0 as f32).min(surface_size.1 as f32 / RENDER_SIZE.1 as f32)
+    };
+    let size = ((RENDER_SIZE.0 as f32 * scale).round(), (RENDER_SIZE.1 as f32 * scale).round());
+    [
+        ((surface_size.0 as f32 - size.0) / 2.0).floor(),
+        ((surface_size.1 as f32 - size.1) / 2.0).floor(),
+        size.0,
+        size.1,
+    ]
+}
+
+// The other way: where a point on the window lands on the frame, in render target
+// pixels from its bottom left. None if it's on the bars around it.
+pub fn render_pos(window_pos: (f32, f32), viewport: [f32; 4]) -> Option<(f32, f32)> {
+    let x = (window_pos.0 - viewport[0]) / viewport[2];
+    let y = (window_pos.1 - viewport[1]) / viewport[3];
+    if !(0.0..=1.0).contains(&x) || !(0.0..=1.0).contains(&y) {
+        return None;
+    }
+    // Window y grows downwards.
+    Some((x * RENDER_SIZE.0 as f32, (1.0 - y) * RENDER_SIZE.1 as f32))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn integer_scaling_centres_the_largest_whole_multiple() {
+        let surface = (RENDER_SIZE.0 * 2 + 100, RENDER_SIZE.1 * 2 + 40);
+        assert_eq!(
+            blit_viewport(surface, true),
+            [50.0, 20.0, (RENDER_SIZE.0 * 2) as f32, (RENDER_SIZE.1 * 2) as f32]
+        );
+        // Too small for 1x: scaled down to fit after all.
+        assert_eq!(blit_viewport((640, 480), true), [0.0, 0.0, 640.0, 480.0]);
+    }
+
+    #[test]
+    fn resizing_keeps_the_shape_with_bars_on_the_spare_sides() {
+        // Too wide: bars left and right.
+        assert_eq!(
+            blit_viewport((RENDER_SIZE.0 + 200, RENDER_SIZE.1), false),
+            [100.0, 0.0, RENDER_SIZE.0 as f32, RENDER_SIZE.1 as f32]
+        );
+        // Too tall: bars top and bottom.
+        assert_eq!(
+            blit_viewport((RENDER_SIZE.0 / 2, RENDER_SIZE.1), false),
+            [0.0, (RENDER_SIZE.1 / 4) as f32, (RENDER_SIZE.0 / 2) as f32, (RENDER_SIZE.1 / 2) as f32]
+        );
+    }
+
+    #[test]
+    fn window_points_map_back_through_the_viewport() {
+        let viewport = blit_viewport((RENDER_SIZE.0 * 2 + 100, RENDER_SIZE.1 * 2 + 40), true);
+        assert_eq!(render_pos((50.0, 20.0), viewport), Some((0.0, RENDER_SIZE.1 as f32)));
+        let bottom_right = (50.0 + viewport[2], 20.0 + viewport[3]);
+        assert_eq!(render_pos(bottom_right, viewport), Some((RENDER_SIZE.0 as f32, 0.0)));
+        assert_eq!(render_pos((10.0, 10.0), viewport), None);
+    }
+}
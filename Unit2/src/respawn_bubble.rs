@@ -0,0 +1,80 @@
+// A short-lived shield around the danmaku player after losing a life. Any enemy
+// bullet that touches it pops, so the player comes back with room to reposition
+// instead of straight into whatever killed them.
+use crate::components::Body;
+use crate::generated_sprites::RING_CELL;
+use crate::{GPUSprite, SpriteHandle, SpriteHolder, SPRITE_SHEET_RESOLUTION};
+
+const BUBBLE_FRAMES: u32 = 90;
+const BUBBLE_RADIUS: f32 = 96.0;
+// It fades over its last frames, so the player can see it's about to go.
+const FADE_FRAMES: u32 = 30;
+const BUBBLE_TINT: [f32; 4] = [1.0, 0.95, 0.6, 0.7];
+
+pub struct RespawnBubble {
+    frames_left: u32,
+    center: (f32, f32),
+    sprite_index: SpriteHandle,
+}
+
+impl RespawnBubble {
+    pub fn new(center: (f32, f32), sprite_holder: &mut SpriteHolder) -> Self {
+        Self {
+            frames_left: BUBBLE_FRAMES,
+            center,
+            sprite_index: sprite_holder.get_next_index(),
+        }
+    }
+
+    pub fn is_done(&self) -> bool {
+        self.frames_left == 0
+    }
+
+    pub fn frames_left(&self) -> u32 {
+        self.frames_left
+    }
+
+    // Follows the player for one frame.
+    pub fn update(&mut self, player_center: (f32, f32), sprite_holder: &mut SpriteHolder) {
+        self.frames_left = self.frames_left.saturating_sub(1);
+        self.center = player_center;
+        sprite_holder.set_sprite(
+            self.sprite_index,
+            GPUSprite {
+                screen_region: [
+                    self.center.0 - BUBBLE_RADIUS,
+                    self.center.1 - BUBBLE_RADIUS,
+                    BUBBLE_RADIUS * 2.0,
+                    BUBBLE_RADIUS * 2.0,
+                ],
+                sheet_region: [
+                    RING_CELL.0 / SPRITE_SHEET_RESOLUTION.0,
+                    RING_CELL.1 / SPRITE_SHEET_RESOLUTION.1,
+                    1.0 / SPRITE_SHEET_RESOLUTION.0,
+                    1.0 / SPRITE_SHEET_RESOLUTION.1,
+                ],
+            },
+        );
+        let mut tint = BUBBLE_TINT;
+        tint[3] *= (self.frames_left as f32 / FADE_FRAMES as f32).min(1.0);
+        sprite_holder.set_tint(self.sprite_index, tint);
+    }
+
+    // True if any part of `body` is inside the bubble.
+    pub fn touches(&self, body: &Body) -> bool {
+        let nearest = (
+            self.center.0.clamp(body.pos.0, body.pos.0 + body.size.0),
+            self.center.1.clamp(body.pos.1, body.pos.1 + body.size.1),
+        );
+        !self.is_done()
+            && (nearest.0 - self.center.0).powi(2) + (nearest.1 - self.center.1).powi(2) <= BUBBLE_RADIUS * BUBBLE_RADIUS
+    }
+
+    pub fn sprite_indices(&self) -> Vec<SpriteHandle> {
+        vec![self.sprite_index]
+    }
+
+    pub fn remove(&self, sprite_holder: &mut SpriteHolder) {
+        sprite_holder.remove_sprite(self.sprite_index);
+    }
+}
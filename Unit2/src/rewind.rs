@@ -0,0 +1,107 @@
+// Practice assist: dying rewinds the run a few seconds instead of ending it, so a
+// player can drill the same bullet wall over and over. The simulation is
+// deterministic, so rather than snapshotting every object this keeps the RNG the
+// level started with plus every input since, and rebuilds the level by replaying
+// them up to the rewind point.
+use rand::rngs::StdRng;
+
+use crate::clock::FRAMES_PER_SECOND;
+use crate::simulation::{self, FrameInput, SimState};
+use crate::SpriteHolder;
+
+const REWIND_FRAMES: usize = 5 * FRAMES_PER_SECOND as usize;
+
+#[derive(Default)]
+pub struct RewindLog {
+    // The RNG as it was on the level's first frame.
+    start_rng: Option<StdRng>,
+    inputs: Vec<FrameInput>,
+}
+
+impl RewindLog {
+    // Call right before simulating a frame, with the input it's about to get.
+    pub fn record(&mut self, sim: &SimState, input: FrameInput) {
+        if sim.frame == 0 {
+            self.start_rng = Some(sim.rng.clone());
+            self.inputs.clear();
+        }
+        self.inputs.push(input);
+    }
+
+    // Reloads the level with `load` and replays everything but the last few
+    // seconds. Returns false if there's nothing recorded to rewind to.
+    pub fn rewind(&mut self, sim: &mut SimState, load: fn(&mut SimState)) -> bool {
+        let Some(rng) = &self.start_rng else {
+            return false;
+        };
+        self.inputs.truncate(self.inputs.len().saturating_sub(REWIND_FRAMES));
+
+        crate::unload_level(sim);
+        load(sim);
+        sim.rng = rng.clone();
+        sim.trans_flag.val = 0;
+        for &input in self.inputs.iter() {
+            let state = std::mem::replace(sim, SimState::new(SpriteHolder::new(0), 0));
+            *sim = simulation::simulate(state, input);
+        }
+        // Everything replayed already happened once; don't play it again.
+        sim.sounds.clear();
+        // Five seconds back can land right in front of a bullet.
+        simulation::respawn(sim);
+        true
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn rewinding_lands_on_the_exact_earlier_frame() {
+        let mut sim = SimState::new(SpriteHolder::new(1000), 47);
+        crate::load_level_1(&mut sim);
+        let mut log = RewindLog::default();
+        let mut checksums = vec![];
+        for frame in 0..900u64 {
+            let input = FrameInput {
+                left: frame % 80 < 30,
+                right: frame % 80 >= 50,
+                shoot: frame % 5 < 2,
+                ability: false,
+                dash: frame % 120 == 60,
+            };
+            log.record(&sim, input);
+            sim = simulation::simulate(sim, input);
+            checksums.push(sim.checksum());
+        }
+
+        assert!(log.rewind(&mut sim, crate::load_level_1));
+        assert_eq!(sim.frame as usize, 900 - REWIND_FRAMES);
+        assert_eq!(sim.checksum(), checksums[900 - REWIND_FRAMES - 1]);
+    }
+
+    #[test]
+    fn rewinding_a_retried_phase_reloads_at_that_phase() {
+        let mut sim = SimState::new(SpriteHolder::new(1000), 47);
+        sim.start_phase = Some(2);
+        crate::load_level_6(&mut sim);
+        let mut log = RewindLog::default();
+        let mut checksums = vec![];
+        for frame in 0..600u64 {
+            let input = FrameInput {
+                left: frame % 60 < 20,
+                shoot: true,
+                ..Default::default()
+            };
+            log.record(&sim, input);
+            sim = simulation::simulate(sim, input);
+            checksums.push(sim.checksum());
+        }
+
+        assert!(log.rewind(&mut sim, crate::load_level_6));
+        assert_eq!(sim.enemy.ai.phase(), 2);
+        // The respawn bubble is the one thing the rewind adds in danmaku.
+        sim.respawn_bubble.take().unwrap().remove(&mut sim.sprite_holder);
+        assert_eq!(sim.checksum(), checksums[600 - REWIND_FRAMES - 1]);
+    }
+}
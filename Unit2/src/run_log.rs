@@ -0,0 +1,129 @@
+// A line of JSON appended to `runs.log` whenever a run ends, however it ends, so
+// players and testers can keep their history outside the game. Deaths and bombs
+// are counted as they happen, so ones undone by the rewind assist still count.
+// There's no difficulty setting; the modifiers and `assisted` say how the run
+// was played. `seed` is what the sim's RNG was seeded with on the run's first
+// frame, which with the same inputs plays the run out again.
+use std::io::Write;
+use web_time::{SystemTime, UNIX_EPOCH};
+
+use crate::clock::FRAMES_PER_SECOND;
+use crate::simulation::SimState;
+
+const RUNS_PATH: &str = "runs.log";
+
+#[derive(Default)]
+pub struct RunLog {
+    seed: u64,
+    // Frames played this run, rewound ones included. None between runs.
+    frames: Option<u64>,
+    deaths: u32,
+    bombs: u32,
+    last_health: f32,
+    last_bombs: u32,
+}
+
+fn bombs_used(sim: &SimState) -> u32 {
+    sim.time_freeze.as_ref().map_or(0, |time_freeze| time_freeze.uses) + sim.catch_return.as_ref().map_or(0, |catch_return| catch_return.uses)
+}
+
+impl RunLog {
+    pub fn in_run(&self) -> bool {
+        self.frames.is_some()
+    }
+
+    // Call before the run's first frame, once the sim is seeded with `seed`.
+    pub fn start(&mut self, sim: &SimState, seed: u64) {
+        *self = Self {
+            seed,
+            frames: Some(0),
+            deaths: 0,
+            bombs: 0,
+            last_health: sim.player_health_bar.currval,
+            last_bombs: bombs_used(sim),
+        };
+    }
+
+    // Call after every simulated frame.
+    pub fn record(&mut self, sim: &SimState) {
+        let Some(frames) = &mut self.frames else {
+            return;
+        };
+        let bombs = bombs_used(sim);
+        let health = sim.player_health_bar.currval;
+        *frames += 1;
+        if health <= 0.0 && self.last_health > 0.0 {
+            self.deaths += 1;
+        }
+        self.bombs += bombs.saturating_sub(self.last_bombs);
+        self.last_health = health;
+        self.last_bombs = bombs;
+    }
+
+    // Appends the run that just ended, if there was one.
+    pub fn finish(&mut self, sim: &SimState, outcome: &str) {
+        let Some(line) = self.line(sim, outcome) else {
+            return;
+        };
+        self.frames = None;
+        let result = std::fs::OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(RUNS_PATH)
+            .and_then(|mut file| file.write_all(line.as_bytes()));
+        match result {
+            Ok(()) => log::info!("Added the run to {}", RUNS_PATH),
+            Err(e) => log::warn!("Couldn't write to {}: {}", RUNS_PATH, e),
+        }
+    }
+
+    fn line(&self, sim: &SimState, outcome: &str) -> Option<String> {
+        let frames = self.frames?;
+        let ended = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map(|d| d.as_secs())
+            .unwrap_or(0);
+        Some(format!(
+            "{{\"ended\":{},\"mode\":{},\"outcome\":\"{}\",\"score\":{},\"seconds\":{:.2},\"deaths\":{},\"bombs\":{},\"modifiers\":\"{}\",\"assisted\":{},\"seed\":{}}}\n",
+            ended,
+            sim.mode,
+            outcome,
+            sim.player.score,
+            frames as f64 / FRAMES_PER_SECOND as f64,
+            self.deaths,
+            self.bombs,
+            sim.modifiers.tag(),
+            !sim.leaderboard_eligible,
+            self.seed,
+        ))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::fixtures;
+
+    #[test]
+    fn deaths_count_as_they_happen_and_the_line_is_one_json_object() {
+        let mut sim = fixtures::loaded(crate::load_level_6);
+        let mut run_log = RunLog::default();
+        assert_eq!(run_log.line(&sim, "quit"), None);
+
+        run_log.start(&sim, 1234);
+        sim.player_health_bar.currval = 0.0;
+        run_log.record(&sim);
+        // Rewound, then dead again.
+        sim.player_health_bar.currval = 1.0;
+        run_log.record(&sim);
+        sim.player_health_bar.currval = 0.0;
+        run_log.record(&sim);
+        sim.player.score = 420;
+
+        let line = run_log.line(&sim, "death").unwrap();
+        assert!(line.ends_with("}\n") && line.matches('\n').count() == 1);
+        for field in ["\"mode\":6", "\"outcome\":\"death\"", "\"score\":420", "\"deaths\":2", "\"bombs\":0", "\"seed\":1234"] {
+            assert!(line.contains(field), "{} in {}", field, line);
+        }
+    }
+}
@@ -0,0 +1,131 @@
+// Progress that outlives a session, kept in a small RON file next to the
+// executable's working directory. A missing or unreadable file just means a fresh
+// save. The file says which version wrote it, so later versions can read old
+// saves; the `key=value` text file from before that is read once and replaced. A
+// file this version can't use is copied aside before anything overwrites it.
+use std::collections::{BTreeMap, BTreeSet};
+use std::path::Path;
+
+use serde::{Deserialize, Serialize};
+
+const SAVE_PATH: &str = "save.ron";
+const LEGACY_SAVE_PATH: &str = "save.txt";
+const BACKUP_PATH: &str = "save.ron.bak";
+// Bump when a field changes meaning. New fields just need `#[serde(default)]`.
+const SAVE_VERSION: u32 = 1;
+
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(default)]
+pub struct SaveData {
+    // Which version wrote it. Always the current one once loaded.
+    version: u32,
+    // Fastest clear of each stage, in gameplay frames, keyed by sim mode.
+    pub best_clear_frames: BTreeMap<usize, u64>,
+    // Every stage the player has got to the end of, assisted or not, by sim mode.
+    pub stages_cleared: BTreeSet<usize>,
+}
+
+impl Default for SaveData {
+    fn default() -> Self {
+        Self {
+            version: SAVE_VERSION,
+            best_clear_frames: BTreeMap::new(),
+            stages_cleared: BTreeSet::new(),
+        }
+    }
+}
+
+impl SaveData {
+    pub fn load() -> Self {
+        let path = Path::new(SAVE_PATH);
+        let Ok(text) = std::fs::read_to_string(path) else {
+            if let Some(save) = Self::load_legacy() {
+                save.store();
+                return save;
+            }
+            log::info!("No save file at {}, starting fresh", path.display());
+            return Self::default();
+        };
+        match ron::from_str::<SaveData>(&text) {
+            Ok(save) if save.version > SAVE_VERSION => {
+                log::warn!("{} is from a newer version ({}), starting fresh", path.display(), save.version);
+                Self::back_up();
+                Self::default()
+            }
+            Ok(save) => Self { version: SAVE_VERSION, ..save },
+            Err(e) => {
+                log::warn!("Couldn't read save file {}, starting fresh: {}", path.display(), e);
+                Self::back_up();
+                Self::default()
+            }
+        }
+    }
+
+    fn back_up() {
+        match std::fs::copy(SAVE_PATH, BACKUP_PATH) {
+            Ok(_) => log::info!("Kept the old save as {}", BACKUP_PATH),
+            Err(e) => log::warn!("Couldn't copy {} to {}: {}", SAVE_PATH, BACKUP_PATH, e),
+        }
+    }
+
+    // The old `best_clear.<mode>=<frames>` lines. Any clear time means the stage was cleared.
+    fn load_legacy() -> Option<Self> {
+        let text = std::fs::read_to_string(LEGACY_SAVE_PATH).ok()?;
+        let mut save = Self::default();
+        for line in text.lines().map(str::trim).filter(|line| !line.is_empty() && !line.starts_with('#')) {
+            let parsed = line.split_once('=').and_then(|(key, value)| {
+                let mode = key.strip_prefix("best_clear.")?.parse().ok()?;
+                Some((mode, value.parse().ok()?))
+            });
+            match parsed {
+                Some((mode, frames)) => {
+                    save.best_clear_frames.insert(mode, frames);
+                    save.stages_cleared.insert(mode);
+                }
+                None => log::warn!("Ignoring unknown line in {}: {}", LEGACY_SAVE_PATH, line),
+            }
+        }
+        log::info!("Moving {} over to {}", LEGACY_SAVE_PATH, SAVE_PATH);
+        Some(save)
+    }
+
+    pub fn store(&self) {
+        let result = ron::ser::to_string_pretty(self, ron::ser::PrettyConfig::default())
+            .map_err(|e| e.to_string())
+            .and_then(|text| std::fs::write(SAVE_PATH, text).map_err(|e| e.to_string()));
+        if let Err(e) = result {
+            log::warn!("Couldn't write save file {}: {}", SAVE_PATH, e);
+        }
+    }
+
+    // Records a clear. Returns true if it beat the stage's previous best.
+    pub fn record_clear(&mut self, mode: usize, frames: u64) -> bool {
+        let best = self.best_clear_frames.entry(mode).or_insert(u64::MAX);
+        if frames >= *best {
+            return false;
+        }
+        *best = frames;
+        true
+    }
+
+    // Notes that a stage was finished. Returns true the first time.
+    pub fn record_stage_cleared(&mut self, mode: usize) -> bool {
+        self.stages_cleared.insert(mode)
+    }
+
+    pub fn has_cleared(&self, mode: usize) -> bool {
+        self.stages_cleared.contains(&mode)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn saves_without_newer_fields_still_load() {
+        let save: SaveData = ron::from_str("(version: 1, best_clear_frames: {6: 4000})").unwrap();
+        assert_eq!(save.best_clear_frames.get(&6), Some(&4000));
+        assert!(!save.has_cleared(1));
+    }
+}
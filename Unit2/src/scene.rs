@@ -0,0 +1,36 @@
+// Parent/child placement for sprites that ride along with another object: an
+// enemy's eyes and health bar, auras, options, boss accessories. The child keeps
+// an offset from its parent's position and is re-placed from the parent every
+// frame, so moving the parent moves everything attached to it.
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub struct Attachment {
+    // From the parent's bottom-left corner to the child's.
+    pub offset: (f32, f32),
+}
+
+impl Attachment {
+    pub fn new(offset: (f32, f32)) -> Self {
+        Self { offset }
+    }
+
+    // Child centered on the parent.
+    pub fn centered(parent_size: (f32, f32), child_size: (f32, f32)) -> Self {
+        Self::new(((parent_size.0 - child_size.0) / 2.0, (parent_size.1 - child_size.1) / 2.0))
+    }
+
+    // Child centered horizontally, `gap` pixels above the parent.
+    pub fn above(parent_size: (f32, f32), child_size: (f32, f32), gap: f32) -> Self {
+        Self::new(((parent_size.0 - child_size.0) / 2.0, parent_size.1 + gap))
+    }
+
+    // The child's position for a parent at `parent_pos`.
+    pub fn resolve(&self, parent_pos: (f32, f32)) -> (f32, f32) {
+        (parent_pos.0 + self.offset.0, parent_pos.1 + self.offset.1)
+    }
+
+    // The child's screen region, for sprites that are placed directly.
+    pub fn place(&self, parent_pos: (f32, f32), child_size: (f32, f32)) -> [f32; 4] {
+        let pos = self.resolve(parent_pos);
+        [pos.0, pos.1, child_size.0, child_size.1]
+    }
+}
@@ -0,0 +1,497 @@
+// The screens themselves. Each game state starts with one scene on the stack in
+// `StateMachine`, and overlays like the pause screen and confirm dialogs are
+// pushed over it. Only the top scene runs, so everything under it stays frozen
+// exactly as it was, and closing an overlay goes straight back without
+// reloading anything.
+use bytemuck::Zeroable;
+
+use crate::game_state::{abandon_run, transition_to_state, GameState};
+use crate::input::{Control, Key};
+use crate::text::TextLabel;
+use crate::widgets::{Menu, MenuEvent, Widget};
+use crate::{dialog, save, sheet_viewer, tween, GPUSprite, GameStateHolder, Screen, SpriteHandle, SpriteHolder};
+
+const PAUSE_PULSE_FRAMES: u64 = 40;
+const PAUSE_SCALE: f32 = 6.0;
+// From the pause label down to the first menu entry.
+const MENU_GAP: f32 = 64.0;
+// The controls line under the pause menu.
+const CONTROLS_SCALE: f32 = 2.0;
+const CONTROLS_GAP: f32 = 48.0;
+const CONTROLS_TINT: [f32; 4] = [0.7, 0.7, 0.7, 1.0];
+// The results screens' keys, under the card.
+const HINT_SCALE: f32 = 2.0;
+const HINT_Y: f32 = 8.0;
+const HINT_TINT: [f32; 4] = [0.8, 0.8, 0.8, 1.0];
+const SCREEN_WIDTH: f32 = 1024.0;
+// The title menu, under the game's name on the card.
+const TITLE_MENU_TOP: f32 = 520.0;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Flow {
+    Continue,
+    // Pop this scene off the stack.
+    Close,
+}
+
+pub trait Scene {
+    // Reacts to this frame's keys. Pushing a scene or moving to another state
+    // from here skips the rest of this scene's frame.
+    fn handle_input(&mut self, gso: &mut GameStateHolder) -> Flow;
+
+    fn update(&mut self, _gso: &mut GameStateHolder) {}
+
+    // Puts this scene's sprites where they belong for the frame.
+    fn render_sprites(&self, gso: &mut GameStateHolder);
+
+    // Sprite slots the scene made itself, for the sprite audit.
+    fn sprite_indices(&self) -> Vec<SpriteHandle> {
+        vec![]
+    }
+
+    fn remove(self: Box<Self>, _sprite_holder: &mut SpriteHolder) {}
+}
+
+// The full-screen title and results artwork.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Card {
+    Title,
+    Title2,
+    Death,
+    DanmakuDeath,
+    Cleared,
+    Win,
+}
+
+impl Card {
+    pub fn screen(self, gso: &mut GameStateHolder) -> &mut Screen {
+        match self {
+            Card::Title => &mut gso.title_screen,
+            Card::Title2 => &mut gso.title_screen_2,
+            Card::Death => &mut gso.death_screen,
+            Card::DanmakuDeath => &mut gso.danmaku_death_screen,
+            Card::Cleared => &mut gso.cleared_screen,
+            Card::Win => &mut gso.win_screen,
+        }
+    }
+
+    fn draw(self, gso: &mut GameStateHolder) {
+        let screen = self.screen(gso);
+        let (sprite_index, sprite) = (screen.sprite_index, screen.sprite);
+        gso.sim.sprite_holder.set_sprite(sprite_index, sprite);
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum TitleChoice {
+    Play,
+    OtherTitle,
+    TrialRoom,
+    // An index into `Modifiers::flags`.
+    Modifier(usize),
+    ResetSave,
+}
+
+// Up/Down picks, Enter or a gamepad's A confirms. Space still starts a run
+// straight away and 1-6 still flip the modifiers.
+pub struct TitleScene {
+    // The danmaku title, which leads to level 6 instead of level 1.
+    danmaku: bool,
+    // Made on the first frame, and again after the sheet viewer closes.
+    menu: Option<Menu<TitleChoice>>,
+}
+
+impl TitleScene {
+    pub fn new(danmaku: bool) -> Self {
+        Self { danmaku, menu: None }
+    }
+
+    // Resetting the save is left out in kiosk mode.
+    fn entries(&self, gso: &GameStateHolder) -> Vec<Widget<TitleChoice>> {
+        let other = if self.danmaku { "NORMAL GAME" } else { "DANMAKU GAME" };
+        let mut entries = vec![
+            Widget::Button(TitleChoice::Play, "PLAY"),
+            Widget::Button(TitleChoice::OtherTitle, other),
+            Widget::Button(TitleChoice::TrialRoom, "TRIAL ROOM"),
+            Widget::Label("MODIFIERS"),
+        ];
+        for (index, (text, on)) in gso.sim.modifiers.flags().into_iter().enumerate() {
+            entries.push(Widget::Toggle(TitleChoice::Modifier(index), text, on));
+        }
+        if gso.kiosk.is_none() {
+            entries.push(Widget::Button(TitleChoice::ResetSave, "RESET SAVE"));
+        }
+        entries
+    }
+
+    fn card(&self) -> Card {
+        if self.danmaku { Card::Title2 } else { Card::Title }
+    }
+}
+
+impl Scene for TitleScene {
+    fn handle_input(&mut self, gso: &mut GameStateHolder) -> Flow {
+        let before = gso.sim.modifiers;
+        gso.sim.modifiers.handle_hotkeys(&gso.input);
+        let entries = self.entries(gso);
+        if gso.sim.modifiers != before {
+            if let Some(menu) = &mut self.menu {
+                menu.set_widgets(&entries, &mut gso.sim.sprite_holder);
+            }
+            return Flow::Continue;
+        }
+        let menu = self.menu.get_or_insert_with(|| Menu::new(&entries, &mut gso.sim.sprite_holder));
+        let (play, other_title) = match self.danmaku {
+            true => (GameState::Danmaku, GameState::Title),
+            false => (GameState::Gameplay, GameState::Title2),
+        };
+        if gso.input.is_key_down(Key::Space) {
+            transition_to_state(play, gso);
+            return Flow::Continue;
+        }
+        if cfg!(debug_assertions) && gso.kiosk.is_none() && gso.input.is_key_pressed(Key::G) {
+            // The card and menu would cover the sheet. They're drawn again once
+            // the viewer closes.
+            let sprite_index = self.card().screen(gso).sprite_index;
+            gso.sim.sprite_holder.set_sprite(sprite_index, GPUSprite::zeroed());
+            if let Some(menu) = self.menu.take() {
+                menu.remove(&mut gso.sim.sprite_holder);
+            }
+            let viewer = sheet_viewer::SheetViewerScene::new(&mut gso.sim.sprite_holder);
+            gso.game_state.push(Box::new(viewer));
+            return Flow::Continue;
+        }
+        match menu.handle_input(&gso.input, &mut gso.sim.sprite_holder) {
+            Some(MenuEvent::Pressed(TitleChoice::Play)) => transition_to_state(play, gso),
+            Some(MenuEvent::Pressed(TitleChoice::OtherTitle)) => transition_to_state(other_title, gso),
+            Some(MenuEvent::Pressed(TitleChoice::TrialRoom)) => {
+                gso.trial_room.opened_from(self.danmaku);
+                transition_to_state(GameState::TrialRoom, gso);
+            }
+            Some(MenuEvent::Toggled(TitleChoice::Modifier(index), on)) => gso.sim.modifiers.set(index, on),
+            Some(MenuEvent::Pressed(TitleChoice::ResetSave)) => {
+                let dialog = dialog::ConfirmDialog::new("RESET SAVE DATA?", dialog::DialogAction::ResetSave, &mut gso.sim.sprite_holder);
+                gso.game_state.push(Box::new(DialogScene { dialog }));
+            }
+            _ => {}
+        }
+        Flow::Continue
+    }
+
+    fn render_sprites(&self, gso: &mut GameStateHolder) {
+        let mode = if self.danmaku { 6 } else { 1 };
+        gso.medals.show_best(mode, &gso.save, &mut gso.sim.sprite_holder);
+        self.card().draw(gso);
+        if let Some(menu) = &self.menu {
+            menu.draw(SCREEN_WIDTH / 2.0, TITLE_MENU_TOP, &mut gso.sim.sprite_holder);
+        }
+    }
+
+    fn sprite_indices(&self) -> Vec<SpriteHandle> {
+        self.menu.as_ref().map_or(vec![], Menu::sprite_indices)
+    }
+
+    fn remove(self: Box<Self>, sprite_holder: &mut SpriteHolder) {
+        if let Some(menu) = self.menu {
+            menu.remove(sprite_holder);
+        }
+    }
+}
+
+// Everything that plays: levels 1 and 6 and the trial room.
+pub struct GameplayScene {
+    pub trial_room: bool,
+}
+
+impl Scene for GameplayScene {
+    fn handle_input(&mut self, gso: &mut GameStateHolder) -> Flow {
+        // Kiosk runs can't be quit; they end on their own or time out.
+        let escape = gso.input.is_key_pressed(Key::Escape);
+        if self.trial_room && escape {
+            transition_to_state(gso.trial_room.title(), gso);
+        } else if (escape && gso.kiosk.is_none()) || gso.input.is_key_pressed(Key::P) {
+            let scene = PauseScene::new(gso);
+            gso.game_state.push(Box::new(scene));
+        }
+        Flow::Continue
+    }
+
+    fn update(&mut self, gso: &mut GameStateHolder) {
+        if !gso.clock.tick_game() {
+            return;
+        }
+        if self.trial_room {
+            crate::trial_room_frame(gso);
+        } else {
+            crate::gameplay_frame(gso);
+        }
+    }
+
+    fn render_sprites(&self, gso: &mut GameStateHolder) {
+        gso.sim.sprite_holder.set_sprite(gso.background.sprite_index, gso.background.sprite);
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ResultsAction {
+    // Straight to another state: a fresh attempt, or back to a title.
+    Goto(GameState),
+    // Back into a stage with the boss skipped ahead to the phase the run ended
+    // in. Only offered once the run got past the first phase.
+    RetryPhase(GameState),
+}
+
+// One of the keys a results screen answers to.
+#[derive(Debug, Clone, Copy)]
+pub struct ResultsChoice {
+    pub key: Key,
+    pub label: &'static str,
+    pub action: ResultsAction,
+}
+
+// The death, stage cleared and win cards, with whatever ways on each one offers.
+pub struct ResultsScene {
+    card: Card,
+    choices: &'static [ResultsChoice],
+    // The keys and what they do, along the bottom. Made on the first frame.
+    hint: Option<TextLabel>,
+}
+
+impl ResultsScene {
+    pub fn new(card: Card, choices: &'static [ResultsChoice]) -> Self {
+        Self { card, choices, hint: None }
+    }
+
+    fn offered(&self, gso: &GameStateHolder) -> Vec<ResultsChoice> {
+        let phase = gso.death_report.phase();
+        self.choices
+            .iter()
+            .filter(|choice| !matches!(choice.action, ResultsAction::RetryPhase(_)) || phase > 0)
+            .copied()
+            .collect()
+    }
+}
+
+impl Scene for ResultsScene {
+    fn handle_input(&mut self, gso: &mut GameStateHolder) -> Flow {
+        let Some(choice) = self.offered(gso).into_iter().find(|choice| gso.input.is_key_down(choice.key)) else {
+            return Flow::Continue;
+        };
+        match choice.action {
+            ResultsAction::Goto(state) => transition_to_state(state, gso),
+            ResultsAction::RetryPhase(state) => {
+                gso.sim.start_phase = Some(gso.death_report.phase());
+                transition_to_state(state, gso);
+            }
+        }
+        Flow::Continue
+    }
+
+    fn update(&mut self, gso: &mut GameStateHolder) {
+        if self.hint.is_none() && !self.choices.is_empty() {
+            let text: Vec<&str> = self.offered(gso).iter().map(|choice| choice.label).collect();
+            self.hint = Some(TextLabel::new(&text.join("   "), &mut gso.sim.sprite_holder));
+        }
+    }
+
+    fn render_sprites(&self, gso: &mut GameStateHolder) {
+        self.card.draw(gso);
+        if let Some(hint) = &self.hint {
+            let x = (SCREEN_WIDTH - hint.width(HINT_SCALE)) / 2.0;
+            hint.draw((x, HINT_Y), HINT_SCALE, HINT_TINT, &mut gso.sim.sprite_holder);
+        }
+    }
+
+    fn sprite_indices(&self) -> Vec<SpriteHandle> {
+        self.hint.as_ref().map_or(vec![], TextLabel::sprite_indices)
+    }
+
+    fn remove(self: Box<Self>, sprite_holder: &mut SpriteHolder) {
+        if let Some(hint) = self.hint {
+            hint.remove(sprite_holder);
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum PauseChoice {
+    Resume,
+    KeysByPosition,
+    Controls,
+    QuitToTitle,
+    // On the controls page.
+    Rebind(Control),
+    DefaultControls,
+    Done,
+}
+
+// Holds gameplay where it is: the scene under it doesn't run, so bullets, AI
+// cooldowns and the clock all stand still. Up/Down picks, Enter confirms, and
+// Escape or P resumes. Quitting still asks first. The controls page rebinds the
+// gameplay keys: Enter on one, then press its new key.
+struct PauseScene {
+    label: TextLabel,
+    menu: Menu<PauseChoice>,
+    on_controls_page: bool,
+    // The gameplay keys, named the way the player's layout names them.
+    controls: TextLabel,
+    shown_controls: String,
+}
+
+impl PauseScene {
+    fn new(gso: &mut GameStateHolder) -> Self {
+        let entries = Self::main_page(gso);
+        let shown_controls = gso.input.controls_text(gso.options.bind_by_position);
+        let sprite_holder = &mut gso.sim.sprite_holder;
+        Self {
+            label: TextLabel::new("PAUSED", sprite_holder),
+            menu: Menu::new(&entries, sprite_holder),
+            on_controls_page: false,
+            controls: TextLabel::new(&shown_controls, sprite_holder),
+            shown_controls,
+        }
+    }
+
+    // Quitting is left out in kiosk mode.
+    fn main_page(gso: &GameStateHolder) -> Vec<Widget<PauseChoice>> {
+        let mut entries = vec![
+            Widget::Button(PauseChoice::Resume, "RESUME"),
+            Widget::Toggle(PauseChoice::KeysByPosition, "KEYS BY POSITION", gso.options.bind_by_position),
+            Widget::Button(PauseChoice::Controls, "CONTROLS"),
+        ];
+        if gso.kiosk.is_none() {
+            entries.push(Widget::Button(PauseChoice::QuitToTitle, "QUIT TO TITLE"));
+        }
+        entries
+    }
+
+    fn controls_page(gso: &GameStateHolder) -> Vec<Widget<PauseChoice>> {
+        let by_position = gso.options.bind_by_position;
+        let mut entries: Vec<_> = Control::ALL
+            .into_iter()
+            .map(|control| Widget::KeyCapture(PauseChoice::Rebind(control), control.name(), gso.input.primary_key(control, by_position)))
+            .collect();
+        entries.push(Widget::Button(PauseChoice::DefaultControls, "DEFAULTS"));
+        entries.push(Widget::Button(PauseChoice::Done, "BACK"));
+        entries
+    }
+
+    fn show_page(&mut self, controls: bool, gso: &mut GameStateHolder) {
+        let entries = if controls { Self::controls_page(gso) } else { Self::main_page(gso) };
+        let menu = Menu::new(&entries, &mut gso.sim.sprite_holder);
+        std::mem::replace(&mut self.menu, menu).remove(&mut gso.sim.sprite_holder);
+        self.on_controls_page = controls;
+    }
+
+    // Rebuilds the controls line if the keys have changed.
+    fn refresh_controls(&mut self, gso: &mut GameStateHolder) {
+        let text = gso.input.controls_text(gso.options.bind_by_position);
+        if text != self.shown_controls {
+            let controls = TextLabel::new(&text, &mut gso.sim.sprite_holder);
+            std::mem::replace(&mut self.controls, controls).remove(&mut gso.sim.sprite_holder);
+            self.shown_controls = text;
+        }
+    }
+}
+
+impl Scene for PauseScene {
+    fn handle_input(&mut self, gso: &mut GameStateHolder) -> Flow {
+        // P is fair game for a key on the controls page.
+        if gso.input.is_key_pressed(Key::P) && !self.on_controls_page {
+            return Flow::Close;
+        }
+        match self.menu.handle_input(&gso.input, &mut gso.sim.sprite_holder) {
+            Some(MenuEvent::Back | MenuEvent::Pressed(PauseChoice::Done)) if self.on_controls_page => self.show_page(false, gso),
+            Some(MenuEvent::Back | MenuEvent::Pressed(PauseChoice::Resume)) => return Flow::Close,
+            Some(MenuEvent::Pressed(PauseChoice::QuitToTitle)) => push_quit_dialog(gso),
+            Some(MenuEvent::Pressed(PauseChoice::Controls)) => self.show_page(true, gso),
+            Some(MenuEvent::Toggled(PauseChoice::KeysByPosition, on)) => gso.options.set_bind_by_position(on),
+            Some(MenuEvent::KeyBound(PauseChoice::Rebind(control), key)) => {
+                let mut changed = gso.input.bind_key(control, key, gso.options.bind_by_position);
+                changed.insert(0, control);
+                gso.options.set_bound_keys(&changed, &gso.input, gso.options.bind_by_position);
+                // Another control may have given the key up.
+                self.menu.set_widgets(&Self::controls_page(gso), &mut gso.sim.sprite_holder);
+            }
+            Some(MenuEvent::Pressed(PauseChoice::DefaultControls)) => {
+                gso.input.reset_bindings();
+                gso.options.clear_bound_keys();
+                self.menu.set_widgets(&Self::controls_page(gso), &mut gso.sim.sprite_holder);
+            }
+            _ => {}
+        }
+        self.refresh_controls(gso);
+        Flow::Continue
+    }
+
+    fn render_sprites(&self, gso: &mut GameStateHolder) {
+        // Pulses on real time, since gameplay time is standing still.
+        let pulse = tween::ping_pong(gso.clock.real_frames(), PAUSE_PULSE_FRAMES, tween::Easing::InOutSine);
+        let alpha = tween::lerp(0.4, 1.0, pulse);
+        let playfield = gso.sim.playfield;
+        let center = playfield.size.0 / 2.0;
+        let top = playfield.size.1 / 2.0 + 16.0;
+        let pos = (center - self.label.width(PAUSE_SCALE) / 2.0, top);
+        self.label.draw(pos, PAUSE_SCALE, [1.0, 1.0, 1.0, alpha], &mut gso.sim.sprite_holder);
+        self.menu.draw(center, top - MENU_GAP, &mut gso.sim.sprite_holder);
+        let pos = (center - self.controls.width(CONTROLS_SCALE) / 2.0, CONTROLS_GAP);
+        self.controls.draw(pos, CONTROLS_SCALE, CONTROLS_TINT, &mut gso.sim.sprite_holder);
+    }
+
+    fn sprite_indices(&self) -> Vec<SpriteHandle> {
+        let mut indices = self.label.sprite_indices();
+        indices.extend(self.menu.sprite_indices());
+        indices.extend(self.controls.sprite_indices());
+        indices
+    }
+
+    fn remove(self: Box<Self>, sprite_holder: &mut SpriteHolder) {
+        self.label.remove(sprite_holder);
+        self.menu.remove(sprite_holder);
+        self.controls.remove(sprite_holder);
+    }
+}
+
+struct DialogScene {
+    dialog: dialog::ConfirmDialog,
+}
+
+impl Scene for DialogScene {
+    fn handle_input(&mut self, gso: &mut GameStateHolder) -> Flow {
+        match self.dialog.handle_input(&gso.input) {
+            dialog::DialogResult::Open => return Flow::Continue,
+            dialog::DialogResult::Confirmed(dialog::DialogAction::QuitRun) => {
+                abandon_run(gso, "quit");
+                // Back to the title screen the run was started from.
+                transition_to_state(if gso.sim.mode == 6 { GameState::Title2 } else { GameState::Title }, gso);
+            }
+            dialog::DialogResult::Confirmed(dialog::DialogAction::ResetSave) => {
+                log::info!("Save data reset");
+                gso.save = save::SaveData::default();
+                gso.save.store();
+                // Drops the stale best time; the title screen puts the new one back up.
+                gso.medals.clear(&mut gso.sim.sprite_holder);
+            }
+            dialog::DialogResult::Cancelled => {}
+        }
+        // Back to whatever it was opened over, e.g. the pause screen.
+        Flow::Close
+    }
+
+    fn render_sprites(&self, gso: &mut GameStateHolder) {
+        self.dialog.draw(&mut gso.sim.sprite_holder);
+    }
+
+    fn sprite_indices(&self) -> Vec<SpriteHandle> {
+        self.dialog.sprite_indices()
+    }
+
+    fn remove(self: Box<Self>, sprite_holder: &mut SpriteHolder) {
+        self.dialog.remove(sprite_holder);
+    }
+}
+
+fn push_quit_dialog(gso: &mut GameStateHolder) {
+    let dialog = dialog::ConfirmDialog::new("QUIT RUN?", dialog::DialogAction::QuitRun, &mut gso.sim.sprite_holder);
+    gso.game_state.push(Box::new(DialogScene { dialog }));
+}
@@ -0,0 +1,110 @@
+// Score over the course of a run, shown as a bar graph on the results screens.
+// Each bar is one sample of the running total, coloured by the boss phase it was
+// taken in, so it's easy to see which phases paid out the most.
+use crate::clock::FRAMES_PER_SECOND;
+use crate::generated_sprites::SOLID_CELL;
+use crate::simulation::SimState;
+use crate::{GPUSprite, Layer, SpriteHandle, SpriteHolder, SPRITE_SHEET_RESOLUTION};
+
+// Graph area on screen, inside the results artwork.
+const AREA: (f32, f32, f32, f32) = (192.0, 96.0, 640.0, 160.0);
+// Longer runs are averaged down to this many bars.
+const MAX_BARS: usize = 80;
+const BACKING_TINT: [f32; 4] = [0.0, 0.0, 0.0, 0.6];
+const PHASE_TINTS: [[f32; 4]; 4] = [
+    [0.4, 0.8, 1.6, 1.0],
+    [1.6, 0.9, 0.3, 1.0],
+    [0.5, 1.5, 0.5, 1.0],
+    [1.5, 0.5, 1.2, 1.0],
+];
+
+#[derive(Debug, Clone, Copy)]
+struct Sample {
+    score: u64,
+    phase: usize,
+}
+
+#[derive(Default)]
+pub struct ScoreGraph {
+    // One per second of gameplay.
+    samples: Vec<Sample>,
+    sprite_indices: Vec<SpriteHandle>,
+}
+
+impl ScoreGraph {
+    // Call after every simulated frame.
+    pub fn record(&mut self, sim: &SimState) {
+        if sim.frame == 1 {
+            self.samples.clear();
+        }
+        if sim.frame.is_multiple_of(FRAMES_PER_SECOND) {
+            self.samples.push(Sample {
+                score: sim.player.score,
+                phase: sim.phase,
+            });
+        }
+    }
+
+    // Drops samples from after `frame`, for when the run is rewound.
+    pub fn rewind_to(&mut self, frame: u64) {
+        self.samples.truncate((frame / FRAMES_PER_SECOND) as usize);
+    }
+
+    pub fn show(&mut self, sprite_holder: &mut SpriteHolder) {
+        self.clear(sprite_holder);
+        let top = self.samples.iter().map(|sample| sample.score).max().unwrap_or(0);
+        if top == 0 {
+            return;
+        }
+        self.add_rect([AREA.0 - 4.0, AREA.1 - 4.0, AREA.2 + 8.0, AREA.3 + 8.0], BACKING_TINT, sprite_holder);
+
+        let per_bar = self.samples.len().div_ceil(MAX_BARS);
+        let bars: Vec<Sample> = self
+            .samples
+            .chunks(per_bar)
+            .map(|chunk| Sample {
+                score: chunk.iter().map(|sample| sample.score).sum::<u64>() / chunk.len() as u64,
+                phase: chunk[chunk.len() - 1].phase,
+            })
+            .collect();
+        let width = AREA.2 / bars.len() as f32;
+        for (i, bar) in bars.iter().enumerate() {
+            let height = AREA.3 * bar.score as f32 / top as f32;
+            if height < 1.0 {
+                continue;
+            }
+            // A pixel of gap between bars when there's room for it.
+            let gap = if width >= 3.0 { 1.0 } else { 0.0 };
+            let rect = [AREA.0 + i as f32 * width, AREA.1, width - gap, height];
+            self.add_rect(rect, PHASE_TINTS[bar.phase % PHASE_TINTS.len()], sprite_holder);
+        }
+    }
+
+    fn add_rect(&mut self, screen_region: [f32; 4], tint: [f32; 4], sprite_holder: &mut SpriteHolder) {
+        let sprite_index = sprite_holder.get_next_index_on(Layer::Ui);
+        sprite_holder.set_sprite(
+            sprite_index,
+            GPUSprite {
+                screen_region,
+                sheet_region: [
+                    SOLID_CELL.0 / SPRITE_SHEET_RESOLUTION.0,
+                    SOLID_CELL.1 / SPRITE_SHEET_RESOLUTION.1,
+                    1.0 / SPRITE_SHEET_RESOLUTION.0,
+                    1.0 / SPRITE_SHEET_RESOLUTION.1,
+                ],
+            },
+        );
+        sprite_holder.set_tint(sprite_index, tint);
+        self.sprite_indices.push(sprite_index);
+    }
+
+    pub fn sprite_indices(&self) -> Vec<SpriteHandle> {
+        self.sprite_indices.clone()
+    }
+
+    pub fn clear(&mut self, sprite_holder: &mut SpriteHolder) {
+        for sprite_index in self.sprite_indices.drain(..) {
+            sprite_holder.remove_sprite(sprite_index);
+        }
+    }
+}
@@ -0,0 +1,61 @@
+// Risk/reward strips of the playfield. While the player stands inside one, grazes
+// and boss hits are worth more. The player only moves sideways, so a zone is just
+// a range of x positions, drawn as a faint column from the floor to the ceiling.
+use crate::{atlas, GPUSprite, Layer, Player, SpriteHandle, SpriteHolder};
+
+const ZONE_TINT: [f32; 4] = [1.4, 1.1, 0.3, 0.12];
+// Brighter while the player is inside, so it's obvious the bonus is active.
+const ZONE_TINT_ACTIVE: [f32; 4] = [1.4, 1.1, 0.3, 0.25];
+const ZONE_HEIGHT: f32 = 760.0;
+
+#[derive(Debug, Clone)]
+pub struct ScoreZone {
+    pub x_range: (f32, f32),
+    pub multiplier: u64,
+    sprite_index: SpriteHandle,
+}
+
+impl ScoreZone {
+    // Allocate zones before anything else in a level so they draw underneath it.
+    pub fn new(x_range: (f32, f32), multiplier: u64, sprite_holder: &mut SpriteHolder) -> Self {
+        Self {
+            x_range,
+            multiplier,
+            sprite_index: sprite_holder.get_next_index_on(Layer::Background),
+        }
+    }
+
+    // The player counts as inside once their center is over the zone.
+    fn contains(&self, player: &Player) -> bool {
+        let center = player.body.center().0;
+        center >= self.x_range.0 && center <= self.x_range.1
+    }
+
+    pub fn sync_sprite(&self, player: &Player, sprite_holder: &mut SpriteHolder) {
+        let sprite = GPUSprite {
+            screen_region: [self.x_range.0, 0.0, self.x_range.1 - self.x_range.0, ZONE_HEIGHT],
+            sheet_region: atlas::sheet_region("score_zone"),
+        };
+        sprite_holder.set_sprite(self.sprite_index, sprite);
+        let tint = if self.contains(player) { ZONE_TINT_ACTIVE } else { ZONE_TINT };
+        sprite_holder.set_tint(self.sprite_index, tint);
+    }
+
+    pub fn sprite_indices(&self) -> Vec<SpriteHandle> {
+        vec![self.sprite_index]
+    }
+
+    pub fn remove(&self, sprite_holder: &mut SpriteHolder) {
+        sprite_holder.remove_sprite(self.sprite_index);
+    }
+}
+
+// Score multiplier for the player's current position. Overlapping zones don't stack.
+pub fn multiplier_at(zones: &[ScoreZone], player: &Player) -> u64 {
+    zones
+        .iter()
+        .filter(|zone| zone.contains(player))
+        .map(|zone| zone.multiplier)
+        .max()
+        .unwrap_or(1)
+}
@@ -23,10 +23,20 @@ struct GPUSprite {
 var<uniform> camera: Camera;
 @group(0) @binding(1)
 var<storage, read> s_sprites: array<GPUSprite>;
+@group(0) @binding(2)
+var<storage, read> s_tints: array<vec4<f32>>;
+@group(0) @binding(3)
+var<storage, read> s_palettes: array<u32>;
 
 struct VertexOutput {
     @builtin(position) clip_position: vec4<f32>,
     @location(0) tex_coords: vec2<f32>,
+    // The sprite's whole sheet region, so effects know where the sprite's edges are.
+    @location(1) @interpolate(flat) tex_rect: vec4<f32>,
+    // Colour multiplier for the whole sprite.
+    @location(2) @interpolate(flat) tint: vec4<f32>,
+    // Which recolour `fs_main` applies, see `palette_color`.
+    @location(3) @interpolate(flat) palette: u32,
 }
 
 @vertex
@@ -40,13 +50,18 @@ fn vs_storage_main(@builtin(vertex_index) in_vertex_index: u32, @builtin(instanc
     let which_uv: vec2<f32> = vec2(VERTICES[in_vertex_index].x, 1.0 - VERTICES[in_vertex_index].y);
     return VertexOutput(
         ((corner + vec4(which_vtx*size,0.,0.) - vec4(camera.screen_pos,0.,0.)) / vec4(camera.screen_size/2., 1.0, 1.0)) - vec4(1.0, 1.0, 0.0, 0.0),
-        tex_corner + which_uv*tex_size
+        tex_corner + which_uv*tex_size,
+        vec4(tex_corner, tex_size),
+        s_tints[sprite_index],
+        s_palettes[sprite_index]
     );
 }
 
 struct InstanceInput {
     @location(0) to_rect: vec4<f32>,
     @location(1) from_rect: vec4<f32>,
+    @location(2) tint: vec4<f32>,
+    @location(3) palette: u32,
 };
 
 @vertex
@@ -60,7 +75,10 @@ fn vs_vbuf_main(@builtin(vertex_index) in_vertex_index: u32, sprite_data:Instanc
     let which_uv: vec2<f32> = vec2(VERTICES[in_vertex_index].x, 1.0 - VERTICES[in_vertex_index].y);
     return VertexOutput(
         ((corner + vec4(which_vtx*size,0.,0.) - vec4(camera.screen_pos,0.,0.)) / vec4(camera.screen_size/2., 1.0, 1.0)) - vec4(1.0, 1.0, 0.0, 0.0),
-        tex_corner + which_uv*tex_size
+        tex_corner + which_uv*tex_size,
+        vec4(tex_corner, tex_size),
+        sprite_data.tint,
+        sprite_data.palette
     );
 }
 
@@ -78,7 +96,73 @@ var s_diffuse: sampler;
 @fragment
 fn fs_main(in:VertexOutput) -> @location(0) vec4<f32> {
     // And we use the tex coords from the vertex output to sample from the texture.
+    let color:vec4<f32> = palette_color(textureSample(t_diffuse, s_diffuse, in.tex_coords), in.palette);
+    if color.w < 0.2 { discard; }
+    // Tint after the cutout, so a faded tint doesn't make sprites lose pixels.
+    return color * in.tint;
+}
+
+// Recolours a texel for a sprite variant, so variants can share the original art.
+// Palette 0 is the sheet's own colours. The others keep each texel's brightness
+// and map it onto a ramp from a dark to a light colour. The numbers match
+// `Palette` in palettes.rs.
+fn palette_color(color: vec4<f32>, palette: u32) -> vec4<f32> {
+    var dark: vec3<f32>;
+    var light: vec3<f32>;
+    switch palette {
+        case 1u: {
+            // Crimson
+            dark = vec3(0.2, 0.0, 0.02);
+            light = vec3(1.0, 0.5, 0.4);
+        }
+        case 2u: {
+            // Frost
+            dark = vec3(0.0, 0.05, 0.2);
+            light = vec3(0.7, 0.95, 1.0);
+        }
+        case 3u: {
+            // Gold
+            dark = vec3(0.2, 0.1, 0.0);
+            light = vec3(1.0, 0.9, 0.45);
+        }
+        default: {
+            return color;
+        }
+    }
+    let shade = dot(color.rgb, vec3(0.299, 0.587, 0.114));
+    return vec4(mix(dark, light, shade), color.w);
+}
+
+// Alpha of the texel at `uv`, treating anything outside the sprite's own region as empty.
+fn alpha_in_rect(uv: vec2<f32>, rect: vec4<f32>) -> f32 {
+    if any(uv < rect.xy) || any(uv > rect.xy + rect.zw) {
+        return 0.0;
+    }
+    return textureSampleLevel(t_diffuse, s_diffuse, uv, 0.0).w;
+}
+
+// Drawn on top of a sprite: paints the transparent pixels that touch an opaque
+// one dark, which gives a 1px outline around the sprite's silhouette.
+@fragment
+fn fs_outline(in:VertexOutput) -> @location(0) vec4<f32> {
+    // One screen pixel, measured in texture coordinates.
+    let px: vec2<f32> = fwidth(in.tex_coords);
+    let center: f32 = alpha_in_rect(in.tex_coords, in.tex_rect);
+    let neighbours: f32 = max(
+        max(alpha_in_rect(in.tex_coords + vec2(px.x, 0.0), in.tex_rect),
+            alpha_in_rect(in.tex_coords - vec2(px.x, 0.0), in.tex_rect)),
+        max(alpha_in_rect(in.tex_coords + vec2(0.0, px.y), in.tex_rect),
+            alpha_in_rect(in.tex_coords - vec2(0.0, px.y), in.tex_rect))
+    );
+    if center >= 0.2 || neighbours < 0.2 { discard; }
+    return vec4(0.05, 0.03, 0.1, 1.0);
+}
+
+// Drawn over everything else, HUD included: the sprite's shape in one bright
+// colour, so the player can't get lost under a pile of bullets.
+@fragment
+fn fs_silhouette(in:VertexOutput) -> @location(0) vec4<f32> {
     let color:vec4<f32> = textureSample(t_diffuse, s_diffuse, in.tex_coords);
     if color.w < 0.2 { discard; }
-    return color;
+    return vec4(0.75, 1.0, 1.0, 0.85);
 }
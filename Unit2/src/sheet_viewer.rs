@@ -0,0 +1,205 @@
+// A debug screen for finding sprite coordinates: the whole spritesheet with a grid
+// over it and a box to move about. Arrows move the box a cell at a time, Shift +
+// arrows resize it, and holding Ctrl makes either go a pixel at a time. Enter logs
+// the box as a `sheet_region`, and as an entry to paste into content/sprites.ron
+// to name it. Opened with G on the title screens in debug builds.
+use crate::atlas;
+use crate::generated_sprites::SOLID_CELL;
+use crate::input::{Input, Key};
+use crate::scenes::{Flow, Scene};
+use crate::text::TextLabel;
+use crate::{GPUSprite, GameStateHolder, Layer, SpriteHandle, SpriteHolder, SPRITE_SHEET_RESOLUTION};
+
+// How big a sheet cell is drawn, and where the sheet's bottom left goes.
+const CELL_SIZE: f32 = 40.0;
+const SHEET_ORIGIN: (f32, f32) = (32.0, 24.0);
+// One pixel of a 16px cell.
+const FINE_STEP: f32 = 1.0 / 16.0;
+const GRID_WIDTH: f32 = 2.0;
+const GRID_TINT: [f32; 4] = [1.0, 1.0, 1.0, 0.2];
+const SELECTION_TINT: [f32; 4] = [1.6, 1.3, 0.3, 0.4];
+const TEXT_SCALE: f32 = 2.0;
+const TEXT_TOP: (f32, f32) = (560.0, 720.0);
+const TEXT_LINE: f32 = 28.0;
+const TEXT_TINT: [f32; 4] = [1.0, 1.0, 1.0, 1.0];
+
+// The box as the game's sheet coordinates, which run 0 to 1 across the whole sheet.
+fn sheet_region([x, y, w, h]: [f32; 4]) -> [f32; 4] {
+    [
+        x / SPRITE_SHEET_RESOLUTION.0,
+        y / SPRITE_SHEET_RESOLUTION.1,
+        w / SPRITE_SHEET_RESOLUTION.0,
+        h / SPRITE_SHEET_RESOLUTION.1,
+    ]
+}
+
+// This frame's arrow keys applied to the box, kept on the sheet.
+fn step_selection(selection: [f32; 4], input: &Input) -> [f32; 4] {
+    let held = |keys: [Key; 2]| keys.iter().any(|&key| input.is_key_down(key));
+    let step = if held([Key::LControl, Key::RControl]) { FINE_STEP } else { 1.0 };
+    let delta = (
+        (input.is_key_pressed(Key::Right) as i32 - input.is_key_pressed(Key::Left) as i32) as f32 * step,
+        (input.is_key_pressed(Key::Down) as i32 - input.is_key_pressed(Key::Up) as i32) as f32 * step,
+    );
+    let [mut x, mut y, mut w, mut h] = selection;
+    if held([Key::LShift, Key::RShift]) {
+        w = (w + delta.0).clamp(FINE_STEP, SPRITE_SHEET_RESOLUTION.0);
+        h = (h + delta.1).clamp(FINE_STEP, SPRITE_SHEET_RESOLUTION.1);
+    } else {
+        x += delta.0;
+        y += delta.1;
+    }
+    x = x.clamp(0.0, SPRITE_SHEET_RESOLUTION.0 - w);
+    y = y.clamp(0.0, SPRITE_SHEET_RESOLUTION.1 - h);
+    [x, y, w, h]
+}
+
+pub struct SheetViewerScene {
+    sheet: SpriteHandle,
+    grid: Vec<SpriteHandle>,
+    selection_sprite: SpriteHandle,
+    // In cells from the sheet's top left.
+    selection: [f32; 4],
+    // The text the labels were made for, so they're only rebuilt when it changes.
+    shown: Vec<String>,
+    labels: Vec<TextLabel>,
+}
+
+impl SheetViewerScene {
+    pub fn new(sprite_holder: &mut SpriteHolder) -> Self {
+        // The grid and box go on a later layer than the sheet, so they're drawn over it.
+        let lines = (SPRITE_SHEET_RESOLUTION.0 + SPRITE_SHEET_RESOLUTION.1) as usize + 2;
+        Self {
+            sheet: sprite_holder.get_next_index_on(Layer::Entities),
+            grid: (0..lines).map(|_| sprite_holder.get_next_index_on(Layer::Ui)).collect(),
+            selection_sprite: sprite_holder.get_next_index_on(Layer::Ui),
+            selection: [0.0, 0.0, 1.0, 1.0],
+            shown: vec![],
+            labels: vec![],
+        }
+    }
+
+    // Where a box of cells lands on screen. Sheet rows count down from the top.
+    fn screen_rect(&self, [x, y, w, h]: [f32; 4]) -> [f32; 4] {
+        [
+            SHEET_ORIGIN.0 + x * CELL_SIZE,
+            SHEET_ORIGIN.1 + (SPRITE_SHEET_RESOLUTION.1 - y - h) * CELL_SIZE,
+            w * CELL_SIZE,
+            h * CELL_SIZE,
+        ]
+    }
+
+    fn text(&self) -> Vec<String> {
+        let [x, y, w, h] = self.selection;
+        let region = sheet_region(self.selection);
+        let name = atlas::entries()
+            .iter()
+            .find(|(_, entry)| entry.contains(self.selection))
+            .map_or("NOT IN THE ATLAS".to_string(), |(name, _)| name.clone());
+        vec![
+            format!("X {} Y {} W {} H {}", x, y, w, h),
+            format!("{:.4} {:.4} {:.4} {:.4}", region[0], region[1], region[2], region[3]),
+            name,
+            "ENTER LOGS IT. ESC CLOSES.".to_string(),
+        ]
+    }
+
+    fn log_selection(&self) {
+        let [x, y, w, h] = self.selection;
+        log::info!(
+            "sheet_region: [{:?} / SPRITE_SHEET_RESOLUTION.0, {:?} / SPRITE_SHEET_RESOLUTION.1, {:?} / SPRITE_SHEET_RESOLUTION.0, {:?} / SPRITE_SHEET_RESOLUTION.1],",
+            x, y, w, h
+        );
+        log::info!("sprites.ron entry: (x: {:?}, y: {:?}, w: {:?}, h: {:?}),", x, y, w, h);
+    }
+}
+
+impl Scene for SheetViewerScene {
+    fn handle_input(&mut self, gso: &mut GameStateHolder) -> Flow {
+        if gso.input.is_key_pressed(Key::Escape) || gso.input.is_key_pressed(Key::G) {
+            return Flow::Close;
+        }
+        self.selection = step_selection(self.selection, &gso.input);
+        if gso.input.is_key_pressed(Key::Return) {
+            self.log_selection();
+        }
+        Flow::Continue
+    }
+
+    fn update(&mut self, gso: &mut GameStateHolder) {
+        let text = self.text();
+        if text != self.shown {
+            for label in self.labels.drain(..) {
+                label.remove(&mut gso.sim.sprite_holder);
+            }
+            self.labels = text.iter().map(|line| TextLabel::new(line, &mut gso.sim.sprite_holder)).collect();
+            self.shown = text;
+        }
+    }
+
+    fn render_sprites(&self, gso: &mut GameStateHolder) {
+        let sprite_holder = &mut gso.sim.sprite_holder;
+        let whole_sheet = [0.0, 0.0, SPRITE_SHEET_RESOLUTION.0, SPRITE_SHEET_RESOLUTION.1];
+        sprite_holder.set_sprite(
+            self.sheet,
+            GPUSprite {
+                screen_region: self.screen_rect(whole_sheet),
+                sheet_region: [0.0, 0.0, 1.0, 1.0],
+            },
+        );
+
+        let solid = sheet_region([SOLID_CELL.0, SOLID_CELL.1, 1.0, 1.0]);
+        let [left, bottom, width, height] = self.screen_rect(whole_sheet);
+        let columns = (0..=SPRITE_SHEET_RESOLUTION.0 as usize).map(|i| [left + i as f32 * CELL_SIZE, bottom, GRID_WIDTH, height]);
+        let rows = (0..=SPRITE_SHEET_RESOLUTION.1 as usize).map(|i| [left, bottom + i as f32 * CELL_SIZE, width, GRID_WIDTH]);
+        for (&handle, screen_region) in self.grid.iter().zip(columns.chain(rows)) {
+            sprite_holder.set_sprite(handle, GPUSprite { screen_region, sheet_region: solid });
+            sprite_holder.set_tint(handle, GRID_TINT);
+        }
+        sprite_holder.set_sprite(
+            self.selection_sprite,
+            GPUSprite {
+                screen_region: self.screen_rect(self.selection),
+                sheet_region: solid,
+            },
+        );
+        sprite_holder.set_tint(self.selection_sprite, SELECTION_TINT);
+
+        for (i, label) in self.labels.iter().enumerate() {
+            label.draw((TEXT_TOP.0, TEXT_TOP.1 - i as f32 * TEXT_LINE), TEXT_SCALE, TEXT_TINT, sprite_holder);
+        }
+    }
+
+    fn sprite_indices(&self) -> Vec<SpriteHandle> {
+        let mut indices = vec![self.sheet, self.selection_sprite];
+        indices.extend(self.grid.iter().copied());
+        for label in self.labels.iter() {
+            indices.extend(label.sprite_indices());
+        }
+        indices
+    }
+
+    fn remove(self: Box<Self>, sprite_holder: &mut SpriteHolder) {
+        for handle in std::iter::once(self.sheet).chain(self.grid).chain(std::iter::once(self.selection_sprite)) {
+            sprite_holder.remove_sprite(handle);
+        }
+        for label in self.labels {
+            label.remove(sprite_holder);
+        }
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn a_box_inside_an_atlas_entry_is_named_by_it() {
+        let entry = atlas::Region { x: 2.0, y: 3.0, w: 2.0, h: 1.0 };
+        assert!(entry.contains([2.0, 3.0, 2.0, 1.0]));
+        assert!(entry.contains([3.0, 3.0, 0.5, 0.5]));
+        assert!(!entry.contains([3.0, 3.0, 2.0, 1.0]));
+        assert_eq!(sheet_region([0.0, 0.0, SPRITE_SHEET_RESOLUTION.0, SPRITE_SHEET_RESOLUTION.1]), [0.0, 0.0, 1.0, 1.0]);
+    }
+}
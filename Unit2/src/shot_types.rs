@@ -0,0 +1,167 @@
+// Player shot configurations. A normal run uses a single shot at power 1, which is
+// the original orange; the trial room is where the others can be tried out. The
+// config lives on the SimState, so whatever was picked there carries into the
+// next run.
+use crate::enemy_ai::aim_at;
+use crate::input::{Input, Key};
+
+pub const MAX_POWER: u8 = 3;
+// Angle between neighbouring shots of a spread, in radians.
+const SPREAD_ANGLE: f32 = 0.12;
+// Horizontal gap between the streams of a powered-up single shot.
+const STREAM_SPACING: f32 = 24.0;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum ShotKind {
+    // Straight up. More power adds parallel streams.
+    #[default]
+    Single,
+    // A fan. More power widens it.
+    Spread,
+    // One shot that costs less juice. More power makes it cheaper still.
+    Rapid,
+}
+
+impl ShotKind {
+    const ALL: [ShotKind; 3] = [ShotKind::Single, ShotKind::Spread, ShotKind::Rapid];
+
+    // `steps` along the list, wrapping round at either end.
+    fn step(self, steps: i32) -> Self {
+        let index = Self::ALL.iter().position(|&kind| kind == self).unwrap_or(0) as i32;
+        Self::ALL[(index + steps).rem_euclid(Self::ALL.len() as i32) as usize]
+    }
+}
+
+// What it takes to fire. Set by the level, not picked by the player.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, serde::Deserialize)]
+pub enum Trigger {
+    // Each volley spends caught fruit.
+    #[default]
+    Charge,
+    // Fires for as long as the button is held, on a cooldown. The danmaku level
+    // uses this since there's nothing to catch.
+    Held,
+}
+
+// The danmaku level's X-button ability.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Special {
+    #[default]
+    TimeFreeze,
+    // Grazes charge a meter that throws every bullet on screen back at the boss.
+    CatchReturn,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ShotConfig {
+    pub kind: ShotKind,
+    // 1 to MAX_POWER.
+    pub power: u8,
+    pub trigger: Trigger,
+    pub special: Special,
+}
+
+impl Default for ShotConfig {
+    fn default() -> Self {
+        Self {
+            kind: ShotKind::Single,
+            power: 1,
+            trigger: Trigger::Charge,
+            special: Special::TimeFreeze,
+        }
+    }
+}
+
+impl ShotConfig {
+    // 1-3 pick the shot type, or the mouse wheel steps through them. 4 swaps the
+    // special, Up and Down change the power.
+    pub fn handle_hotkeys(&mut self, input: &Input) {
+        let before = *self;
+        self.kind = self.kind.step(input.wheel_steps());
+        if input.is_key_pressed(Key::Key1) {
+            self.kind = ShotKind::Single;
+        }
+        if input.is_key_pressed(Key::Key2) {
+            self.kind = ShotKind::Spread;
+        }
+        if input.is_key_pressed(Key::Key3) {
+            self.kind = ShotKind::Rapid;
+        }
+        if input.is_key_pressed(Key::Key4) {
+            self.special = match self.special {
+                Special::TimeFreeze => Special::CatchReturn,
+                Special::CatchReturn => Special::TimeFreeze,
+            };
+        }
+        if input.is_key_pressed(Key::Up) {
+            self.power = (self.power + 1).min(MAX_POWER);
+        }
+        if input.is_key_pressed(Key::Down) {
+            self.power = (self.power - 1).max(1);
+        }
+        if *self != before {
+            log::info!("Shot type: {}", self.name());
+        }
+    }
+
+    // Caught fruit needed per volley.
+    pub fn charge_cost(&self) -> usize {
+        match self.kind {
+            ShotKind::Rapid => 4 - self.power as usize,
+            _ => 3,
+        }
+    }
+
+    // Frames between volleys with the Held trigger.
+    pub fn cooldown_frames(&self) -> u32 {
+        match self.kind {
+            ShotKind::Rapid => 9 - 2 * self.power as u32,
+            _ => 10,
+        }
+    }
+
+    // (x offset from the player's muzzle, velocity) for every shot in one volley.
+    pub fn volley(&self, speed: f32) -> Vec<(f32, (f32, f32))> {
+        let power = self.power as i32;
+        match self.kind {
+            ShotKind::Single => (0..power)
+                .map(|i| ((i as f32 - (power - 1) as f32 / 2.0) * STREAM_SPACING, (0.0, speed)))
+                .collect(),
+            ShotKind::Spread => (-power..=power)
+                .map(|i| {
+                    let angle = i as f32 * SPREAD_ANGLE;
+                    (0.0, (angle.sin() * speed, angle.cos() * speed))
+                })
+                .collect(),
+            ShotKind::Rapid => vec![(0.0, (0.0, speed))],
+        }
+    }
+
+    // Velocity for an enemy bullet centred on `from` when the catch-and-return
+    // special throws it back. Single sends everything straight up, Spread scatters
+    // it outwards from the player and Rapid aims it all at the boss.
+    pub fn return_velocity(&self, from: (f32, f32), player: (f32, f32), boss: (f32, f32), speed: f32) -> (f32, f32) {
+        match self.kind {
+            ShotKind::Single => (0.0, speed),
+            // Bullets below the player are mirrored above it, so nothing goes downwards.
+            ShotKind::Spread => aim_at(player, (from.0, player.1 + (from.1 - player.1).abs()), speed),
+            ShotKind::Rapid => aim_at(from, boss, speed),
+        }
+    }
+
+    pub fn name(&self) -> String {
+        let kind = match self.kind {
+            ShotKind::Single => "SINGLE",
+            ShotKind::Spread => "SPREAD",
+            ShotKind::Rapid => "RAPID",
+        };
+        match self.special {
+            Special::TimeFreeze => format!("{} P{}", kind, self.power),
+            Special::CatchReturn => format!("{} P{} RETURN", kind, self.power),
+        }
+    }
+
+    pub fn to_bits(self) -> u8 {
+        (self.special as u8) << 5 | (self.trigger as u8) << 4 | (self.kind as u8) << 2 | self.power
+    }
+}
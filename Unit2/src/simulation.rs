@@ -0,0 +1,1362 @@
+use rand::rngs::StdRng;
+use rand::{Rng, SeedableRng};
+
+use crate::audio::SoundId;
+use crate::catch_return::CatchReturn;
+use crate::combo::{self, Combo};
+use crate::components;
+use crate::death_report::KilledBy;
+use crate::entity_pool::EntityPool;
+use crate::hit_feedback::HitFeedback;
+use crate::hud::SidePanel;
+use crate::input::{Control, Input};
+use crate::items::{self, Item, ItemKind};
+use crate::level;
+use crate::midboss::{MidBoss, MidBossEvent};
+use crate::destructibles::DestructibleBlock;
+use crate::game_events::{EventBus, Foe, GameEvent};
+use crate::game_state::GameState;
+use crate::density_governor::DensityGovernor;
+use crate::modifiers::Modifiers;
+use crate::obstacles::{self, Obstacle};
+use crate::particles::{self, Particles};
+use crate::playfield::Playfield;
+use crate::projectile_tags::{self, Cancel, Pattern, ProjectileTag};
+use crate::respawn_bubble::RespawnBubble;
+use crate::score_zones::{self, ScoreZone};
+use crate::shot_types::ShotConfig;
+use crate::spawn_queue::SpawnQueue;
+use crate::stage_intro::StageIntro;
+use crate::survival_timer::SurvivalTimer;
+use crate::telegraphs::Telegraphs;
+use crate::time_freeze::{self, TimeFreeze};
+use crate::{Entity, HealthBar, Layer, Player, Projectile, SpriteHandle, SpriteHolder, TransitionFlag};
+
+// The only things a player can do in a single frame. Kept tiny on purpose so
+// it can be sent over the wire every tick for lockstep netplay.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct FrameInput {
+    pub left: bool,
+    pub right: bool,
+    pub shoot: bool,
+    pub ability: bool,
+    // A burst of speed the way the player is moving, on the frame they asked for it.
+    pub dash: bool,
+}
+
+impl FrameInput {
+    // `by_position` reads the letter keys by scancode; see `Control::bindings`.
+    pub fn from_input(input: &Input, by_position: bool) -> Self {
+        Self {
+            left: input.is_control_down(Control::Left, by_position),
+            right: input.is_control_down(Control::Right, by_position),
+            shoot: input.is_control_down(Control::Shoot, by_position),
+            ability: input.is_control_down(Control::Ability, by_position),
+            dash: input.is_control_down(Control::DashLeft, by_position) || input.is_control_down(Control::DashRight, by_position),
+        }
+    }
+
+    // Packs the input into a single byte for the network.
+    pub fn to_bits(self) -> u8 {
+        (self.left as u8) | (self.right as u8) << 1 | (self.shoot as u8) << 2 | (self.ability as u8) << 3 | (self.dash as u8) << 4
+    }
+
+    pub fn from_bits(bits: u8) -> Self {
+        Self {
+            left: bits & 1 != 0,
+            right: bits & 2 != 0,
+            shoot: bits & 4 != 0,
+            ability: bits & 8 != 0,
+            dash: bits & 16 != 0,
+        }
+    }
+
+    // -1 for left, 1 for right, 0 for both or neither.
+    fn axis(&self) -> f32 {
+        (if self.right { 1.0 } else { 0.0 }) - (if self.left { 1.0 } else { 0.0 })
+    }
+}
+
+// Everything that changes during gameplay. Given the same starting state and the
+// same inputs, `simulate` always produces the same next state: all randomness
+// comes from the seeded `rng`, and no audio or window calls happen in here.
+pub struct SimState {
+    pub player: Player,
+    pub enemy: Entity,
+    // Small enemies called in by the boss. Killing them can drop items.
+    pub minions: Vec<Entity>,
+    // Minions that have left, for the next summons to reuse.
+    pub entity_pool: EntityPool,
+    pub items: Vec<Item>,
+    // Stage 1's mid-fight visitor, if the level has one.
+    pub midboss: Option<MidBoss>,
+    pub sprite_holder: SpriteHolder,
+    pub projectiles: Vec<Projectile>,
+    pub player_health_bar: HealthBar,
+    pub trans_flag: TransitionFlag,
+    // Which gameplay state (1 or 6) the loaded level belongs to.
+    pub mode: usize,
+    // Set by the danmaku game over's retry-from-phase. Every load of the stage
+    // starts the boss there until the run ends, rewinds' reloads included.
+    pub start_phase: Option<usize>,
+    pub rng: StdRng,
+    pub frame: u64,
+    // Frames since GO, or since the start for a level without a countdown. The
+    // level's timeline, like when the mid-boss turns up, runs on this.
+    pub fight_frame: u64,
+    // The boss's current attack phase, and how many it has moved past this level.
+    pub phase: usize,
+    pub phases_cleared: u32,
+    // Phases cleared without taking a hit, and whether the current one has been spoiled.
+    pub spells_captured: u32,
+    pub phase_hit: bool,
+    // Bullets grazed this level.
+    pub grazes: u32,
+    // Keeps the player's chain alive between links, and its multiplier on screen.
+    pub combo: Combo,
+    // What took the player's last health, once something has. Only for the death screen.
+    pub killed_by: Option<KilledBy>,
+    // Cleared as soon as an assist changes how the run plays out.
+    pub leaderboard_eligible: bool,
+    // Movement bounds and edge rules for the current level.
+    pub playfield: Playfield,
+    // Walls and spinners. They block the player and eat bullets.
+    pub obstacles: Vec<Obstacle>,
+    // Breakable cover that soaks up enemy bullets.
+    pub blocks: Vec<DestructibleBlock>,
+    // The player's bullet-slowing ability. Only the danmaku level has it.
+    pub time_freeze: Option<TimeFreeze>,
+    // Its alternative, if the player picked catch-and-return in the trial room.
+    pub catch_return: Option<CatchReturn>,
+    // Pops enemy bullets around the danmaku player for a moment after a lost life.
+    pub respawn_bubble: Option<RespawnBubble>,
+    // The danmaku level is also cleared by outlasting this, whatever the boss's health.
+    pub survival_timer: Option<SurvivalTimer>,
+    // The readouts beside the playfield, for levels whose HUD layout has them.
+    pub side_panel: Option<SidePanel>,
+    // Areas where grazes and boss hits score extra, set up by the level loader.
+    pub score_zones: Vec<ScoreZone>,
+    // Thins out bullet walls around the player, if the level or an assist wants it.
+    pub density_governor: Option<DensityGovernor>,
+    // The stage's banner and countdown, if it has one. Enemies hold their fire
+    // until the countdown's done.
+    pub stage_intro: Option<StageIntro>,
+    // Challenge mutators picked on the title screen for this run.
+    pub modifiers: Modifiers,
+    // The player's shot type and power, picked in the trial room.
+    pub shot: ShotConfig,
+    // Enemy bullets asked for this frame, by AIs or by other bullets. They're
+    // created in cleanup, after everything has moved.
+    pub spawn_queue: SpawnQueue,
+    // Hit flashes, sounds and sparks for everything damaged this frame.
+    pub hit_feedback: HitFeedback,
+    // Sparks, death bursts and muzzle flashes. Only for show.
+    pub particles: Particles,
+    // Warnings the AIs have put up ahead of their attacks.
+    pub telegraphs: Telegraphs,
+    // Sounds requested this frame, played by the frame loop afterwards.
+    pub sounds: Vec<SoundId>,
+    // Hits, kills and state changes reported this frame, applied by `dispatch`.
+    pub events: EventBus,
+}
+
+impl SimState {
+    pub fn new(sprite_holder: SpriteHolder, seed: u64) -> Self {
+        let (player, enemy, player_health_bar) = level::between_stages();
+        Self {
+            player,
+            enemy,
+            minions: vec![],
+            entity_pool: EntityPool::default(),
+            items: vec![],
+            midboss: None,
+            sprite_holder,
+            projectiles: vec![],
+            player_health_bar,
+            trans_flag: TransitionFlag { val: 0 },
+            mode: 0,
+            start_phase: None,
+            rng: StdRng::seed_from_u64(seed),
+            frame: 0,
+            fight_frame: 0,
+            phase: 0,
+            phases_cleared: 0,
+            spells_captured: 0,
+            phase_hit: false,
+            grazes: 0,
+            combo: Combo::default(),
+            killed_by: None,
+            leaderboard_eligible: true,
+            playfield: Playfield::default(),
+            obstacles: vec![],
+            blocks: vec![],
+            time_freeze: None,
+            catch_return: None,
+            respawn_bubble: None,
+            survival_timer: None,
+            side_panel: None,
+            score_zones: vec![],
+            density_governor: None,
+            stage_intro: None,
+            modifiers: Modifiers::default(),
+            shot: ShotConfig::default(),
+            spawn_queue: SpawnQueue::default(),
+            hit_feedback: HitFeedback::default(),
+            particles: Particles::default(),
+            telegraphs: Telegraphs::default(),
+            sounds: vec![],
+            events: EventBus::default(),
+        }
+    }
+
+    // Resets the per-level bookkeeping. Called by the level loaders.
+    pub fn start_level(&mut self, mode: usize) {
+        self.mode = mode;
+        self.frame = 0;
+        self.fight_frame = 0;
+        self.phase = 0;
+        self.phases_cleared = 0;
+        self.spells_captured = 0;
+        self.phase_hit = false;
+        self.grazes = 0;
+        self.killed_by = None;
+        self.leaderboard_eligible = true;
+    }
+
+    // Whether the stage's opening countdown is still running.
+    pub fn in_countdown(&self) -> bool {
+        self.stage_intro.as_ref().is_some_and(StageIntro::holding_fire)
+    }
+
+    // Every sprite slot the gameplay objects currently own, for the sprite audit.
+    // Between levels the player and boss are the between-stages ones that own nothing.
+    pub fn sprite_indices(&self) -> Vec<SpriteHandle> {
+        let mut indices = vec![];
+        if self.mode != 0 {
+            indices.push(self.player.sprite_index);
+            indices.extend(self.player_health_bar.sprite_indices());
+            indices.extend(self.enemy.sprite_indices());
+        }
+        for proj in self.projectiles.iter() {
+            indices.extend(proj.sprite_indices());
+        }
+        for minion in self.minions.iter() {
+            indices.extend(minion.sprite_indices());
+        }
+        indices.extend(self.entity_pool.sprite_indices());
+        for item in self.items.iter() {
+            indices.extend(item.sprite_indices());
+        }
+        if let Some(midboss) = &self.midboss {
+            indices.extend(midboss.sprite_indices());
+        }
+        for obstacle in self.obstacles.iter() {
+            indices.extend(obstacle.sprite_indices());
+        }
+        for block in self.blocks.iter() {
+            indices.extend(block.sprite_indices());
+        }
+        if let Some(time_freeze) = &self.time_freeze {
+            indices.extend(time_freeze.sprite_indices());
+        }
+        if let Some(catch_return) = &self.catch_return {
+            indices.extend(catch_return.sprite_indices());
+        }
+        if let Some(respawn_bubble) = &self.respawn_bubble {
+            indices.extend(respawn_bubble.sprite_indices());
+        }
+        if let Some(survival_timer) = &self.survival_timer {
+            indices.extend(survival_timer.sprite_indices());
+        }
+        if let Some(side_panel) = &self.side_panel {
+            indices.extend(side_panel.sprite_indices());
+        }
+        for zone in self.score_zones.iter() {
+            indices.extend(zone.sprite_indices());
+        }
+        indices.extend(self.combo.sprite_indices());
+        indices.extend(self.particles.sprite_indices());
+        indices.extend(self.telegraphs.sprite_indices());
+        indices
+    }
+
+    // The sprites that sit in the frame around the playfield. They're drawn a second
+    // time after the frame so it doesn't dim them.
+    pub fn hud_sprite_indices(&self) -> Vec<SpriteHandle> {
+        let mut indices = vec![];
+        // First, so the readouts go over its backdrop.
+        if let Some(side_panel) = &self.side_panel {
+            indices.extend(side_panel.sprite_indices());
+        }
+        if self.mode != 0 {
+            indices.extend(self.player_health_bar.sprite_indices());
+        }
+        if let Some(midboss) = &self.midboss {
+            indices.extend(midboss.hud_sprite_indices());
+        }
+        if let Some(time_freeze) = &self.time_freeze {
+            indices.extend(time_freeze.hud_sprite_indices());
+        }
+        if let Some(catch_return) = &self.catch_return {
+            indices.extend(catch_return.sprite_indices());
+        }
+        if let Some(survival_timer) = &self.survival_timer {
+            indices.extend(survival_timer.sprite_indices());
+        }
+        indices
+    }
+
+    // Hash of every value that affects future frames. Two peers running the same
+    // inputs must produce the same checksum every frame; if they don't, they've
+    // desynced.
+    pub fn checksum(&self) -> u64 {
+        // FNV-1a, hashing the raw float bits so -0.0 and 0.0 count as different.
+        fn feed(hash: &mut u64, value: u64) {
+            for byte in value.to_le_bytes() {
+                *hash ^= byte as u64;
+                *hash = hash.wrapping_mul(0x100000001b3);
+            }
+        }
+        let mut hash: u64 = 0xcbf29ce484222325;
+
+        for value in [
+            self.player.body.pos.0,
+            self.player.body.pos.1,
+            self.player_health_bar.currval,
+            self.enemy.enemy.body.pos.0,
+            self.enemy.enemy.body.pos.1,
+            self.enemy.enemy.health_bar.currval,
+        ] {
+            feed(&mut hash, value.to_bits() as u64);
+        }
+        // The dash's own frames run out before its cooldown, so this covers both.
+        feed(&mut hash, self.player.dash_cooldown as u64);
+        feed(&mut hash, self.combo.frames_left() as u64);
+        for obstacle in self.obstacles.iter() {
+            feed(&mut hash, obstacle.pos.0.to_bits() as u64);
+            feed(&mut hash, obstacle.pos.1.to_bits() as u64);
+        }
+        if let Some(time_freeze) = &self.time_freeze {
+            feed(&mut hash, time_freeze.charge.to_bits() as u64);
+            feed(&mut hash, time_freeze.uses as u64);
+        }
+        if let Some(catch_return) = &self.catch_return {
+            feed(&mut hash, catch_return.charge.to_bits() as u64);
+            feed(&mut hash, catch_return.uses as u64);
+        }
+        if let Some(respawn_bubble) = &self.respawn_bubble {
+            feed(&mut hash, respawn_bubble.frames_left() as u64);
+        }
+        if let Some(survival_timer) = &self.survival_timer {
+            feed(&mut hash, survival_timer.frames_left() as u64);
+        }
+        for block in self.blocks.iter() {
+            feed(&mut hash, block.health as u64);
+        }
+        for minion in self.minions.iter() {
+            for value in [minion.enemy.body.pos.0, minion.enemy.body.pos.1, minion.enemy.health_bar.currval] {
+                feed(&mut hash, value.to_bits() as u64);
+            }
+        }
+        if let Some(entity) = self.midboss.as_ref().and_then(|midboss| midboss.entity.as_ref()) {
+            for value in [entity.enemy.body.pos.0, entity.enemy.body.pos.1, entity.enemy.health_bar.currval] {
+                feed(&mut hash, value.to_bits() as u64);
+            }
+        }
+        for item in self.items.iter() {
+            feed(&mut hash, item.body.pos.0.to_bits() as u64);
+            feed(&mut hash, item.body.pos.1.to_bits() as u64);
+        }
+        for proj in self.projectiles.iter() {
+            feed(&mut hash, proj.age as u64);
+            for value in [proj.body.pos.0, proj.body.pos.1, proj.speed, proj.direction.0, proj.direction.1] {
+                feed(&mut hash, value.to_bits() as u64);
+            }
+        }
+        feed(&mut hash, self.player.charges as u64);
+        feed(&mut hash, self.player.score);
+        feed(&mut hash, self.projectiles.len() as u64);
+        feed(&mut hash, self.frame);
+        feed(&mut hash, self.fight_frame);
+        feed(&mut hash, self.stage_intro.as_ref().map_or(0, StageIntro::elapsed));
+        feed(&mut hash, self.phases_cleared as u64);
+        feed(&mut hash, self.spells_captured as u64);
+        feed(&mut hash, self.player.chain as u64);
+        feed(&mut hash, self.modifiers.to_bits() as u64);
+        feed(&mut hash, self.density_governor.map_or(0, |governor| governor.max_bullets as u64));
+        feed(&mut hash, self.shot.to_bits() as u64);
+        hash
+    }
+}
+
+// A bullet that passes this close to the player without hitting counts as a graze.
+const GRAZE_RADIUS: f32 = 24.0;
+const GRAZE_POINTS: u64 = 5;
+// Summons past this many are dropped, so a long fight can't flood the screen.
+const MAX_MINIONS: usize = 6;
+const MINION_POINTS: u64 = 50;
+// Chance that a killed minion leaves an item behind.
+const ITEM_DROP_CHANCE: f64 = 0.6;
+// How fast bullets thrown back by catch-and-return fly.
+const RETURN_SPEED: f32 = 10.0;
+// Bonus for taking the mid-boss down before it leaves.
+const MIDBOSS_POINTS: u64 = 1000;
+
+// Values one system works out for the ones after it. Only good for the current frame.
+struct FrameScratch {
+    input: FrameInput,
+    midboss_event: MidBossEvent,
+    score_multiplier: u64,
+}
+
+type System = fn(&mut SimState, &mut FrameScratch);
+
+// Every frame runs these once each, in this order:
+//   input:       the player's controls turn into velocity and shots
+//   ai:          enemies and bullet behaviours decide what to do and fire
+//   movement:    everything moves, nothing is checked yet
+//   collision:   hits, grazes and pickups at the new positions
+//   events:      AI reactions, phase changes, timers and scoring that depend on this frame's hits
+//   dispatch:    the frame's reported GameEvents are applied: damage, kills, state changes
+//   cleanup:     dead things are removed and queued spawns are added
+//   sprite sync: every sprite is written once, from final values
+// Sprites are only written at the end so nothing shows last frame's health or position.
+const UPDATE_ORDER: [System; 8] = [read_input, run_ai, movement, collision, events, dispatch, cleanup, sync_sprites];
+
+// Advances the game by exactly one frame.
+// Within each system, order matters for determinism: player, then enemy, then
+// projectiles in spawn order.
+pub fn simulate(mut state: SimState, input: FrameInput) -> SimState {
+    let mut scratch = FrameScratch {
+        input,
+        midboss_event: MidBossEvent::None,
+        score_multiplier: 1,
+    };
+    for system in UPDATE_ORDER {
+        system(&mut state, &mut scratch);
+    }
+    state.frame += 1;
+    if !state.in_countdown() {
+        state.fight_frame += 1;
+    }
+    if let Some(intro) = &mut state.stage_intro {
+        intro.tick();
+    }
+    state
+}
+
+fn read_input(state: &mut SimState, scratch: &mut FrameScratch) {
+    if state.modifiers.mirrored_controls {
+        std::mem::swap(&mut scratch.input.left, &mut scratch.input.right);
+    }
+    state.player.velocity = components::Velocity(scratch.input.axis() * state.player.speed, 0.0);
+    if scratch.input.dash {
+        state.player.dash();
+    }
+    state.player.fire_cooldown = state.player.fire_cooldown.saturating_sub(1);
+    if scratch.input.shoot {
+        let fired = state.projectiles.len();
+        state.player.spawn_new_projectile(
+            10.0,
+            &state.shot,
+            &mut state.projectiles,
+            &mut state.sprite_holder,
+            &mut state.sounds,
+        );
+        if state.projectiles.len() > fired {
+            let muzzle = (state.player.center().0, state.player.body.pos.1 + state.player.body.size.1);
+            state
+                .particles
+                .burst(muzzle, 3, 3.0, particles::MUZZLE_FLASH, &mut state.sprite_holder);
+        }
+    }
+    if state
+        .catch_return
+        .as_mut()
+        .is_some_and(|catch_return| catch_return.try_release(scratch.input.ability))
+    {
+        return_enemy_bullets(state);
+    }
+}
+
+// Every enemy bullet on screen becomes the player's, thrown back the way the shot type says.
+fn return_enemy_bullets(state: &mut SimState) {
+    let player_center = state.player.center();
+    let boss = &state.enemy.enemy;
+    let boss_center = boss.body.center();
+    for proj in state.projectiles.iter_mut().filter(|proj| !proj.player_spawned && !proj.is_dead) {
+        let center = proj.body.center();
+        let velocity = state.shot.return_velocity(center, player_center, boss_center, RETURN_SPEED);
+        proj.turn_around(velocity, &mut state.sprite_holder);
+    }
+    state.sounds.push(SoundId::PlayerShoot);
+}
+
+fn run_ai(state: &mut SimState, scratch: &mut FrameScratch) {
+    if !state.in_countdown() {
+        enemies_think(state, scratch);
+    }
+
+    // Anything a bullet spawns belongs to the same group as it.
+    let player_center = state.player.center();
+    for proj in state.projectiles.iter_mut() {
+        let center = proj.body.center();
+        state.spawn_queue.set_tag(proj.tag);
+        if !proj
+            .behavior
+            .update(center, &mut proj.speed, &mut proj.direction, player_center, &mut state.spawn_queue)
+        {
+            proj.kill();
+        }
+    }
+}
+
+// The boss, its minions and the mid-boss decide what to do and fire.
+fn enemies_think(state: &mut SimState, scratch: &mut FrameScratch) {
+    let player_center = state.player.center();
+    state.spawn_queue.set_tag(ProjectileTag::new(Pattern::Boss, state.enemy.ai.phase()));
+    state.enemy.think(&mut state.spawn_queue, player_center, &mut state.rng);
+    for summon in state.enemy.ai.take_summons() {
+        if state.minions.len() < MAX_MINIONS {
+            state.minions.push(state.entity_pool.spawn_minion(summon, &mut state.sprite_holder));
+        }
+    }
+    for minion in state.minions.iter_mut() {
+        if state.playfield.is_far_outside(&minion.enemy.body) {
+            minion.ai.idle_loop();
+            continue;
+        }
+        state.spawn_queue.set_tag(ProjectileTag::new(Pattern::Minion, minion.ai.phase()));
+        minion.think(&mut state.spawn_queue, player_center, &mut state.rng);
+    }
+    // The mid-boss flies on a script, so it moves itself here too.
+    scratch.midboss_event = match &mut state.midboss {
+        Some(midboss) => {
+            let phase = midboss.entity.as_ref().map_or(0, |entity| entity.ai.phase());
+            state.spawn_queue.set_tag(ProjectileTag::new(Pattern::MidBoss, phase));
+            midboss.update(
+                state.fight_frame,
+                player_center,
+                &mut state.spawn_queue,
+                &mut state.sprite_holder,
+                &mut state.rng,
+            )
+        }
+        None => MidBossEvent::None,
+    };
+    let mut telegraphs = state.enemy.ai.take_telegraphs();
+    let midboss = state.midboss.as_mut().and_then(|midboss| midboss.entity.as_mut());
+    for entity in state.minions.iter_mut().chain(midboss) {
+        telegraphs.extend(entity.ai.take_telegraphs());
+    }
+    for telegraph in telegraphs {
+        state.telegraphs.add(telegraph, &mut state.sprite_holder);
+    }
+}
+
+fn movement(state: &mut SimState, scratch: &mut FrameScratch) {
+    for obstacle in state.obstacles.iter_mut() {
+        obstacle.update(&mut state.sprite_holder);
+    }
+    state.player.move_step(&state.playfield, &state.obstacles);
+    if let Some(time_freeze) = &mut state.time_freeze {
+        time_freeze.update(scratch.input.ability, state.player.center(), &mut state.sprite_holder);
+    }
+    if let Some(respawn_bubble) = &mut state.respawn_bubble {
+        respawn_bubble.update(state.player.center(), &mut state.sprite_holder);
+    }
+    state.enemy.move_step();
+    for minion in state.minions.iter_mut() {
+        minion.move_step();
+    }
+
+    // Enemy bullets all run at the run's speed setting, and slower inside a time-freeze bubble.
+    let bullet_speed_scale = state.modifiers.bullet_speed_scale();
+    for proj in state.projectiles.iter_mut().filter(|proj| !proj.is_dead) {
+        let center = proj.body.center();
+        let time_scale = match &state.time_freeze {
+            _ if proj.player_spawned => 1.0,
+            Some(time_freeze) if time_freeze.contains(center) => bullet_speed_scale * time_freeze::FIELD_TIME_SCALE,
+            _ => bullet_speed_scale,
+        };
+        proj.move_proj(&mut state.events, time_scale);
+    }
+}
+
+fn collision(state: &mut SimState, scratch: &mut FrameScratch) {
+    // The zone bonus and the chain's stack.
+    let zone_multiplier = score_zones::multiplier_at(&state.score_zones, &state.player);
+    scratch.score_multiplier = zone_multiplier * combo::multiplier(state.player.chain);
+    let score_multiplier = scratch.score_multiplier;
+
+    // Before anything can land, so a wall it thins can't still hit.
+    if let Some(governor) = state.density_governor {
+        for center in governor.thin(&mut state.projectiles, &state.player) {
+            state.particles.burst(center, 3, 2.0, particles::SPARK, &mut state.sprite_holder);
+        }
+    }
+    if state.mode == 6 {
+        // First, so anything inside the bubble pops instead of landing.
+        pop_bubbled_bullets(state);
+        resolve_player_hits(state);
+    }
+
+    let counting_down = state.in_countdown();
+    for proj in state.projectiles.iter_mut().filter(|proj| !proj.is_dead && proj.collidable()) {
+        if obstacles::blocks(&state.obstacles, proj.body.pos, proj.body.size) {
+            proj.kill();
+        } else if let Some(block) = state.blocks.iter_mut().find(|block| block.absorbs(proj)) {
+            block.hit();
+            proj.kill();
+        } else if proj.player_spawned && counting_down {
+            // Nothing can be hurt before GO; the player's shots fly straight through.
+        } else if let Some(minion) = state
+            .minions
+            .iter_mut()
+            .find(|minion| proj.player_spawned && minion.enemy.health_bar.currval > 0.0 && components::touching(&proj.body, &minion.enemy.body))
+        {
+            if minion.enemy.hit(1.0, &mut state.hit_feedback) {
+                state.events.push(GameEvent::EnemyKilled(Foe::Minion));
+            }
+            proj.kill();
+        } else if let Some(midboss) = state
+            .midboss
+            .as_mut()
+            .and_then(|midboss| midboss.entity.as_mut())
+            .filter(|midboss| proj.player_spawned && midboss.enemy.health_bar.currval > 0.0 && components::touching(&proj.body, &midboss.enemy.body))
+        {
+            midboss.enemy.hit(1.0, &mut state.hit_feedback);
+            state.player.score += 100 * score_multiplier;
+            state.player.chain += 1;
+            proj.kill();
+        } else {
+            proj.check_collision(
+                &mut state.player,
+                &mut state.enemy.enemy,
+                &mut state.events,
+                &mut state.hit_feedback,
+                state.mode,
+                score_multiplier,
+            );
+        }
+
+        // Grazing only makes sense for bullets you're meant to dodge.
+        if state.mode == 6
+            && !proj.is_dead
+            && !proj.player_spawned
+            && !proj.grazed
+            && distance_to_player(proj, &state.player) <= GRAZE_RADIUS
+        {
+            proj.grazed = true;
+            state.grazes += 1;
+            state.player.score += GRAZE_POINTS * score_multiplier;
+            state.player.chain += 1;
+            if let Some(time_freeze) = &mut state.time_freeze {
+                time_freeze.add_charge(time_freeze::GRAZE_CHARGE);
+            }
+            if let Some(catch_return) = &mut state.catch_return {
+                catch_return.add_charge(time_freeze::GRAZE_CHARGE);
+            }
+        }
+    }
+
+    for item in state.items.iter_mut() {
+        if item.update(&state.player, &mut state.sprite_holder) {
+            match item.kind {
+                ItemKind::Charge => state.player.charges += 1,
+                ItemKind::Points => state.player.score += items::ITEM_POINTS * score_multiplier,
+            }
+        }
+    }
+}
+
+fn events(state: &mut SimState, scratch: &mut FrameScratch) {
+    // Before cleanup, so enemies that just died still get to react.
+    let boss = std::iter::once((Pattern::Boss, &mut state.enemy));
+    let minions = state.minions.iter_mut().map(|minion| (Pattern::Minion, minion));
+    let midboss = state.midboss.as_mut().and_then(|midboss| midboss.entity.as_mut()).map(|entity| (Pattern::MidBoss, entity));
+    for (pattern, entity) in boss.chain(minions).chain(midboss) {
+        state.spawn_queue.set_tag(ProjectileTag::new(pattern, entity.ai.phase()));
+        entity.react(&mut state.spawn_queue, &mut state.rng);
+    }
+
+    // Nothing to survive until the countdown's over.
+    let counting_down = state.in_countdown();
+    if let Some(survival_timer) = state.survival_timer.as_mut().filter(|_| !counting_down) {
+        if survival_timer.tick() {
+            state.events.push(GameEvent::StateChangeRequested(GameState::YouWin));
+        }
+        // Points for every frame survived.
+        state.player.score += 1;
+    }
+
+    // Checked before this frame's hit is recorded, so a hit on the frame a phase
+    // ends counts against the next phase.
+    let phase = state.enemy.ai.phase();
+    if phase > state.phase {
+        state.phases_cleared += (phase - state.phase) as u32;
+        // The old phase's bullets go with it, as points if it was captured.
+        let into = if state.phase_hit { Cancel::Vanish } else { Cancel::Points };
+        if !state.phase_hit {
+            state.spells_captured += 1;
+        }
+        state.phase_hit = false;
+        projectile_tags::cancel_tagged(
+            &mut state.projectiles,
+            &mut state.items,
+            &mut state.sprite_holder,
+            &state.playfield,
+            |tag| tag.pattern == Pattern::Boss && tag.phase < phase,
+            into,
+        );
+    }
+    state.phase = phase;
+
+    if scratch.midboss_event == MidBossEvent::Killed {
+        state.player.score += MIDBOSS_POINTS * scratch.score_multiplier;
+    }
+}
+
+// Applies everything reported this frame, in the order it was reported. A later
+// state change overrides an earlier one.
+fn dispatch(state: &mut SimState, scratch: &mut FrameScratch) {
+    let mut hurt = false;
+    let mut bombed = false;
+    for event in state.events.drain() {
+        match event {
+            // The auto-bomb covers every other hit on the frame it goes off, too.
+            GameEvent::PlayerHit { .. } if state.modifiers.auto_bomb && (bombed || auto_bomb(state)) => bombed = true,
+            GameEvent::PlayerHit { damage, tag } => hurt |= hurt_player(state, damage, KilledBy::Bullet(tag)),
+            // Level 1's fruit: every one that lands costs health.
+            GameEvent::ProjectileMissed if state.mode == 1 => hurt |= hurt_player(state, 1.0, KilledBy::DroppedFruit),
+            GameEvent::ProjectileMissed => {}
+            GameEvent::ProjectileCaught => {
+                state.sounds.push(SoundId::PlayerHit);
+                state.player.charges += 1;
+                state.player.score += 10 * scratch.score_multiplier;
+                state.player.chain += 1;
+            }
+            GameEvent::EnemyKilled(Foe::Boss) => {
+                let center = state.enemy.enemy.body.center();
+                state
+                    .particles
+                    .burst(center, 24, 6.0, particles::DEATH_BURST, &mut state.sprite_holder);
+                request_state(state, GameState::YouWin);
+            }
+            GameEvent::EnemyKilled(Foe::Minion) => {
+                state.player.score += MINION_POINTS * scratch.score_multiplier;
+                state.player.chain += 1;
+            }
+            GameEvent::StateChangeRequested(next) => request_state(state, next),
+        }
+    }
+    // After everything else, so nothing gained on the frame of a hit survives it.
+    if hurt {
+        state.player.chain = 0;
+        state.phase_hit = true;
+        if state.player_health_bar.currval > 0.0 {
+            respawn(state);
+        }
+    }
+    state.combo.update(&mut state.player.chain);
+}
+
+// Sets off the player's special in place of a hit, if its meter is full. Returns
+// whether it went off.
+fn auto_bomb(state: &mut SimState) -> bool {
+    let fired = if let Some(time_freeze) = &mut state.time_freeze {
+        time_freeze.trigger()
+    } else if let Some(catch_return) = &mut state.catch_return {
+        let released = catch_return.try_release(true);
+        if released {
+            return_enemy_bullets(state);
+        }
+        released
+    } else {
+        false
+    };
+    if fired {
+        log::info!("Auto-bomb on frame {}", state.frame);
+    }
+    fired
+}
+
+// Puts up a fresh bubble around the player after a lost life. Only the danmaku
+// level has lives to lose; elsewhere this does nothing.
+pub fn respawn(state: &mut SimState) {
+    if state.mode != 6 {
+        return;
+    }
+    if let Some(old) = state.respawn_bubble.take() {
+        old.remove(&mut state.sprite_holder);
+    }
+    state.respawn_bubble = Some(RespawnBubble::new(state.player.center(), &mut state.sprite_holder));
+}
+
+// Returns true if the hit got through the player's i-frames.
+fn hurt_player(state: &mut SimState, damage: f32, cause: KilledBy) -> bool {
+    if !state.player.damage(damage, &mut state.player_health_bar, &mut state.hit_feedback) {
+        return false;
+    }
+    if state.player_health_bar.currval <= 0.0 {
+        state.killed_by.get_or_insert(cause);
+        match state.mode {
+            1 => request_state(state, GameState::GameOver),
+            6 => request_state(state, GameState::DanmakuGameOver),
+            _ => {}
+        }
+    }
+    true
+}
+
+// The frame loop reads the flag after the frame and makes the transition.
+fn request_state(state: &mut SimState, next: GameState) {
+    state.trans_flag.val = next.index();
+}
+
+fn cleanup(state: &mut SimState, _scratch: &mut FrameScratch) {
+    // Code to remove projectiles. Not very optimal but rust likes it.
+    state.projectiles.iter_mut().for_each(|proj| {
+        if proj.is_dead {
+            proj.clean_dead(&mut state.sprite_holder)
+        }
+    });
+    state.projectiles.retain(|proj| !proj.is_dead);
+    state
+        .spawn_queue
+        .flush(&mut state.projectiles, &mut state.sprite_holder);
+
+    for block in state.blocks.iter_mut() {
+        block.update(&mut state.sprite_holder);
+        if block.is_dead {
+            block.clean_dead(&mut state.sprite_holder);
+        }
+    }
+    state.blocks.retain(|block| !block.is_dead);
+
+    // Minions leave when they're killed or drift off the playfield, and wait in the
+    // pool for the next summon.
+    let width = state.playfield.size.0;
+    let leaving = |minion: &mut Entity| {
+        let pos = minion.enemy.body.pos;
+        minion.enemy.health_bar.currval <= 0.0 || pos.0 < -64.0 || pos.0 > width || pos.1 < -64.0
+    };
+    for minion in state.minions.extract_if(.., leaving) {
+        let pos = minion.enemy.body.pos;
+        let killed = minion.enemy.health_bar.currval <= 0.0;
+        if killed {
+            state
+                .particles
+                .burst(minion.enemy.body.center(), 12, 4.0, particles::DEATH_BURST, &mut state.sprite_holder);
+        }
+        if killed && state.rng.gen_bool(ITEM_DROP_CHANCE) {
+            let kind = if state.rng.gen_bool(0.5) { ItemKind::Charge } else { ItemKind::Points };
+            let center = (pos.0 + minion.enemy.body.size.0 / 2.0, pos.1 + minion.enemy.body.size.1 / 2.0);
+            state.items.push(Item::new(center, kind, &mut state.sprite_holder));
+        }
+        state.entity_pool.release(minion, &mut state.sprite_holder);
+    }
+
+    for item in state.items.iter() {
+        if item.is_dead {
+            item.clean_dead(&mut state.sprite_holder);
+        }
+    }
+    state.items.retain(|item| !item.is_dead);
+
+    if state.respawn_bubble.as_ref().is_some_and(|respawn_bubble| respawn_bubble.is_done()) {
+        if let Some(respawn_bubble) = state.respawn_bubble.take() {
+            respawn_bubble.remove(&mut state.sprite_holder);
+        }
+    }
+
+    tick_vulnerability(state);
+}
+
+fn sync_sprites(state: &mut SimState, _scratch: &mut FrameScratch) {
+    state.player.sync_sprite(&mut state.sprite_holder);
+    state.player_health_bar.health_bar_loop(&mut state.sprite_holder);
+    state.enemy.sync_sprites(&mut state.sprite_holder);
+    for minion in state.minions.iter_mut().filter(|minion| !state.playfield.is_far_outside(&minion.enemy.body)) {
+        minion.sync_sprites(&mut state.sprite_holder);
+    }
+    if let Some(midboss) = &mut state.midboss {
+        midboss.sync_sprites(&mut state.sprite_holder);
+    }
+    for zone in state.score_zones.iter() {
+        zone.sync_sprite(&state.player, &mut state.sprite_holder);
+    }
+    if let Some(catch_return) = &mut state.catch_return {
+        catch_return.sync_sprites(&mut state.sprite_holder);
+    }
+    state.telegraphs.sync_sprites(&mut state.sprite_holder);
+    state.combo.sync_sprites(&state.player, &mut state.sprite_holder);
+    if let Some(survival_timer) = &mut state.survival_timer {
+        survival_timer.sync_sprites(&state.playfield, &mut state.sprite_holder);
+    }
+    if let Some(mut side_panel) = state.side_panel.take() {
+        side_panel.sync_sprites(state);
+        state.side_panel = Some(side_panel);
+    }
+    for proj in state.projectiles.iter_mut() {
+        components::sync_sprite(&proj.body, &mut proj.sprite, proj.sprite_index, &mut state.sprite_holder);
+        state.sprite_holder.set_tint(proj.sprite_index, [1.0, 1.0, 1.0, proj.warm_up_alpha()]);
+        proj.behavior
+            .sync_sprites(state.frame, proj.sprite.screen_region, &mut state.sprite_holder);
+    }
+    apply_hit_feedback(state);
+}
+
+fn tick_vulnerability(state: &mut SimState) {
+    state.player.vulnerability.tick();
+    state.enemy.enemy.vulnerability.tick();
+    for minion in state.minions.iter_mut() {
+        minion.enemy.vulnerability.tick();
+    }
+    if let Some(entity) = state.midboss.as_mut().and_then(|midboss| midboss.entity.as_mut()) {
+        entity.enemy.vulnerability.tick();
+    }
+}
+
+// Flashes everything that was hit recently, then plays this frame's hit sounds and sparks.
+fn apply_hit_feedback(state: &mut SimState) {
+    let sprite_holder = &mut state.sprite_holder;
+    state.player.flash.apply(state.player.sprite_index, sprite_holder);
+    state.enemy.enemy.flash.apply(state.enemy.enemy.sprite_index, sprite_holder);
+    for minion in state.minions.iter_mut() {
+        minion.enemy.flash.apply(minion.enemy.sprite_index, sprite_holder);
+    }
+    if let Some(entity) = state.midboss.as_mut().and_then(|midboss| midboss.entity.as_mut()) {
+        entity.enemy.flash.apply(entity.enemy.sprite_index, sprite_holder);
+    }
+    state.particles.update(sprite_holder);
+    state.hit_feedback.update(&mut state.sounds, &mut state.particles, sprite_holder);
+}
+
+// Distance from a projectile's center to the closest point of the player's hurtbox.
+fn distance_to_player(proj: &Projectile, player: &Player) -> f32 {
+    let center = proj.body.center();
+    let dx = center.0 - center.0.clamp(player.body.pos.0, player.body.pos.0 + player.body.size.0);
+    let dy = center.1 - center.1.clamp(player.body.pos.1, player.body.pos.1 + player.body.size.1);
+    (dx * dx + dy * dy).sqrt()
+}
+
+// Every enemy bullet touching the player this frame, earliest impact first. The
+// first one hurts and the i-frames it starts cancel the rest, which are used up
+// harmlessly, so a dense wall can't take several hits off at once.
+fn pop_bubbled_bullets(state: &mut SimState) {
+    let Some(respawn_bubble) = &state.respawn_bubble else {
+        return;
+    };
+    for proj in state.projectiles.iter_mut().filter(|proj| !proj.is_dead && !proj.player_spawned) {
+        if respawn_bubble.touches(&proj.body) {
+            proj.kill();
+            state
+                .particles
+                .burst(proj.body.center(), 3, 2.0, particles::SPARK, &mut state.sprite_holder);
+        }
+    }
+}
+
+fn resolve_player_hits(state: &mut SimState) {
+    let player = &state.player;
+    let mut impacts: Vec<(f32, usize)> = state
+        .projectiles
+        .iter()
+        .enumerate()
+        .filter(|(_, proj)| {
+            !proj.is_dead && !proj.player_spawned && proj.collidable() && components::touching(&proj.body, &player.body)
+        })
+        .map(|(i, proj)| (time_of_impact(proj, player.body.pos, player.body.size), i))
+        .collect();
+    // Stable, so bullets arriving at the same moment keep their list order.
+    impacts.sort_by(|a, b| a.0.total_cmp(&b.0));
+    for (_, i) in impacts {
+        let tag = state.projectiles[i].tag;
+        state.events.push(GameEvent::PlayerHit { damage: 1.0, tag });
+        state.projectiles[i].kill();
+    }
+}
+
+// How far through this frame's move, from 0.0 to 1.0, the bullet first touched
+// the box. Only the bullet's motion is swept; the box counts as standing still.
+fn time_of_impact(proj: &Projectile, pos: (f32, f32), size: (f32, f32)) -> f32 {
+    // The bullet's corner against the box grown by the bullet's size.
+    let min = (pos.0 - proj.body.size.0, pos.1 - proj.body.size.1);
+    let max = (pos.0 + size.0, pos.1 + size.1);
+    let start = proj.prev_pos;
+    let step = (proj.body.pos.0 - start.0, proj.body.pos.1 - start.1);
+    let entry = |start: f32, step: f32, min: f32, max: f32| {
+        if start < min {
+            (min - start) / step
+        } else if start > max {
+            (max - start) / step
+        } else {
+            0.0
+        }
+    };
+    entry(start.0, step.0, min.0, max.0)
+        .max(entry(start.1, step.1, min.1, max.1))
+        .clamp(0.0, 1.0)
+}
+
+// True if any enemy bullet's center is within `radius` of the player's hurtbox.
+pub fn bullet_near_player(state: &SimState, radius: f32) -> bool {
+    state
+        .projectiles
+        .iter()
+        .filter(|proj| !proj.player_spawned)
+        .any(|proj| distance_to_player(proj, &state.player) <= radius)
+}
+
+// Drops extra bullets in from the top of the screen, used when the versus
+// opponent clears a phase.
+pub fn spawn_garbage(state: &mut SimState, count: u16) {
+    for _ in 0..count {
+        let (width, height) = state.playfield.size;
+        let pos = (state.rng.gen_range(0.0..width - 64.0), height - 8.0);
+        let velocity = (state.rng.gen_range(-1.5..1.5), -state.rng.gen_range(4.0..7.0));
+        crate::make_projectile(
+            &mut state.projectiles,
+            state.sprite_holder.get_next_index_on(Layer::Projectiles),
+            pos,
+            velocity,
+        );
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{enemy_ai, fixtures, vulnerability, Enemy};
+
+    // A fixed, input-heavy script: sweep left and right while firing in bursts.
+    fn scripted_input(frame: u64) -> FrameInput {
+        FrameInput {
+            left: frame % 90 < 40,
+            right: frame % 90 >= 50,
+            shoot: frame % 7 < 3,
+            ability: frame % 400 == 200,
+            dash: frame % 90 == 45,
+        }
+    }
+
+    fn run(mode: usize, seed: u64, frames: u64) -> Vec<u64> {
+        let mut state = SimState::new(SpriteHolder::new(1000), seed);
+        match mode {
+            1 => crate::load_level_1(&mut state),
+            _ => crate::load_level_6(&mut state),
+        }
+        let mut checksums = vec![];
+        for frame in 0..frames {
+            state = simulate(state, scripted_input(frame));
+            checksums.push(state.checksum());
+        }
+        checksums
+    }
+
+    #[test]
+    fn replaying_the_same_inputs_never_diverges() {
+        for mode in [1, 6] {
+            let first = run(mode, 47, 1500);
+            let second = run(mode, 47, 1500);
+            let diverged = first.iter().zip(second.iter()).position(|(a, b)| a != b);
+            assert_eq!(diverged, None, "mode {} diverged at frame {:?}", mode, diverged);
+        }
+    }
+
+    #[test]
+    fn levels_clean_up_every_sprite() {
+        for mode in [1, 6] {
+            let mut state = SimState::new(SpriteHolder::new(1000), 47);
+            match mode {
+                1 => crate::load_level_1(&mut state),
+                _ => crate::load_level_6(&mut state),
+            }
+            for frame in 0..1500 {
+                state = simulate(state, scripted_input(frame));
+                let orphans = crate::sprite_audit::find_orphans(&state.sprite_holder, &state.sprite_indices());
+                assert_eq!(orphans, vec![], "mode {} leaked on frame {}", mode, frame);
+                let stale = crate::sprite_audit::find_stale(&state.sprite_holder, &state.sprite_indices());
+                assert_eq!(stale, vec![], "mode {} kept removed sprites on frame {}", mode, frame);
+                let violations = crate::invariants::find_violations(&state);
+                assert_eq!(violations, Vec::<String>::new(), "mode {} on frame {}", mode, frame);
+            }
+            crate::load_dead_level(&mut state);
+            let orphans = crate::sprite_audit::find_orphans(&state.sprite_holder, &state.sprite_indices());
+            assert_eq!(orphans, vec![], "mode {} leaked on unload", mode);
+        }
+    }
+
+    #[test]
+    fn danmaku_fires_on_a_cooldown_and_clears_on_time() {
+        let mut state = fixtures::boss_running(enemy_ai::Level0AI {});
+        let held = FrameInput {
+            shoot: true,
+            ..Default::default()
+        };
+        for _ in 0..30 {
+            state = simulate(state, held);
+        }
+        let volleys = state.sounds.iter().filter(|&&sound| sound == SoundId::PlayerShoot).count();
+        assert_eq!(volleys, 3);
+
+        for _ in 30..1799 {
+            state = simulate(state, FrameInput::default());
+        }
+        assert_eq!(state.trans_flag.val, 0);
+        state = simulate(state, FrameInput::default());
+        assert_eq!(state.trans_flag.val, 4);
+    }
+
+    // Counts every hook call, shared with the test since the AI is moved into the sim.
+    #[derive(Default)]
+    struct HookCounts {
+        spawns: u32,
+        damage: f32,
+        deaths: u32,
+    }
+
+    struct HookRecorder(std::rc::Rc<std::cell::RefCell<HookCounts>>);
+
+    impl enemy_ai::AI for HookRecorder {
+        fn ai_loop(&mut self, _spawns: &mut SpawnQueue, _enemy: &Enemy, _player: (f32, f32), _rng: &mut StdRng) {}
+
+        fn on_spawn(&mut self, _spawns: &mut SpawnQueue, _enemy: &Enemy, _rng: &mut StdRng) {
+            self.0.borrow_mut().spawns += 1;
+        }
+
+        fn on_damage(&mut self, amount: f32, _enemy: &Enemy) {
+            self.0.borrow_mut().damage += amount;
+        }
+
+        fn on_death(&mut self, _spawns: &mut SpawnQueue, _enemy: &Enemy, _rng: &mut StdRng) {
+            self.0.borrow_mut().deaths += 1;
+        }
+    }
+
+    #[test]
+    fn ai_hooks_hear_about_spawning_hits_and_death_once_each() {
+        let counts = std::rc::Rc::new(std::cell::RefCell::new(HookCounts::default()));
+        let mut state = fixtures::boss_running(HookRecorder(counts.clone()));
+        state.enemy.enemy.health_bar.currval = 2.0;
+
+        state = simulate(state, FrameInput::default());
+        state.enemy.enemy.hit(1.0, &mut state.hit_feedback);
+        // Turned away by the first hit's i-frames.
+        state.enemy.enemy.hit(1.0, &mut state.hit_feedback);
+        for _ in 0..vulnerability::ENEMY_COOLDOWN {
+            state = simulate(state, FrameInput::default());
+        }
+        assert_eq!((counts.borrow().spawns, counts.borrow().damage, counts.borrow().deaths), (1, 1.0, 0));
+
+        state.enemy.enemy.hit(1.0, &mut state.hit_feedback);
+        for _ in 0..vulnerability::ENEMY_COOLDOWN {
+            state = simulate(state, FrameInput::default());
+        }
+        assert_eq!((counts.borrow().spawns, counts.borrow().damage, counts.borrow().deaths), (1, 2.0, 1));
+
+        // Health stays at zero, and later hits don't kill it a second time.
+        for _ in 0..30 {
+            state.enemy.enemy.hit(1.0, &mut state.hit_feedback);
+            state = simulate(state, FrameInput::default());
+        }
+        assert_eq!(state.enemy.enemy.health_bar.currval, 0.0);
+        assert_eq!(counts.borrow().deaths, 1);
+    }
+
+    #[test]
+    fn catch_return_throws_every_enemy_bullet_back() {
+        let mut state = fixtures::loaded_with(crate::load_level_6, |state| {
+            state.shot.special = crate::shot_types::Special::CatchReturn;
+        });
+        state.enemy.ai = Box::new(enemy_ai::Level0AI {});
+        spawn_garbage(&mut state, 8);
+        state.catch_return.as_mut().unwrap().add_charge(1.0);
+
+        let ability = FrameInput {
+            ability: true,
+            ..Default::default()
+        };
+        state = simulate(state, ability);
+        assert!(!state.projectiles.is_empty());
+        assert!(state.projectiles.iter().all(|proj| proj.player_spawned));
+        assert!(state.projectiles.iter().all(|proj| proj.direction.1 > 0.0));
+        assert_eq!(state.catch_return.as_ref().unwrap().uses, 1);
+        assert!(state.time_freeze.is_none());
+    }
+
+    #[test]
+    fn a_wall_of_bullets_only_takes_one_hit() {
+        let mut state = fixtures::boss_running(enemy_ai::Level0AI {});
+        state.player_health_bar.currval = 3.0;
+        state.player_health_bar.maxval = 3.0;
+        // Both land on the player's top edge this frame, the second one a third of
+        // the way through the move and the first one right at the end.
+        let x = state.player.body.pos.0;
+        for y in [170.0, 166.0] {
+            fixtures::enemy_bullet(&mut state, (x, y), (0.0, -6.0));
+        }
+        let player = (state.player.body.pos, state.player.body.size);
+        let mut moved = state.projectiles.clone();
+        for proj in moved.iter_mut() {
+            proj.prev_pos = proj.body.pos;
+            proj.body.pos.1 -= 6.0;
+        }
+        assert_eq!(time_of_impact(&moved[0], player.0, player.1), 1.0);
+        assert!((time_of_impact(&moved[1], player.0, player.1) - 1.0 / 3.0).abs() < 1e-5);
+
+        state = simulate(state, FrameInput::default());
+        assert_eq!(state.player_health_bar.currval, 2.0);
+        assert!(state.projectiles.iter().all(|proj| proj.player_spawned));
+    }
+
+    #[test]
+    fn auto_bomb_spends_a_full_meter_instead_of_a_life() {
+        let mut state = fixtures::loaded_with(crate::load_level_6, |state| state.modifiers.auto_bomb = true);
+        state.enemy.ai = Box::new(enemy_ai::Level0AI {});
+        let health = state.player_health_bar.currval;
+        for height in [40.0, 42.0] {
+            fixtures::bullet_above_player(&mut state, height);
+        }
+        state = simulate(state, FrameInput::default());
+        assert_eq!(state.player_health_bar.currval, health);
+        assert_eq!(state.time_freeze.as_ref().unwrap().uses, 1);
+        assert!(state.respawn_bubble.is_none());
+
+        // The meter is empty now, so the next hit lands.
+        for _ in 0..30 {
+            state = simulate(state, FrameInput::default());
+        }
+        fixtures::bullet_above_player(&mut state, 40.0);
+        state = simulate(state, FrameInput::default());
+        assert_eq!(state.player_health_bar.currval, health - 1.0);
+    }
+
+    #[test]
+    fn losing_a_life_puts_up_a_bubble_that_pops_bullets() {
+        let mut state = fixtures::boss_running(enemy_ai::Level0AI {});
+        state.player_health_bar.currval = 3.0;
+        state.player_health_bar.maxval = 3.0;
+        fixtures::bullet_above_player(&mut state, 40.0);
+        state = simulate(state, FrameInput::default());
+        assert_eq!(state.player_health_bar.currval, 2.0);
+        assert!(state.respawn_bubble.is_some());
+
+        // Close enough to land next frame, but it pops on the bubble instead.
+        fixtures::bullet_above_player(&mut state, 70.0);
+        for _ in 0..40 {
+            state = simulate(state, FrameInput::default());
+        }
+        assert_eq!(state.player_health_bar.currval, 2.0);
+        assert!(state.projectiles.iter().all(|proj| proj.player_spawned));
+
+        for _ in 40..90 {
+            state = simulate(state, FrameInput::default());
+        }
+        assert!(state.respawn_bubble.is_none());
+    }
+
+    #[test]
+    fn minions_far_off_screen_keep_moving_but_hold_fire() {
+        let mut state = fixtures::boss_running(enemy_ai::Level0AI {});
+        let summon = enemy_ai::Summon {
+            pos: (400.0, state.playfield.size.1 + 600.0),
+            velocity: (0.0, -10.0),
+            strong: false,
+        };
+        state.minions.push(state.entity_pool.spawn_minion(summon, &mut state.sprite_holder));
+        // Would fire on its first frame if it were in view.
+        state.minions[0].ai = Box::new(enemy_ai::MinionAI { cooldown: 0, max_cooldown: 30 });
+        for _ in 0..20 {
+            state = simulate(state, FrameInput::default());
+        }
+        assert_eq!(state.minions[0].enemy.body.pos.1, summon.pos.1 - 200.0);
+        assert!(state.projectiles.iter().all(|proj| proj.player_spawned));
+
+        // In view, it fires on the same beat it would have kept all along.
+        for _ in 20..70 {
+            state = simulate(state, FrameInput::default());
+        }
+        assert!(!state.playfield.is_far_outside(&state.minions[0].enemy.body));
+        assert_eq!(fixtures::enemy_bullets(&state), 1);
+    }
+
+    #[test]
+    fn dropped_fruit_hurts_once_and_breaks_the_chain_gained_that_frame() {
+        let mut state = fixtures::loaded(crate::load_level_1);
+        state.enemy.ai = Box::new(enemy_ai::Level0AI {});
+        let health = state.player_health_bar.currval;
+        // One lands well away from the player, one falls into their hands.
+        let player = state.player.body.pos;
+        for pos in [(100.0, 2.0), (player.0, player.1 + 70.0)] {
+            fixtures::enemy_bullet(&mut state, pos, (0.0, -6.0));
+        }
+        state.player.chain = 5;
+
+        state = simulate(state, FrameInput::default());
+        assert_eq!(state.player_health_bar.currval, health - 1.0);
+        assert_eq!(state.player.charges, 1);
+        assert_eq!(state.player.chain, 0);
+        assert!(state.phase_hit);
+        assert_eq!(state.trans_flag.val, 0);
+    }
+
+    #[test]
+    fn enemies_and_the_survival_clock_wait_out_the_stage_countdown() {
+        let mut state = fixtures::loaded(crate::load_level_6);
+        let clock = state.survival_timer.as_ref().unwrap().frames_left();
+        for _ in 0..crate::stage_intro::COUNTDOWN_FRAMES {
+            state = simulate(state, FrameInput::default());
+        }
+        assert_eq!(fixtures::enemy_bullets(&state), 0);
+        assert_eq!(state.survival_timer.as_ref().unwrap().frames_left(), clock);
+        for _ in 0..120 {
+            state = simulate(state, FrameInput::default());
+        }
+        assert!(fixtures::enemy_bullets(&state) > 0);
+    }
+
+    #[test]
+    fn the_boss_and_the_midboss_timeline_wait_for_go() {
+        let mut state = fixtures::loaded(crate::load_level_1);
+        state.enemy.ai = Box::new(enemy_ai::Level0AI {});
+        let health = state.enemy.enemy.health_bar.currval;
+        // A shot sitting right on the boss goes straight through it.
+        let boss = state.enemy.enemy.body.pos;
+        let index = state.sprite_holder.get_next_index_on(Layer::Projectiles);
+        crate::make_player_projectile(&mut state.projectiles, index, boss, (0.0, 0.0));
+        for _ in 0..crate::stage_intro::COUNTDOWN_FRAMES {
+            state = simulate(state, FrameInput::default());
+        }
+        assert_eq!(state.enemy.enemy.health_bar.currval, health);
+        assert_eq!(state.fight_frame, 0);
+
+        // Level 1's mid-boss is due 1200 frames into the fight, not into the level.
+        for _ in 0..1200 {
+            state = simulate(state, FrameInput::default());
+        }
+        assert!(state.midboss.as_ref().unwrap().entity.is_none());
+        state = simulate(state, FrameInput::default());
+        assert!(state.midboss.as_ref().unwrap().entity.is_some());
+    }
+
+    #[test]
+    fn retrying_from_a_phase_starts_the_boss_and_clock_there() {
+        let mut state = fixtures::loaded(crate::load_level_6);
+        let clock = state.survival_timer.as_ref().unwrap().frames_left();
+        state.start_phase = Some(2);
+        crate::load_level_6(&mut state);
+        state.stage_intro = None;
+        state = simulate(state, FrameInput::default());
+        assert_eq!((state.phase, state.enemy.ai.phase()), (2, 2));
+        assert_eq!(state.phases_cleared, 0);
+        assert_eq!(state.survival_timer.as_ref().unwrap().frames_left(), clock - 1201);
+        assert!(!state.leaderboard_eligible);
+    }
+
+    #[test]
+    fn different_seeds_produce_different_games() {
+        assert_ne!(run(1, 1, 600).last(), run(1, 2, 600).last());
+    }
+}
@@ -0,0 +1,88 @@
+// Every enemy bullet starts life as a request here: AIs and bullet behaviours ask
+// for bullets while the projectile list is being walked, and the simulation turns
+// the requests into bullets once a frame, after everything has moved.
+use crate::projectile_behavior::ProjectileBehavior;
+use crate::projectile_tags::ProjectileTag;
+use crate::{make_projectile, Layer, Projectile, SpriteHolder};
+
+// How long a new enemy bullet takes to fade in, unless its request says otherwise.
+// Until then it can't hit, so one that appears on top of the player is dodgeable.
+pub const WARM_UP_FRAMES: u32 = 8;
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct SpawnRequest {
+    pub pos: (f32, f32),
+    pub velocity: (f32, f32),
+    pub behavior: ProjectileBehavior,
+    // Frames spent fading in, harmless, before it can hit anything.
+    pub warm_up: u32,
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct SpawnQueue {
+    // Each with the tag that was current when it was made.
+    requests: Vec<(SpawnRequest, ProjectileTag)>,
+    tag: ProjectileTag,
+}
+
+impl SpawnQueue {
+    pub fn push_with_behavior(&mut self, pos: (f32, f32), velocity: (f32, f32), behavior: ProjectileBehavior) {
+        self.push_request(SpawnRequest {
+            pos,
+            velocity,
+            behavior,
+            warm_up: WARM_UP_FRAMES,
+        });
+    }
+
+    pub fn push_request(&mut self, request: SpawnRequest) {
+        self.requests.push((request, self.tag));
+    }
+
+    // Tags every request from here on, until the next call. The simulation sets it
+    // before handing the queue to whatever is about to fire.
+    pub fn set_tag(&mut self, tag: ProjectileTag) {
+        self.tag = tag;
+    }
+
+    // Turns every request into an enemy bullet, in the order they were made.
+    pub fn flush(&mut self, projectiles: &mut Vec<Projectile>, sprite_holder: &mut SpriteHolder) {
+        for (mut request, tag) in self.requests.drain(..) {
+            make_projectile(projectiles, sprite_holder.get_next_index_on(Layer::Projectiles), request.pos, request.velocity);
+            request.behavior.claim_sprites(sprite_holder);
+            if let Some(proj) = projectiles.last_mut() {
+                sprite_holder.set_palette(proj.sprite_index, request.behavior.palette());
+                proj.behavior = request.behavior;
+                proj.warm_up = request.warm_up;
+                proj.tag = tag;
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::palettes::Palette;
+
+    #[test]
+    fn flush_keeps_request_order_and_claims_ring_sprites() {
+        let mut sprite_holder = SpriteHolder::new(16);
+        let mut projectiles = vec![];
+        let mut spawns = SpawnQueue::default();
+        spawns.push_with_behavior((0.0, 0.0), (1.0, 0.0), ProjectileBehavior::Straight);
+        spawns.push_with_behavior((10.0, 0.0), (0.0, -2.0), ProjectileBehavior::magnet());
+        spawns.flush(&mut projectiles, &mut sprite_holder);
+
+        assert!(spawns.requests.is_empty());
+        assert_eq!(projectiles.len(), 2);
+        assert_eq!((projectiles[0].speed, projectiles[0].direction), (1.0, (1.0, 0.0)));
+        assert_eq!((projectiles[1].speed, projectiles[1].direction), (2.0, (0.0, -1.0)));
+        assert_eq!(projectiles[0].warm_up, WARM_UP_FRAMES);
+        assert!(!projectiles[0].collidable());
+        let ring = projectiles[1].behavior.sprite_indices();
+        assert_eq!(ring.len(), 1);
+        assert_ne!(ring[0], projectiles[1].sprite_index);
+        assert_eq!(sprite_holder.palettes[projectiles[1].sprite_index.index()], Palette::Gold as u32);
+    }
+}
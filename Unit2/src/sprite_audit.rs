@@ -0,0 +1,84 @@
+// Leak check for the sprite holder. Every frame, the slots marked active are
+// compared with the sprite handles that live objects say they own. A slot that
+// is active but owned by nobody is an orphan: something was killed or cleared
+// without removing its sprite, and that slot will never be handed out again.
+// A handle whose sprite was already removed is stale: its owner outlived it.
+use crate::game_state::GameState;
+use crate::{SpriteHandle, SpriteHolder};
+
+// Active slots that nothing in `owned` accounts for, lowest first.
+pub fn find_orphans(sprite_holder: &SpriteHolder, owned: &[SpriteHandle]) -> Vec<usize> {
+    let mut is_owned = vec![false; sprite_holder.active.len()];
+    for &handle in owned.iter().filter(|&&handle| sprite_holder.is_live(handle)) {
+        is_owned[handle.index()] = true;
+    }
+    sprite_holder
+        .active
+        .iter()
+        .enumerate()
+        .filter(|&(index, &active)| active && !is_owned[index])
+        .map(|(index, _)| index)
+        .collect()
+}
+
+// Handles in `owned` to sprites that have since been removed.
+pub fn find_stale(sprite_holder: &SpriteHolder, owned: &[SpriteHandle]) -> Vec<SpriteHandle> {
+    owned.iter().copied().filter(|&handle| !sprite_holder.is_live(handle)).collect()
+}
+
+// Logs any orphans. Debug builds panic instead, so leaks get fixed where they start.
+// Stale handles are only logged: the holder already ignores them.
+pub fn check(sprite_holder: &SpriteHolder, owned: &[SpriteHandle], game_state: GameState) {
+    let stale = find_stale(sprite_holder, owned);
+    if !stale.is_empty() {
+        log::error!("Stale sprite handles in state {:?}: {:?}", game_state, stale);
+    }
+    let orphans = find_orphans(sprite_holder, owned);
+    if orphans.is_empty() {
+        return;
+    }
+    log::error!("Orphaned sprite slots in state {:?}: {:?}", game_state, orphans);
+    if cfg!(debug_assertions) {
+        panic!("Sprite leak: slots {:?} are active but not owned by anything", orphans);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::GPUSprite;
+    use bytemuck::Zeroable;
+
+    #[test]
+    fn a_removed_handle_cant_touch_the_slots_next_owner() {
+        let mut sprite_holder = SpriteHolder::new(4);
+        let old = sprite_holder.get_next_index();
+        sprite_holder.remove_sprite(old);
+        let new = sprite_holder.get_next_index();
+        assert_eq!(new.index(), old.index());
+
+        let mut sprite = GPUSprite::zeroed();
+        sprite.screen_region = [1.0, 2.0, 3.0, 4.0];
+        sprite_holder.set_sprite(new, sprite);
+        sprite_holder.set_sprite(old, GPUSprite::zeroed());
+        sprite_holder.remove_sprite(old);
+        assert!(sprite_holder.is_live(new));
+        assert_eq!(sprite_holder.sprites[new.index()].screen_region, sprite.screen_region);
+        assert_eq!(find_stale(&sprite_holder, &[old, new]), vec![old]);
+        assert_eq!(find_orphans(&sprite_holder, &[old]), vec![new.index()]);
+    }
+
+    #[test]
+    fn a_full_holder_grows_instead_of_reusing_a_slot() {
+        let mut sprite_holder = SpriteHolder::new(2);
+        let first = sprite_holder.get_next_index();
+        let second = sprite_holder.get_next_index();
+        let third = sprite_holder.get_next_index();
+        assert_eq!(sprite_holder.sprites.len(), 4);
+        assert_eq!(third.index(), 2);
+        for handle in [first, second, third] {
+            assert!(sprite_holder.is_live(handle));
+        }
+        assert!(find_orphans(&sprite_holder, &[first, second, third]).is_empty());
+    }
+}
@@ -0,0 +1,248 @@
+// The GPU side of the sprite holder: one buffer each for sprites, tints and
+// palettes, and the bind group the sprite pipeline reads them through. The holder
+// grows when it runs out of slots, so these get rebuilt to match when it does.
+// Sprites are uploaded sorted by layer rather than in slot order, so instanced
+// drawing puts the background at the back and the UI in front. The UI comes last
+// so it can be drawn through its own camera, one that pans, zooms and shakes
+// don't touch.
+use bytemuck::Zeroable;
+
+use crate::{merge_spans, GPUCamera, GPUSprite, Layer, SpriteHandle, SpriteHolder, TINT_NONE, USE_STORAGE};
+
+// The two views sprites are drawn through: the camera's for the world, and the
+// playfield's resting one for anything fixed to the screen.
+pub struct CameraBuffers {
+    world: wgpu::Buffer,
+    screen: wgpu::Buffer,
+}
+
+impl CameraBuffers {
+    pub fn new(device: &wgpu::Device) -> Self {
+        let create = || {
+            device.create_buffer(&wgpu::BufferDescriptor {
+                label: None,
+                size: std::mem::size_of::<GPUCamera>() as u64,
+                usage: wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST,
+                mapped_at_creation: false,
+            })
+        };
+        Self {
+            world: create(),
+            screen: create(),
+        }
+    }
+
+    pub fn write(&self, queue: &wgpu::Queue, world: GPUCamera, screen: GPUCamera) {
+        queue.write_buffer(&self.world, 0, bytemuck::bytes_of(&world));
+        queue.write_buffer(&self.screen, 0, bytemuck::bytes_of(&screen));
+    }
+}
+
+pub struct SpriteBuffers {
+    pub sprites: wgpu::Buffer,
+    pub tints: wgpu::Buffer,
+    pub palettes: wgpu::Buffer,
+    // The same buffers seen through the world camera, and through the screen's.
+    pub bind_group: wgpu::BindGroup,
+    pub screen_bind_group: wgpu::BindGroup,
+    // How many sprites the buffers have room for.
+    capacity: usize,
+    // Slot at each draw position, and the other way round.
+    order: Vec<usize>,
+    positions: Vec<usize>,
+    // Draw position of the first UI sprite; everything from there on is on the UI layer.
+    screen_start: usize,
+    // CPU copies of the buffers, in draw order, so runs of positions can be
+    // written in one go.
+    staged_sprites: Vec<GPUSprite>,
+    staged_tints: Vec<[f32; 4]>,
+    staged_palettes: Vec<u32>,
+}
+
+impl SpriteBuffers {
+    pub fn new(
+        device: &wgpu::Device,
+        layout: &wgpu::BindGroupLayout,
+        cameras: &CameraBuffers,
+        capacity: usize,
+    ) -> Self {
+        let create = |element_size: usize| {
+            device.create_buffer(&wgpu::BufferDescriptor {
+                label: None,
+                size: (capacity * element_size) as u64,
+                usage: if USE_STORAGE {
+                    wgpu::BufferUsages::STORAGE
+                } else {
+                    wgpu::BufferUsages::VERTEX
+                } | wgpu::BufferUsages::COPY_DST,
+                mapped_at_creation: false,
+            })
+        };
+        let sprites = create(std::mem::size_of::<GPUSprite>());
+        let tints = create(std::mem::size_of::<[f32; 4]>());
+        let palettes = create(std::mem::size_of::<u32>());
+        let bind_group = |camera: &wgpu::Buffer| {
+            let camera_entry = wgpu::BindGroupEntry {
+                binding: 0,
+                resource: camera.as_entire_binding(),
+            };
+            if USE_STORAGE {
+                device.create_bind_group(&wgpu::BindGroupDescriptor {
+                    label: None,
+                    layout,
+                    entries: &[
+                        camera_entry,
+                        wgpu::BindGroupEntry {
+                            binding: 1,
+                            resource: sprites.as_entire_binding(),
+                        },
+                        wgpu::BindGroupEntry {
+                            binding: 2,
+                            resource: tints.as_entire_binding(),
+                        },
+                        wgpu::BindGroupEntry {
+                            binding: 3,
+                            resource: palettes.as_entire_binding(),
+                        },
+                    ],
+                })
+            } else {
+                device.create_bind_group(&wgpu::BindGroupDescriptor {
+                    label: None,
+                    layout,
+                    entries: &[camera_entry],
+                })
+            }
+        };
+        Self {
+            bind_group: bind_group(&cameras.world),
+            screen_bind_group: bind_group(&cameras.screen),
+            sprites,
+            tints,
+            palettes,
+            capacity,
+            order: vec![],
+            positions: vec![],
+            screen_start: 0,
+            staged_sprites: vec![GPUSprite::zeroed(); capacity],
+            staged_tints: vec![TINT_NONE; capacity],
+            staged_palettes: vec![0; capacity],
+        }
+    }
+
+    // Where a sprite sits in the buffers, for drawing it on its own.
+    pub fn position(&self, handle: SpriteHandle) -> u32 {
+        self.positions[handle.index()] as u32
+    }
+
+    // Draw positions of the sprites that move with the camera, and of the UI.
+    pub fn world_range(&self) -> std::ops::Range<u32> {
+        0..self.screen_start as u32
+    }
+    pub fn screen_range(&self) -> std::ops::Range<u32> {
+        self.screen_start as u32..self.order.len() as u32
+    }
+
+    // Sends the slots changed since the last upload to the GPU, along with any
+    // that moved because something changed layer. If the holder has grown since,
+    // the buffers are reallocated first and everything goes up.
+    pub fn upload(
+        &mut self,
+        device: &wgpu::Device,
+        queue: &wgpu::Queue,
+        layout: &wgpu::BindGroupLayout,
+        cameras: &CameraBuffers,
+        sprite_holder: &mut SpriteHolder,
+    ) {
+        if sprite_holder.sprites.len() > self.capacity {
+            log::info!("Reallocating sprite buffers for {} sprites", sprite_holder.sprites.len());
+            *self = Self::new(device, layout, cameras, sprite_holder.sprites.len());
+            sprite_holder.dirty.fill(true);
+        }
+
+        let mut dirty = vec![];
+        if sprite_holder.take_layers_changed() || self.order.len() != sprite_holder.sprites.len() {
+            let order = draw_order(&sprite_holder.layers);
+            dirty.extend((0..order.len()).filter(|&position| self.order.get(position) != Some(&order[position])));
+            self.positions = vec![0; order.len()];
+            for (position, &slot) in order.iter().enumerate() {
+                self.positions[slot] = position;
+            }
+            self.order = order;
+            self.screen_start = sprite_holder.layers.iter().filter(|&&layer| layer != Layer::Ui).count();
+        }
+        for span in sprite_holder.take_dirty_spans() {
+            dirty.extend(span.map(|slot| self.positions[slot]));
+        }
+        dirty.sort_unstable();
+        dirty.dedup();
+
+        for &position in dirty.iter() {
+            let slot = self.order[position];
+            self.staged_sprites[position] = sprite_holder.sprites[slot];
+            self.staged_tints[position] = sprite_holder.tints[slot];
+            self.staged_palettes[position] = sprite_holder.palettes[slot];
+        }
+        for span in merge_spans(dirty) {
+            write_span(queue, &self.sprites, &self.staged_sprites, span.clone());
+            write_span(queue, &self.tints, &self.staged_tints, span.clone());
+            write_span(queue, &self.palettes, &self.staged_palettes, span);
+        }
+    }
+}
+
+// Every slot, back layer first. Within a layer they keep slot order.
+fn draw_order(layers: &[Layer]) -> Vec<usize> {
+    let mut order: Vec<usize> = (0..layers.len()).collect();
+    order.sort_by_key(|&slot| layers[slot] as usize);
+    order
+}
+
+// Every element type here is a multiple of 4 bytes, so any span satisfies wgpu's
+// copy alignment.
+fn write_span<T: bytemuck::Pod>(queue: &wgpu::Queue, buffer: &wgpu::Buffer, data: &[T], span: std::ops::Range<usize>) {
+    let offset = (span.start * std::mem::size_of::<T>()) as u64;
+    queue.write_buffer(buffer, offset, bytemuck::cast_slice(&data[span]));
+}
+
+#[cfg(test)]
+mod tests {
+    use super::draw_order;
+    use crate::{GPUSprite, Layer, SpriteHolder};
+    use bytemuck::Zeroable;
+
+    #[test]
+    fn layers_draw_back_to_front_and_keep_slot_order_within_one() {
+        let mut sprite_holder = SpriteHolder::new(6);
+        assert!(sprite_holder.take_layers_changed());
+        for layer in [Layer::Ui, Layer::Projectiles, Layer::Entities, Layer::Projectiles, Layer::Background] {
+            sprite_holder.get_next_index_on(layer);
+        }
+        assert!(sprite_holder.take_layers_changed());
+        // The unused slot is on the default layer.
+        assert_eq!(draw_order(&sprite_holder.layers), [4, 2, 5, 1, 3, 0]);
+
+        let last = sprite_holder.get_next_index();
+        assert_eq!(last.index(), 5);
+        assert!(!sprite_holder.take_layers_changed());
+    }
+
+    #[test]
+    fn only_changed_slots_are_uploaded() {
+        let mut sprite_holder = SpriteHolder::new(64);
+        let handles: Vec<_> = (0..40).map(|_| sprite_holder.get_next_index()).collect();
+        // Brand new, so all of it needs uploading once.
+        let spans = sprite_holder.take_dirty_spans();
+        assert_eq!((spans.len(), spans[0].clone()), (1, 0..64));
+        assert!(sprite_holder.take_dirty_spans().is_empty());
+
+        let mut sprite = GPUSprite::zeroed();
+        sprite.screen_region = [1.0, 1.0, 1.0, 1.0];
+        sprite_holder.set_sprite(handles[2], sprite);
+        sprite_holder.set_tint(handles[5], [0.5; 4]);
+        sprite_holder.remove_sprite(handles[30]);
+        // Writing what's already there doesn't count.
+        sprite_holder.set_sprite(handles[20], GPUSprite::zeroed());
+        assert_eq!(sprite_holder.take_dirty_spans(), vec![2..6, 30..31]);
+    }
+}
@@ -0,0 +1,123 @@
+// The "STAGE 1 - NAME" banner and 3-2-1-GO countdown at the start of a stage. A
+// level turns it on with `intro: Some((stage: 1, name: "..."))`. The countdown
+// lives on the SimState and is ticked with it, a second of game time per cue.
+// Until GO the enemies hold their fire and can't be hurt, and the survival clock
+// and the level's own timeline wait, so it stays in step with replays, rewinds
+// and versus. The banner itself is only for show and lives outside the sim, put
+// up by the state's enter hook.
+use serde::Deserialize;
+
+use crate::clock::FRAMES_PER_SECOND;
+use crate::text::TextLabel;
+use crate::tween::{lerp, Easing};
+use crate::{SpriteHandle, SpriteHolder};
+
+const STEP_FRAMES: u64 = FRAMES_PER_SECOND;
+// Enemies start firing as GO goes up.
+pub const COUNTDOWN_FRAMES: u64 = 3 * STEP_FRAMES;
+// What the countdown shows from which frame, until the next cue's. The empty one
+// takes the banner down.
+const CUES: [(u64, &str); 5] = [
+    (0, "3"),
+    (STEP_FRAMES, "2"),
+    (2 * STEP_FRAMES, "1"),
+    (COUNTDOWN_FRAMES, "GO"),
+    (COUNTDOWN_FRAMES + STEP_FRAMES, ""),
+];
+const SLIDE_FRAMES: u64 = 24;
+const TITLE_SCALE: f32 = 5.0;
+const TITLE_Y: f32 = 520.0;
+const CUE_SCALE: f32 = 9.0;
+const CUE_Y: f32 = 340.0;
+const SCREEN_WIDTH: f32 = 1024.0;
+const TITLE_TINT: [f32; 4] = [1.0, 1.0, 1.0, 1.0];
+const CUE_TINT: [f32; 4] = [1.0, 0.85, 0.3, 1.0];
+const GO_TINT: [f32; 4] = [0.4, 1.0, 0.5, 1.0];
+
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+pub struct StageIntro {
+    pub stage: u32,
+    pub name: String,
+    // Sim frames since the level started.
+    #[serde(skip)]
+    elapsed: u64,
+}
+
+impl StageIntro {
+    // Once per sim frame.
+    pub fn tick(&mut self) {
+        self.elapsed += 1;
+    }
+
+    pub fn holding_fire(&self) -> bool {
+        self.elapsed < COUNTDOWN_FRAMES
+    }
+
+    pub fn elapsed(&self) -> u64 {
+        self.elapsed
+    }
+
+    fn title(&self) -> String {
+        format!("STAGE {} - {}", self.stage, self.name)
+    }
+}
+
+// The banner and countdown on screen, drawn from the sim's countdown.
+#[derive(Default)]
+pub struct IntroBanner {
+    title: Option<TextLabel>,
+    // The cue showing, by its place in CUES.
+    cue: Option<(usize, TextLabel)>,
+}
+
+impl IntroBanner {
+    pub fn show(&mut self, intro: &StageIntro, sprite_holder: &mut SpriteHolder) {
+        self.clear(sprite_holder);
+        self.title = Some(TextLabel::new(&intro.title(), sprite_holder));
+    }
+
+    pub fn update(&mut self, intro: Option<&StageIntro>, sprite_holder: &mut SpriteHolder) {
+        let (Some(title), Some(intro)) = (&self.title, intro) else {
+            return;
+        };
+        let frame = intro.elapsed();
+        let showing = CUES.iter().rposition(|&(at, _)| frame >= at).unwrap_or(0);
+        if CUES[showing].1.is_empty() {
+            self.clear(sprite_holder);
+            return;
+        }
+
+        // Slides in from the left, then stays put.
+        let width = title.width(TITLE_SCALE);
+        let t = Easing::OutCubic.apply(frame as f32 / SLIDE_FRAMES as f32);
+        title.draw((lerp(-width, (SCREEN_WIDTH - width) / 2.0, t), TITLE_Y), TITLE_SCALE, TITLE_TINT, sprite_holder);
+
+        if self.cue.as_ref().map(|(cue, _)| *cue) != Some(showing) {
+            if let Some((_, label)) = self.cue.take() {
+                label.remove(sprite_holder);
+            }
+            self.cue = Some((showing, TextLabel::new(CUES[showing].1, sprite_holder)));
+        }
+        if let Some((_, label)) = &self.cue {
+            let tint = if frame >= COUNTDOWN_FRAMES { GO_TINT } else { CUE_TINT };
+            label.draw(((SCREEN_WIDTH - label.width(CUE_SCALE)) / 2.0, CUE_Y), CUE_SCALE, tint, sprite_holder);
+        }
+    }
+
+    pub fn sprite_indices(&self) -> Vec<SpriteHandle> {
+        let mut indices = self.title.as_ref().map_or(vec![], TextLabel::sprite_indices);
+        if let Some((_, label)) = &self.cue {
+            indices.extend(label.sprite_indices());
+        }
+        indices
+    }
+
+    pub fn clear(&mut self, sprite_holder: &mut SpriteHolder) {
+        if let Some(title) = self.title.take() {
+            title.remove(sprite_holder);
+        }
+        if let Some((_, label)) = self.cue.take() {
+            label.remove(sprite_holder);
+        }
+    }
+}
@@ -0,0 +1,82 @@
+// The danmaku level's clear condition: last until the clock runs out. It counts
+// down in whole seconds in the frame above the playfield.
+use crate::clock::FRAMES_PER_SECOND;
+use crate::playfield::Playfield;
+use crate::text::TextLabel;
+use crate::{SpriteHandle, SpriteHolder};
+
+const SCALE: f32 = 4.0;
+// Above the top edge of the playfield.
+const LABEL_GAP: f32 = 40.0;
+const TINT: [f32; 4] = [1.0, 1.0, 1.0, 1.0];
+// Last few seconds go red.
+const WARNING_SECONDS: u64 = 5;
+const WARNING_TINT: [f32; 4] = [1.6, 0.4, 0.4, 1.0];
+
+pub struct SurvivalTimer {
+    frames: u32,
+    frames_left: u32,
+    // The seconds `label` was made for, so it's only rebuilt when they change.
+    shown_seconds: u64,
+    label: TextLabel,
+}
+
+impl SurvivalTimer {
+    pub fn new(frames: u32, sprite_holder: &mut SpriteHolder) -> Self {
+        let shown_seconds = seconds_left(frames);
+        Self {
+            frames,
+            frames_left: frames,
+            shown_seconds,
+            label: TextLabel::new(&shown_seconds.to_string(), sprite_holder),
+        }
+    }
+
+    // Counts down one frame. True on the frame the clock runs out.
+    pub fn tick(&mut self) -> bool {
+        if self.frames_left == 0 {
+            return false;
+        }
+        self.frames_left -= 1;
+        self.frames_left == 0
+    }
+
+    // Runs the clock down without ticking through it. Never finishes it.
+    pub fn skip(&mut self, frames: u32) {
+        self.frames_left = self.frames_left.saturating_sub(frames).max(1);
+    }
+
+    pub fn frames_left(&self) -> u32 {
+        self.frames_left
+    }
+
+    // 0.0 at the start, 1.0 once it's run out.
+    pub fn progress(&self) -> f32 {
+        1.0 - self.frames_left as f32 / self.frames as f32
+    }
+
+    pub fn sync_sprites(&mut self, playfield: &Playfield, sprite_holder: &mut SpriteHolder) {
+        let seconds = seconds_left(self.frames_left);
+        if seconds != self.shown_seconds {
+            let label = std::mem::replace(&mut self.label, TextLabel::new(&seconds.to_string(), sprite_holder));
+            label.remove(sprite_holder);
+            self.shown_seconds = seconds;
+        }
+        let tint = if seconds <= WARNING_SECONDS { WARNING_TINT } else { TINT };
+        let x = (playfield.size.0 - self.label.width(SCALE)) / 2.0;
+        self.label.draw((x, playfield.size.1 + LABEL_GAP), SCALE, tint, sprite_holder);
+    }
+
+    pub fn sprite_indices(&self) -> Vec<SpriteHandle> {
+        self.label.sprite_indices()
+    }
+
+    pub fn remove(self, sprite_holder: &mut SpriteHolder) {
+        self.label.remove(sprite_holder);
+    }
+}
+
+// Rounded up, so it reads 0 only once time is actually up.
+fn seconds_left(frames: u32) -> u64 {
+    (frames as u64).div_ceil(FRAMES_PER_SECOND)
+}
@@ -0,0 +1,74 @@
+// Warnings drawn ahead of an attack so it can be read before it lands: a ring that
+// closes in on the spot and is gone when the attack goes off. AIs ask for them
+// through `AI::take_telegraphs`. They're only drawn and never affect gameplay.
+use crate::generated_sprites::RING_CELL;
+use crate::{GPUSprite, SpriteHandle, SpriteHolder, SPRITE_SHEET_RESOLUTION};
+
+// Ring size at the start and end of the warning.
+const RING_SIZE: (f32, f32) = (192.0, 48.0);
+const TINT: [f32; 4] = [1.6, 0.3, 0.3, 1.0];
+
+// A warning an AI wants shown: centred on `pos`, lasting until `frames` from now.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Telegraph {
+    pub pos: (f32, f32),
+    pub frames: u32,
+}
+
+struct Marker {
+    telegraph: Telegraph,
+    age: u32,
+    sprite_index: SpriteHandle,
+}
+
+#[derive(Default)]
+pub struct Telegraphs {
+    markers: Vec<Marker>,
+}
+
+impl Telegraphs {
+    pub fn add(&mut self, telegraph: Telegraph, sprite_holder: &mut SpriteHolder) {
+        self.markers.push(Marker {
+            telegraph,
+            age: 0,
+            sprite_index: sprite_holder.get_next_index(),
+        });
+    }
+
+    pub fn sync_sprites(&mut self, sprite_holder: &mut SpriteHolder) {
+        for marker in self.markers.iter_mut() {
+            marker.age += 1;
+            let t = marker.age as f32 / marker.telegraph.frames as f32;
+            let size = RING_SIZE.0 + (RING_SIZE.1 - RING_SIZE.0) * t;
+            let (x, y) = marker.telegraph.pos;
+            sprite_holder.set_sprite(
+                marker.sprite_index,
+                GPUSprite {
+                    screen_region: [x - size / 2.0, y - size / 2.0, size, size],
+                    sheet_region: [
+                        RING_CELL.0 / SPRITE_SHEET_RESOLUTION.0,
+                        RING_CELL.1 / SPRITE_SHEET_RESOLUTION.1,
+                        1.0 / SPRITE_SHEET_RESOLUTION.0,
+                        1.0 / SPRITE_SHEET_RESOLUTION.1,
+                    ],
+                },
+            );
+            // Fades in, so it's brightest just before the attack.
+            sprite_holder.set_tint(marker.sprite_index, [TINT[0], TINT[1], TINT[2], t.min(1.0)]);
+            if marker.age >= marker.telegraph.frames {
+                sprite_holder.remove_sprite(marker.sprite_index);
+            }
+        }
+        self.markers.retain(|marker| marker.age < marker.telegraph.frames);
+    }
+
+    pub fn sprite_indices(&self) -> Vec<SpriteHandle> {
+        self.markers.iter().map(|marker| marker.sprite_index).collect()
+    }
+
+    pub fn clear(&mut self, sprite_holder: &mut SpriteHolder) {
+        for marker in self.markers.drain(..) {
+            sprite_holder.remove_sprite(marker.sprite_index);
+        }
+    }
+}
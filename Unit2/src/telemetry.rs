@@ -0,0 +1,128 @@
+// Balancing data for pattern designers. Launch with `--telemetry <dir>` and every
+// stage writes a JSON report to that directory when it ends, with one entry per
+// boss pattern: how long it lasted, how often it hit the player and how crowded
+// the screen got over time.
+use std::path::PathBuf;
+use web_time::{SystemTime, UNIX_EPOCH};
+
+use crate::simulation::SimState;
+
+// Bullet counts are sampled this often, which is plenty for a density graph.
+const DENSITY_INTERVAL: u64 = 30;
+
+struct PatternStats {
+    name: &'static str,
+    frames_alive: u64,
+    hits_taken: u32,
+    // Time-freeze activations.
+    bombs_used: u32,
+    // Enemy bullets on screen, every DENSITY_INTERVAL frames.
+    density: Vec<usize>,
+}
+
+pub struct Telemetry {
+    dir: PathBuf,
+    mode: usize,
+    phase: usize,
+    last_health: f32,
+    last_bombs: u32,
+    patterns: Vec<PatternStats>,
+}
+
+impl Telemetry {
+    // Looks for `--telemetry <dir>` in the command line.
+    pub fn from_args(mut args: impl Iterator<Item = String>) -> Option<Telemetry> {
+        args.find(|arg| arg == "--telemetry")?;
+        let dir = PathBuf::from(args.next()?);
+        log::info!("Writing pattern telemetry to {}", dir.display());
+        Some(Telemetry {
+            dir,
+            mode: 0,
+            phase: 0,
+            last_health: 0.0,
+            last_bombs: 0,
+            patterns: vec![],
+        })
+    }
+
+    // Call after every simulated frame.
+    pub fn record(&mut self, sim: &SimState) {
+        // First frame of a new stage, or the boss moved on to its next pattern.
+        if sim.frame == 1 {
+            self.patterns.clear();
+            self.mode = sim.mode;
+            self.last_health = sim.player_health_bar.currval;
+            self.last_bombs = 0;
+        }
+        if self.patterns.is_empty() || sim.phase != self.phase {
+            self.phase = sim.phase;
+            self.patterns.push(PatternStats {
+                name: sim.enemy.ai.phase_name(),
+                frames_alive: 0,
+                hits_taken: 0,
+                bombs_used: 0,
+                density: vec![],
+            });
+        }
+
+        let Some(stats) = self.patterns.last_mut() else {
+            return;
+        };
+        if stats.frames_alive.is_multiple_of(DENSITY_INTERVAL) {
+            stats
+                .density
+                .push(sim.projectiles.iter().filter(|proj| !proj.player_spawned).count());
+        }
+        stats.frames_alive += 1;
+        if sim.player_health_bar.currval < self.last_health {
+            stats.hits_taken += 1;
+        }
+        self.last_health = sim.player_health_bar.currval;
+
+        let bombs = sim.time_freeze.as_ref().map_or(0, |time_freeze| time_freeze.uses)
+            + sim.catch_return.as_ref().map_or(0, |catch_return| catch_return.uses);
+        stats.bombs_used += bombs.saturating_sub(self.last_bombs);
+        self.last_bombs = bombs;
+    }
+
+    // Writes the report for the stage that just ended.
+    pub fn finish(&mut self, outcome: &str) {
+        if self.patterns.is_empty() {
+            return;
+        }
+        let patterns: Vec<String> = self
+            .patterns
+            .drain(..)
+            .map(|stats| {
+                let density: Vec<String> = stats.density.iter().map(|n| n.to_string()).collect();
+                format!(
+                    "{{\"pattern\":\"{}\",\"frames_alive\":{},\"hits_taken\":{},\"bombs_used\":{},\"peak_bullets\":{},\"bullet_density\":[{}]}}",
+                    stats.name,
+                    stats.frames_alive,
+                    stats.hits_taken,
+                    stats.bombs_used,
+                    stats.density.iter().max().unwrap_or(&0),
+                    density.join(","),
+                )
+            })
+            .collect();
+        let json = format!(
+            "{{\"mode\":{},\"outcome\":\"{}\",\"density_interval\":{},\"patterns\":[\n  {}\n]}}\n",
+            self.mode,
+            outcome,
+            DENSITY_INTERVAL,
+            patterns.join(",\n  "),
+        );
+
+        let stamp = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map(|d| d.as_secs())
+            .unwrap_or(0);
+        let path = self.dir.join(format!("stage{}-{}.json", self.mode, stamp));
+        let result = std::fs::create_dir_all(&self.dir).and_then(|_| std::fs::write(&path, json));
+        match result {
+            Ok(()) => log::info!("Wrote telemetry report {}", path.display()),
+            Err(e) => log::warn!("Couldn't write telemetry report {}: {}", path.display(), e),
+        }
+    }
+}
@@ -0,0 +1,163 @@
+// Bitmap text drawn with ordinary sprites. The spritesheet has no font, so a small
+// 5x7 one is baked into two extra rows of 8x8 cells appended below the sheet at load.
+use image::RgbaImage;
+
+use crate::{atlas, GPUSprite, Layer, SpriteHandle, SpriteHolder, SPRITE_SHEET_RESOLUTION};
+
+// Height of the sheet as drawn by the artists, in 16px cells. The font starts below it.
+const FONT_FIRST_ROW: f32 = atlas::SHEET_ROWS;
+const GLYPH_PIXELS: u32 = 8;
+const GLYPHS_PER_ROW: u32 = 24;
+// Glyph cells are half a sheet cell in each direction.
+const GLYPH_CELL: f32 = 0.5;
+
+// Rows top to bottom, 5 bits each with the leftmost pixel in bit 4.
+// Lowercase letters are drawn as uppercase; anything missing draws as a gap.
+const FONT: [(char, [u8; 7]); 45] = [
+    ('A', [0b01110, 0b10001, 0b10001, 0b11111, 0b10001, 0b10001, 0b10001]),
+    ('B', [0b11110, 0b10001, 0b10001, 0b11110, 0b10001, 0b10001, 0b11110]),
+    ('C', [0b01110, 0b10001, 0b10000, 0b10000, 0b10000, 0b10001, 0b01110]),
+    ('D', [0b11110, 0b10001, 0b10001, 0b10001, 0b10001, 0b10001, 0b11110]),
+    ('E', [0b11111, 0b10000, 0b10000, 0b11110, 0b10000, 0b10000, 0b11111]),
+    ('F', [0b11111, 0b10000, 0b10000, 0b11110, 0b10000, 0b10000, 0b10000]),
+    ('G', [0b01110, 0b10001, 0b10000, 0b10111, 0b10001, 0b10001, 0b01111]),
+    ('H', [0b10001, 0b10001, 0b10001, 0b11111, 0b10001, 0b10001, 0b10001]),
+    ('I', [0b01110, 0b00100, 0b00100, 0b00100, 0b00100, 0b00100, 0b01110]),
+    ('J', [0b00111, 0b00010, 0b00010, 0b00010, 0b00010, 0b10010, 0b01100]),
+    ('K', [0b10001, 0b10010, 0b10100, 0b11000, 0b10100, 0b10010, 0b10001]),
+    ('L', [0b10000, 0b10000, 0b10000, 0b10000, 0b10000, 0b10000, 0b11111]),
+    ('M', [0b10001, 0b11011, 0b10101, 0b10101, 0b10001, 0b10001, 0b10001]),
+    ('N', [0b10001, 0b10001, 0b11001, 0b10101, 0b10011, 0b10001, 0b10001]),
+    ('O', [0b01110, 0b10001, 0b10001, 0b10001, 0b10001, 0b10001, 0b01110]),
+    ('P', [0b11110, 0b10001, 0b10001, 0b11110, 0b10000, 0b10000, 0b10000]),
+    ('Q', [0b01110, 0b10001, 0b10001, 0b10001, 0b10101, 0b10010, 0b01101]),
+    ('R', [0b11110, 0b10001, 0b10001, 0b11110, 0b10100, 0b10010, 0b10001]),
+    ('S', [0b01111, 0b10000, 0b10000, 0b01110, 0b00001, 0b00001, 0b11110]),
+    ('T', [0b11111, 0b00100, 0b00100, 0b00100, 0b00100, 0b00100, 0b00100]),
+    ('U', [0b10001, 0b10001, 0b10001, 0b10001, 0b10001, 0b10001, 0b01110]),
+    ('V', [0b10001, 0b10001, 0b10001, 0b10001, 0b10001, 0b01010, 0b00100]),
+    ('W', [0b10001, 0b10001, 0b10001, 0b10101, 0b10101, 0b10101, 0b01010]),
+    ('X', [0b10001, 0b10001, 0b01010, 0b00100, 0b01010, 0b10001, 0b10001]),
+    ('Y', [0b10001, 0b10001, 0b01010, 0b00100, 0b00100, 0b00100, 0b00100]),
+    ('Z', [0b11111, 0b00001, 0b00010, 0b00100, 0b01000, 0b10000, 0b11111]),
+    ('0', [0b01110, 0b10001, 0b10011, 0b10101, 0b11001, 0b10001, 0b01110]),
+    ('1', [0b00100, 0b01100, 0b00100, 0b00100, 0b00100, 0b00100, 0b01110]),
+    ('2', [0b01110, 0b10001, 0b00001, 0b00010, 0b00100, 0b01000, 0b11111]),
+    ('3', [0b11111, 0b00010, 0b00100, 0b00010, 0b00001, 0b10001, 0b01110]),
+    ('4', [0b00010, 0b00110, 0b01010, 0b10010, 0b11111, 0b00010, 0b00010]),
+    ('5', [0b11111, 0b10000, 0b11110, 0b00001, 0b00001, 0b10001, 0b01110]),
+    ('6', [0b00110, 0b01000, 0b10000, 0b11110, 0b10001, 0b10001, 0b01110]),
+    ('7', [0b11111, 0b00001, 0b00010, 0b00100, 0b01000, 0b01000, 0b01000]),
+    ('8', [0b01110, 0b10001, 0b10001, 0b01110, 0b10001, 0b10001, 0b01110]),
+    ('9', [0b01110, 0b10001, 0b10001, 0b01111, 0b00001, 0b00010, 0b01100]),
+    ('!', [0b00100, 0b00100, 0b00100, 0b00100, 0b00100, 0b00000, 0b00100]),
+    ('?', [0b01110, 0b10001, 0b00001, 0b00010, 0b00100, 0b00000, 0b00100]),
+    ('.', [0b00000, 0b00000, 0b00000, 0b00000, 0b00000, 0b01100, 0b01100]),
+    (':', [0b00000, 0b01100, 0b01100, 0b00000, 0b01100, 0b01100, 0b00000]),
+    ('-', [0b00000, 0b00000, 0b00000, 0b11111, 0b00000, 0b00000, 0b00000]),
+    ('+', [0b00000, 0b00100, 0b00100, 0b11111, 0b00100, 0b00100, 0b00000]),
+    ('/', [0b00001, 0b00001, 0b00010, 0b00100, 0b01000, 0b10000, 0b10000]),
+    ('%', [0b11000, 0b11001, 0b00010, 0b00100, 0b01000, 0b10011, 0b00011]),
+    ('\'', [0b00100, 0b00100, 0b01000, 0b00000, 0b00000, 0b00000, 0b00000]),
+];
+
+// Returns the sheet with the font rows added underneath. Glyphs are white so a
+// sprite tint can colour them.
+pub fn append_font(sheet: RgbaImage) -> RgbaImage {
+    let font_height = (FONT.len() as u32).div_ceil(GLYPHS_PER_ROW) * GLYPH_PIXELS;
+    let mut out = RgbaImage::new(sheet.width(), sheet.height() + font_height);
+    image::imageops::replace(&mut out, &sheet, 0, 0);
+    for (i, (_, rows)) in FONT.iter().enumerate() {
+        let cell_x = (i as u32 % GLYPHS_PER_ROW) * GLYPH_PIXELS;
+        let cell_y = sheet.height() + (i as u32 / GLYPHS_PER_ROW) * GLYPH_PIXELS;
+        for (y, row) in rows.iter().enumerate() {
+            for x in 0..5 {
+                if row & (0b10000 >> x) != 0 {
+                    // One pixel of padding on the left keeps neighbouring glyphs apart.
+                    out.put_pixel(cell_x + 1 + x, cell_y + y as u32, image::Rgba([255, 255, 255, 255]));
+                }
+            }
+        }
+    }
+    out
+}
+
+fn glyph_index(c: char) -> Option<usize> {
+    let c = c.to_ascii_uppercase();
+    FONT.iter().position(|(glyph, _)| *glyph == c)
+}
+
+fn glyph_sheet_region(index: usize) -> [f32; 4] {
+    let col = (index as u32 % GLYPHS_PER_ROW) as f32;
+    let row = (index as u32 / GLYPHS_PER_ROW) as f32;
+    [
+        (col * GLYPH_CELL) / SPRITE_SHEET_RESOLUTION.0,
+        (FONT_FIRST_ROW + row * GLYPH_CELL) / SPRITE_SHEET_RESOLUTION.1,
+        GLYPH_CELL / SPRITE_SHEET_RESOLUTION.0,
+        GLYPH_CELL / SPRITE_SHEET_RESOLUTION.1,
+    ]
+}
+
+// A line of text, one sprite per visible character.
+#[derive(Debug)]
+pub struct TextLabel {
+    // (column, sprite index) for every character that has a glyph.
+    glyphs: Vec<(usize, SpriteHandle)>,
+    len: usize,
+}
+
+impl TextLabel {
+    // On the UI layer, fixed to the screen.
+    pub fn new(text: &str, sprite_holder: &mut SpriteHolder) -> Self {
+        Self::new_on(text, Layer::Ui, sprite_holder)
+    }
+
+    pub fn new_on(text: &str, layer: Layer, sprite_holder: &mut SpriteHolder) -> Self {
+        let mut glyphs = vec![];
+        for (column, c) in text.chars().enumerate() {
+            let Some(glyph) = glyph_index(c) else {
+                continue;
+            };
+            // Reserve the slot now; `draw` puts it on screen.
+            let sprite_index = sprite_holder.get_next_index_on(layer);
+            sprite_holder.set_sprite(
+                sprite_index,
+                GPUSprite {
+                    screen_region: [0.0; 4],
+                    sheet_region: glyph_sheet_region(glyph),
+                },
+            );
+            glyphs.push((column, sprite_index));
+        }
+        Self {
+            glyphs,
+            len: text.chars().count(),
+        }
+    }
+
+    // Width on screen at the given scale, where scale 1 is 8px per character.
+    pub fn width(&self, scale: f32) -> f32 {
+        self.len as f32 * GLYPH_PIXELS as f32 * scale
+    }
+
+    // Places the label with its bottom-left corner at `pos`.
+    pub fn draw(&self, pos: (f32, f32), scale: f32, tint: [f32; 4], sprite_holder: &mut SpriteHolder) {
+        let size = GLYPH_PIXELS as f32 * scale;
+        for &(column, sprite_index) in self.glyphs.iter() {
+            if let Some(sprite) = sprite_holder.sprite_mut(sprite_index) {
+                sprite.screen_region = [pos.0 + column as f32 * size, pos.1, size, size];
+            }
+            sprite_holder.set_tint(sprite_index, tint);
+        }
+    }
+
+    pub fn sprite_indices(&self) -> Vec<SpriteHandle> {
+        self.glyphs.iter().map(|&(_, sprite_index)| sprite_index).collect()
+    }
+
+    pub fn remove(self, sprite_holder: &mut SpriteHolder) {
+        for (_, sprite_index) in self.glyphs {
+            sprite_holder.remove_sprite(sprite_index);
+        }
+    }
+}
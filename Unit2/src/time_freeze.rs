@@ -0,0 +1,139 @@
+// The danmaku player's ability: press X with a full meter to surround yourself
+// with a bubble that slows enemy bullets for three seconds. Grazing bullets is the
+// only way to fill the meter back up.
+use bytemuck::Zeroable;
+
+use crate::generated_sprites::RING_CELL;
+use crate::render_target::LEFT_GAUGE_X;
+use crate::{atlas, GPUSprite, HealthBar, Layer, SpriteHandle, SpriteHolder, SPRITE_SHEET_RESOLUTION};
+
+const FIELD_FRAMES: u32 = 180;
+const FIELD_RADIUS: f32 = 160.0;
+// Bullets inside the bubble move at this fraction of their speed.
+pub const FIELD_TIME_SCALE: f32 = 0.5;
+// Meter gained per graze; twenty grazes for a full charge.
+pub const GRAZE_CHARGE: f32 = 0.05;
+const BUBBLE_TINT: [f32; 4] = [0.4, 0.9, 1.0, 0.6];
+const METER_TINT: [f32; 4] = [0.4, 0.9, 1.6, 1.0];
+
+pub struct TimeFreeze {
+    // 0.0 to 1.0; the ability can only be used at 1.0.
+    pub charge: f32,
+    frames_left: u32,
+    center: (f32, f32),
+    // How many times it has been used this level.
+    pub uses: u32,
+    meter: HealthBar,
+    bubble_sprite_index: SpriteHandle,
+}
+
+impl TimeFreeze {
+    // Starts full, so the first use is free.
+    pub fn new(sprite_holder: &mut SpriteHolder) -> Self {
+        Self {
+            charge: 1.0,
+            frames_left: 0,
+            center: (0.0, 0.0),
+            uses: 0,
+            meter: HealthBar {
+                currval: 1.0,
+                maxval: 1.0,
+                body: crate::components::Body::new((LEFT_GAUGE_X, 64.0), (128.0, 16.0)),
+                units_per_pixel: 4.0,
+                sprite_border: GPUSprite {
+                    screen_region: [LEFT_GAUGE_X, 64.0, 128.0, 16.0],
+                    sheet_region: atlas::sheet_region("bar_border"),
+                },
+                sprite_index_border: sprite_holder.get_next_index_on(Layer::Ui),
+                sprite_bar: GPUSprite {
+                    screen_region: [LEFT_GAUGE_X, 68.0, 128.0, 8.0],
+                    sheet_region: atlas::sheet_region("player_bar_fill"),
+                },
+                sprite_index_bar: sprite_holder.get_next_index_on(Layer::Ui),
+                ghost: None,
+            },
+            bubble_sprite_index: sprite_holder.get_next_index(),
+        }
+    }
+
+    pub fn is_active(&self) -> bool {
+        self.frames_left > 0
+    }
+
+    pub fn add_charge(&mut self, amount: f32) {
+        // No refilling while the bubble is up.
+        if !self.is_active() {
+            self.charge = (self.charge + amount).min(1.0);
+        }
+    }
+
+    // Puts the bubble up if the meter is full. Returns whether it did.
+    pub fn trigger(&mut self) -> bool {
+        if self.charge < 1.0 || self.is_active() {
+            return false;
+        }
+        self.charge = 0.0;
+        self.frames_left = FIELD_FRAMES;
+        self.uses += 1;
+        true
+    }
+
+    // Activates on request if the meter is full. The bubble follows the player.
+    pub fn update(&mut self, activate: bool, player_center: (f32, f32), sprite_holder: &mut SpriteHolder) {
+        if activate {
+            self.trigger();
+        }
+        self.center = player_center;
+
+        let mut bubble = GPUSprite::zeroed();
+        if self.is_active() {
+            self.frames_left -= 1;
+            bubble = GPUSprite {
+                screen_region: [
+                    self.center.0 - FIELD_RADIUS,
+                    self.center.1 - FIELD_RADIUS,
+                    FIELD_RADIUS * 2.0,
+                    FIELD_RADIUS * 2.0,
+                ],
+                sheet_region: [
+                    RING_CELL.0 / SPRITE_SHEET_RESOLUTION.0,
+                    RING_CELL.1 / SPRITE_SHEET_RESOLUTION.1,
+                    1.0 / SPRITE_SHEET_RESOLUTION.0,
+                    1.0 / SPRITE_SHEET_RESOLUTION.1,
+                ],
+            };
+        }
+        sprite_holder.set_sprite(self.bubble_sprite_index, bubble);
+        sprite_holder.set_tint(self.bubble_sprite_index, BUBBLE_TINT);
+
+        // While active the meter shows the time left instead of the charge.
+        self.meter.currval = if self.is_active() {
+            self.frames_left as f32 / FIELD_FRAMES as f32
+        } else {
+            self.charge
+        };
+        self.meter.health_bar_loop(sprite_holder);
+        sprite_holder.set_tint(self.meter.sprite_index_bar, METER_TINT);
+    }
+
+    // True if a point is inside the bubble right now.
+    pub fn contains(&self, point: (f32, f32)) -> bool {
+        self.is_active()
+            && (point.0 - self.center.0).powi(2) + (point.1 - self.center.1).powi(2) <= FIELD_RADIUS * FIELD_RADIUS
+    }
+
+    pub fn sprite_indices(&self) -> Vec<SpriteHandle> {
+        vec![self.bubble_sprite_index, self.meter.sprite_index_bar, self.meter.sprite_index_border]
+    }
+
+    // Just the meter, which lives in the frame.
+    pub fn hud_sprite_indices(&self) -> Vec<SpriteHandle> {
+        vec![self.meter.sprite_index_bar, self.meter.sprite_index_border]
+    }
+
+    pub fn remove(&self, sprite_holder: &mut SpriteHolder) {
+        sprite_holder.remove_sprite(self.bubble_sprite_index);
+        sprite_holder.remove_sprite(self.meter.sprite_index_bar);
+        sprite_holder.remove_sprite(self.meter.sprite_index_border);
+    }
+}
@@ -0,0 +1,98 @@
+// Shot-type sandbox, opened from either title screen's menu. The boss is a
+// harmless dummy that can't be killed, the player gets a steady supply of juice,
+// and a meter shows the damage per second of whatever shot is selected. 1-3 and
+// Up/Down switch shots without leaving the room; Escape goes back to the title it
+// was opened from.
+use std::collections::VecDeque;
+
+use crate::game_state::GameState;
+use crate::simulation::SimState;
+use crate::text::TextLabel;
+use crate::{SpriteHandle, SpriteHolder};
+
+// DPS is averaged over the last two seconds.
+const DPS_WINDOW: usize = 120;
+const FRAMES_PER_SECOND: f32 = 60.0;
+// One free charge this often, about as fast as a good player catches fruit.
+const FEED_FRAMES: u64 = 10;
+const LABEL_SCALE: f32 = 3.0;
+const LABEL_TINT: [f32; 4] = [1.0, 1.0, 1.0, 1.0];
+
+#[derive(Default)]
+pub struct TrialRoom {
+    // Damage dealt to the dummy on each of the last DPS_WINDOW frames.
+    damage: VecDeque<f32>,
+    // What the labels currently say, so they're only rebuilt when it changes.
+    shown: String,
+    labels: Vec<TextLabel>,
+    // Opened from the danmaku title rather than the normal one.
+    from_danmaku: bool,
+}
+
+impl TrialRoom {
+    pub fn dps(&self) -> f32 {
+        if self.damage.is_empty() {
+            return 0.0;
+        }
+        self.damage.iter().sum::<f32>() * FRAMES_PER_SECOND / self.damage.len() as f32
+    }
+
+    // Call after every simulated frame.
+    pub fn update(&mut self, sim: &mut SimState) {
+        // Measure the hit, then heal it straight away so the dummy never dies.
+        let health_bar = &mut sim.enemy.enemy.health_bar;
+        self.damage.push_back(health_bar.maxval - health_bar.currval);
+        if self.damage.len() > DPS_WINDOW {
+            self.damage.pop_front();
+        }
+        health_bar.currval = health_bar.maxval;
+        sim.trans_flag.val = 0;
+
+        if sim.frame.is_multiple_of(FEED_FRAMES) {
+            sim.player.charges += 1;
+        }
+
+        let text = format!("{}\nDPS {:.1}", sim.shot.name(), self.dps());
+        if text != self.shown {
+            self.clear(&mut sim.sprite_holder);
+            self.labels = text
+                .lines()
+                .map(|line| TextLabel::new(line, &mut sim.sprite_holder))
+                .collect();
+            self.shown = text;
+        }
+        for (row, label) in self.labels.iter().enumerate() {
+            let pos = (32.0, 720.0 - row as f32 * 32.0);
+            label.draw(pos, LABEL_SCALE, LABEL_TINT, &mut sim.sprite_holder);
+        }
+    }
+
+    pub fn opened_from(&mut self, danmaku: bool) {
+        self.from_danmaku = danmaku;
+    }
+
+    // Where Escape leaves to.
+    pub fn title(&self) -> GameState {
+        if self.from_danmaku {
+            GameState::Title2
+        } else {
+            GameState::Title
+        }
+    }
+
+    // Switching shots starts a fresh measurement.
+    pub fn reset_meter(&mut self) {
+        self.damage.clear();
+    }
+
+    pub fn sprite_indices(&self) -> Vec<SpriteHandle> {
+        self.labels.iter().flat_map(|label| label.sprite_indices()).collect()
+    }
+
+    pub fn clear(&mut self, sprite_holder: &mut SpriteHolder) {
+        for label in self.labels.drain(..) {
+            label.remove(sprite_holder);
+        }
+        self.shown.clear();
+    }
+}
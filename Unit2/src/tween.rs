@@ -0,0 +1,142 @@
+// Frame-counted interpolation for anything that slides, grows or fades: popup
+// banners, boss fly-ins, menu focus, health bar drains. A tween goes from one value to
+// another over a fixed number of frames along an easing curve, and can run a
+// callback on the frame it finishes.
+use crate::SpriteHolder;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Easing {
+    Linear,
+    // Starts slow, ends fast. Good for things leaving the screen.
+    InCubic,
+    // Starts fast, settles gently. Good for things arriving.
+    OutCubic,
+    InOutSine,
+}
+
+impl Easing {
+    // Maps progress in 0..=1 to eased progress in 0..=1.
+    pub fn apply(self, t: f32) -> f32 {
+        let t = t.clamp(0.0, 1.0);
+        match self {
+            Easing::Linear => t,
+            Easing::InCubic => t.powi(3),
+            Easing::OutCubic => 1.0 - (1.0 - t).powi(3),
+            Easing::InOutSine => (1.0 - (t * std::f32::consts::PI).cos()) / 2.0,
+        }
+    }
+}
+
+pub fn lerp(from: f32, to: f32, t: f32) -> f32 {
+    from + (to - from) * t
+}
+
+// Goes 0 to 1 over `period` frames, then back down over the next `period`, forever.
+// For pulsing things that don't have a start or an end.
+pub fn ping_pong(frame: u64, period: u64, easing: Easing) -> f32 {
+    let phase = (frame % (2 * period)) as f32 / period as f32;
+    easing.apply(if phase < 1.0 { phase } else { 2.0 - phase })
+}
+
+// Most tweens move sprites, so the completion callback gets the sprite holder to
+// tidy up after itself.
+type OnComplete = Box<dyn FnOnce(&mut SpriteHolder)>;
+
+pub struct Tween {
+    from: f32,
+    to: f32,
+    duration: u32,
+    elapsed: u32,
+    easing: Easing,
+    on_complete: Option<OnComplete>,
+}
+
+impl Tween {
+    pub fn new(from: f32, to: f32, duration: u32, easing: Easing) -> Self {
+        Self {
+            from,
+            to,
+            duration,
+            elapsed: 0,
+            easing,
+            on_complete: None,
+        }
+    }
+
+    pub fn on_complete(mut self, callback: impl FnOnce(&mut SpriteHolder) + 'static) -> Self {
+        self.on_complete = Some(Box::new(callback));
+        self
+    }
+
+    pub fn value(&self) -> f32 {
+        if self.duration == 0 {
+            return self.to;
+        }
+        lerp(self.from, self.to, self.easing.apply(self.elapsed as f32 / self.duration as f32))
+    }
+
+    pub fn is_done(&self) -> bool {
+        self.elapsed >= self.duration
+    }
+
+    // Advances one frame and returns the new value.
+    pub fn step(&mut self, sprite_holder: &mut SpriteHolder) -> f32 {
+        if !self.is_done() {
+            self.elapsed += 1;
+        }
+        if self.is_done() {
+            if let Some(callback) = self.on_complete.take() {
+                callback(sprite_holder);
+            }
+        }
+        self.value()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn every_easing_starts_at_0_ends_at_1_and_clamps() {
+        for easing in [Easing::Linear, Easing::InCubic, Easing::OutCubic, Easing::InOutSine] {
+            assert_eq!(easing.apply(0.0), 0.0, "{:?}", easing);
+            assert!((easing.apply(1.0) - 1.0).abs() < 1e-6, "{:?}", easing);
+            assert_eq!(easing.apply(-1.0), easing.apply(0.0), "{:?}", easing);
+            assert_eq!(easing.apply(2.0), easing.apply(1.0), "{:?}", easing);
+        }
+        assert!(Easing::InCubic.apply(0.5) < 0.5);
+        assert!(Easing::OutCubic.apply(0.5) > 0.5);
+        assert!((Easing::InOutSine.apply(0.5) - 0.5).abs() < 1e-6);
+    }
+
+    #[test]
+    fn lerp_and_ping_pong_hit_their_ends() {
+        assert_eq!(lerp(2.0, 6.0, 0.0), 2.0);
+        assert_eq!(lerp(2.0, 6.0, 0.25), 3.0);
+        assert_eq!(lerp(2.0, 6.0, 1.0), 6.0);
+        assert_eq!(ping_pong(0, 10, Easing::Linear), 0.0);
+        assert_eq!(ping_pong(5, 10, Easing::Linear), 0.5);
+        assert_eq!(ping_pong(10, 10, Easing::Linear), 1.0);
+        assert_eq!(ping_pong(15, 10, Easing::Linear), 0.5);
+        assert_eq!(ping_pong(20, 10, Easing::Linear), 0.0);
+    }
+
+    #[test]
+    fn a_tween_arrives_on_its_last_frame_and_calls_back_once() {
+        let calls = std::rc::Rc::new(std::cell::Cell::new(0));
+        let counter = calls.clone();
+        let mut tween = Tween::new(0.0, 10.0, 4, Easing::Linear).on_complete(move |_| counter.set(counter.get() + 1));
+        let mut sprite_holder = SpriteHolder::new(0);
+        assert_eq!(tween.value(), 0.0);
+        assert_eq!(tween.step(&mut sprite_holder), 2.5);
+        for _ in 0..2 {
+            tween.step(&mut sprite_holder);
+        }
+        assert!(!tween.is_done());
+        assert_eq!(tween.step(&mut sprite_holder), 10.0);
+        assert!(tween.is_done());
+        assert_eq!(tween.step(&mut sprite_holder), 10.0);
+        assert_eq!(calls.get(), 1);
+    }
+}
@@ -0,0 +1,71 @@
+// Invulnerability-frame bookkeeping shared by the player and every enemy. Damage
+// code asks `try_hit` before applying anything, so no matter how many sources
+// overlap an entity at once (bullets now, body contact or lasers later) it can
+// only be hurt once per cooldown.
+
+// Enemies shrug off hits for a few frames after taking one, so a spread landing
+// at once counts once. The fastest Held trigger fires every 3 frames, so no
+// volley is lost to it.
+pub const ENEMY_COOLDOWN: u32 = 3;
+
+#[derive(Debug, Clone, Copy)]
+pub struct Vulnerability {
+    // Frames of invulnerability after a hit. 1 means at most one hit per frame;
+    // 0 means every hit lands.
+    cooldown: u32,
+    frames_left: u32,
+}
+
+impl Vulnerability {
+    pub fn new(cooldown: u32) -> Self {
+        Self {
+            cooldown,
+            frames_left: 0,
+        }
+    }
+
+    // Returns false if the hit should be ignored; otherwise starts the cooldown.
+    pub fn try_hit(&mut self) -> bool {
+        if self.frames_left > 0 {
+            return false;
+        }
+        self.frames_left = self.cooldown;
+        true
+    }
+
+    // Once per simulated frame, after all damage has been dealt.
+    pub fn tick(&mut self) {
+        self.frames_left = self.frames_left.saturating_sub(1);
+    }
+}
+
+impl Default for Vulnerability {
+    fn default() -> Self {
+        Self::new(1)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn hits_are_turned_away_until_the_window_runs_out() {
+        let mut vulnerability = Vulnerability::new(ENEMY_COOLDOWN);
+        assert!(vulnerability.try_hit());
+        for _ in 0..ENEMY_COOLDOWN {
+            assert!(!vulnerability.try_hit());
+            vulnerability.tick();
+        }
+        assert!(vulnerability.try_hit());
+    }
+
+    #[test]
+    fn the_default_window_allows_one_hit_per_frame() {
+        let mut vulnerability = Vulnerability::default();
+        assert!(vulnerability.try_hit());
+        assert!(!vulnerability.try_hit());
+        vulnerability.tick();
+        assert!(vulnerability.try_hit());
+    }
+}
@@ -0,0 +1,313 @@
+// Menus as data. A screen lists its rows as `Widget`s and hands them to `Menu`,
+// which owns their text, moves the focus, reads navigation and draws the rows.
+// The screen only handles the `MenuEvent`s that come back, tagged with whatever
+// id type it picked for its rows.
+use crate::input::{Input, Key, PadButton};
+use crate::text::TextLabel;
+use crate::tween::{Easing, Tween};
+use crate::{SpriteHandle, SpriteHolder};
+
+const MENU_SCALE: f32 = 3.0;
+// From one row's baseline to the next.
+const MENU_LINE: f32 = 40.0;
+// Between a row's text and its value.
+const VALUE_GAP: f32 = 32.0;
+const SELECTED_TINT: [f32; 4] = [1.6, 1.3, 0.3, 1.0];
+const UNSELECTED_TINT: [f32; 4] = [0.5, 0.5, 0.5, 1.0];
+const LABEL_TINT: [f32; 4] = [1.0, 1.0, 1.0, 1.0];
+// The focused row slides this far right when the focus lands on it.
+const FOCUS_NUDGE: f32 = 12.0;
+const NUDGE_FRAMES: u32 = 8;
+
+// A menu input, whatever it came from: the keyboard, the mouse wheel or a
+// gamepad's d-pad and face buttons.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Nav {
+    Up,
+    Down,
+    Left,
+    Right,
+    Confirm,
+    Back,
+}
+
+impl Nav {
+    // This frame's navigation. Space is left alone since it's held down to shoot
+    // and would carry over into whatever screen comes next. The mouse wheel
+    // scrolls up and down.
+    pub fn from_input(input: &Input) -> Option<Nav> {
+        match input.wheel_steps() {
+            steps if steps > 0 => return Some(Nav::Up),
+            steps if steps < 0 => return Some(Nav::Down),
+            _ => {}
+        }
+        [
+            (Key::Up, Nav::Up),
+            (Key::Down, Nav::Down),
+            (Key::Left, Nav::Left),
+            (Key::Right, Nav::Right),
+            (Key::Return, Nav::Confirm),
+            (Key::Escape, Nav::Back),
+        ]
+        .into_iter()
+        .find(|&(key, _)| input.is_key_pressed(key))
+        .map(|(_, nav)| nav)
+        .or_else(|| {
+            [
+                (PadButton::Up, Nav::Up),
+                (PadButton::Down, Nav::Down),
+                (PadButton::Left, Nav::Left),
+                (PadButton::Right, Nav::Right),
+                (PadButton::South, Nav::Confirm),
+                (PadButton::Start, Nav::Confirm),
+                (PadButton::East, Nav::Back),
+            ]
+            .into_iter()
+            .find(|&(button, _)| input.is_pad_pressed(button))
+            .map(|(_, nav)| nav)
+        })
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Widget<Id> {
+    // Text that can't be focused, e.g. a section heading.
+    Label(&'static str),
+    Button(Id, &'static str),
+    Toggle(Id, &'static str, bool),
+    // Confirm, then press the key to bind. Back cancels. Shows NONE without a key.
+    KeyCapture(Id, &'static str, Option<Key>),
+}
+
+impl<Id> Widget<Id> {
+    fn text(&self) -> &'static str {
+        match self {
+            Widget::Label(text) | Widget::Button(_, text) | Widget::Toggle(_, text, _) | Widget::KeyCapture(_, text, _) => {
+                text
+            }
+        }
+    }
+
+    fn focusable(&self) -> bool {
+        !matches!(self, Widget::Label(_))
+    }
+
+    fn value(&self, listening: bool) -> Option<String> {
+        match self {
+            Widget::Label(_) | Widget::Button(..) => None,
+            Widget::Toggle(_, _, on) => Some(if *on { "ON" } else { "OFF" }.to_string()),
+            Widget::KeyCapture(..) if listening => Some("...".to_string()),
+            Widget::KeyCapture(_, _, Some(key)) => Some(format!("{:?}", key)),
+            Widget::KeyCapture(_, _, None) => Some("NONE".to_string()),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MenuEvent<Id> {
+    Pressed(Id),
+    Toggled(Id, bool),
+    KeyBound(Id, Key),
+    // Back with nothing to cancel. Up to the screen what that means.
+    Back,
+}
+
+struct Row<Id> {
+    widget: Widget<Id>,
+    text: TextLabel,
+    // The text `value` was made for, so it's only rebuilt when it changes.
+    shown: Option<String>,
+    value: Option<TextLabel>,
+}
+
+pub struct Menu<Id> {
+    rows: Vec<Row<Id>>,
+    focus: usize,
+    // The row waiting for a key to bind.
+    listening: Option<usize>,
+    nudge: Tween,
+}
+
+impl<Id: Copy> Menu<Id> {
+    pub fn new(widgets: &[Widget<Id>], sprite_holder: &mut SpriteHolder) -> Self {
+        let rows: Vec<Row<Id>> = widgets
+            .iter()
+            .map(|&widget| {
+                let shown = widget.value(false);
+                Row {
+                    widget,
+                    text: TextLabel::new(widget.text(), sprite_holder),
+                    value: shown.as_deref().map(|value| TextLabel::new(value, sprite_holder)),
+                    shown,
+                }
+            })
+            .collect();
+        let focus = rows.iter().position(|row| row.widget.focusable()).unwrap_or(0);
+        Self {
+            rows,
+            focus,
+            listening: None,
+            nudge: Tween::new(FOCUS_NUDGE, FOCUS_NUDGE, 0, Easing::Linear),
+        }
+    }
+
+    pub fn handle_input(&mut self, input: &Input, sprite_holder: &mut SpriteHolder) -> Option<MenuEvent<Id>> {
+        self.nudge.step(sprite_holder);
+        if let Some(row) = self.listening {
+            if input.is_key_pressed(Key::Escape) {
+                self.listening = None;
+            } else if let (Some(key), Widget::KeyCapture(id, text, _)) = (input.pressed_key(), self.rows[row].widget) {
+                self.listening = None;
+                self.rows[row].widget = Widget::KeyCapture(id, text, Some(key));
+                self.refresh(row, sprite_holder);
+                return Some(MenuEvent::KeyBound(id, key));
+            }
+            self.refresh(row, sprite_holder);
+            return None;
+        }
+
+        let event = match (Nav::from_input(input)?, self.rows.get_mut(self.focus)?.widget) {
+            (Nav::Up, _) => {
+                self.move_focus(-1);
+                None
+            }
+            (Nav::Down, _) => {
+                self.move_focus(1);
+                None
+            }
+            (Nav::Back, _) => Some(MenuEvent::Back),
+            (Nav::Confirm, Widget::Button(id, _)) => Some(MenuEvent::Pressed(id)),
+            (Nav::Confirm | Nav::Left | Nav::Right, Widget::Toggle(id, text, on)) => {
+                self.rows[self.focus].widget = Widget::Toggle(id, text, !on);
+                Some(MenuEvent::Toggled(id, !on))
+            }
+            (Nav::Confirm, Widget::KeyCapture(..)) => {
+                self.listening = Some(self.focus);
+                None
+            }
+            _ => None,
+        };
+        self.refresh(self.focus, sprite_holder);
+        event
+    }
+
+    // Swaps in new rows, keeping the focus where it was. For when what the rows
+    // show has changed under the menu.
+    pub fn set_widgets(&mut self, widgets: &[Widget<Id>], sprite_holder: &mut SpriteHolder) {
+        let focus = self.focus;
+        std::mem::replace(self, Menu::new(widgets, sprite_holder)).remove(sprite_holder);
+        if self.rows.get(focus).is_some_and(|row| row.widget.focusable()) {
+            self.focus = focus;
+        }
+    }
+
+    // Next focusable row in `step`'s direction. Stops at the ends.
+    fn move_focus(&mut self, step: isize) {
+        let mut row = self.focus as isize + step;
+        while row >= 0 && (row as usize) < self.rows.len() {
+            if self.rows[row as usize].widget.focusable() {
+                self.focus = row as usize;
+                self.nudge = Tween::new(0.0, FOCUS_NUDGE, NUDGE_FRAMES, Easing::OutCubic);
+                return;
+            }
+            row += step;
+        }
+    }
+
+    fn refresh(&mut self, row: usize, sprite_holder: &mut SpriteHolder) {
+        let listening = self.listening == Some(row);
+        let row = &mut self.rows[row];
+        let shown = row.widget.value(listening);
+        if shown != row.shown {
+            if let Some(label) = row.value.take() {
+                label.remove(sprite_holder);
+            }
+            row.value = shown.as_deref().map(|value| TextLabel::new(value, sprite_holder));
+            row.shown = shown;
+        }
+    }
+
+    // Rows are centred on `center_x`, the first with its baseline at `top`.
+    pub fn draw(&self, center_x: f32, top: f32, sprite_holder: &mut SpriteHolder) {
+        for (index, row) in self.rows.iter().enumerate() {
+            let (tint, nudge) = match (row.widget.focusable(), index == self.focus) {
+                (false, _) => (LABEL_TINT, 0.0),
+                (true, true) => (SELECTED_TINT, self.nudge.value()),
+                (true, false) => (UNSELECTED_TINT, 0.0),
+            };
+            let value_width = row.value.as_ref().map_or(0.0, |value| VALUE_GAP + value.width(MENU_SCALE));
+            let x = center_x - (row.text.width(MENU_SCALE) + value_width) / 2.0 + nudge;
+            let y = top - index as f32 * MENU_LINE;
+            row.text.draw((x, y), MENU_SCALE, tint, sprite_holder);
+            if let Some(value) = &row.value {
+                let value_x = x + row.text.width(MENU_SCALE) + VALUE_GAP;
+                value.draw((value_x, y), MENU_SCALE, tint, sprite_holder);
+            }
+        }
+    }
+
+    pub fn sprite_indices(&self) -> Vec<SpriteHandle> {
+        let mut indices = vec![];
+        for row in self.rows.iter() {
+            indices.extend(row.text.sprite_indices());
+            if let Some(value) = &row.value {
+                indices.extend(value.sprite_indices());
+            }
+        }
+        indices
+    }
+
+    pub fn remove(self, sprite_holder: &mut SpriteHolder) {
+        for row in self.rows {
+            row.text.remove(sprite_holder);
+            if let Some(value) = row.value {
+                value.remove(sprite_holder);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn press(key: Key) -> Input {
+        let mut input = Input::default();
+        #[allow(deprecated)]
+        input.handle_key_event(winit::event::KeyboardInput {
+            scancode: 0,
+            state: winit::event::ElementState::Pressed,
+            virtual_keycode: Some(key),
+            modifiers: winit::event::ModifiersState::empty(),
+        });
+        input
+    }
+
+    #[test]
+    fn focus_skips_labels_and_widgets_report_their_changes() {
+        let mut sprite_holder = SpriteHolder::new(256);
+        let widgets = [
+            Widget::Label("VIDEO"),
+            Widget::Button(0, "APPLY"),
+            Widget::Toggle(1, "VSYNC", true),
+            Widget::KeyCapture(2, "FIRE", Some(Key::Space)),
+            Widget::Button(3, "BACK"),
+        ];
+        let mut menu = Menu::new(&widgets, &mut sprite_holder);
+        assert_eq!(menu.handle_input(&press(Key::Return), &mut sprite_holder), Some(MenuEvent::Pressed(0)));
+        menu.handle_input(&press(Key::Down), &mut sprite_holder);
+        assert_eq!(menu.handle_input(&press(Key::Right), &mut sprite_holder), Some(MenuEvent::Toggled(1, false)));
+        assert_eq!(menu.handle_input(&press(Key::Return), &mut sprite_holder), Some(MenuEvent::Toggled(1, true)));
+        menu.handle_input(&press(Key::Down), &mut sprite_holder);
+        assert_eq!(menu.handle_input(&press(Key::Return), &mut sprite_holder), None);
+        assert_eq!(menu.handle_input(&press(Key::Z), &mut sprite_holder), Some(MenuEvent::KeyBound(2, Key::Z)));
+        menu.handle_input(&press(Key::Down), &mut sprite_holder);
+        menu.handle_input(&press(Key::Down), &mut sprite_holder);
+        assert_eq!(menu.handle_input(&press(Key::Return), &mut sprite_holder), Some(MenuEvent::Pressed(3)));
+        assert_eq!(menu.handle_input(&press(Key::Escape), &mut sprite_holder), Some(MenuEvent::Back));
+
+        let indices = menu.sprite_indices();
+        menu.remove(&mut sprite_holder);
+        assert!(indices.iter().all(|&handle| !sprite_holder.is_live(handle)));
+    }
+}